@@ -0,0 +1,154 @@
+//! What a provider can do, derived from its capability tags and kind.
+//!
+//! Tags are free-form strings stored with each provider (`vision`,
+//! `ctx-128k`, `openai-compatible`, ...). This module is the one place that
+//! interprets them, so the orchestrator, the summariser and the UI agree.
+
+use serde::Serialize;
+
+use super::config::{AiProviderInfo, AZURE_OPENAI_KIND};
+
+/// Context window assumed when neither the provider nor its tags state one.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 4096;
+
+/// Structured view of a provider's abilities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// Replies can be streamed token by token.
+    pub streaming: bool,
+    /// The API has a native JSON response mode; without it JSON requests
+    /// are steered by a system instruction instead.
+    pub json_mode: bool,
+    /// Image parts are accepted in chat messages.
+    pub vision: bool,
+    /// An embeddings endpoint is available for semantic search.
+    pub embeddings: bool,
+    /// Context window in tokens; zero from [`from_tags`](Self::from_tags)
+    /// when no `ctx-*` tag states one.
+    pub context_window: usize,
+    pub requires_api_key: bool,
+}
+
+impl Capabilities {
+    /// Read the capabilities stated by tags alone.
+    ///
+    /// Recognised tags are `streaming`, `json`, `vision`, `embeddings`, and
+    /// `ctx-<n>` / `ctx-<n>k` for the context window.
+    pub fn from_tags<S: AsRef<str>>(tags: &[S]) -> Self {
+        let mut caps = Self::default();
+        for tag in tags.iter().map(AsRef::as_ref) {
+            match tag {
+                "streaming" => caps.streaming = true,
+                "json" | "json-mode" => caps.json_mode = true,
+                "vision" => caps.vision = true,
+                "embeddings" => caps.embeddings = true,
+                other => {
+                    if let Some(window) = parse_context_tag(other) {
+                        if caps.context_window == 0 {
+                            caps.context_window = window;
+                        }
+                    }
+                }
+            }
+        }
+        caps
+    }
+
+    /// Combine a provider's tags with what its wire format supports.
+    ///
+    /// The context window prefers the default model's configured window,
+    /// then the `ctx-*` tag, then [`DEFAULT_CONTEXT_WINDOW`].
+    pub fn for_provider(provider: &AiProviderInfo) -> Self {
+        let mut caps = Self::from_tags(&provider.capability_tags);
+        let openai = speaks_openai(provider);
+        let ollama = speaks_ollama(provider);
+        caps.json_mode |= openai || matches!(provider.id.as_str(), "google" | "cohere");
+        caps.embeddings |= openai || ollama;
+        caps.context_window = provider
+            .context_window
+            .filter(|window| *window > 0)
+            .or((caps.context_window > 0).then_some(caps.context_window))
+            .unwrap_or(DEFAULT_CONTEXT_WINDOW);
+        caps.requires_api_key = provider.requires_api_key;
+        caps
+    }
+}
+
+/// Whether the provider uses the OpenAI Chat Completions wire format,
+/// including Azure OpenAI deployments.
+pub(crate) fn speaks_openai(provider: &AiProviderInfo) -> bool {
+    matches!(provider.id.as_str(), "openai" | "lmstudio")
+        || provider.kind == AZURE_OPENAI_KIND
+        || provider
+            .capability_tags
+            .iter()
+            .any(|tag| tag.contains("openai"))
+}
+
+/// Whether the provider uses Ollama's native API.
+pub(crate) fn speaks_ollama(provider: &AiProviderInfo) -> bool {
+    provider.id == "ollama" || provider.capability_tags.iter().any(|tag| tag == "ollama")
+}
+
+fn parse_context_tag(tag: &str) -> Option<usize> {
+    let rest = tag.strip_prefix("ctx-")?;
+    let limit = if let Some(thousands) = rest.strip_suffix('k') {
+        let digits: String = thousands.chars().filter(|c| c.is_ascii_digit()).collect();
+        digits.parse::<usize>().ok()? * 1000
+    } else {
+        rest.parse::<usize>().ok()?
+    };
+    (limit > 0).then_some(limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_context_tag_handles_suffixes() {
+        assert_eq!(parse_context_tag("ctx-4096"), Some(4096));
+        assert_eq!(parse_context_tag("ctx-8k"), Some(8000));
+        assert_eq!(parse_context_tag("other"), None);
+        assert_eq!(parse_context_tag("ctx-0"), None);
+        assert_eq!(parse_context_tag("ctx-0k"), None);
+    }
+
+    #[test]
+    fn provider_capabilities_combine_tags_and_wire_format() {
+        let tags = Capabilities::from_tags(&["chat", "vision", "ctx-200k"]);
+        assert!(tags.vision && !tags.json_mode && !tags.embeddings);
+        assert_eq!(tags.context_window, 200_000);
+
+        let mut provider = AiProviderInfo {
+            id: "anthropic".into(),
+            kind: "cloud".into(),
+            display_name: "Anthropic".into(),
+            description: None,
+            base_url: None,
+            default_model: None,
+            models: Vec::new(),
+            capability_tags: vec!["vision".into(), "ctx-200k".into()],
+            requires_api_key: true,
+            has_credentials: false,
+            context_window: None,
+            is_custom: false,
+            request_timeout_secs: None,
+            headers: Default::default(),
+            rate_limit_rpm: None,
+            default_temperature: None,
+            default_top_p: None,
+        };
+        let anthropic = Capabilities::for_provider(&provider);
+        assert!(anthropic.vision && anthropic.requires_api_key);
+        assert!(!anthropic.json_mode && !anthropic.embeddings && !anthropic.streaming);
+        assert_eq!(anthropic.context_window, 200_000);
+
+        provider.id = "ollama".into();
+        provider.capability_tags = vec!["local".into()];
+        provider.requires_api_key = false;
+        let ollama = Capabilities::for_provider(&provider);
+        assert!(ollama.embeddings && !ollama.json_mode && !ollama.vision);
+        assert_eq!(ollama.context_window, DEFAULT_CONTEXT_WINDOW);
+    }
+}
@@ -9,9 +9,12 @@ use base64::Engine;
 use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
 use time::OffsetDateTime;
 
-use super::providers::PROVIDER_SEEDS;
+use super::capabilities::Capabilities;
+use super::providers::{MODEL_CONTEXT_SEEDS, PROVIDER_SEEDS};
+use crate::errors::InkOsError;
 use crate::logging::log_event;
 
 /// Serializable view of an AI provider record.
@@ -28,6 +31,28 @@ pub struct AiProviderInfo {
     pub capability_tags: Vec<String>,
     pub requires_api_key: bool,
     pub has_credentials: bool,
+    /// Context window of the default model in tokens, when known.
+    #[serde(default)]
+    pub context_window: Option<usize>,
+    /// Registered at runtime rather than bundled in [`PROVIDER_SEEDS`].
+    #[serde(default)]
+    pub is_custom: bool,
+    /// Per-request HTTP timeout; `None` uses the orchestrator default.
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+    /// Extra request headers; values may contain `${ENV_VAR}` references.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Outbound requests per minute across the whole process; `None` is
+    /// unlimited.
+    #[serde(default)]
+    pub rate_limit_rpm: Option<u32>,
+    /// Temperature used when a request sets none; `None` means 0.2.
+    #[serde(default)]
+    pub default_temperature: Option<f32>,
+    /// Top-p used when a request sets none; `None` leaves it out.
+    #[serde(default)]
+    pub default_top_p: Option<f32>,
 }
 
 /// Snapshot returned to the UI describing the active AI settings.
@@ -44,6 +69,18 @@ pub struct AiRuntimeSelection {
     pub provider: AiProviderInfo,
     pub model: String,
     pub secret: Option<String>,
+    /// Where `secret` came from, so logs can note env-injected keys.
+    pub secret_source: Option<SecretSource>,
+}
+
+/// Origin of a provider credential.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    /// Saved through the settings UI in `ai_credentials`.
+    Stored,
+    /// Read from `INKOS_<PROVIDER>_API_KEY`; never written to the database.
+    Environment,
 }
 
 /// Input payload accepted by [`update_settings`].
@@ -55,6 +92,24 @@ pub struct AiSettingsUpdate {
     pub base_url: Option<String>,
 }
 
+/// User-supplied definition of a self-hosted or third-party provider.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomProviderInput {
+    pub id: String,
+    pub kind: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub base_url: String,
+    pub default_model: Option<String>,
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub capability_tags: Vec<String>,
+    #[serde(default)]
+    pub requires_api_key: bool,
+    #[serde(default)]
+    pub request_timeout_secs: Option<u64>,
+}
+
 /// Insert baked-in AI provider defaults and ensure an active selection.
 pub fn seed_defaults(conn: &rusqlite::Connection) -> Result<()> {
     let now = OffsetDateTime::now_utc().unix_timestamp();
@@ -62,8 +117,8 @@ pub fn seed_defaults(conn: &rusqlite::Connection) -> Result<()> {
         let models_json = serde_json::to_string(seed.models)?;
         let caps_json = serde_json::to_string(seed.tags)?;
         conn.execute(
-            "INSERT INTO ai_providers (id, kind, display_name, description, base_url, default_model, models_json, capabilities_json, requires_api_key, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)
+            "INSERT INTO ai_providers (id, kind, display_name, description, base_url, default_model, models_json, capabilities_json, requires_api_key, context_window, request_timeout_secs, default_temperature, default_top_p, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?11, ?12, ?13, ?14, ?10, ?10)
              ON CONFLICT(id) DO UPDATE SET
                  kind = excluded.kind,
                  display_name = excluded.display_name,
@@ -73,7 +128,12 @@ pub fn seed_defaults(conn: &rusqlite::Connection) -> Result<()> {
                  models_json = excluded.models_json,
                  capabilities_json = excluded.capabilities_json,
                  requires_api_key = excluded.requires_api_key,
-                 updated_at = excluded.updated_at",
+                 context_window = excluded.context_window,
+                 request_timeout_secs = COALESCE(ai_providers.request_timeout_secs, excluded.request_timeout_secs),
+                 default_temperature = COALESCE(ai_providers.default_temperature, excluded.default_temperature),
+                 default_top_p = COALESCE(ai_providers.default_top_p, excluded.default_top_p),
+                 updated_at = excluded.updated_at
+             WHERE ai_providers.is_custom = 0",
             params![
                 seed.id,
                 seed.kind,
@@ -85,6 +145,25 @@ pub fn seed_defaults(conn: &rusqlite::Connection) -> Result<()> {
                 caps_json,
                 seed.requires_api_key as i32,
                 now,
+                seed.context_window.map(|w| w as i64),
+                seed.request_timeout_secs.map(|secs| secs as i64),
+                seed.default_temperature.map(f64::from),
+                seed.default_top_p.map(f64::from),
+            ],
+        )?;
+    }
+
+    // Seeded windows never overwrite an override the user has already stored.
+    for seed in MODEL_CONTEXT_SEEDS {
+        conn.execute(
+            "INSERT INTO model_context (provider_id, model_id, context_window, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(provider_id, model_id) DO NOTHING",
+            params![
+                seed.provider_id,
+                seed.model,
+                seed.context_window as i64,
+                now
             ],
         )?;
     }
@@ -113,31 +192,8 @@ pub fn seed_defaults(conn: &rusqlite::Connection) -> Result<()> {
 
 /// Fetch all available providers ordered by display name.
 pub fn list_providers(conn: &rusqlite::Connection) -> Result<Vec<AiProviderInfo>> {
-    let mut stmt = conn.prepare(
-        "SELECT p.id, p.kind, p.display_name, p.description, p.base_url, p.default_model, p.models_json, p.capabilities_json, p.requires_api_key, \
-                (SELECT COUNT(1) FROM ai_credentials c WHERE c.provider_id = p.id) as has_secret
-         FROM ai_providers p
-         ORDER BY p.display_name",
-    )?;
-
-    let rows = stmt.query_map([], |row| {
-        let models_json: String = row.get(6)?;
-        let caps_json: String = row.get(7)?;
-        let models: Vec<String> = serde_json::from_str(&models_json).unwrap_or_default();
-        let caps: Vec<String> = serde_json::from_str(&caps_json).unwrap_or_default();
-        Ok(AiProviderInfo {
-            id: row.get(0)?,
-            kind: row.get(1)?,
-            display_name: row.get(2)?,
-            description: row.get(3)?,
-            base_url: row.get(4)?,
-            default_model: row.get(5)?,
-            models,
-            capability_tags: caps,
-            requires_api_key: row.get::<_, i64>(8)? != 0,
-            has_credentials: row.get::<_, i64>(9)? > 0,
-        })
-    })?;
+    let mut stmt = conn.prepare(&format!("{PROVIDER_SELECT} ORDER BY p.display_name"))?;
+    let rows = stmt.query_map([], map_provider_row)?;
 
     let mut providers = Vec::new();
     for row in rows {
@@ -169,6 +225,9 @@ pub fn update_settings(
     let provider = get_provider(conn, &update.provider_id)?;
 
     if let Some(base_url) = update.base_url {
+        if provider.kind == AZURE_OPENAI_KIND {
+            validate_azure_base_url(&base_url, &provider.capability_tags)?;
+        }
         conn.execute(
             "UPDATE ai_providers SET base_url = ?1, updated_at = ?2 WHERE id = ?3",
             params![
@@ -203,45 +262,381 @@ pub fn update_settings(
     get_settings(conn)
 }
 
+/// Register a custom provider and return its stored record.
+pub fn add_provider(
+    conn: &rusqlite::Connection,
+    input: CustomProviderInput,
+) -> Result<AiProviderInfo> {
+    validate_custom_provider(&input)?;
+    if PROVIDER_SEEDS.iter().any(|seed| seed.id == input.id) {
+        return Err(validation(&format!(
+            "Provider id '{}' is reserved for a built-in provider",
+            input.id
+        )));
+    }
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM ai_providers WHERE id = ?1",
+            params![input.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_some() {
+        return Err(validation(&format!(
+            "Provider id '{}' is already in use",
+            input.id
+        )));
+    }
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    conn.execute(
+        "INSERT INTO ai_providers (id, kind, display_name, description, base_url, default_model, models_json, capabilities_json, requires_api_key, request_timeout_secs, is_custom, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?11, 1, ?10, ?10)",
+        params![
+            input.id,
+            input.kind,
+            input.display_name,
+            input.description,
+            input.base_url,
+            input.default_model.clone().or_else(|| input.models.first().cloned()),
+            serde_json::to_string(&input.models)?,
+            serde_json::to_string(&input.capability_tags)?,
+            input.requires_api_key as i32,
+            now,
+            input.request_timeout_secs.map(|secs| secs as i64),
+        ],
+    )?;
+    get_provider(conn, &input.id)
+}
+
+/// Replace the definition of an existing custom provider.
+pub fn update_provider(
+    conn: &rusqlite::Connection,
+    input: CustomProviderInput,
+) -> Result<AiProviderInfo> {
+    validate_custom_provider(&input)?;
+    ensure_custom(conn, &input.id)?;
+    conn.execute(
+        "UPDATE ai_providers SET kind = ?2, display_name = ?3, description = ?4, base_url = ?5, default_model = ?6,
+                models_json = ?7, capabilities_json = ?8, requires_api_key = ?9, updated_at = ?10,
+                request_timeout_secs = ?11
+         WHERE id = ?1",
+        params![
+            input.id,
+            input.kind,
+            input.display_name,
+            input.description,
+            input.base_url,
+            input.default_model.clone().or_else(|| input.models.first().cloned()),
+            serde_json::to_string(&input.models)?,
+            serde_json::to_string(&input.capability_tags)?,
+            input.requires_api_key as i32,
+            OffsetDateTime::now_utc().unix_timestamp(),
+            input.request_timeout_secs.map(|secs| secs as i64),
+        ],
+    )?;
+    get_provider(conn, &input.id)
+}
+
+/// Delete a custom provider along with its credentials.
+///
+/// When the provider was active the selection falls back to the first
+/// built-in provider.
+pub fn remove_provider(conn: &rusqlite::Connection, provider_id: &str) -> Result<()> {
+    ensure_custom(conn, provider_id)?;
+    conn.execute(
+        "DELETE FROM ai_credentials WHERE provider_id = ?1",
+        params![provider_id],
+    )?;
+    conn.execute(
+        "DELETE FROM model_context WHERE provider_id = ?1",
+        params![provider_id],
+    )?;
+    conn.execute(
+        "DELETE FROM ai_providers WHERE id = ?1",
+        params![provider_id],
+    )?;
+
+    if active_provider_id(conn)?.as_deref() == Some(provider_id) {
+        let default_provider = PROVIDER_SEEDS
+            .first()
+            .ok_or_else(|| anyhow!("no providers seeded"))?;
+        set_active_setting(
+            conn,
+            default_provider.id,
+            Some(default_provider.default_model),
+        )?;
+    }
+    Ok(())
+}
+
+fn validate_custom_provider(input: &CustomProviderInput) -> Result<()> {
+    let valid_id = !input.id.is_empty()
+        && input
+            .id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid_id {
+        return Err(validation(
+            "Provider id must be lowercase letters, digits, '-' or '_'",
+        ));
+    }
+    if !matches!(input.kind.as_str(), "cloud" | "local" | AZURE_OPENAI_KIND) {
+        return Err(validation(
+            "Provider kind must be 'cloud', 'local' or 'azure-openai'",
+        ));
+    }
+    if input.display_name.trim().is_empty() {
+        return Err(validation("Provider display name is required"));
+    }
+    if !input.base_url.starts_with("http://") && !input.base_url.starts_with("https://") {
+        return Err(validation(
+            "Provider base URL must start with http:// or https://",
+        ));
+    }
+    if input.models.is_empty() && input.default_model.is_none() {
+        return Err(validation("Provider needs at least one model"));
+    }
+    if input.kind == AZURE_OPENAI_KIND {
+        validate_azure_base_url(&input.base_url, &input.capability_tags)?;
+    }
+    Ok(())
+}
+
+/// Provider kind for Azure OpenAI resources. Their models are deployment
+/// names and requests carry an `api-version`.
+pub const AZURE_OPENAI_KIND: &str = "azure-openai";
+const API_VERSION_TAG_PREFIX: &str = "api-version-";
+
+/// The Azure `api-version`: the base URL's `api-version` query parameter,
+/// else an `api-version-<version>` capability tag.
+pub fn azure_api_version(base_url: &str, tags: &[String]) -> Option<String> {
+    reqwest::Url::parse(base_url)
+        .ok()
+        .and_then(|url| {
+            url.query_pairs()
+                .find(|(key, _)| key == "api-version")
+                .map(|(_, version)| version.into_owned())
+        })
+        .or_else(|| {
+            tags.iter()
+                .find_map(|tag| tag.strip_prefix(API_VERSION_TAG_PREFIX))
+                .map(str::to_string)
+        })
+        .filter(|version| !version.is_empty())
+}
+
+/// Check an Azure base URL has the shape
+/// `https://<resource>.openai.azure.com[?api-version=<version>]` and that an
+/// `api-version` is known.
+fn validate_azure_base_url(base_url: &str, tags: &[String]) -> Result<()> {
+    let url = reqwest::Url::parse(base_url)
+        .map_err(|err| validation(&format!("Azure OpenAI base URL is invalid: {err}")))?;
+    if url.scheme() != "https" || url.host_str().is_none() {
+        return Err(validation(
+            "Azure OpenAI base URL must be https://<resource>.openai.azure.com",
+        ));
+    }
+    if azure_api_version(base_url, tags).is_none() {
+        return Err(validation(
+            "Azure OpenAI needs an api-version: add ?api-version=<version> to the base URL or an 'api-version-<version>' tag",
+        ));
+    }
+    Ok(())
+}
+
+fn validation(message: &str) -> anyhow::Error {
+    InkOsError::ValidationFailed(message.to_string()).into()
+}
+
+fn ensure_custom(conn: &rusqlite::Connection, provider_id: &str) -> Result<()> {
+    if !get_provider(conn, provider_id)?.is_custom {
+        return Err(validation(&format!(
+            "Built-in provider '{provider_id}' cannot be modified or removed"
+        )));
+    }
+    Ok(())
+}
+
 /// Determine which provider/model/secret should be used for a request.
 pub fn resolve_runtime(
     conn: &rusqlite::Connection,
     provider_override: Option<String>,
     model_override: Option<String>,
+) -> Result<AiRuntimeSelection> {
+    resolve_runtime_with(conn, provider_override, model_override, process_env)
+}
+
+/// [`resolve_runtime`] reading environment keys through `env`.
+pub fn resolve_runtime_with(
+    conn: &rusqlite::Connection,
+    provider_override: Option<String>,
+    model_override: Option<String>,
+    env: EnvLookup,
 ) -> Result<AiRuntimeSelection> {
     let (active_provider_id, active_model) = read_active_setting(conn)?;
 
     let provider_id = provider_override
-        .or(active_provider_id)
-        .ok_or_else(|| anyhow!("No AI provider configured"))?;
+        .or_else(|| active_provider_id.clone())
+        .ok_or(InkOsError::ProviderNotConfigured)?;
 
     let provider = get_provider(conn, &provider_id)?;
-    let mut model = model_override
-        .or_else(|| {
-            if provider.id == provider_id {
+    let fallback_model = provider
+        .default_model
+        .clone()
+        .or_else(|| provider.models.first().cloned());
+    let model = match model_override {
+        // An explicit model must be one the provider offers; providers that
+        // list no models accept any name.
+        Some(model) => {
+            if !provider.models.is_empty() && !provider.models.contains(&model) {
+                return Err(InkOsError::ModelNotFound {
+                    model,
+                    available: provider.models.clone(),
+                }
+                .into());
+            }
+            model
+        }
+        None => {
+            let model = if active_provider_id.as_deref() == Some(provider.id.as_str()) {
                 active_model.clone()
             } else {
                 None
             }
-        })
-        .or_else(|| provider.default_model.clone())
-        .or_else(|| provider.models.first().cloned())
-        .ok_or_else(|| anyhow!("No model configured for provider"))?;
-
-    // allow override to reference canonical names stored in models list
-    if !provider.models.is_empty() && !provider.models.contains(&model) {
-        model = provider.models.first().cloned().unwrap_or(model);
-    }
+            .or_else(|| fallback_model.clone())
+            .ok_or_else(|| anyhow!("No model configured for provider"))?;
+            // A stale active model falls back to the provider default.
+            if !provider.models.is_empty() && !provider.models.contains(&model) {
+                fallback_model.unwrap_or(model)
+            } else {
+                model
+            }
+        }
+    };
 
-    let secret = load_secret(conn, &provider.id)?;
+    let (secret, secret_source) = resolve_secret(conn, &provider.id, env)?;
 
     Ok(AiRuntimeSelection {
         provider,
         model,
         secret,
+        secret_source,
+    })
+}
+
+/// Stored credential for a provider, falling back to its environment key.
+fn resolve_secret(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    env: EnvLookup,
+) -> Result<(Option<String>, Option<SecretSource>)> {
+    Ok(match load_secret(conn, provider_id)? {
+        Some(secret) => (Some(secret), Some(SecretSource::Stored)),
+        None => match env_secret(provider_id, env) {
+            Some(secret) => (Some(secret), Some(SecretSource::Environment)),
+            None => (None, None),
+        },
     })
 }
 
+/// Setting key holding the embedding provider/model pair.
+const EMBEDDING_KEY: &str = "ai.embedding";
+
+/// Pick the provider/model used for note embeddings.
+///
+/// An explicit `ai.embedding` setting wins; otherwise the active provider's
+/// bundled embedding model is used. Returns `None` when neither applies, in
+/// which case semantic search falls back to full-text search.
+pub fn resolve_embedding_runtime(
+    conn: &rusqlite::Connection,
+) -> Result<Option<AiRuntimeSelection>> {
+    resolve_embedding_runtime_with(conn, process_env)
+}
+
+/// [`resolve_embedding_runtime`] reading environment keys through `env`.
+pub fn resolve_embedding_runtime_with(
+    conn: &rusqlite::Connection,
+    env: EnvLookup,
+) -> Result<Option<AiRuntimeSelection>> {
+    let configured: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![EMBEDDING_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let (provider_id, model) = match configured {
+        Some(raw) => {
+            let data: serde_json::Value = serde_json::from_str(&raw)?;
+            let provider_id = data.get("provider_id").and_then(|v| v.as_str());
+            let model = data.get("model").and_then(|v| v.as_str());
+            match (provider_id, model) {
+                (Some(provider_id), Some(model)) => (provider_id.to_string(), model.to_string()),
+                _ => return Ok(None),
+            }
+        }
+        None => {
+            let Some(provider_id) = active_provider_id(conn)? else {
+                return Ok(None);
+            };
+            let seeded = PROVIDER_SEEDS
+                .iter()
+                .find(|seed| seed.id == provider_id)
+                .and_then(|seed| seed.embedding_model);
+            match seeded {
+                Some(model) => (provider_id, model.to_string()),
+                None => return Ok(None),
+            }
+        }
+    };
+    let provider = get_provider(conn, &provider_id)?;
+    let (secret, secret_source) = resolve_secret(conn, &provider.id, env)?;
+    Ok(Some(AiRuntimeSelection {
+        provider,
+        model,
+        secret,
+        secret_source,
+    }))
+}
+
+/// Store or clear (`None`) the provider/model used for note embeddings.
+pub fn set_embedding_model(
+    conn: &rusqlite::Connection,
+    selection: Option<(&str, &str)>,
+) -> Result<()> {
+    match selection {
+        Some((provider_id, model)) => {
+            get_provider(conn, provider_id)?;
+            if model.trim().is_empty() {
+                return Err(validation("Embedding model must not be empty"));
+            }
+            let payload = json!({ "provider_id": provider_id, "model": model }).to_string();
+            conn.execute(
+                "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                params![
+                    EMBEDDING_KEY,
+                    payload,
+                    OffsetDateTime::now_utc().unix_timestamp()
+                ],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM app_settings WHERE key = ?1",
+                params![EMBEDDING_KEY],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Id of the provider currently selected in settings, if any.
+pub fn active_provider_id(conn: &rusqlite::Connection) -> Result<Option<String>> {
+    Ok(read_active_setting(conn)?.0)
+}
+
 /// Read the active provider/model pair from `app_settings`.
 fn read_active_setting(conn: &rusqlite::Connection) -> Result<(Option<String>, Option<String>)> {
     let value: Option<String> = conn
@@ -288,35 +683,220 @@ fn set_active_setting(
     Ok(())
 }
 
+/// Look up the stored context window override for a provider/model pair.
+pub fn model_context_window(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    model_id: &str,
+) -> Result<Option<usize>> {
+    let window: Option<i64> = conn
+        .query_row(
+            "SELECT context_window FROM model_context WHERE provider_id = ?1 AND model_id = ?2",
+            params![provider_id, model_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(window.map(|w| w as usize))
+}
+
+/// Store or clear (`None`) the context window override for a model.
+pub fn set_model_context_window(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    model_id: &str,
+    context_window: Option<usize>,
+) -> Result<()> {
+    get_provider(conn, provider_id)?;
+    match context_window {
+        Some(window) => {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            conn.execute(
+                "INSERT INTO model_context (provider_id, model_id, context_window, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(provider_id, model_id) DO UPDATE SET
+                     context_window = excluded.context_window,
+                     updated_at = excluded.updated_at",
+                params![provider_id, model_id, window as i64, now],
+            )?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM model_context WHERE provider_id = ?1 AND model_id = ?2",
+                params![provider_id, model_id],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Store or clear (`None`) the HTTP timeout used for a provider's requests.
+///
+/// Clearing a built-in provider restores its seeded default on next start.
+pub fn set_provider_timeout(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    timeout_secs: Option<u64>,
+) -> Result<AiProviderInfo> {
+    get_provider(conn, provider_id)?;
+    if timeout_secs == Some(0) {
+        return Err(validation("Request timeout must be at least one second"));
+    }
+    conn.execute(
+        "UPDATE ai_providers SET request_timeout_secs = ?2, updated_at = ?3 WHERE id = ?1",
+        params![
+            provider_id,
+            timeout_secs.map(|secs| secs as i64),
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    get_provider(conn, provider_id)
+}
+
+/// Store a provider's default temperature and top-p. `None` restores the
+/// bundled default for built-in providers and clears it for custom ones.
+pub fn set_provider_sampling(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    default_temperature: Option<f32>,
+    default_top_p: Option<f32>,
+) -> Result<AiProviderInfo> {
+    get_provider(conn, provider_id)?;
+    if default_temperature.is_some_and(|t| !(0.0..=2.0).contains(&t)) {
+        return Err(validation("Default temperature must be between 0 and 2"));
+    }
+    if default_top_p.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+        return Err(validation(
+            "Default top_p must be greater than 0 and at most 1",
+        ));
+    }
+    conn.execute(
+        "UPDATE ai_providers SET default_temperature = ?2, default_top_p = ?3, updated_at = ?4 WHERE id = ?1",
+        params![
+            provider_id,
+            default_temperature.map(f64::from),
+            default_top_p.map(f64::from),
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    // Built-in providers get their bundled values back, as on startup.
+    seed_sampling_defaults(conn, provider_id)?;
+    get_provider(conn, provider_id)
+}
+
+fn seed_sampling_defaults(conn: &rusqlite::Connection, provider_id: &str) -> Result<()> {
+    if let Some(seed) = PROVIDER_SEEDS.iter().find(|seed| seed.id == provider_id) {
+        conn.execute(
+            "UPDATE ai_providers SET
+                 default_temperature = COALESCE(default_temperature, ?2),
+                 default_top_p = COALESCE(default_top_p, ?3)
+             WHERE id = ?1 AND is_custom = 0",
+            params![
+                provider_id,
+                seed.default_temperature.map(f64::from),
+                seed.default_top_p.map(f64::from)
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Structured capabilities of a provider, derived from its tags and kind.
+pub fn provider_capabilities(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+) -> Result<Capabilities> {
+    Ok(Capabilities::for_provider(&get_provider(
+        conn,
+        provider_id,
+    )?))
+}
+
+/// Store or clear (`None`) a provider's requests-per-minute limit.
+pub fn set_provider_rate_limit(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    rate_limit_rpm: Option<u32>,
+) -> Result<AiProviderInfo> {
+    get_provider(conn, provider_id)?;
+    if rate_limit_rpm == Some(0) {
+        return Err(validation(
+            "Rate limit must allow at least one request per minute",
+        ));
+    }
+    conn.execute(
+        "UPDATE ai_providers SET rate_limit_rpm = ?2, updated_at = ?3 WHERE id = ?1",
+        params![
+            provider_id,
+            rate_limit_rpm.map(i64::from),
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    get_provider(conn, provider_id)
+}
+
+/// Replace the extra headers sent with a provider's requests.
+pub fn set_provider_headers(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    headers: BTreeMap<String, String>,
+) -> Result<AiProviderInfo> {
+    get_provider(conn, provider_id)?;
+    for name in headers.keys() {
+        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+            return Err(validation(&format!("'{name}' is not a valid header name")));
+        }
+    }
+    conn.execute(
+        "UPDATE ai_providers SET headers_json = ?2, updated_at = ?3 WHERE id = ?1",
+        params![
+            provider_id,
+            serde_json::to_string(&headers)?,
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    get_provider(conn, provider_id)
+}
+
 /// Load a single provider row or return an error when missing.
 fn get_provider(conn: &rusqlite::Connection, provider_id: &str) -> Result<AiProviderInfo> {
     conn.query_row(
-        "SELECT p.id, p.kind, p.display_name, p.description, p.base_url, p.default_model, p.models_json, p.capabilities_json, p.requires_api_key,
-                (SELECT COUNT(1) FROM ai_credentials c WHERE c.provider_id = p.id) as has_secret
-         FROM ai_providers p WHERE p.id = ?1",
+        &format!("{PROVIDER_SELECT} WHERE p.id = ?1"),
         params![provider_id],
-        |row| {
-            let models_json: String = row.get(6)?;
-            let caps_json: String = row.get(7)?;
-            let models: Vec<String> = serde_json::from_str(&models_json).unwrap_or_default();
-            let caps: Vec<String> = serde_json::from_str(&caps_json).unwrap_or_default();
-            Ok(AiProviderInfo {
-                id: row.get(0)?,
-                kind: row.get(1)?,
-                display_name: row.get(2)?,
-                description: row.get(3)?,
-                base_url: row.get(4)?,
-                default_model: row.get(5)?,
-                models,
-                capability_tags: caps,
-                requires_api_key: row.get::<_, i64>(8)? != 0,
-                has_credentials: row.get::<_, i64>(9)? > 0,
-            })
-        },
+        map_provider_row,
     )
     .map_err(|_| anyhow!("Unknown AI provider: {provider_id}"))
 }
 
+/// Column list shared by every provider read; see [`map_provider_row`].
+const PROVIDER_SELECT: &str = "SELECT p.id, p.kind, p.display_name, p.description, p.base_url, p.default_model, p.models_json, p.capabilities_json, p.requires_api_key,
+        (SELECT COUNT(1) FROM ai_credentials c WHERE c.provider_id = p.id) as has_secret, p.context_window, p.is_custom, p.request_timeout_secs, p.headers_json, p.rate_limit_rpm,
+        p.default_temperature, p.default_top_p
+    FROM ai_providers p";
+
+fn map_provider_row(row: &rusqlite::Row) -> rusqlite::Result<AiProviderInfo> {
+    let models_json: String = row.get(6)?;
+    let caps_json: String = row.get(7)?;
+    Ok(AiProviderInfo {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        display_name: row.get(2)?,
+        description: row.get(3)?,
+        base_url: row.get(4)?,
+        default_model: row.get(5)?,
+        models: serde_json::from_str(&models_json).unwrap_or_default(),
+        capability_tags: serde_json::from_str(&caps_json).unwrap_or_default(),
+        requires_api_key: row.get::<_, i64>(8)? != 0,
+        has_credentials: row.get::<_, i64>(9)? > 0,
+        context_window: row.get::<_, Option<i64>>(10)?.map(|w| w as usize),
+        is_custom: row.get::<_, i64>(11)? != 0,
+        request_timeout_secs: row.get::<_, Option<i64>>(12)?.map(|secs| secs as u64),
+        headers: serde_json::from_str(&row.get::<_, String>(13)?).unwrap_or_default(),
+        rate_limit_rpm: row.get::<_, Option<i64>>(14)?.map(|rpm| rpm as u32),
+        default_temperature: row.get::<_, Option<f64>>(15)?.map(|t| t as f32),
+        default_top_p: row.get::<_, Option<f64>>(16)?.map(|p| p as f32),
+    })
+}
+
 /// Fetch and decrypt the stored API secret, if present.
 fn load_secret(conn: &rusqlite::Connection, provider_id: &str) -> Result<Option<String>> {
     let secret: Option<String> = conn
@@ -339,6 +919,43 @@ fn load_secret(conn: &rusqlite::Connection, provider_id: &str) -> Result<Option<
     }
 }
 
+/// Environment variable consulted when no credential is stored, e.g.
+/// `INKOS_OPENAI_API_KEY` or `INKOS_MY_GATEWAY_API_KEY` for `my-gateway`.
+pub fn env_key_var(provider_id: &str) -> String {
+    let id: String = provider_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("INKOS_{id}_API_KEY")
+}
+
+/// Reads an environment variable. Passed in so tests can supply keys
+/// without touching the shared process environment.
+pub type EnvLookup = fn(&str) -> Option<String>;
+
+/// [`EnvLookup`] backed by the process environment.
+pub fn process_env(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+
+fn env_secret(provider_id: &str, env: EnvLookup) -> Option<String> {
+    env(&env_key_var(provider_id))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// Whether a request to `provider` would have a key: a stored credential
+/// or its `INKOS_<PROVIDER>_API_KEY` environment variable.
+pub fn has_secret(provider: &AiProviderInfo, env: EnvLookup) -> bool {
+    provider.has_credentials || env_secret(&provider.id, env).is_some()
+}
+
 /// Emit a structured log entry describing a settings change.
 pub fn audit_settings_change(conn: &rusqlite::Connection, message: &str) {
     let _ = log_event(
@@ -351,3 +968,154 @@ pub fn audit_settings_change(conn: &rusqlite::Connection, message: &str) {
         None,
     );
 }
+
+/// Make Ollama, served at `base_url`, the active provider.
+#[cfg(test)]
+pub(crate) fn use_ollama_at(conn: &rusqlite::Connection, base_url: String) {
+    seed_defaults(conn).unwrap();
+    update_settings(
+        conn,
+        AiSettingsUpdate {
+            provider_id: "ollama".into(),
+            model: None,
+            api_key: None,
+            base_url: Some(base_url),
+        },
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_keys_fill_in_missing_credentials_without_persisting() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        add_provider(
+            &conn,
+            CustomProviderInput {
+                id: "env-gateway".into(),
+                kind: "cloud".into(),
+                display_name: "Env Gateway".into(),
+                description: None,
+                base_url: "http://localhost:9999/v1".into(),
+                default_model: Some("m1".into()),
+                models: vec!["m1".into()],
+                capability_tags: Vec::new(),
+                requires_api_key: true,
+                request_timeout_secs: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(env_key_var("env-gateway"), "INKOS_ENV_GATEWAY_API_KEY");
+        let env: EnvLookup = |var| (var == "INKOS_ENV_GATEWAY_API_KEY").then(|| "sk-env".into());
+        let selection = resolve_runtime_with(&conn, Some("env-gateway".into()), None, env).unwrap();
+        assert_eq!(selection.secret.as_deref(), Some("sk-env"));
+        assert_eq!(selection.secret_source, Some(SecretSource::Environment));
+
+        set_embedding_model(&conn, Some(("env-gateway", "m1"))).unwrap();
+        let embedding = resolve_embedding_runtime_with(&conn, env).unwrap().unwrap();
+        assert_eq!(embedding.secret.as_deref(), Some("sk-env"));
+        assert_eq!(embedding.secret_source, Some(SecretSource::Environment));
+
+        let stored: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ai_credentials", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, 0);
+    }
+
+    #[test]
+    fn unknown_model_overrides_are_rejected() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        add_provider(
+            &conn,
+            CustomProviderInput {
+                id: "gateway".into(),
+                kind: "local".into(),
+                display_name: "Gateway".into(),
+                description: None,
+                base_url: "http://localhost:9999/v1".into(),
+                default_model: Some("m1".into()),
+                models: vec!["m1".into(), "m2".into()],
+                capability_tags: Vec::new(),
+                requires_api_key: false,
+                request_timeout_secs: None,
+            },
+        )
+        .unwrap();
+
+        let err = resolve_runtime(&conn, Some("gateway".into()), Some("m3".into())).unwrap_err();
+        match err.downcast_ref::<InkOsError>() {
+            Some(InkOsError::ModelNotFound { model, available }) => {
+                assert_eq!(model, "m3");
+                assert_eq!(available, &["m1", "m2"]);
+            }
+            other => panic!("expected ModelNotFound, got {other:?}"),
+        }
+        let chosen = resolve_runtime(&conn, Some("gateway".into()), Some("m2".into())).unwrap();
+        assert_eq!(chosen.model, "m2");
+        let default = resolve_runtime(&conn, Some("gateway".into()), None).unwrap();
+        assert_eq!(default.model, "m1");
+    }
+
+    #[test]
+    fn azure_providers_need_an_api_version() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        let azure = |base_url: &str, tags: &[&str]| CustomProviderInput {
+            id: "contoso".into(),
+            kind: AZURE_OPENAI_KIND.into(),
+            display_name: "Contoso Azure".into(),
+            description: None,
+            base_url: base_url.into(),
+            default_model: Some("gpt4o-prod".into()),
+            models: vec!["gpt4o-prod".into()],
+            capability_tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            requires_api_key: true,
+            request_timeout_secs: None,
+        };
+        assert!(add_provider(&conn, azure("https://contoso.openai.azure.com", &[])).is_err());
+        assert!(add_provider(
+            &conn,
+            azure(
+                "http://contoso.openai.azure.com?api-version=2024-10-21",
+                &[]
+            )
+        )
+        .is_err());
+        add_provider(
+            &conn,
+            azure(
+                "https://contoso.openai.azure.com?api-version=2024-10-21",
+                &[],
+            ),
+        )
+        .unwrap();
+
+        let update = |base_url: &str| AiSettingsUpdate {
+            provider_id: "contoso".into(),
+            model: None,
+            api_key: None,
+            base_url: Some(base_url.into()),
+        };
+        assert!(update_settings(&conn, update("https://contoso.openai.azure.com")).is_err());
+        update_settings(
+            &conn,
+            update("https://fabrikam.openai.azure.com?api-version=2024-06-01"),
+        )
+        .unwrap();
+        let provider = get_provider(&conn, "contoso").unwrap();
+        assert_eq!(
+            azure_api_version(
+                provider.base_url.as_deref().unwrap(),
+                &provider.capability_tags
+            )
+            .as_deref(),
+            Some("2024-06-01")
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Detection of AI runtimes served from the local machine.
+//!
+//! Ollama and LM Studio both expose a cheap model listing endpoint, so a
+//! short probe tells us whether each one is running and which models it can
+//! serve without the user having to configure anything first.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::config::{self, AiProviderInfo, AiSettingsSnapshot, AiSettingsUpdate};
+
+/// How long a single probe may take before the endpoint counts as down.
+pub const PROBE_TIMEOUT: Duration = Duration::from_millis(1_500);
+
+const OLLAMA_ID: &str = "ollama";
+const LMSTUDIO_ID: &str = "lmstudio";
+const OLLAMA_DEFAULT_URL: &str = "http://127.0.0.1:11434";
+const LMSTUDIO_DEFAULT_URL: &str = "http://127.0.0.1:1234/v1";
+
+/// Outcome of probing one local runtime.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalRuntime {
+    pub provider_id: String,
+    pub base_url: String,
+    pub reachable: bool,
+    /// Models the runtime reported; empty when it is unreachable.
+    pub models: Vec<String>,
+}
+
+/// Base URLs to probe, preferring what is stored on the provider rows.
+pub fn probe_targets(providers: &[AiProviderInfo]) -> Vec<(String, String)> {
+    [
+        (OLLAMA_ID, OLLAMA_DEFAULT_URL),
+        (LMSTUDIO_ID, LMSTUDIO_DEFAULT_URL),
+    ]
+    .into_iter()
+    .map(|(id, fallback)| {
+        let base_url = providers
+            .iter()
+            .find(|provider| provider.id == id)
+            .and_then(|provider| provider.base_url.as_deref())
+            .unwrap_or(fallback)
+            .trim_end_matches('/')
+            .to_string();
+        (id.to_string(), base_url)
+    })
+    .collect()
+}
+
+/// Probe every target concurrently. Failures mark a runtime unreachable
+/// rather than erroring, so the call always reports every target.
+pub async fn probe_all(
+    client: &reqwest::Client,
+    targets: Vec<(String, String)>,
+) -> Vec<LocalRuntime> {
+    let probes = targets
+        .into_iter()
+        .map(|(provider_id, base_url)| async move {
+            let models = probe(client, &provider_id, &base_url).await;
+            LocalRuntime {
+                reachable: models.is_some(),
+                models: models.unwrap_or_default(),
+                provider_id,
+                base_url,
+            }
+        });
+    futures_util::future::join_all(probes).await
+}
+
+async fn probe(client: &reqwest::Client, provider_id: &str, base_url: &str) -> Option<Vec<String>> {
+    let (url, list_key, name_key) = if provider_id == OLLAMA_ID {
+        (format!("{base_url}/api/tags"), "models", "name")
+    } else {
+        (format!("{base_url}/models"), "data", "id")
+    };
+    let response = client
+        .get(url)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    let body: Value = response.json().await.ok()?;
+    let models = body
+        .get(list_key)?
+        .as_array()?
+        .iter()
+        .filter_map(|entry| entry.get(name_key).and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+    Some(models)
+}
+
+/// Switch to the first reachable local runtime when no cloud provider has a
+/// key and the active provider is a cloud one that could not answer anyway.
+/// Environment keys, read through `env`, count as keys.
+///
+/// A discovered model is only selected when the provider already lists it
+/// (ignoring Ollama's `:latest` tag); otherwise the provider default is
+/// kept. Returns the new settings when a switch happened.
+pub fn select_if_unconfigured(
+    conn: &rusqlite::Connection,
+    runtimes: &[LocalRuntime],
+    env: config::EnvLookup,
+) -> Result<Option<AiSettingsSnapshot>> {
+    let providers = config::list_providers(conn)?;
+    let is_cloud = |provider: &AiProviderInfo| provider.kind != "local";
+    if providers
+        .iter()
+        .any(|provider| is_cloud(provider) && config::has_secret(provider, env))
+    {
+        return Ok(None);
+    }
+    let active_is_cloud = config::get_settings(conn)?
+        .provider
+        .as_ref()
+        .is_none_or(is_cloud);
+    if !active_is_cloud {
+        return Ok(None);
+    }
+
+    for runtime in runtimes.iter().filter(|runtime| runtime.reachable) {
+        let Some(provider) = providers.iter().find(|p| p.id == runtime.provider_id) else {
+            continue;
+        };
+        let model = runtime
+            .models
+            .iter()
+            .map(|model| model.strip_suffix(":latest").unwrap_or(model))
+            .find(|model| provider.models.iter().any(|known| known == model))
+            .map(str::to_string);
+        let snapshot = config::update_settings(
+            conn,
+            AiSettingsUpdate {
+                provider_id: provider.id.clone(),
+                model,
+                api_key: None,
+                base_url: None,
+            },
+        )?;
+        return Ok(Some(snapshot));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn serve_once(body: &'static str) -> String {
+        crate::mock_provider::serve(move |index, _| (index == 0).then(|| ("200 OK", body.into())))
+    }
+
+    #[tokio::test]
+    async fn probes_report_models_and_unreachable_endpoints() {
+        let ollama = serve_once(r#"{"models":[{"name":"llama3.1:latest"},{"name":"phi3"}]}"#);
+        let lmstudio = {
+            // Bind then drop so the port is closed when probed.
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}/v1", listener.local_addr().unwrap())
+        };
+        let client = reqwest::Client::new();
+        let runtimes = probe_all(
+            &client,
+            vec![
+                (OLLAMA_ID.to_string(), ollama),
+                (LMSTUDIO_ID.to_string(), lmstudio),
+            ],
+        )
+        .await;
+
+        assert!(runtimes[0].reachable);
+        assert_eq!(runtimes[0].models, vec!["llama3.1:latest", "phi3"]);
+        assert!(!runtimes[1].reachable);
+        assert!(runtimes[1].models.is_empty());
+    }
+
+    #[test]
+    fn environment_keys_keep_the_cloud_provider_selected() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        config::seed_defaults(&conn).unwrap();
+        let runtimes = [LocalRuntime {
+            provider_id: OLLAMA_ID.into(),
+            base_url: OLLAMA_DEFAULT_URL.into(),
+            reachable: true,
+            models: vec!["llama3.1:latest".into()],
+        }];
+
+        let env: config::EnvLookup = |var| (var == "INKOS_OPENAI_API_KEY").then(|| "sk-env".into());
+        assert!(select_if_unconfigured(&conn, &runtimes, env)
+            .unwrap()
+            .is_none());
+
+        let selected = select_if_unconfigured(&conn, &runtimes, |_| None)
+            .unwrap()
+            .expect("switched to the local runtime");
+        assert_eq!(selected.active_provider_id.as_deref(), Some(OLLAMA_ID));
+    }
+}
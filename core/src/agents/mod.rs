@@ -1,12 +1,20 @@
 //! AI subsystem glue code.
 //!
 //! `config` owns persistence of provider metadata and secrets, `providers`
-//! defines the baked-in seeds, and `orchestrator` executes chat completions
-//! against the selected runtime.
+//! defines the baked-in seeds, `capabilities` interprets provider tags,
+//! `discovery` probes locally running runtimes, and `orchestrator` executes
+//! chat completions against the selected runtime.
 
+pub mod capabilities;
 pub mod config;
+pub mod discovery;
 pub mod orchestrator;
 pub mod providers;
 
+pub use capabilities::Capabilities;
 pub use config::{AiProviderInfo, AiRuntimeSelection, AiSettingsSnapshot};
-pub use orchestrator::{AiChatInput, AiChatMessage, AiChatResponse, AiOrchestrator};
+pub use discovery::LocalRuntime;
+pub use orchestrator::{
+    AiChatInput, AiChatMessage, AiChatResponse, AiImage, AiOrchestrator, AiUsageMetrics,
+    OrchestratorConfig, OrchestratorError, PullProgress, ReasoningEffort, ResponseFormat,
+};
@@ -1,23 +1,37 @@
 //! HTTP chat orchestration across multiple AI providers.
 //!
 //! The orchestrator exposes a single `chat` method that fan-outs to provider
-//! specific HTTP APIs (OpenAI, Anthropic, Gemini, Ollama, LM Studio). All
+//! specific HTTP APIs (OpenAI, Anthropic, Gemini, Cohere, Ollama, LM Studio). All
 //! responses are normalised into a consistent structure for the UI layer.
 
+use std::fmt;
 use std::time::Duration;
 
-use anyhow::{anyhow, Context, Result};
-use reqwest::Client;
+use anyhow::{Context, Result};
+use reqwest::{Client, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use thiserror::Error;
 
-use super::config::AiRuntimeSelection;
+use super::capabilities::{speaks_ollama, speaks_openai, Capabilities};
+use super::config::{azure_api_version, AiProviderInfo, AiRuntimeSelection, AZURE_OPENAI_KIND};
+use crate::redact::{redact_text, strip_auth_fields};
 
 /// Canonical representation of a chat message fed into an AI provider.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiChatMessage {
     pub role: String,
     pub content: String,
+    /// Images sent alongside `content` to providers tagged `vision`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<AiImage>,
+}
+
+/// Base64-encoded image attached to a chat message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiImage {
+    pub mime_type: String,
+    pub data: String,
 }
 
 /// Request payload given to [`AiOrchestrator::chat`].
@@ -25,6 +39,70 @@ pub struct AiChatMessage {
 pub struct AiChatInput {
     pub messages: Vec<AiChatMessage>,
     pub temperature: Option<f32>,
+    /// Structured output mode; the reply is validated before it is returned.
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+    /// Sequences that end generation. Omitted from the payload when unset.
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    /// Nucleus sampling mass. Omitted from the payload when unset.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Cap on generated tokens. Anthropic requires one and defaults to
+    /// [`ANTHROPIC_DEFAULT_MAX_TOKENS`]; elsewhere it is omitted when unset.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Extended thinking for Anthropic and reasoning models on
+    /// OpenAI-compatible APIs. Other providers ignore it.
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Correlation id recorded with every log line for this request. The
+    /// model manager assigns one when the caller leaves it unset.
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+/// How much a model may reason before answering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+impl ReasoningEffort {
+    /// Value of OpenAI's `reasoning_effort` parameter.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+
+    /// Anthropic `budget_tokens`; 1024 is the smallest budget it accepts.
+    fn thinking_budget(self) -> u32 {
+        match self {
+            Self::Low => 1024,
+            Self::Medium => 4096,
+            Self::High => 16_384,
+        }
+    }
+}
+
+/// `max_tokens` sent to Anthropic when the request does not set one.
+pub const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Output constraint requested from the provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Strict JSON, optionally matching a JSON Schema.
+    Json {
+        #[serde(default)]
+        schema: Option<Value>,
+    },
 }
 
 /// Usage metrics reported by certain providers.
@@ -43,6 +121,306 @@ pub struct AiChatResponse {
     pub content: String,
     pub usage: Option<AiUsageMetrics>,
     pub raw: Value,
+    /// Served from the response cache rather than the provider.
+    #[serde(default)]
+    pub cached: bool,
+    /// Thinking or reasoning text the provider returned apart from the
+    /// answer, when reasoning was requested.
+    #[serde(default)]
+    pub reasoning: Option<String>,
+    /// Correlation id of the request that produced this reply.
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+/// Classified failure from a provider call.
+///
+/// Variants mirror the remediation the user needs, so the UI can tell a bad
+/// API key apart from an unreachable endpoint.
+#[derive(Debug, Error)]
+pub enum OrchestratorError {
+    #[error("{0}")]
+    Auth(Failure),
+    #[error("{message}")]
+    RateLimited {
+        message: Failure,
+        retry_after_secs: Option<u64>,
+        /// Raised by InkOS's own per-provider limiter rather than the
+        /// provider, so it says nothing about the provider's health.
+        local: bool,
+    },
+    #[error("{0}")]
+    Timeout(Failure),
+    #[error("{0}")]
+    Network(Failure),
+    #[error("{0}")]
+    BadRequest(Failure),
+    #[error("{0}")]
+    ServerError(Failure),
+    #[error("{0}")]
+    Unsupported(Failure),
+    #[error("{0}")]
+    Decode(Failure),
+    #[error("{0}")]
+    InvalidJson(Failure),
+    #[error("{0}")]
+    BadResponse(Failure),
+    #[error("{0}")]
+    ModelNotFound(Failure),
+}
+
+/// Message of an [`OrchestratorError`], with the provider's reply when the
+/// failure came from an HTTP response.
+#[derive(Debug)]
+pub struct Failure {
+    message: String,
+    response: Option<Box<FailedResponse>>,
+}
+
+impl Failure {
+    fn with_response(message: String, status: StatusCode, body: &str) -> Self {
+        let body = serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()));
+        Self {
+            message,
+            response: Some(Box::new(FailedResponse {
+                status: status.as_u16(),
+                body,
+            })),
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            response: None,
+        }
+    }
+}
+
+impl From<&str> for Failure {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// A provider reply that was not a success, kept for the raw capture log.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedResponse {
+    pub status: u16,
+    /// The body as JSON when it parses, otherwise as text.
+    pub body: Value,
+}
+
+impl OrchestratorError {
+    /// The provider's reply, when the failure came from an HTTP response.
+    pub fn response(&self) -> Option<&FailedResponse> {
+        let failure = match self {
+            Self::Auth(failure)
+            | Self::RateLimited {
+                message: failure, ..
+            }
+            | Self::Timeout(failure)
+            | Self::Network(failure)
+            | Self::BadRequest(failure)
+            | Self::ServerError(failure)
+            | Self::Unsupported(failure)
+            | Self::Decode(failure)
+            | Self::InvalidJson(failure)
+            | Self::BadResponse(failure)
+            | Self::ModelNotFound(failure) => failure,
+        };
+        failure.response.as_deref()
+    }
+
+    /// Stable code recorded in the event log and returned over IPC.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Auth(_) => "AI-1001",
+            Self::RateLimited { .. } => "AI-1002",
+            Self::Timeout(_) => "AI-1003",
+            Self::Network(_) => "AI-1004",
+            Self::BadRequest(_) => "AI-1005",
+            Self::ServerError(_) => "AI-1006",
+            Self::Unsupported(_) => "AI-1007",
+            Self::Decode(_) => "AI-1008",
+            Self::InvalidJson(_) => "AI-JSON-INVALID",
+            Self::BadResponse(_) => "AI-BAD-RESPONSE",
+            Self::ModelNotFound(_) => "AI-MODEL-MISSING",
+        }
+    }
+
+    /// Remediation hint suitable for the UI.
+    pub fn explain(&self) -> &'static str {
+        match self {
+            Self::Auth(_) => {
+                "The provider rejected the credentials. Check the API key in Settings."
+            }
+            Self::RateLimited { .. } => {
+                "The provider is throttling requests. Wait a moment or switch provider."
+            }
+            Self::Timeout(_) => {
+                "The provider did not answer in time. Try again or pick a faster model."
+            }
+            Self::Network(_) => {
+                "The provider could not be reached. Check the network connection or base URL."
+            }
+            Self::BadRequest(_) => {
+                "The provider refused the request. Check the model name and message contents."
+            }
+            Self::ServerError(_) => "The provider reported an internal error. Try again later.",
+            Self::Unsupported(_) => "This provider or feature is not supported by InkOS.",
+            Self::Decode(_) => "The provider returned a response InkOS could not read.",
+            Self::InvalidJson(_) => {
+                "The model was asked for JSON but replied with something else. Retry the request."
+            }
+            Self::BadResponse(_) => {
+                "The provider answered with a web page instead of an API response. Check the base URL."
+            }
+            Self::ModelNotFound(_) => {
+                "The model is not installed on this provider. Download it or pick another model."
+            }
+        }
+    }
+
+    /// Classify a transport-level failure from `reqwest`.
+    fn from_transport(provider_id: &str, err: reqwest::Error) -> Self {
+        // reqwest includes the URL, which carries Gemini's `?key=`.
+        let message = redact_text(&format!("{provider_id} request failed: {err}")).into();
+        if err.is_timeout() {
+            Self::Timeout(message)
+        } else if err.is_decode() || err.is_body() {
+            Self::Decode(message)
+        } else {
+            Self::Network(message)
+        }
+    }
+
+    /// Classify a non-success HTTP status, keeping a short body excerpt.
+    fn from_status(
+        provider_id: &str,
+        status: StatusCode,
+        retry_after_secs: Option<u64>,
+        body: &str,
+    ) -> Self {
+        let excerpt: String = error_detail(body).chars().take(300).collect();
+        let message = Failure::with_response(
+            redact_text(&format!("{provider_id} returned {status}: {excerpt}")),
+            status,
+            body,
+        );
+        if status.is_redirection() {
+            return Self::non_json(provider_id, status, body);
+        }
+        match status.as_u16() {
+            // Cohere answers an invalid token with 498.
+            401 | 403 | 498 => Self::Auth(message),
+            429 => Self::RateLimited {
+                message,
+                retry_after_secs,
+                local: false,
+            },
+            408 | 504 => Self::Timeout(message),
+            500..=599 => Self::ServerError(message),
+            _ => Self::BadRequest(message),
+        }
+    }
+
+    /// [`from_status`](Self::from_status) for a call to `provider`.
+    ///
+    /// Ollama answers an unknown model with a 404 naming it, which becomes
+    /// [`ModelNotFound`](Self::ModelNotFound) so it can be pulled. Other
+    /// providers' 404s stay rejected requests.
+    fn from_provider_status(
+        provider: &AiProviderInfo,
+        status: StatusCode,
+        retry_after_secs: Option<u64>,
+        body: &str,
+    ) -> Self {
+        let err = Self::from_status(&provider.id, status, retry_after_secs, body);
+        let lowered = error_detail(body).to_lowercase();
+        match err {
+            Self::BadRequest(message)
+                if speaks_ollama(provider)
+                    && status == StatusCode::NOT_FOUND
+                    && lowered.contains("model")
+                    && lowered.contains("not found") =>
+            {
+                Self::ModelNotFound(message)
+            }
+            err => err,
+        }
+    }
+
+    /// A body that is not JSON, typically an HTML login or proxy page served
+    /// from a misconfigured base URL.
+    fn non_json(provider_id: &str, status: StatusCode, body: &str) -> Self {
+        let snippet: String = body.trim().chars().take(120).collect();
+        Self::BadResponse(Failure::with_response(
+            redact_text(&format!(
+                "{provider_id} returned non-JSON (HTTP {}): {snippet}",
+                status.as_u16()
+            )),
+            status,
+            body,
+        ))
+    }
+}
+
+/// Parse a provider's response body, reporting non-JSON bodies with their
+/// status and a snippet instead of the bare serde error.
+fn parse_body(
+    provider_id: &str,
+    status: StatusCode,
+    body: &str,
+) -> Result<Value, OrchestratorError> {
+    serde_json::from_str(body).map_err(|_| OrchestratorError::non_json(provider_id, status, body))
+}
+
+/// The human-readable part of an error body.
+///
+/// Providers wrap the reason as `{"message": ...}` (Cohere) or
+/// `{"error": {"message": ...}}` (OpenAI, Anthropic, Gemini); anything else
+/// is returned as sent.
+fn error_detail(body: &str) -> String {
+    let Ok(parsed) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    let error = parsed.get("error");
+    let detail = [
+        parsed.get("message"),
+        error.and_then(|e| e.get("message")),
+        error,
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|value| value.as_str())
+    .map(str::to_string);
+    detail.unwrap_or_else(|| body.to_string())
+}
+
+/// Client identification and other construction options for
+/// [`AiOrchestrator::new`].
+#[derive(Debug, Clone, Default)]
+pub struct OrchestratorConfig {
+    /// `User-Agent` sent with every outbound request; defaults to
+    /// [`default_user_agent`]. Embedders can set their own identity here.
+    pub user_agent: Option<String>,
+}
+
+/// `InkOS-Core/<crate version> (+https://github.com/inkos)`.
+pub fn default_user_agent() -> String {
+    format!(
+        "InkOS-Core/{} (+https://github.com/inkos)",
+        env!("CARGO_PKG_VERSION")
+    )
 }
 
 /// Thin wrapper around a configured [`reqwest::Client`].
@@ -52,41 +430,133 @@ pub struct AiOrchestrator {
 
 impl AiOrchestrator {
     /// Construct a new orchestrator with sane HTTP defaults.
-    pub fn new() -> Result<Self> {
+    pub fn new(config: OrchestratorConfig) -> Result<Self> {
+        let user_agent = config
+            .user_agent
+            .map(|agent| agent.trim().to_string())
+            .filter(|agent| !agent.is_empty())
+            .unwrap_or_else(default_user_agent);
+        // Total request time is bounded per provider in `send_json`.
         let client = Client::builder()
-            .timeout(Duration::from_secs(45))
-            .user_agent("InkOS-Core/0.1 (+https://github.com/inkos)")
+            .connect_timeout(Duration::from_secs(10))
+            .user_agent(user_agent)
             .build()
             .context("failed to construct HTTP client")?;
         Ok(Self { client })
     }
 
+    /// The shared HTTP client, for outbound calls that are not provider requests.
+    pub fn http_client(&self) -> Client {
+        self.client.clone()
+    }
+
     /// Execute a chat completion against the selected runtime.
     ///
     /// Provider specific behaviour is handled internally so that callers only
     /// need to supply the [`AiRuntimeSelection`] and desired message history.
+    /// In JSON mode a reply that does not parse fails with
+    /// [`OrchestratorError::InvalidJson`]. Image attachments are replaced by
+    /// a text placeholder unless the provider is tagged `vision`. Unset
+    /// temperature and top-p take the provider's defaults.
     pub async fn chat(
         &self,
         selection: &AiRuntimeSelection,
-        input: AiChatInput,
-    ) -> Result<AiChatResponse> {
+        mut input: AiChatInput,
+    ) -> Result<AiChatResponse, OrchestratorError> {
+        input.temperature = input.temperature.or(selection.provider.default_temperature);
+        input.top_p = input.top_p.or(selection.provider.default_top_p);
+        if !supports_vision(&selection.provider) {
+            describe_images(&mut input.messages);
+        }
+        let mut response = self.dispatch(selection, &input).await?;
+        if input.response_format.is_some() {
+            parse_json_content(&selection.provider.id, &response.content)?;
+        }
+        response.request_id = input.request_id;
+        Ok(response)
+    }
+
+    /// Embed each of `texts` with the selected runtime's embedding model.
+    ///
+    /// OpenAI-compatible providers are called once via `/v1/embeddings`;
+    /// Ollama's `/api/embeddings` takes a single prompt, so it is called per
+    /// text. Vectors are returned in input order.
+    pub async fn embed(
+        &self,
+        selection: &AiRuntimeSelection,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, OrchestratorError> {
+        let provider = &selection.provider;
+        if provider.kind == AZURE_OPENAI_KIND {
+            let secret = selection.secret.as_ref().ok_or_else(|| {
+                OrchestratorError::Auth("Azure OpenAI API key is not configured".into())
+            })?;
+            let url = azure_url(provider, &selection.model, "embeddings")?;
+            let payload = serde_json::json!({ "input": texts });
+            let request = self.client.post(url).header("api-key", secret);
+            let body = send_json(provider, request.json(&payload)).await?;
+            return parse_openai_embeddings(&provider.id, &body, texts.len());
+        }
+        if speaks_ollama(provider) {
+            let url = format!("{}/api/embeddings", ollama_base_url(provider));
+            let mut vectors = Vec::with_capacity(texts.len());
+            for text in texts {
+                let payload = serde_json::json!({ "model": selection.model, "prompt": text });
+                let body = send_json(provider, self.client.post(&url).json(&payload)).await?;
+                vectors.push(parse_vector(&provider.id, body.get("embedding"))?);
+            }
+            Ok(vectors)
+        } else if speaks_openai(provider) {
+            let base_url = provider
+                .base_url
+                .clone()
+                .unwrap_or_else(|| "https://api.openai.com".to_string());
+            let url = format!("{}/v1/embeddings", base_url.trim_end_matches('/'));
+            let mut request = self.client.post(url);
+            if let Some(secret) = &selection.secret {
+                request = request.bearer_auth(secret);
+            } else if provider.id == "openai" {
+                return Err(OrchestratorError::Auth(
+                    "OpenAI API key is not configured".into(),
+                ));
+            }
+            let payload = serde_json::json!({ "model": selection.model, "input": texts });
+            let body = send_json(provider, request.json(&payload)).await?;
+            parse_openai_embeddings(&provider.id, &body, texts.len())
+        } else {
+            Err(OrchestratorError::Unsupported(
+                format!("{} does not offer embeddings", provider.id).into(),
+            ))
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        selection: &AiRuntimeSelection,
+        input: &AiChatInput,
+    ) -> Result<AiChatResponse, OrchestratorError> {
         match selection.provider.id.as_str() {
-            "openai" => self.chat_openai(selection, &input).await,
-            "anthropic" => self.chat_anthropic(selection, &input).await,
-            "google" => self.chat_gemini(selection, &input).await,
-            "ollama" => self.chat_ollama(selection, &input).await,
-            "lmstudio" => self.chat_lmstudio(selection, &input).await,
+            "openai" => self.chat_openai(selection, input).await,
+            "anthropic" => self.chat_anthropic(selection, input).await,
+            "google" => self.chat_gemini(selection, input).await,
+            "cohere" => self.chat_cohere(selection, input).await,
+            "ollama" => self.chat_ollama(selection, input).await,
+            "lmstudio" => self.chat_lmstudio(selection, input).await,
             other => {
-                if selection.provider.kind == "local"
-                    && selection
-                        .provider
-                        .capability_tags
-                        .iter()
-                        .any(|t| t.contains("openai"))
-                {
-                    self.chat_openai_like(selection, &input, false).await
+                // Custom providers declare their wire format through their
+                // kind or tags.
+                let tags = &selection.provider.capability_tags;
+                if selection.provider.kind == AZURE_OPENAI_KIND {
+                    self.chat_azure_openai(selection, input).await
+                } else if tags.iter().any(|t| t.contains("openai")) {
+                    let include_auth = selection.secret.is_some();
+                    self.chat_openai_like(selection, input, include_auth).await
+                } else if tags.iter().any(|t| t == "ollama") {
+                    self.chat_ollama(selection, input).await
                 } else {
-                    Err(anyhow!("Unsupported AI provider: {other}"))
+                    Err(OrchestratorError::Unsupported(
+                        format!("Unsupported AI provider: {other}").into(),
+                    ))
                 }
             }
         }
@@ -97,13 +567,13 @@ impl AiOrchestrator {
         &self,
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
         if selection.secret.is_none() {
-            return Err(anyhow!("OpenAI API key is not configured"));
+            return Err(OrchestratorError::Auth(
+                "OpenAI API key is not configured".into(),
+            ));
         }
-        self.chat_openai_like(selection, input, true)
-            .await
-            .with_context(|| "OpenAI request failed".to_string())
+        self.chat_openai_like(selection, input, true).await
     }
 
     /// Invoke an OpenAI-compatible API endpoint.
@@ -115,7 +585,7 @@ impl AiOrchestrator {
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
         include_auth: bool,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
         let base_url = selection
             .provider
             .base_url
@@ -124,38 +594,40 @@ impl AiOrchestrator {
         let url = format!("{}/v1/chat/completions", base_url.trim_end_matches('/'));
         let mut request = self.client.post(url);
         if include_auth {
-            let secret = selection
-                .secret
-                .as_ref()
-                .ok_or_else(|| anyhow!("API key missing for provider {}", selection.provider.id))?;
+            let secret = selection.secret.as_ref().ok_or_else(|| {
+                OrchestratorError::Auth(
+                    format!("API key missing for provider {}", selection.provider.id).into(),
+                )
+            })?;
             request = request.bearer_auth(secret);
         }
 
-        let payload = serde_json::json!({
-            "model": selection.model.clone(),
-            "messages": normalise_messages(&input.messages),
-            "temperature": input.temperature.unwrap_or(0.2),
-        });
-
-        let response = request.json(&payload).send().await?.error_for_status()?;
-        let body: Value = response.json().await?;
-
-        let content = body
-            .get("choices")
-            .and_then(|choices| choices.get(0))
-            .and_then(|choice| choice.get("message"))
-            .and_then(|msg| msg.get("content"))
-            .and_then(|val| val.as_str())
-            .unwrap_or_default()
-            .to_string();
+        let payload = openai_payload(selection, input);
+        let body = send_json(&selection.provider, request.json(&payload)).await?;
+        Ok(openai_response(selection, body))
+    }
 
-        Ok(AiChatResponse {
-            provider_id: selection.provider.id.clone(),
-            model: selection.model.clone(),
-            usage: extract_openai_usage(&body),
-            content,
-            raw: body,
-        })
+    /// Call an Azure OpenAI deployment.
+    ///
+    /// The selected model is the deployment name, so it goes in the URL, and
+    /// the key is sent in the `api-key` header rather than as a bearer token.
+    async fn chat_azure_openai(
+        &self,
+        selection: &AiRuntimeSelection,
+        input: &AiChatInput,
+    ) -> Result<AiChatResponse, OrchestratorError> {
+        let secret = selection.secret.as_ref().ok_or_else(|| {
+            OrchestratorError::Auth("Azure OpenAI API key is not configured".into())
+        })?;
+        let url = azure_url(&selection.provider, &selection.model, "chat/completions")?;
+        let payload = openai_payload(selection, input);
+        let request = self
+            .client
+            .post(url)
+            .header("api-key", secret)
+            .json(&payload);
+        let body = send_json(&selection.provider, request).await?;
+        Ok(openai_response(selection, body))
     }
 
     /// Call LM Studio using its OpenAI-compatible surface.
@@ -163,7 +635,7 @@ impl AiOrchestrator {
         &self,
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
         self.chat_openai_like(selection, input, false).await
     }
 
@@ -172,11 +644,11 @@ impl AiOrchestrator {
         &self,
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
         let secret = selection
             .secret
             .as_ref()
-            .ok_or_else(|| anyhow!("Anthropic API key is not configured"))?;
+            .ok_or_else(|| OrchestratorError::Auth("Anthropic API key is not configured".into()))?;
         let base_url = selection
             .provider
             .base_url
@@ -185,7 +657,7 @@ impl AiOrchestrator {
         let url = format!("{}/v1/messages", base_url.trim_end_matches('/'));
         let mut system_prompt = String::new();
         let mut messages = Vec::new();
-        for msg in &input.messages {
+        for msg in &with_json_instruction(input) {
             match msg.role.as_str() {
                 "system" => {
                     if !system_prompt.is_empty() {
@@ -194,9 +666,21 @@ impl AiOrchestrator {
                     system_prompt.push_str(&msg.content);
                 }
                 "assistant" | "user" => {
+                    let mut content =
+                        vec![serde_json::json!({"type": "text", "text": msg.content})];
+                    content.extend(msg.images.iter().map(|image| {
+                        serde_json::json!({
+                            "type": "image",
+                            "source": {
+                                "type": "base64",
+                                "media_type": image.mime_type,
+                                "data": image.data,
+                            },
+                        })
+                    }));
                     messages.push(serde_json::json!({
                         "role": msg.role,
-                        "content": [{"type": "text", "text": msg.content}],
+                        "content": content,
                     }));
                 }
                 _ => {}
@@ -210,37 +694,49 @@ impl AiOrchestrator {
             }));
         }
 
-        let payload = serde_json::json!({
+        let mut max_tokens = input.max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS);
+        let budget = input.reasoning_effort.map(ReasoningEffort::thinking_budget);
+        if let Some(budget) = budget {
+            // The thinking budget is spent out of `max_tokens`, which must
+            // exceed it; keep room for the answer itself.
+            max_tokens = max_tokens.max(budget + ANTHROPIC_DEFAULT_MAX_TOKENS);
+        }
+        let mut payload = serde_json::json!({
             "model": selection.model.clone(),
-            "max_tokens": 1024,
+            "max_tokens": max_tokens,
             "system": if system_prompt.is_empty() { Value::Null } else { Value::String(system_prompt.clone()) },
             "messages": messages,
             "temperature": input.temperature.unwrap_or(0.2),
         });
+        set_sampling(&mut payload, input, "stop_sequences", "top_p");
+        if let Some(budget) = budget {
+            payload["thinking"] = serde_json::json!({ "type": "enabled", "budget_tokens": budget });
+            // Extended thinking only runs at the default sampling settings.
+            if let Some(fields) = payload.as_object_mut() {
+                fields.remove("temperature");
+                fields.remove("top_p");
+            }
+        }
 
-        let response = self
+        let request = self
             .client
             .post(url)
             .header("x-api-key", secret)
             .header("anthropic-version", "2023-06-01")
-            .json(&payload)
-            .send()
-            .await?
-            .error_for_status()?;
-        let body: Value = response.json().await?;
-        let content = body
-            .get("content")
-            .and_then(|c| c.get(0))
-            .and_then(|part| part.get("text"))
-            .and_then(|text| text.as_str())
-            .unwrap_or_default()
-            .to_string();
+            .json(&payload);
+        let body = send_json(&selection.provider, request).await?;
+        let content = anthropic_blocks(&body, "text", "text");
+        let reasoning = Some(anthropic_blocks(&body, "thinking", "thinking"))
+            .filter(|thinking| !thinking.is_empty());
         Ok(AiChatResponse {
             provider_id: selection.provider.id.clone(),
             model: selection.model.clone(),
             usage: extract_anthropic_usage(&body),
             content,
             raw: body,
+            cached: false,
+            reasoning,
+            request_id: None,
         })
     }
 
@@ -249,11 +745,11 @@ impl AiOrchestrator {
         &self,
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
         let secret = selection
             .secret
             .as_ref()
-            .ok_or_else(|| anyhow!("Gemini API key is not configured"))?;
+            .ok_or_else(|| OrchestratorError::Auth("Gemini API key is not configured".into()))?;
         let base_url = selection
             .provider
             .base_url
@@ -266,27 +762,47 @@ impl AiOrchestrator {
             secret
         );
 
-        let conversation = build_conversation_prompt(&input.messages);
-        let payload = serde_json::json!({
-            "contents": [
-                {
-                    "role": "user",
-                    "parts": [{"text": conversation}]
-                }
-            ],
-            "generationConfig": {
-                "temperature": input.temperature.unwrap_or(0.2)
+        let mut generation_config = serde_json::json!({
+            "temperature": input.temperature.unwrap_or(0.2)
+        });
+        if let Some(ResponseFormat::Json { schema }) = &input.response_format {
+            generation_config["responseMimeType"] = "application/json".into();
+            if let Some(schema) = schema {
+                generation_config["responseSchema"] = schema.clone();
             }
+        }
+        set_sampling(&mut generation_config, input, "stopSequences", "topP");
+        if let Some(max_tokens) = input.max_tokens {
+            generation_config["maxOutputTokens"] = max_tokens.into();
+        }
+        let (contents, system) = gemini_contents(&input.messages);
+        let mut payload = serde_json::json!({
+            "contents": contents,
+            "generationConfig": generation_config,
         });
+        if let Some(system) = &system {
+            payload["systemInstruction"] = serde_json::json!({ "parts": [{ "text": system }] });
+        }
 
-        let response = self
-            .client
-            .post(endpoint)
-            .json(&payload)
-            .send()
-            .await?
-            .error_for_status()?;
-        let body: Value = response.json().await?;
+        let request = self.client.post(&endpoint).json(&payload);
+        let body = match send_json(&selection.provider, request).await {
+            Err(OrchestratorError::BadRequest(message))
+                if system.is_some() && rejects_system_instruction(&message.to_string()) =>
+            {
+                // Some proxies and older endpoints reject `systemInstruction`;
+                // resend the conversation flattened into one user turn.
+                let payload = serde_json::json!({
+                    "contents": [{
+                        "role": "user",
+                        "parts": [{ "text": build_conversation_prompt(&input.messages) }]
+                    }],
+                    "generationConfig": generation_config,
+                });
+                let request = self.client.post(&endpoint).json(&payload);
+                send_json(&selection.provider, request).await?
+            }
+            other => other?,
+        };
         let content = body
             .get("candidates")
             .and_then(|c| c.get(0))
@@ -300,40 +816,96 @@ impl AiOrchestrator {
         Ok(AiChatResponse {
             provider_id: selection.provider.id.clone(),
             model: selection.model.clone(),
-            usage: None,
+            usage: extract_gemini_usage(&body),
             content,
             raw: body,
+            cached: false,
+            reasoning: None,
+            request_id: None,
         })
     }
 
-    /// Call the local Ollama HTTP API.
-    async fn chat_ollama(
+    /// Call Cohere's v2 Chat API.
+    async fn chat_cohere(
         &self,
         selection: &AiRuntimeSelection,
         input: &AiChatInput,
-    ) -> Result<AiChatResponse> {
+    ) -> Result<AiChatResponse, OrchestratorError> {
+        let secret = selection
+            .secret
+            .as_ref()
+            .ok_or_else(|| OrchestratorError::Auth("Cohere API key is not configured".into()))?;
         let base_url = selection
             .provider
             .base_url
             .clone()
-            .unwrap_or_else(|| "http://127.0.0.1:11434".to_string());
-        let url = format!("{}/api/chat", base_url.trim_end_matches('/'));
-        let payload = serde_json::json!({
+            .unwrap_or_else(|| "https://api.cohere.com".to_string());
+        let url = format!("{}/v2/chat", base_url.trim_end_matches('/'));
+        let mut payload = serde_json::json!({
             "model": selection.model.clone(),
             "messages": normalise_messages(&input.messages),
-            "stream": false,
-            "options": {
-                "temperature": input.temperature.unwrap_or(0.2)
+            "temperature": input.temperature.unwrap_or(0.2),
+        });
+        if let Some(ResponseFormat::Json { schema }) = &input.response_format {
+            payload["response_format"] = serde_json::json!({ "type": "json_object" });
+            if let Some(schema) = schema {
+                payload["response_format"]["json_schema"] = schema.clone();
             }
+        }
+        set_sampling(&mut payload, input, "stop_sequences", "p");
+        if let Some(max_tokens) = input.max_tokens {
+            payload["max_tokens"] = max_tokens.into();
+        }
+
+        let request = self.client.post(url).bearer_auth(secret).json(&payload);
+        let body = send_json(&selection.provider, request).await?;
+        let content = body
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .and_then(|c| c.as_array())
+            .and_then(|parts| {
+                parts
+                    .iter()
+                    .find(|part| part.get("type").and_then(|t| t.as_str()) == Some("text"))
+            })
+            .and_then(|part| part.get("text"))
+            .and_then(|text| text.as_str())
+            .unwrap_or_default()
+            .to_string();
+        Ok(AiChatResponse {
+            provider_id: selection.provider.id.clone(),
+            model: selection.model.clone(),
+            usage: extract_cohere_usage(&body),
+            content,
+            raw: body,
+            cached: false,
+            reasoning: None,
+            request_id: None,
+        })
+    }
+
+    /// Call the local Ollama HTTP API.
+    async fn chat_ollama(
+        &self,
+        selection: &AiRuntimeSelection,
+        input: &AiChatInput,
+    ) -> Result<AiChatResponse, OrchestratorError> {
+        let url = format!("{}/api/chat", ollama_base_url(&selection.provider));
+        let mut options = serde_json::json!({
+            "temperature": input.temperature.unwrap_or(0.2)
         });
-        let response = self
-            .client
-            .post(url)
-            .json(&payload)
-            .send()
-            .await?
-            .error_for_status()?;
-        let body: Value = response.json().await?;
+        set_sampling(&mut options, input, "stop", "top_p");
+        if let Some(max_tokens) = input.max_tokens {
+            options["num_predict"] = max_tokens.into();
+        }
+        let payload = serde_json::json!({
+            "model": selection.model.clone(),
+            "messages": ollama_messages(&with_json_instruction(input)),
+            "stream": false,
+            "options": options,
+        });
+        let request = self.client.post(url).json(&payload);
+        let body = send_json(&selection.provider, request).await?;
         let content = body
             .get("message")
             .and_then(|m| m.get("content"))
@@ -343,11 +915,443 @@ impl AiOrchestrator {
         Ok(AiChatResponse {
             provider_id: selection.provider.id.clone(),
             model: selection.model.clone(),
-            usage: None,
+            usage: extract_ollama_usage(&body),
             content,
             raw: body,
+            cached: false,
+            reasoning: None,
+            request_id: None,
         })
     }
+
+    /// Download `model` into an Ollama runtime through `/api/pull`, reporting
+    /// each streamed status line to `progress`.
+    ///
+    /// No request timeout applies, since large models take many minutes, but
+    /// a stream that goes quiet for [`PULL_IDLE_TIMEOUT`] fails with
+    /// [`OrchestratorError::Timeout`].
+    pub async fn pull_ollama_model(
+        &self,
+        provider: &AiProviderInfo,
+        model: &str,
+        progress: &(dyn Fn(&PullProgress) + Send + Sync),
+    ) -> Result<(), OrchestratorError> {
+        self.pull_with_idle_timeout(provider, model, progress, PULL_IDLE_TIMEOUT)
+            .await
+    }
+
+    async fn pull_with_idle_timeout(
+        &self,
+        provider: &AiProviderInfo,
+        model: &str,
+        progress: &(dyn Fn(&PullProgress) + Send + Sync),
+        idle_timeout: Duration,
+    ) -> Result<(), OrchestratorError> {
+        let provider_id = provider.id.as_str();
+        let url = format!("{}/api/pull", ollama_base_url(provider));
+        let payload = serde_json::json!({ "model": model, "stream": true });
+        let mut response = apply_headers(provider, self.client.post(url).json(&payload))?
+            .send()
+            .await
+            .map_err(|err| OrchestratorError::from_transport(provider_id, err))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(OrchestratorError::from_provider_status(
+                provider, status, None, &body,
+            ));
+        }
+
+        let mut buffer = Vec::new();
+        let mut succeeded = false;
+        loop {
+            let chunk = tokio::time::timeout(idle_timeout, response.chunk())
+                .await
+                .map_err(|_| {
+                    OrchestratorError::Timeout(
+                        format!(
+                            "{provider_id} sent nothing for {}s while pulling {model}",
+                            idle_timeout.as_secs()
+                        )
+                        .into(),
+                    )
+                })?
+                .map_err(|err| OrchestratorError::from_transport(provider_id, err))?;
+            let finished = chunk.is_none();
+            if let Some(chunk) = chunk {
+                buffer.extend_from_slice(&chunk);
+            }
+            while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                succeeded |= pull_status(provider_id, model, &line, progress)?;
+            }
+            if finished {
+                break;
+            }
+        }
+        succeeded |= pull_status(provider_id, model, &buffer, progress)?;
+        if succeeded {
+            Ok(())
+        } else {
+            Err(OrchestratorError::Network(
+                format!("{provider_id} stopped pulling {model} before it finished").into(),
+            ))
+        }
+    }
+}
+
+/// Step reported while Ollama downloads a model.
+#[derive(Debug, Clone, Serialize)]
+pub struct PullProgress {
+    pub provider_id: String,
+    pub model: String,
+    /// Ollama's status line, such as `pulling manifest`, `pulling <digest>`,
+    /// `verifying sha256 digest`, or `success`.
+    pub status: String,
+    /// Bytes downloaded of the current layer, when Ollama reports them.
+    pub completed: Option<u64>,
+    pub total: Option<u64>,
+}
+
+/// Handle one NDJSON line from `/api/pull`; `true` once Ollama reports success.
+fn pull_status(
+    provider_id: &str,
+    model: &str,
+    line: &[u8],
+    progress: &(dyn Fn(&PullProgress) + Send + Sync),
+) -> Result<bool, OrchestratorError> {
+    let line = String::from_utf8_lossy(line);
+    if line.trim().is_empty() {
+        return Ok(false);
+    }
+    let event: Value = serde_json::from_str(line.trim())
+        .map_err(|_| OrchestratorError::non_json(provider_id, StatusCode::OK, &line))?;
+    if let Some(error) = event.get("error").and_then(Value::as_str) {
+        return Err(OrchestratorError::BadRequest(
+            redact_text(&format!("{provider_id} could not pull {model}: {error}")).into(),
+        ));
+    }
+    let status = event
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let done = status == "success";
+    progress(&PullProgress {
+        provider_id: provider_id.to_string(),
+        model: model.to_string(),
+        status,
+        completed: event.get("completed").and_then(Value::as_u64),
+        total: event.get("total").and_then(Value::as_u64),
+    });
+    Ok(done)
+}
+
+/// Base URL of an Ollama runtime, without a trailing slash.
+fn ollama_base_url(provider: &AiProviderInfo) -> String {
+    provider
+        .base_url
+        .as_deref()
+        .unwrap_or("http://127.0.0.1:11434")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Request timeout used when a provider has no `request_timeout_secs`.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Longest silence allowed between chunks of an Ollama pull stream.
+const PULL_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Send a request and decode its JSON body, classifying any failure.
+///
+/// The provider's timeout applies to the whole request; hitting it yields
+/// [`OrchestratorError::Timeout`].
+async fn send_json(
+    provider: &AiProviderInfo,
+    request: RequestBuilder,
+) -> Result<Value, OrchestratorError> {
+    let provider_id = provider.id.as_str();
+    let timeout = provider
+        .request_timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_REQUEST_TIMEOUT);
+    let response = apply_headers(provider, request)?
+        .timeout(timeout)
+        .send()
+        .await
+        .map_err(|err| OrchestratorError::from_transport(provider_id, err))?;
+    let status = response.status();
+    if !status.is_success() {
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        let body = response.text().await.unwrap_or_default();
+        return Err(OrchestratorError::from_provider_status(
+            provider,
+            status,
+            retry_after_secs,
+            &body,
+        ));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|err| OrchestratorError::from_transport(provider_id, err))?;
+    let mut body = parse_body(provider_id, status, &text)?;
+    // The body is kept as `AiChatResponse.raw`; drop any echoed credentials.
+    strip_auth_fields(&mut body);
+    Ok(body)
+}
+
+/// Decode an `/embeddings` response, restoring input order.
+fn parse_openai_embeddings(
+    provider_id: &str,
+    body: &Value,
+    expected: usize,
+) -> Result<Vec<Vec<f32>>, OrchestratorError> {
+    let mut data: Vec<&Value> = body
+        .get("data")
+        .and_then(|data| data.as_array())
+        .map(|items| items.iter().collect())
+        .unwrap_or_default();
+    data.sort_by_key(|item| item.get("index").and_then(|i| i.as_u64()).unwrap_or(0));
+    if data.len() != expected {
+        return Err(OrchestratorError::Decode(
+            format!(
+                "{provider_id} returned {} embeddings for {expected} inputs",
+                data.len(),
+            )
+            .into(),
+        ));
+    }
+    data.into_iter()
+        .map(|item| parse_vector(provider_id, item.get("embedding")))
+        .collect()
+}
+
+/// Decode an embedding array into `f32`s.
+fn parse_vector(provider_id: &str, value: Option<&Value>) -> Result<Vec<f32>, OrchestratorError> {
+    value
+        .and_then(|v| v.as_array())
+        .filter(|items| !items.is_empty())
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_f64().map(|f| f as f32))
+                .collect::<Option<Vec<f32>>>()
+        })
+        .ok_or_else(|| {
+            OrchestratorError::Decode(format!("{provider_id} returned no embedding").into())
+        })
+}
+
+/// Merge the provider's configured headers into a request.
+///
+/// `${ENV_VAR}` references are expanded on every call so rotating tokens
+/// exported by the host are picked up without a restart.
+fn apply_headers(
+    provider: &AiProviderInfo,
+    mut request: RequestBuilder,
+) -> Result<RequestBuilder, OrchestratorError> {
+    for (name, template) in &provider.headers {
+        let value = interpolate_env(template).map_err(|var| {
+            OrchestratorError::Auth(
+                format!(
+                    "{} header {name} references unset environment variable {var}",
+                    provider.id
+                )
+                .into(),
+            )
+        })?;
+        request = request.header(name.as_str(), value);
+    }
+    Ok(request)
+}
+
+/// Expand `${NAME}` references from the environment, returning the first
+/// unset variable name on failure. An unterminated `${` is kept verbatim.
+fn interpolate_env(template: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let var = &after[..end];
+        out.push_str(&std::env::var(var).map_err(|_| var.to_string())?);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Copy `stop` and `top_p` into `target` under the provider's key names,
+/// leaving the keys out entirely when unset.
+fn set_sampling(target: &mut Value, input: &AiChatInput, stop_key: &str, top_p_key: &str) {
+    if let Some(stop) = input.stop.as_ref().filter(|stop| !stop.is_empty()) {
+        target[stop_key] = serde_json::json!(stop);
+    }
+    if let Some(top_p) = input.top_p {
+        target[top_p_key] = serde_json::json!(top_p);
+    }
+}
+
+/// Prepend a strict JSON instruction for providers without a native JSON
+/// mode, and for OpenAI's `json_object` mode, which requires one. Returns
+/// the messages unchanged outside JSON mode.
+fn with_json_instruction(input: &AiChatInput) -> Vec<AiChatMessage> {
+    let Some(ResponseFormat::Json { schema }) = &input.response_format else {
+        return input.messages.clone();
+    };
+    let mut instruction = String::from(
+        "Respond with a single valid JSON value and nothing else. Do not wrap it in Markdown code fences or add commentary.",
+    );
+    if let Some(schema) = schema {
+        instruction.push_str(&format!(
+            " The JSON must conform to this JSON Schema: {schema}"
+        ));
+    }
+    let mut messages = Vec::with_capacity(input.messages.len() + 1);
+    messages.push(AiChatMessage {
+        role: "system".into(),
+        content: instruction,
+        images: Vec::new(),
+    });
+    messages.extend(input.messages.iter().cloned());
+    messages
+}
+
+/// Parse a JSON-mode reply, tolerating a surrounding Markdown code fence.
+pub fn parse_json_content(provider_id: &str, content: &str) -> Result<Value, OrchestratorError> {
+    let trimmed = content.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim()).map_err(|err| {
+        let excerpt: String = trimmed.chars().take(120).collect();
+        OrchestratorError::InvalidJson(
+            format!("{provider_id} reply is not valid JSON ({err}): {excerpt}").into(),
+        )
+    })
+}
+
+/// Chat Completions request body shared by OpenAI-compatible providers and
+/// Azure OpenAI.
+fn openai_payload(selection: &AiRuntimeSelection, input: &AiChatInput) -> Value {
+    // `json_object` mode is refused unless the messages mention JSON; a
+    // schema is enforced by `json_schema` mode instead.
+    let (messages, response_format) = match &input.response_format {
+        Some(ResponseFormat::Json {
+            schema: Some(schema),
+        }) => (
+            input.messages.clone(),
+            Some(serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            })),
+        ),
+        Some(ResponseFormat::Json { schema: None }) => (
+            with_json_instruction(input),
+            Some(serde_json::json!({ "type": "json_object" })),
+        ),
+        None => (input.messages.clone(), None),
+    };
+    let mut payload = serde_json::json!({
+        "model": selection.model.clone(),
+        "messages": normalise_messages(&messages),
+        "temperature": input.temperature.unwrap_or(0.2),
+    });
+    if let Some(response_format) = response_format {
+        payload["response_format"] = response_format;
+    }
+    set_sampling(&mut payload, input, "stop", "top_p");
+    if let Some(effort) = input.reasoning_effort {
+        payload["reasoning_effort"] = effort.as_str().into();
+        // Reasoning models only run at the default sampling settings.
+        if let Some(fields) = payload.as_object_mut() {
+            fields.remove("temperature");
+            fields.remove("top_p");
+        }
+    }
+    if let Some(max_tokens) = input.max_tokens {
+        // Reasoning models reject `max_tokens` in favour of the newer key.
+        let key = if input.reasoning_effort.is_some() {
+            "max_completion_tokens"
+        } else {
+            "max_tokens"
+        };
+        payload[key] = max_tokens.into();
+    }
+    payload
+}
+
+/// Normalise a Chat Completions response body.
+fn openai_response(selection: &AiRuntimeSelection, body: Value) -> AiChatResponse {
+    let message = body
+        .get("choices")
+        .and_then(|choices| choices.get(0))
+        .and_then(|choice| choice.get("message"));
+    let content = message
+        .and_then(|msg| msg.get("content"))
+        .and_then(|val| val.as_str())
+        .unwrap_or_default()
+        .to_string();
+    // OpenAI keeps reasoning hidden; compatible servers such as DeepSeek
+    // and LM Studio return it beside the answer.
+    let reasoning = message
+        .and_then(|msg| {
+            msg.get("reasoning_content")
+                .or_else(|| msg.get("reasoning"))
+        })
+        .and_then(|val| val.as_str())
+        .filter(|text| !text.is_empty())
+        .map(str::to_string);
+    AiChatResponse {
+        provider_id: selection.provider.id.clone(),
+        model: selection.model.clone(),
+        usage: extract_openai_usage(&body),
+        content,
+        raw: body,
+        cached: false,
+        reasoning,
+        request_id: None,
+    }
+}
+
+/// `{base}/openai/deployments/{deployment}/{operation}?api-version=...` for
+/// an Azure OpenAI provider.
+fn azure_url(
+    provider: &AiProviderInfo,
+    deployment: &str,
+    operation: &str,
+) -> Result<reqwest::Url, OrchestratorError> {
+    let base_url = provider.base_url.as_deref().unwrap_or_default();
+    let api_version = azure_api_version(base_url, &provider.capability_tags).ok_or_else(|| {
+        OrchestratorError::BadRequest(format!("{} has no Azure api-version", provider.id).into())
+    })?;
+    let mut url = reqwest::Url::parse(base_url).map_err(|err| {
+        OrchestratorError::BadRequest(format!("{} base URL is invalid: {err}", provider.id).into())
+    })?;
+    let root = url
+        .path()
+        .trim_end_matches('/')
+        .trim_end_matches("/openai")
+        .to_string();
+    url.set_path(&format!(
+        "{root}/openai/deployments/{deployment}/{operation}"
+    ));
+    url.query_pairs_mut()
+        .clear()
+        .append_pair("api-version", &api_version);
+    Ok(url)
 }
 
 /// Convert high level chat messages into the OpenAI JSON wire format.
@@ -360,14 +1364,74 @@ fn normalise_messages(messages: &[AiChatMessage]) -> Vec<Value> {
                 "assistant" => "assistant",
                 _ => "user",
             };
-            serde_json::json!({
-                "role": role,
-                "content": m.content,
-            })
+            if m.images.is_empty() {
+                return serde_json::json!({
+                    "role": role,
+                    "content": m.content,
+                });
+            }
+            let mut parts = vec![serde_json::json!({ "type": "text", "text": m.content })];
+            parts.extend(m.images.iter().map(|image| {
+                serde_json::json!({
+                    "type": "image_url",
+                    "image_url": { "url": format!("data:{};base64,{}", image.mime_type, image.data) },
+                })
+            }));
+            serde_json::json!({ "role": role, "content": parts })
         })
         .collect()
 }
 
+/// Ollama's chat format: plain text content plus a bare base64 `images` list.
+fn ollama_messages(messages: &[AiChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .zip(normalise_messages(messages))
+        .map(|(m, mut wire)| {
+            if !m.images.is_empty() {
+                wire["content"] = Value::String(m.content.clone());
+                wire["images"] = m.images.iter().map(|image| image.data.clone()).collect();
+            }
+            wire
+        })
+        .collect()
+}
+
+/// Whether the provider can read image parts.
+fn supports_vision(provider: &AiProviderInfo) -> bool {
+    Capabilities::from_tags(&provider.capability_tags).vision
+}
+
+/// Drop image parts, noting in the text how many were left out.
+fn describe_images(messages: &mut [AiChatMessage]) {
+    for message in messages.iter_mut().filter(|m| !m.images.is_empty()) {
+        let count = message.images.len();
+        message.content.push_str(&format!(
+            "\n\n[{count} image attachment{} omitted: this model cannot view images]",
+            if count == 1 { "" } else { "s" }
+        ));
+        message.images.clear();
+    }
+}
+
+/// Join the `field` of every Anthropic content block of type `kind`.
+///
+/// With extended thinking the answer no longer comes first: thinking blocks
+/// precede the text blocks.
+fn anthropic_blocks(body: &Value, kind: &str, field: &str) -> String {
+    body.get("content")
+        .and_then(|content| content.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some(kind))
+                .filter_map(|block| block.get(field).and_then(|text| text.as_str()))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .unwrap_or_default()
+}
+
 /// Pull token counts from OpenAI-style response bodies.
 fn extract_openai_usage(body: &Value) -> Option<AiUsageMetrics> {
     body.get("usage").map(|usage| AiUsageMetrics {
@@ -401,7 +1465,113 @@ fn extract_anthropic_usage(body: &Value) -> Option<AiUsageMetrics> {
     })
 }
 
-/// Collapse chat messages into a single plain-text prompt (Gemini helper).
+/// Pull token counts from Gemini's `usageMetadata`.
+fn extract_gemini_usage(body: &Value) -> Option<AiUsageMetrics> {
+    body.get("usageMetadata").map(|usage| AiUsageMetrics {
+        prompt_tokens: usage
+            .get("promptTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32),
+        completion_tokens: usage
+            .get("candidatesTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32),
+        total_tokens: usage
+            .get("totalTokenCount")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as u32),
+    })
+}
+
+/// Pull billed token counts from Cohere's `usage.tokens`.
+fn extract_cohere_usage(body: &Value) -> Option<AiUsageMetrics> {
+    let tokens = body.get("usage")?.get("tokens")?;
+    let prompt = tokens
+        .get("input_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    let completion = tokens
+        .get("output_tokens")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    Some(AiUsageMetrics {
+        prompt_tokens: prompt,
+        completion_tokens: completion,
+        total_tokens: prompt.zip(completion).map(|(p, c)| p + c),
+    })
+}
+
+/// Pull token counts from Ollama's final chat message.
+///
+/// Ollama omits `prompt_eval_count` when the prompt was served from its
+/// cache, so the total is only reported when both counts are present.
+fn extract_ollama_usage(body: &Value) -> Option<AiUsageMetrics> {
+    let prompt = body
+        .get("prompt_eval_count")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    let completion = body
+        .get("eval_count")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32);
+    if prompt.is_none() && completion.is_none() {
+        return None;
+    }
+    Some(AiUsageMetrics {
+        prompt_tokens: prompt,
+        completion_tokens: completion,
+        total_tokens: prompt.zip(completion).map(|(p, c)| p + c),
+    })
+}
+
+/// Map chat messages onto Gemini `contents` plus a system instruction.
+///
+/// Assistant turns use the `model` role, consecutive turns with the same
+/// role are merged (Gemini requires alternation), and system messages are
+/// joined into the returned instruction. A system-only history is sent as a
+/// user turn instead.
+fn gemini_contents(messages: &[AiChatMessage]) -> (Vec<Value>, Option<String>) {
+    let mut system = Vec::new();
+    let mut contents: Vec<Value> = Vec::new();
+    for msg in messages {
+        let role = match msg.role.to_lowercase().as_str() {
+            "system" => {
+                system.push(msg.content.trim());
+                continue;
+            }
+            "assistant" | "model" => "model",
+            _ => "user",
+        };
+        let mut parts = vec![serde_json::json!({ "text": msg.content })];
+        parts.extend(msg.images.iter().map(|image| {
+            serde_json::json!({ "inline_data": { "mime_type": image.mime_type, "data": image.data } })
+        }));
+        match contents.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(existing) = last["parts"].as_array_mut() {
+                    existing.extend(parts);
+                }
+            }
+            _ => contents.push(serde_json::json!({ "role": role, "parts": parts })),
+        }
+    }
+    let system = (!system.is_empty()).then(|| system.join("\n\n"));
+    if contents.is_empty() {
+        let text = system.unwrap_or_else(|| "Hello from InkOS".to_string());
+        return (
+            vec![serde_json::json!({ "role": "user", "parts": [{ "text": text }] })],
+            None,
+        );
+    }
+    (contents, system)
+}
+
+/// Whether a 400 from Gemini complains about the `systemInstruction` field.
+fn rejects_system_instruction(message: &str) -> bool {
+    message.contains("systemInstruction") || message.contains("system_instruction")
+}
+
+/// Collapse chat messages into a single plain-text prompt (Gemini fallback).
 fn build_conversation_prompt(messages: &[AiChatMessage]) -> String {
     let mut sections = Vec::new();
     for msg in messages {
@@ -413,3 +1583,534 @@ fn build_conversation_prompt(messages: &[AiChatMessage]) -> String {
     }
     sections.join("\n\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_statuses_map_to_remediation_variants() {
+        let classify = |code: u16| {
+            OrchestratorError::from_status("openai", StatusCode::from_u16(code).unwrap(), None, "")
+                .code()
+        };
+        assert_eq!(classify(401), "AI-1001");
+        assert_eq!(classify(429), "AI-1002");
+        assert_eq!(classify(504), "AI-1003");
+        assert_eq!(classify(400), "AI-1005");
+        assert_eq!(classify(503), "AI-1006");
+        assert_eq!(classify(302), "AI-BAD-RESPONSE");
+    }
+
+    #[test]
+    fn html_bodies_report_status_and_snippet() {
+        let html = "<!DOCTYPE html><html><head><title>Sign in</title></head><body>api_key=sk-abcdef1234567890abcdef</body></html>";
+        let err = parse_body("openai", StatusCode::OK, html).unwrap_err();
+        assert_eq!(err.code(), "AI-BAD-RESPONSE");
+        let message = err.to_string();
+        assert!(message.starts_with("openai returned non-JSON (HTTP 200): <!DOCTYPE html>"));
+        assert!(!message.contains("sk-abcdef1234567890abcdef"));
+        assert_eq!(
+            parse_body("openai", StatusCode::OK, r#"{"ok":true}"#).unwrap()["ok"],
+            true
+        );
+    }
+
+    #[test]
+    fn header_templates_expand_environment_variables() {
+        std::env::set_var("INKOS_TEST_ROTATING_TOKEN", "tok-123");
+        assert_eq!(
+            interpolate_env("Bearer ${INKOS_TEST_ROTATING_TOKEN}").unwrap(),
+            "Bearer tok-123"
+        );
+        assert_eq!(
+            interpolate_env("static ${unterminated").unwrap(),
+            "static ${unterminated"
+        );
+        assert_eq!(
+            interpolate_env("${INKOS_TEST_UNSET_VAR}").unwrap_err(),
+            "INKOS_TEST_UNSET_VAR"
+        );
+    }
+
+    #[test]
+    fn json_replies_are_validated() {
+        assert_eq!(
+            parse_json_content("openai", "```json\n{\"items\": [1]}\n```").unwrap()["items"][0],
+            1
+        );
+        let err = parse_json_content("anthropic", "Sure! Here are the items.").unwrap_err();
+        assert_eq!(err.code(), "AI-JSON-INVALID");
+    }
+
+    #[test]
+    fn gemini_and_ollama_usage_is_extracted() {
+        let gemini = serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": "ok" }], "role": "model" } }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 5, "totalTokenCount": 17 }
+        });
+        let usage = extract_gemini_usage(&gemini).unwrap();
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (Some(12), Some(5), Some(17))
+        );
+
+        let ollama = serde_json::json!({
+            "model": "llama3.1",
+            "message": { "role": "assistant", "content": "ok" },
+            "done": true,
+            "prompt_eval_count": 26,
+            "eval_count": 298
+        });
+        let usage = extract_ollama_usage(&ollama).unwrap();
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (Some(26), Some(298), Some(324))
+        );
+
+        let cached = serde_json::json!({ "done": true, "eval_count": 40 });
+        let usage = extract_ollama_usage(&cached).unwrap();
+        assert_eq!((usage.prompt_tokens, usage.total_tokens), (None, None));
+        assert!(extract_ollama_usage(&serde_json::json!({ "done": true })).is_none());
+    }
+
+    #[test]
+    fn gemini_turns_keep_roles_and_merge_repeats() {
+        let msg = |role: &str, content: &str| AiChatMessage {
+            role: role.into(),
+            content: content.into(),
+            images: Vec::new(),
+        };
+        let (contents, system) = gemini_contents(&[
+            msg("system", "Be brief."),
+            msg("user", "Hi"),
+            msg("user", "Are you there?"),
+            msg("assistant", "Yes."),
+            msg("system", "Answer in English."),
+            msg("user", "Thanks"),
+        ]);
+        assert_eq!(system.as_deref(), Some("Be brief.\n\nAnswer in English."));
+        let roles: Vec<_> = contents
+            .iter()
+            .map(|c| c["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["user", "model", "user"]);
+        assert_eq!(contents[0]["parts"].as_array().unwrap().len(), 2);
+
+        let (only_system, system) = gemini_contents(&[msg("system", "Summarise this.")]);
+        assert!(system.is_none());
+        assert_eq!(only_system[0]["parts"][0]["text"], "Summarise this.");
+    }
+
+    fn test_selection(provider_id: &str, base_url: String) -> AiRuntimeSelection {
+        AiRuntimeSelection {
+            provider: AiProviderInfo {
+                id: provider_id.into(),
+                kind: "local".into(),
+                display_name: provider_id.into(),
+                description: None,
+                base_url: Some(base_url),
+                default_model: Some("m".into()),
+                models: vec!["m".into()],
+                capability_tags: Vec::new(),
+                requires_api_key: false,
+                has_credentials: false,
+                context_window: None,
+                is_custom: false,
+                request_timeout_secs: Some(5),
+                headers: Default::default(),
+                rate_limit_rpm: None,
+                default_temperature: None,
+                default_top_p: None,
+            },
+            model: "m".into(),
+            secret: Some("test-key".into()),
+            secret_source: None,
+        }
+    }
+
+    fn test_input() -> AiChatInput {
+        AiChatInput {
+            messages: vec![AiChatMessage {
+                role: "user".into(),
+                content: "hi".into(),
+                images: Vec::new(),
+            }],
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        }
+    }
+
+    /// Answer every request with `{}` and forward each JSON request body.
+    fn mock_provider() -> (String, std::sync::mpsc::Receiver<Value>) {
+        mock_provider_replying(serde_json::json!({}))
+    }
+
+    /// Answer every request with `reply` and forward each JSON request body.
+    fn mock_provider_replying(reply: Value) -> (String, std::sync::mpsc::Receiver<Value>) {
+        let reply = reply.to_string();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let base_url = crate::mock_provider::serve(move |_, request| {
+            let _ = tx.send(request.json());
+            Some(("200 OK", reply.clone()))
+        });
+        (base_url, rx)
+    }
+
+    #[tokio::test]
+    async fn stop_and_top_p_are_sent_only_when_set() {
+        let (base_url, requests) = mock_provider();
+        let orchestrator = AiOrchestrator::new(Default::default()).unwrap();
+        // (provider, pointer to the stop list, pointer to top_p)
+        let cases = [
+            ("lmstudio", "/stop", "/top_p"),
+            ("anthropic", "/stop_sequences", "/top_p"),
+            (
+                "google",
+                "/generationConfig/stopSequences",
+                "/generationConfig/topP",
+            ),
+            ("ollama", "/options/stop", "/options/top_p"),
+        ];
+        for (provider_id, stop_ptr, top_p_ptr) in cases {
+            let selection = test_selection(provider_id, base_url.clone());
+
+            orchestrator.chat(&selection, test_input()).await.unwrap();
+            let plain = requests.recv().unwrap();
+            assert!(plain.pointer(stop_ptr).is_none(), "{provider_id} sent stop");
+            assert!(
+                plain.pointer(top_p_ptr).is_none(),
+                "{provider_id} sent top_p"
+            );
+
+            let mut input = test_input();
+            input.stop = Some(vec!["\n\n".into()]);
+            input.top_p = Some(0.9);
+            orchestrator.chat(&selection, input).await.unwrap();
+            let tuned = requests.recv().unwrap();
+            assert_eq!(
+                tuned.pointer(stop_ptr).unwrap(),
+                &serde_json::json!(["\n\n"])
+            );
+            let top_p = tuned.pointer(top_p_ptr).unwrap().as_f64().unwrap();
+            assert!((top_p - 0.9).abs() < 1e-6, "{provider_id} top_p {top_p}");
+        }
+    }
+
+    #[test]
+    fn openai_json_mode_sends_the_schema_or_a_json_instruction() {
+        let selection = test_selection("openai", "http://localhost".into());
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "title": { "type": "string" } },
+        });
+        let mut input = test_input();
+        input.response_format = Some(ResponseFormat::Json {
+            schema: Some(schema.clone()),
+        });
+        let payload = openai_payload(&selection, &input);
+        assert_eq!(payload["response_format"]["type"], "json_schema");
+        assert_eq!(payload["response_format"]["json_schema"]["schema"], schema);
+        assert_eq!(payload["messages"].as_array().unwrap().len(), 1);
+
+        input.response_format = Some(ResponseFormat::Json { schema: None });
+        let payload = openai_payload(&selection, &input);
+        assert_eq!(
+            payload["response_format"],
+            serde_json::json!({ "type": "json_object" })
+        );
+        assert_eq!(payload["messages"][0]["role"], "system");
+        assert!(payload["messages"][0]["content"]
+            .as_str()
+            .unwrap()
+            .contains("JSON"));
+    }
+
+    #[test]
+    fn openai_reasoning_requests_leave_sampling_at_the_default() {
+        let selection = test_selection("openai", "http://localhost".into());
+        let mut input = test_input();
+        input.temperature = Some(0.7);
+        input.top_p = Some(0.9);
+        input.max_tokens = Some(2000);
+        let plain = openai_payload(&selection, &input);
+        assert!(plain.get("temperature").is_some());
+        assert!(plain.get("top_p").is_some());
+
+        input.reasoning_effort = Some(ReasoningEffort::Low);
+        let payload = openai_payload(&selection, &input);
+        assert_eq!(payload["reasoning_effort"], "low");
+        assert_eq!(payload["max_completion_tokens"], 2000);
+        assert!(payload.get("temperature").is_none());
+        assert!(payload.get("top_p").is_none());
+    }
+
+    #[tokio::test]
+    async fn anthropic_thinking_is_requested_and_kept_apart_from_the_answer() {
+        let (base_url, requests) = mock_provider_replying(serde_json::json!({
+            "content": [
+                { "type": "thinking", "thinking": "Weigh both dates.", "signature": "sig" },
+                { "type": "text", "text": "Friday." }
+            ],
+            "usage": { "input_tokens": 10, "output_tokens": 30 }
+        }));
+        let orchestrator = AiOrchestrator::new(Default::default()).unwrap();
+        let selection = test_selection("anthropic", base_url);
+
+        let plain = orchestrator.chat(&selection, test_input()).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(sent["max_tokens"], ANTHROPIC_DEFAULT_MAX_TOKENS);
+        assert!(sent.get("thinking").is_none());
+        assert_eq!(plain.content, "Friday.");
+
+        let mut input = test_input();
+        input.max_tokens = Some(8000);
+        input.top_p = Some(0.9);
+        input.reasoning_effort = Some(ReasoningEffort::Medium);
+        let response = orchestrator.chat(&selection, input).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(sent["max_tokens"], 8000);
+        assert_eq!(
+            sent["thinking"],
+            serde_json::json!({ "type": "enabled", "budget_tokens": 4096 })
+        );
+        assert!(sent.get("temperature").is_none());
+        assert!(sent.get("top_p").is_none());
+        assert_eq!(response.content, "Friday.");
+        assert_eq!(response.reasoning.as_deref(), Some("Weigh both dates."));
+
+        let mut input = test_input();
+        input.reasoning_effort = Some(ReasoningEffort::High);
+        orchestrator.chat(&selection, input).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(sent["max_tokens"], 16_384 + ANTHROPIC_DEFAULT_MAX_TOKENS);
+    }
+
+    #[tokio::test]
+    async fn cohere_requests_use_v2_messages_and_read_text_blocks() {
+        let (base_url, requests) = mock_provider_replying(serde_json::json!({
+            "id": "c1",
+            "finish_reason": "COMPLETE",
+            "message": {
+                "role": "assistant",
+                "content": [{ "type": "text", "text": "Hello from Cohere." }]
+            },
+            "usage": {
+                "billed_units": { "input_tokens": 5, "output_tokens": 4 },
+                "tokens": { "input_tokens": 71, "output_tokens": 4 }
+            }
+        }));
+        let selection = test_selection("cohere", base_url);
+        let mut input = test_input();
+        input.messages.insert(
+            0,
+            AiChatMessage {
+                role: "system".into(),
+                content: "Be brief.".into(),
+                images: Vec::new(),
+            },
+        );
+        input.top_p = Some(0.5);
+        input.max_tokens = Some(200);
+        let response = AiOrchestrator::new(Default::default())
+            .unwrap()
+            .chat(&selection, input)
+            .await
+            .unwrap();
+
+        let sent = requests.recv().unwrap();
+        assert_eq!(sent["model"], "m");
+        assert_eq!(
+            sent["messages"],
+            serde_json::json!([
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "hi" }
+            ])
+        );
+        assert_eq!(sent["max_tokens"], 200);
+        assert!((sent["p"].as_f64().unwrap() - 0.5).abs() < 1e-6);
+        assert_eq!(response.content, "Hello from Cohere.");
+        let usage = response.usage.unwrap();
+        assert_eq!(
+            (
+                usage.prompt_tokens,
+                usage.completion_tokens,
+                usage.total_tokens
+            ),
+            (Some(71), Some(4), Some(75))
+        );
+
+        let err = OrchestratorError::from_status(
+            "cohere",
+            StatusCode::from_u16(498).unwrap(),
+            None,
+            r#"{"id":"e1","message":"invalid api token"}"#,
+        );
+        assert_eq!(err.code(), "AI-1001");
+        assert!(err.to_string().ends_with(": invalid api token"));
+    }
+
+    #[test]
+    fn only_ollama_404s_name_a_missing_model() {
+        let body = r#"{"error":"model 'llama9' not found"}"#;
+        let classify = |provider_id: &str| {
+            let provider = test_selection(provider_id, String::new()).provider;
+            OrchestratorError::from_provider_status(&provider, StatusCode::NOT_FOUND, None, body)
+                .code()
+        };
+        assert_eq!(classify("ollama"), "AI-MODEL-MISSING");
+        assert_eq!(classify("openai"), "AI-1005");
+    }
+
+    #[tokio::test]
+    async fn stalled_pulls_time_out() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let line = "{\"status\":\"pulling manifest\"}\n";
+            let _ = stream.write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{line}\r\n",
+                    line.len()
+                )
+                .as_bytes(),
+            );
+            // Hold the stream open without sending anything more.
+            std::thread::sleep(Duration::from_secs(5));
+        });
+        let orchestrator = AiOrchestrator::new(Default::default()).unwrap();
+        let provider = test_selection("ollama", format!("http://{addr}")).provider;
+        let statuses = std::sync::Mutex::new(Vec::new());
+        let err = orchestrator
+            .pull_with_idle_timeout(
+                &provider,
+                "llama3.1",
+                &|progress: &PullProgress| statuses.lock().unwrap().push(progress.status.clone()),
+                Duration::from_millis(200),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "AI-1003");
+        assert_eq!(*statuses.lock().unwrap(), ["pulling manifest"]);
+    }
+
+    #[tokio::test]
+    async fn provider_sampling_defaults_fill_unset_fields() {
+        let (base_url, requests) = mock_provider();
+        let orchestrator = AiOrchestrator::new(Default::default()).unwrap();
+        let mut selection = test_selection("ollama", base_url);
+
+        orchestrator.chat(&selection, test_input()).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert!((sent["options"]["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        assert!(sent["options"].get("top_p").is_none());
+
+        selection.provider.default_temperature = Some(0.6);
+        selection.provider.default_top_p = Some(0.9);
+        orchestrator.chat(&selection, test_input()).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert!((sent["options"]["temperature"].as_f64().unwrap() - 0.6).abs() < 1e-6);
+        assert!((sent["options"]["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+
+        let mut input = test_input();
+        input.temperature = Some(0.0);
+        orchestrator.chat(&selection, input).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(sent["options"]["temperature"].as_f64(), Some(0.0));
+    }
+
+    #[test]
+    fn azure_requests_target_the_deployment() {
+        let mut provider = test_selection(
+            "contoso",
+            "https://contoso.openai.azure.com/?api-version=2024-10-21".into(),
+        )
+        .provider;
+        provider.kind = AZURE_OPENAI_KIND.into();
+        assert_eq!(
+            azure_url(&provider, "gpt4o-prod", "chat/completions")
+                .unwrap()
+                .as_str(),
+            "https://contoso.openai.azure.com/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-10-21"
+        );
+
+        provider.base_url = Some("https://contoso.openai.azure.com/openai/".into());
+        provider.capability_tags = vec!["api-version-2024-06-01".into()];
+        assert_eq!(
+            azure_url(&provider, "embed", "embeddings").unwrap().as_str(),
+            "https://contoso.openai.azure.com/openai/deployments/embed/embeddings?api-version=2024-06-01"
+        );
+
+        provider.capability_tags.clear();
+        assert!(azure_url(&provider, "embed", "embeddings").is_err());
+    }
+
+    #[tokio::test]
+    async fn images_reach_vision_providers_and_are_described_otherwise() {
+        let (base_url, requests) = mock_provider();
+        let orchestrator = AiOrchestrator::new(Default::default()).unwrap();
+        let mut input = test_input();
+        input.messages[0].images = vec![AiImage {
+            mime_type: "image/png".into(),
+            data: "aGk=".into(),
+        }];
+
+        let mut vision = test_selection("lmstudio", base_url.clone());
+        vision.provider.capability_tags = vec!["vision".into()];
+        orchestrator.chat(&vision, input.clone()).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert_eq!(
+            sent.pointer("/messages/0/content/1/image_url/url").unwrap(),
+            "data:image/png;base64,aGk="
+        );
+
+        let text_only = test_selection("ollama", base_url);
+        orchestrator.chat(&text_only, input).await.unwrap();
+        let sent = requests.recv().unwrap();
+        assert!(sent.pointer("/messages/0/images").is_none());
+        let content = sent
+            .pointer("/messages/0/content")
+            .unwrap()
+            .as_str()
+            .unwrap();
+        assert!(content.ends_with("[1 image attachment omitted: this model cannot view images]"));
+    }
+
+    #[tokio::test]
+    async fn provider_timeouts_surface_as_timeout_errors() {
+        // Accept connections but never answer.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().take(4).collect();
+        });
+
+        let mut selection = test_selection("lmstudio", format!("http://{addr}"));
+        selection.provider.request_timeout_secs = Some(1);
+        let input = test_input();
+        let err = AiOrchestrator::new(Default::default())
+            .unwrap()
+            .chat(&selection, input)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "AI-1003");
+    }
+}
@@ -14,6 +14,15 @@ pub struct ProviderSeed {
     pub models: &'static [&'static str],
     pub tags: &'static [&'static str],
     pub requires_api_key: bool,
+    /// Context window of `default_model`, in tokens.
+    pub context_window: Option<usize>,
+    /// Per-request HTTP timeout. Local runtimes get longer on slow hardware.
+    pub request_timeout_secs: Option<u64>,
+    /// Model used for note embeddings when `ai.embedding` is unset.
+    pub embedding_model: Option<&'static str>,
+    /// Sampling applied when a request leaves it unset.
+    pub default_temperature: Option<f32>,
+    pub default_top_p: Option<f32>,
 }
 
 /// Providers the runtime knows about out of the box.
@@ -26,8 +35,13 @@ pub const PROVIDER_SEEDS: &[ProviderSeed] = &[
         base_url: Some("https://api.openai.com"),
         default_model: "gpt-4o-mini",
         models: &["gpt-4o", "gpt-4o-mini", "gpt-4.1", "gpt-3.5-turbo"],
-        tags: &["chat", "multimodal", "tools", "ctx-128k"],
+        tags: &["chat", "multimodal", "vision", "tools", "ctx-128k"],
         requires_api_key: true,
+        context_window: Some(128_000),
+        request_timeout_secs: Some(45),
+        embedding_model: Some("text-embedding-3-small"),
+        default_temperature: Some(0.2),
+        default_top_p: None,
     },
     ProviderSeed {
         id: "anthropic",
@@ -37,8 +51,13 @@ pub const PROVIDER_SEEDS: &[ProviderSeed] = &[
         base_url: Some("https://api.anthropic.com"),
         default_model: "claude-3-opus-20240229",
         models: &["claude-3-opus-20240229", "claude-3-5-sonnet-20241022", "claude-3-haiku-20240307"],
-        tags: &["chat", "analysis", "long-context", "ctx-200k"],
+        tags: &["chat", "analysis", "long-context", "vision", "ctx-200k"],
         requires_api_key: true,
+        context_window: Some(200_000),
+        request_timeout_secs: Some(45),
+        embedding_model: None,
+        default_temperature: Some(0.3),
+        default_top_p: None,
     },
     ProviderSeed {
         id: "google",
@@ -48,8 +67,29 @@ pub const PROVIDER_SEEDS: &[ProviderSeed] = &[
         base_url: Some("https://generativelanguage.googleapis.com/v1beta"),
         default_model: "models/gemini-2.0-flash",
         models: &["models/gemini-2.0-flash", "models/gemini-1.5-pro", "models/gemini-1.5-flash"],
-        tags: &["chat", "multimodal", "ctx-120k"],
+        tags: &["chat", "multimodal", "vision", "ctx-120k"],
         requires_api_key: true,
+        context_window: Some(1_048_576),
+        request_timeout_secs: Some(45),
+        embedding_model: None,
+        default_temperature: Some(0.4),
+        default_top_p: None,
+    },
+    ProviderSeed {
+        id: "cohere",
+        kind: "cloud",
+        display: "Cohere Command",
+        description: "Cohere's Command models are tuned for retrieval-grounded chat and long documents.",
+        base_url: Some("https://api.cohere.com"),
+        default_model: "command-r-plus",
+        models: &["command-r-plus", "command-r", "command-a-03-2025"],
+        tags: &["chat", "long-context", "ctx-128k"],
+        requires_api_key: true,
+        context_window: Some(128_000),
+        request_timeout_secs: Some(45),
+        embedding_model: None,
+        default_temperature: Some(0.3),
+        default_top_p: None,
     },
     ProviderSeed {
         id: "ollama",
@@ -61,6 +101,11 @@ pub const PROVIDER_SEEDS: &[ProviderSeed] = &[
         models: &["llama3.1", "mistral", "codellama", "phi3"],
         tags: &["chat", "local", "ctx-8k"],
         requires_api_key: false,
+        context_window: Some(8_192),
+        request_timeout_secs: Some(120),
+        embedding_model: Some("nomic-embed-text"),
+        default_temperature: Some(0.6),
+        default_top_p: Some(0.9),
     },
     ProviderSeed {
         id: "lmstudio",
@@ -72,5 +117,184 @@ pub const PROVIDER_SEEDS: &[ProviderSeed] = &[
         models: &["lmstudio-community/llama-3-8b-instruct", "lmstudio-community/qwen2-7b-instruct"],
         tags: &["chat", "local", "openai-compatible", "ctx-8k"],
         requires_api_key: false,
+        context_window: Some(8_192),
+        request_timeout_secs: Some(120),
+        embedding_model: Some("text-embedding-nomic-embed-text-v1.5"),
+        default_temperature: Some(0.6),
+        default_top_p: Some(0.9),
+    },
+];
+
+/// Known context window for a bundled model, in tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelContextSeed {
+    pub provider_id: &'static str,
+    pub model: &'static str,
+    pub context_window: usize,
+}
+
+/// Context windows seeded into `model_context` on first run.
+///
+/// Local runtimes are listed at the window they load with by default rather
+/// than the model's theoretical maximum.
+pub const MODEL_CONTEXT_SEEDS: &[ModelContextSeed] = &[
+    ModelContextSeed {
+        provider_id: "openai",
+        model: "gpt-4o",
+        context_window: 128_000,
+    },
+    ModelContextSeed {
+        provider_id: "openai",
+        model: "gpt-4o-mini",
+        context_window: 128_000,
+    },
+    ModelContextSeed {
+        provider_id: "openai",
+        model: "gpt-4.1",
+        context_window: 1_047_576,
+    },
+    ModelContextSeed {
+        provider_id: "openai",
+        model: "gpt-3.5-turbo",
+        context_window: 16_385,
+    },
+    ModelContextSeed {
+        provider_id: "anthropic",
+        model: "claude-3-opus-20240229",
+        context_window: 200_000,
+    },
+    ModelContextSeed {
+        provider_id: "anthropic",
+        model: "claude-3-5-sonnet-20241022",
+        context_window: 200_000,
+    },
+    ModelContextSeed {
+        provider_id: "anthropic",
+        model: "claude-3-haiku-20240307",
+        context_window: 200_000,
+    },
+    ModelContextSeed {
+        provider_id: "google",
+        model: "models/gemini-2.0-flash",
+        context_window: 1_048_576,
+    },
+    ModelContextSeed {
+        provider_id: "google",
+        model: "models/gemini-1.5-pro",
+        context_window: 2_097_152,
+    },
+    ModelContextSeed {
+        provider_id: "google",
+        model: "models/gemini-1.5-flash",
+        context_window: 1_048_576,
+    },
+    ModelContextSeed {
+        provider_id: "cohere",
+        model: "command-r-plus",
+        context_window: 128_000,
+    },
+    ModelContextSeed {
+        provider_id: "cohere",
+        model: "command-r",
+        context_window: 128_000,
+    },
+    ModelContextSeed {
+        provider_id: "cohere",
+        model: "command-a-03-2025",
+        context_window: 256_000,
+    },
+    ModelContextSeed {
+        provider_id: "ollama",
+        model: "llama3.1",
+        context_window: 8_192,
+    },
+    ModelContextSeed {
+        provider_id: "lmstudio",
+        model: "lmstudio-community/llama-3-8b-instruct",
+        context_window: 8_192,
+    },
+];
+
+/// Published per-token pricing for a cloud model, in USD per million tokens.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPrice {
+    pub model: &'static str,
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+/// Price table used to estimate spend for conversations.
+///
+/// Local runtimes are free and therefore intentionally absent.
+pub const MODEL_PRICES: &[ModelPrice] = &[
+    ModelPrice {
+        model: "gpt-4o",
+        input_per_mtok: 2.50,
+        output_per_mtok: 10.00,
+    },
+    ModelPrice {
+        model: "gpt-4o-mini",
+        input_per_mtok: 0.15,
+        output_per_mtok: 0.60,
+    },
+    ModelPrice {
+        model: "gpt-4.1",
+        input_per_mtok: 2.00,
+        output_per_mtok: 8.00,
+    },
+    ModelPrice {
+        model: "gpt-3.5-turbo",
+        input_per_mtok: 0.50,
+        output_per_mtok: 1.50,
+    },
+    ModelPrice {
+        model: "claude-3-opus-20240229",
+        input_per_mtok: 15.00,
+        output_per_mtok: 75.00,
+    },
+    ModelPrice {
+        model: "claude-3-5-sonnet-20241022",
+        input_per_mtok: 3.00,
+        output_per_mtok: 15.00,
+    },
+    ModelPrice {
+        model: "claude-3-haiku-20240307",
+        input_per_mtok: 0.25,
+        output_per_mtok: 1.25,
+    },
+    ModelPrice {
+        model: "models/gemini-2.0-flash",
+        input_per_mtok: 0.10,
+        output_per_mtok: 0.40,
+    },
+    ModelPrice {
+        model: "models/gemini-1.5-pro",
+        input_per_mtok: 1.25,
+        output_per_mtok: 5.00,
+    },
+    ModelPrice {
+        model: "models/gemini-1.5-flash",
+        input_per_mtok: 0.075,
+        output_per_mtok: 0.30,
+    },
+    ModelPrice {
+        model: "command-r-plus",
+        input_per_mtok: 2.50,
+        output_per_mtok: 10.00,
+    },
+    ModelPrice {
+        model: "command-r",
+        input_per_mtok: 0.15,
+        output_per_mtok: 0.60,
+    },
+    ModelPrice {
+        model: "command-a-03-2025",
+        input_per_mtok: 2.50,
+        output_per_mtok: 10.00,
     },
 ];
+
+/// Look up the price entry for a model id, if one is known.
+pub fn price_for_model(model: &str) -> Option<&'static ModelPrice> {
+    MODEL_PRICES.iter().find(|price| price.model == model)
+}
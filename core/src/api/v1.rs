@@ -4,23 +4,45 @@
 //! on background threads where needed, and return JSON-friendly payloads to
 //! the UI.
 
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::agents::config::{self, AiSettingsUpdate};
-use crate::agents::{AiChatInput, AiChatMessage, AiChatResponse};
+use crate::agents::orchestrator::parse_json_content;
+use crate::agents::{
+    AiChatInput, AiChatMessage, AiChatResponse, AiUsageMetrics, Capabilities, LocalRuntime,
+    ReasoningEffort, ResponseFormat,
+};
+use crate::attachments::{self, NoteAttachment};
+use crate::db::backup::{self as db_backup, BackupReport, RestoreReport};
+use crate::db::encryption as db_encryption;
 use crate::db::DbPool;
+use crate::embeddings::{self, SemanticHit};
+use crate::errors::{InkOsError, IpcError};
 use crate::logging::log_event;
-use crate::model_manager::ModelManager;
+use crate::model_manager::{self, BreakerStatus, FallbackOrder, ModelManager, RateLimitStatus};
+use crate::note_links::{self, NoteLink};
+use crate::note_markdown::{self, ExportReport, ImportReport, IMPORT_PROGRESS_EVENT};
+use crate::pagination::{cursor_params, fetch_limit, Cursor, Page, PinnedKey};
+use crate::revisions::{self, NoteRevision};
 use crate::summarizer::{
-    AppendResult, ConversationRecord, MessageRecord, RolloverOutcome, Summarizer, SummaryRecord,
+    AppendResult, ConversationMatches, ConversationRecord, ConversationUsage, ExportFormat,
+    HistoryUpdate, MessageRecord, RolloverOutcome, RolloverPreview, Summarizer, SummarizerConfig,
+    SummaryRecord,
+};
+use crate::tags;
+use crate::validation;
+use crate::workers::{
+    self, DigestSchedule, JobRunResult, JobSchedule, JobScheduler, LogbookSearchHit,
+    SchedulerStatus, WebhookConfig, WorkspaceStats,
 };
-use crate::workers::{JobRunResult, JobScheduler};
-use r2d2_sqlite::rusqlite::OptionalExtension;
+use base64::engine::general_purpose::STANDARD as B64_ENGINE;
+use base64::Engine;
+use r2d2_sqlite::rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tauri::{async_runtime::spawn_blocking, State};
-use time::macros::format_description;
-use time::Date;
+use tauri::{async_runtime::spawn_blocking, AppHandle, Emitter, State};
 use time::OffsetDateTime;
 use uuid::Uuid;
 
@@ -44,21 +66,49 @@ pub fn ping() -> serde_json::Value {
 
 /// Inspect the SQLite catalog to confirm the database is reachable.
 #[tauri::command]
-pub fn db_status(state: State<ApiState>) -> Result<serde_json::Value, String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
-    let mut stmt = conn
-        .prepare("SELECT name FROM sqlite_master WHERE type='table'")
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map([], |row| Ok(row.get::<_, String>(0)?))
-        .map_err(|e| e.to_string())?;
+pub fn db_status(state: State<ApiState>) -> Result<serde_json::Value, IpcError> {
+    let conn = state.db.get()?;
+    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type='table'")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
     let mut names = Vec::new();
     for r in rows {
-        names.push(r.map_err(|e| e.to_string())?);
+        names.push(r?);
     }
     Ok(serde_json::json!({ "ok": true, "tables": names }))
 }
 
+/// Connection pool occupancy reported by `db_pool_stats`.
+#[derive(Serialize)]
+pub struct DbPoolStats {
+    pub connections: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max_connections: u32,
+    pub min_idle: Option<u32>,
+    pub connection_timeout_ms: u64,
+}
+
+/// Report how many pooled connections are open, idle, and checked out.
+#[tauri::command]
+pub fn db_pool_stats(state: State<ApiState>) -> DbPoolStats {
+    let pool_state = state.db.state();
+    DbPoolStats {
+        connections: pool_state.connections,
+        idle: pool_state.idle_connections,
+        in_use: pool_state.connections - pool_state.idle_connections,
+        max_connections: state.db.max_size(),
+        min_idle: state.db.min_idle(),
+        connection_timeout_ms: state.db.connection_timeout().as_millis() as u64,
+    }
+}
+
+/// Dashboard counts for notes, conversations, summaries, jobs and AI calls.
+#[tauri::command]
+pub fn workspace_stats(state: State<ApiState>) -> Result<WorkspaceStats, IpcError> {
+    let conn = state.db.get()?;
+    Ok(workers::workspace_stats(&conn)?)
+}
+
 #[derive(Deserialize)]
 pub struct CreateNoteInput {
     pub title: String,
@@ -67,6 +117,9 @@ pub struct CreateNoteInput {
 #[derive(Serialize)]
 pub struct CreateNoteOutput {
     pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
 }
 
 #[derive(Serialize)]
@@ -76,6 +129,8 @@ pub struct AiSettingsView {
     pub warn_ratio: f32,
     pub force_ratio: f32,
     pub summarizer_model: Option<String>,
+    pub rollover_tail: usize,
+    pub rollover_tail_auto: bool,
 }
 
 /// Persist a note and log the action for the activity feed.
@@ -83,16 +138,16 @@ pub struct AiSettingsView {
 pub fn create_note(
     state: State<ApiState>,
     input: CreateNoteInput,
-) -> Result<CreateNoteOutput, String> {
+) -> Result<CreateNoteOutput, IpcError> {
+    let title = validation::required_text("title", &input.title)?;
     let id = Uuid::new_v4().to_string();
     let now = OffsetDateTime::now_utc().unix_timestamp();
     let body = input.body.unwrap_or_default();
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let conn = state.db.get()?;
     conn.execute(
         "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
-        (id.as_str(), input.title.as_str(), body.as_str(), now, now),
-    )
-    .map_err(|e| e.to_string())?;
+        (id.as_str(), title.as_str(), body.as_str(), now, now),
+    )?;
     log_event(
         &conn,
         "info",
@@ -101,58 +156,708 @@ pub fn create_note(
         "note created",
         Some("created via IPC"),
         Some(serde_json::json!({ "id": id })),
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(CreateNoteOutput { id })
+    )?;
+    queue_note_embedding(&state, &id, now);
+    Ok(CreateNoteOutput {
+        id,
+        title,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+/// Return a single live note, including its body.
+#[tauri::command]
+pub fn get_note(state: State<ApiState>, id: String) -> Result<NoteRecord, IpcError> {
+    let conn = state.db.get()?;
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_none() {
+        return Err(InkOsError::NoteNotFound.into());
+    }
+    load_note_record(&conn, &id)
+}
+
+#[derive(Deserialize)]
+pub struct UpdateNoteInput {
+    pub id: String,
+    pub title: Option<String>,
+    pub body: Option<String>,
+    /// Reject the write if the note's `updated_at` no longer matches.
+    pub expected_updated_at: Option<i64>,
+}
+
+/// Full note record returned after edits.
+#[derive(Serialize)]
+pub struct NoteRecord {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub pinned: bool,
+}
+
+/// Edit a note's title and/or body, refreshing its search index entry.
+///
+/// `updated_at` always advances so that optimistic concurrency checks detect
+/// consecutive edits made within the same second. When the text changes, the
+/// previous version is kept in the note's revision history.
+#[tauri::command]
+pub fn update_note(
+    state: State<ApiState>,
+    mut input: UpdateNoteInput,
+) -> Result<NoteRecord, IpcError> {
+    if let Some(title) = input.title.as_deref() {
+        input.title = Some(validation::required_text("title", title)?);
+    }
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut conn = state.db.get()?;
+    let tx = conn.transaction()?;
+    let changes_text: bool = tx
+        .query_row(
+            "SELECT (?2 IS NOT NULL AND ?2 != title) OR (?3 IS NOT NULL AND ?3 != body) FROM notes WHERE id = ?1",
+            params![input.id, input.title, input.body],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(false);
+    if changes_text {
+        revisions::snapshot(&tx, &input.id, now)?;
+    }
+    let updated = tx
+        .execute(
+            "UPDATE notes SET title = COALESCE(?2, title), body = COALESCE(?3, body), updated_at = MAX(?4, updated_at + 1)
+             WHERE id = ?1 AND deleted_at IS NULL AND (?5 IS NULL OR updated_at = ?5)",
+            params![input.id, input.title, input.body, now, input.expected_updated_at],
+        )?;
+    if updated == 0 {
+        let exists: Option<i64> = tx
+            .query_row(
+                "SELECT updated_at FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+                params![input.id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        return Err(match exists {
+            Some(_) => InkOsError::NoteConflict,
+            None => InkOsError::NoteNotFound,
+        }
+        .into());
+    }
+    tx.commit()?;
+    let note = load_note_record(&conn, &input.id)?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-UPDATE"),
+        "notes",
+        "note updated",
+        Some("edited via IPC"),
+        Some(serde_json::json!({ "id": note.id })),
+    )?;
+    queue_note_embedding(&state, &note.id, now);
+    Ok(note)
+}
+
+fn load_note_record(
+    conn: &r2d2_sqlite::rusqlite::Connection,
+    id: &str,
+) -> Result<NoteRecord, IpcError> {
+    Ok(conn.query_row(
+        "SELECT id, title, body, created_at, updated_at, pinned FROM notes WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(NoteRecord {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                body: row.get(2)?,
+                created_at: row.get(3)?,
+                updated_at: row.get(4)?,
+                pinned: row.get(5)?,
+            })
+        },
+    )?)
+}
+
+/// List the stored previous versions of a note, newest first.
+#[tauri::command]
+pub fn list_note_revisions(
+    state: State<ApiState>,
+    note_id: String,
+) -> Result<Vec<NoteRevision>, IpcError> {
+    let conn = state.db.get()?;
+    Ok(revisions::list(&conn, &note_id)?)
+}
+
+#[derive(Deserialize)]
+pub struct RestoreNoteRevisionInput {
+    pub note_id: String,
+    pub revision_id: String,
+}
+
+/// Write a previous version back as the note's current text.
+///
+/// The text being replaced is itself stored as a revision, so a restore can
+/// be undone by restoring again.
+#[tauri::command]
+pub fn restore_note_revision(
+    state: State<ApiState>,
+    input: RestoreNoteRevisionInput,
+) -> Result<NoteRecord, IpcError> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut conn = state.db.get()?;
+    let tx = conn.transaction()?;
+    let revision = revisions::get(&tx, &input.note_id, &input.revision_id)?.ok_or_else(|| {
+        InkOsError::ValidationFailed(format!(
+            "Revision '{}' does not belong to note '{}'",
+            input.revision_id, input.note_id
+        ))
+    })?;
+    let live: Option<i64> = tx
+        .query_row(
+            "SELECT 1 FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![input.note_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if live.is_none() {
+        return Err(InkOsError::NoteNotFound.into());
+    }
+    revisions::snapshot(&tx, &input.note_id, now)?;
+    tx.execute(
+        "UPDATE notes SET title = ?2, body = ?3, updated_at = MAX(?4, updated_at + 1) WHERE id = ?1",
+        params![input.note_id, revision.title, revision.body, now],
+    )?;
+    tx.commit()?;
+    let note = load_note_record(&conn, &input.note_id)?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-RESTORE-REV"),
+        "notes",
+        "note revision restored",
+        Some("restored via IPC"),
+        Some(serde_json::json!({ "id": note.id, "revision_id": revision.id })),
+    )?;
+    queue_note_embedding(&state, &note.id, now);
+    Ok(note)
+}
+
+/// Refresh a note's search vector in the background. Queueing failures are
+/// ignored so they never fail the edit itself.
+fn queue_note_embedding(state: &ApiState, note_id: &str, now: i64) {
+    let _ = state
+        .scheduler
+        .enqueue_at_blocking("notes.embed", json!({ "note_id": note_id }), now);
+}
+
+/// Notes ranked for `search_notes_semantic`.
+#[derive(Serialize)]
+pub struct SemanticSearchResult {
+    /// `"semantic"` when ranked by embeddings, `"fts"` after falling back.
+    pub mode: &'static str,
+    pub items: Vec<SemanticHit>,
+}
+
+/// Return the `k` notes closest in meaning to `query`.
+///
+/// Falls back to full-text search when no embedding model is configured or
+/// the embedding call fails; the failure itself is in the event log.
+#[tauri::command]
+pub async fn search_notes_semantic(
+    state: State<'_, ApiState>,
+    query: String,
+    k: Option<usize>,
+) -> Result<SemanticSearchResult, IpcError> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err(InkOsError::ValidationFailed("query must not be empty".into()).into());
+    }
+    let k = k.unwrap_or(10).clamp(1, 100);
+    let pool = state.db.clone();
+    let selection = spawn_blocking(move || {
+        let conn = pool.get()?;
+        Ok::<_, IpcError>(config::resolve_embedding_runtime(&conn)?)
+    })
+    .await??;
+
+    if let Some(selection) = selection {
+        if let Ok(mut vectors) = state
+            .model_manager
+            .embed(&selection, std::slice::from_ref(&query))
+            .await
+        {
+            let vector = vectors.pop().unwrap_or_default();
+            let pool = state.db.clone();
+            let items = spawn_blocking(move || {
+                let conn = pool.get()?;
+                Ok::<_, IpcError>(embeddings::search(
+                    &conn,
+                    &selection.provider.id,
+                    &selection.model,
+                    &vector,
+                    k,
+                )?)
+            })
+            .await??;
+            return Ok(SemanticSearchResult {
+                mode: "semantic",
+                items,
+            });
+        }
+    }
+
+    let pool = state.db.clone();
+    let items = spawn_blocking(move || {
+        let conn = pool.get()?;
+        // Quote each word so natural-language queries are not parsed as FTS syntax.
+        let terms = crate::db::fts_phrase_query(&query, " OR ");
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.title, -bm25(fts_notes) FROM fts_notes
+             JOIN notes n ON n.rowid = fts_notes.rowid
+             WHERE fts_notes MATCH ?1 AND n.deleted_at IS NULL
+             ORDER BY bm25(fts_notes) LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![terms, k as i64], |row| {
+            Ok(SemanticHit {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                score: row.get::<_, f64>(2)? as f32,
+            })
+        })?;
+        Ok::<_, IpcError>(rows.collect::<Result<Vec<_>, _>>()?)
+    })
+    .await??;
+    Ok(SemanticSearchResult { mode: "fts", items })
 }
 
 #[derive(Deserialize)]
 pub struct ListNotesInput {
     pub q: Option<String>,
+    #[serde(default)]
+    pub include_deleted: bool,
+    /// Only return notes carrying every one of these tags.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Continue after the last note of the previous page.
+    pub before: Option<Cursor<PinnedKey>>,
+    pub limit: Option<usize>,
 }
 
-/// Return notes optionally filtered by a full-text query.
+/// Return a page of notes optionally filtered by a full-text query.
+///
+/// Pinned notes come first, then the most recently edited. Soft-deleted
+/// notes are excluded unless `include_deleted` is set.
 #[tauri::command]
 pub fn list_notes(
     state: State<ApiState>,
     input: Option<ListNotesInput>,
-) -> Result<Vec<serde_json::Value>, String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+) -> Result<Page<serde_json::Value, PinnedKey>, IpcError> {
+    let conn = state.db.get()?;
+    let (q, include_deleted, tags, before, limit) = match input {
+        Some(i) => (
+            i.q,
+            i.include_deleted,
+            tags::normalize(&i.tags),
+            i.before,
+            i.limit,
+        ),
+        None => (None, false, Vec::new(), None, None),
+    };
+    let (before_key, before_id) = cursor_params(&before);
+    let (before_pinned, before_updated) =
+        before_key.map(|key| (key.pinned, key.updated_at)).unzip();
+    let tag_count = tags.len() as i64;
+    let tags_json = serde_json::to_string(&tags)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, created_at, deleted_at,
+                    (SELECT group_concat(t.name, ',') FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = notes.id),
+                    updated_at, pinned
+             FROM notes
+             WHERE (?1 IS NULL OR rowid IN (SELECT rowid FROM fts_notes WHERE fts_notes MATCH ?1))
+               AND (?2 OR deleted_at IS NULL)
+               AND (?3 = 0 OR id IN (
+                 SELECT nt.note_id FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                 WHERE t.name IN (SELECT value FROM json_each(?4))
+                 GROUP BY nt.note_id HAVING COUNT(*) = ?3))
+               AND (?5 IS NULL OR (pinned, updated_at, id) < (?8, ?5, ?6))
+             ORDER BY pinned DESC, updated_at DESC, id DESC LIMIT ?7",
+        )?;
+    let params = params![
+        q,
+        include_deleted,
+        tag_count,
+        tags_json,
+        before_updated,
+        before_id,
+        fetch_limit(limit),
+        before_pinned
+    ];
+    let rows = stmt.query_map(params, |row| {
+        let tags: Option<String> = row.get(4)?;
+        Ok(serde_json::json!({
+            "id": row.get::<_, String>(0)?,
+            "title": row.get::<_, String>(1)?,
+            "created_at": row.get::<_, i64>(2)?,
+            "updated_at": row.get::<_, i64>(5)?,
+            "deleted_at": row.get::<_, Option<i64>>(3)?,
+            "pinned": row.get::<_, bool>(6)?,
+            "tags": tags
+                .map(|raw| raw.split(',').map(str::to_string).collect::<Vec<_>>())
+                .unwrap_or_default()
+        }))
+    })?;
     let mut results = Vec::new();
-    if let Some(i) = input {
-        if let Some(q) = i.q {
-            let mut stmt = conn.prepare("SELECT id, title, created_at FROM notes WHERE rowid IN (SELECT rowid FROM fts_notes WHERE fts_notes MATCH ?1) ORDER BY created_at DESC").map_err(|e| e.to_string())?;
-            let rows = stmt
-                .query_map([q], |row| {
-                    Ok(serde_json::json!({
-                        "id": row.get::<_, String>(0)?,
-                        "title": row.get::<_, String>(1)?,
-                        "created_at": row.get::<_, i64>(2)?
-                    }))
-                })
-                .map_err(|e| e.to_string())?;
-            for r in rows {
-                results.push(r.map_err(|e| e.to_string())?);
-            }
-            return Ok(results);
-        }
+    for r in rows {
+        results.push(r?);
     }
-    let mut stmt = conn
-        .prepare("SELECT id, title, created_at FROM notes ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(serde_json::json!({
-                "id": row.get::<_, String>(0)?,
-                "title": row.get::<_, String>(1)?,
-                "created_at": row.get::<_, i64>(2)?
-            }))
+    Ok(Page::from_rows(results, limit, |note| Cursor {
+        key: PinnedKey {
+            pinned: note["pinned"].as_bool().unwrap_or_default(),
+            updated_at: note["updated_at"].as_i64().unwrap_or_default(),
+        },
+        id: note["id"].as_str().unwrap_or_default().to_string(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct SetNoteTagsInput {
+    pub note_id: String,
+    pub tags: Vec<String>,
+}
+
+/// Tag with the number of live notes carrying it.
+#[derive(Serialize)]
+pub struct TagCount {
+    pub name: String,
+    pub note_count: i64,
+}
+
+/// Replace a live note's tags, returning the normalised tag names.
+///
+/// Tags are trimmed, lowercased, and de-duplicated, and may not contain
+/// commas; tags no longer attached to any note are removed.
+#[tauri::command]
+pub fn set_note_tags(
+    state: State<ApiState>,
+    input: SetNoteTagsInput,
+) -> Result<Vec<String>, IpcError> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let tags = validation::tag_names("tags", &input.tags)?;
+    let mut conn = state.db.get()?;
+    let tx = conn.transaction()?;
+    let exists: Option<String> = tx
+        .query_row(
+            "SELECT id FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![input.note_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_none() {
+        return Err(InkOsError::NoteNotFound.into());
+    }
+    tags::replace(&tx, &input.note_id, &tags, now)?;
+    tx.commit()?;
+    Ok(tags)
+}
+
+/// List every tag with its note count, most used first.
+#[tauri::command]
+pub fn list_tags(state: State<ApiState>) -> Result<Vec<TagCount>, IpcError> {
+    let conn = state.db.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT t.name, COUNT(n.id) FROM tags t
+             LEFT JOIN note_tags nt ON nt.tag_id = t.id
+             LEFT JOIN notes n ON n.id = nt.note_id AND n.deleted_at IS NULL
+             GROUP BY t.id ORDER BY COUNT(n.id) DESC, t.name ASC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TagCount {
+            name: row.get(0)?,
+            note_count: row.get(1)?,
         })
-        .map_err(|e| e.to_string())?;
-    for r in rows {
-        results.push(r.map_err(|e| e.to_string())?);
+    })?;
+    let mut tags = Vec::new();
+    for row in rows {
+        tags.push(row?);
+    }
+    Ok(tags)
+}
+
+/// Soft-delete a live note, removing it from search until restored.
+#[tauri::command]
+pub fn delete_note(state: State<ApiState>, id: String) -> Result<(), IpcError> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let conn = state.db.get()?;
+    set_note_deleted_at(&conn, &id, Some(now))?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-DELETE"),
+        "notes",
+        "note deleted",
+        Some("soft-deleted via IPC; restore_note brings it back"),
+        Some(serde_json::json!({ "id": id })),
+    )?;
+    Ok(())
+}
+
+/// Restore a soft-deleted note and re-index it for search. Restoring a
+/// live note is rejected.
+#[tauri::command]
+pub fn restore_note(state: State<ApiState>, id: String) -> Result<(), IpcError> {
+    let conn = state.db.get()?;
+    set_note_deleted_at(&conn, &id, None)?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-RESTORE"),
+        "notes",
+        "note restored",
+        Some("restored via IPC"),
+        Some(serde_json::json!({ "id": id })),
+    )?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct SetNotePinnedInput {
+    pub note_id: String,
+    pub pinned: bool,
+}
+
+/// Pin or unpin a live note. Pinning is not an edit, so `updated_at` and the
+/// revision history are left alone.
+#[tauri::command]
+pub fn set_note_pinned(
+    state: State<ApiState>,
+    input: SetNotePinnedInput,
+) -> Result<NoteRecord, IpcError> {
+    let conn = state.db.get()?;
+    let updated = conn.execute(
+        "UPDATE notes SET pinned = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![input.note_id, input.pinned],
+    )?;
+    if updated == 0 {
+        return Err(InkOsError::NoteNotFound.into());
+    }
+    load_note_record(&conn, &input.note_id)
+}
+
+#[derive(Deserialize)]
+pub struct AddNoteAttachmentInput {
+    pub note_id: String,
+    pub filename: String,
+    pub mime: String,
+    /// File contents, base64-encoded.
+    pub data: String,
+}
+
+/// Store a file (for example a pasted screenshot) alongside a note.
+#[tauri::command]
+pub fn add_note_attachment(
+    state: State<ApiState>,
+    input: AddNoteAttachmentInput,
+) -> Result<NoteAttachment, IpcError> {
+    let bytes = B64_ENGINE.decode(input.data.trim()).map_err(|err| {
+        InkOsError::ValidationFailed(format!("attachment data is not valid base64: {err}"))
+    })?;
+    let conn = state.db.get()?;
+    let attachment = attachments::add(
+        &conn,
+        &input.note_id,
+        &input.filename,
+        input.mime.trim(),
+        &bytes,
+    )?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-ATTACH"),
+        "notes",
+        "attachment added",
+        Some("added via IPC"),
+        Some(json!({
+            "note_id": attachment.note_id,
+            "attachment_id": attachment.id,
+            "mime": attachment.mime,
+            "size_bytes": attachment.size_bytes,
+        })),
+    )?;
+    Ok(attachment)
+}
+
+/// List a note's attachments, oldest first.
+#[tauri::command]
+pub fn list_note_attachments(
+    state: State<ApiState>,
+    note_id: String,
+) -> Result<Vec<NoteAttachment>, IpcError> {
+    let conn = state.db.get()?;
+    Ok(attachments::list(&conn, &note_id)?)
+}
+
+/// Delete an attachment and its stored file.
+#[tauri::command]
+pub fn delete_note_attachment(state: State<ApiState>, id: String) -> Result<(), IpcError> {
+    let conn = state.db.get()?;
+    attachments::delete(&conn, &id)?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-DETACH"),
+        "notes",
+        "attachment deleted",
+        Some("deleted via IPC"),
+        Some(json!({ "attachment_id": id })),
+    )?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct LinkNotesInput {
+    pub src_id: String,
+    pub dst_id: String,
+    pub rel: Option<String>,
+}
+
+/// Link one note to another; `rel` defaults to `related`.
+#[tauri::command]
+pub fn link_notes(state: State<ApiState>, input: LinkNotesInput) -> Result<NoteLink, IpcError> {
+    let conn = state.db.get()?;
+    let link = note_links::link(&conn, &input.src_id, &input.dst_id, input.rel.as_deref())?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-LINK"),
+        "notes",
+        "notes linked",
+        Some("linked via IPC"),
+        Some(
+            json!({ "link_id": link.link_id, "src_id": input.src_id, "dst_id": input.dst_id, "rel": link.rel }),
+        ),
+    )?;
+    Ok(link)
+}
+
+/// A note's outgoing links followed by its backlinks.
+#[tauri::command]
+pub fn list_note_links(state: State<ApiState>, note_id: String) -> Result<Vec<NoteLink>, IpcError> {
+    let conn = state.db.get()?;
+    Ok(note_links::list(&conn, &note_id)?)
+}
+
+/// Remove the links from `src_id` to `dst_id`, returning how many were removed.
+#[tauri::command]
+pub fn unlink_notes(state: State<ApiState>, input: LinkNotesInput) -> Result<usize, IpcError> {
+    let conn = state.db.get()?;
+    let removed = note_links::unlink(&conn, &input.src_id, &input.dst_id, input.rel.as_deref())?;
+    log_event(
+        &conn,
+        "info",
+        Some("NTE-UNLINK"),
+        "notes",
+        "notes unlinked",
+        Some("unlinked via IPC"),
+        Some(
+            json!({ "src_id": input.src_id, "dst_id": input.dst_id, "rel": input.rel, "removed": removed }),
+        ),
+    )?;
+    Ok(removed)
+}
+
+/// Import every Markdown file under `dir_path` as a note, emitting
+/// `notes://import-progress` after each file.
+#[tauri::command]
+pub async fn import_notes(
+    app: AppHandle,
+    state: State<'_, ApiState>,
+    dir_path: String,
+) -> Result<ImportReport, IpcError> {
+    let state = state.inner().clone();
+    let report = spawn_blocking(move || {
+        let mut conn = state.db.get()?;
+        let dir = dir_path.trim();
+        let report = note_markdown::import_dir(&mut conn, Path::new(dir), &|progress| {
+            let _ = app.emit(IMPORT_PROGRESS_EVENT, progress);
+        })?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for id in &report.note_ids {
+            queue_note_embedding(&state, id, now);
+        }
+        log_event(
+            &conn,
+            "info",
+            Some("NTE-IMPORT"),
+            "notes",
+            "Markdown notes imported",
+            Some("imported via IPC"),
+            Some(json!({ "dir": dir, "imported": report.imported, "failed": report.failed.len() })),
+        )?;
+        Ok::<_, IpcError>(report)
+    })
+    .await??;
+    Ok(report)
+}
+
+/// Write every live note as Markdown into a zip archive at `dest_path`.
+#[tauri::command]
+pub async fn export_notes(
+    state: State<'_, ApiState>,
+    dest_path: String,
+) -> Result<ExportReport, IpcError> {
+    let pool = state.db.clone();
+    let report = spawn_blocking(move || {
+        let conn = pool.get()?;
+        let report = note_markdown::export_zip(&conn, Path::new(dest_path.trim()))?;
+        log_event(
+            &conn,
+            "info",
+            Some("NTE-EXPORT"),
+            "notes",
+            "Notes exported to Markdown archive",
+            None,
+            Some(json!({ "path": report.path, "bytes": report.bytes, "notes": report.notes })),
+        )?;
+        Ok::<_, IpcError>(report)
+    })
+    .await??;
+    Ok(report)
+}
+
+fn set_note_deleted_at(
+    conn: &r2d2_sqlite::rusqlite::Connection,
+    id: &str,
+    deleted_at: Option<i64>,
+) -> Result<(), IpcError> {
+    // Only live notes can be deleted and only trashed ones restored.
+    let updated = conn.execute(
+        "UPDATE notes SET deleted_at = ?2 WHERE id = ?1 AND (deleted_at IS NULL) = (?2 IS NOT NULL)",
+        params![id, deleted_at],
+    )?;
+    if updated > 0 {
+        return Ok(());
+    }
+    let live: Option<bool> = conn
+        .query_row(
+            "SELECT deleted_at IS NULL FROM notes WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match live {
+        Some(true) if deleted_at.is_none() => {
+            Err(InkOsError::ValidationFailed(format!("note '{id}' is not in the trash")).into())
+        }
+        _ => Err(InkOsError::NoteNotFound.into()),
     }
-    Ok(results)
 }
 
 /// Summarised view of each logbook record.
@@ -161,58 +866,169 @@ pub struct LogbookEntry {
     pub id: String,
     pub entry_date: String,
     pub summary: String,
+    /// Hand-written addendum; the daily digest never overwrites it.
+    pub manual_notes: Option<String>,
     pub created_at: i64,
 }
 
+fn logbook_entry_from_row(
+    row: &r2d2_sqlite::rusqlite::Row,
+) -> r2d2_sqlite::rusqlite::Result<LogbookEntry> {
+    Ok(LogbookEntry {
+        id: row.get(0)?,
+        entry_date: row.get(1)?,
+        summary: row.get(2)?,
+        manual_notes: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
 /// List daily logbook entries, ensuring today's digest is queued if missing.
 #[tauri::command]
 pub fn list_logbook_entries(
     state: State<ApiState>,
     limit: Option<usize>,
-) -> Result<Vec<LogbookEntry>, String> {
+    before: Option<Cursor<String>>,
+) -> Result<Page<LogbookEntry, String>, IpcError> {
     ensure_today_digest(&state)?;
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let conn = state.db.get()?;
+    let (before_key, before_id) = cursor_params(&before);
 
+    let mut stmt = conn.prepare(
+        "SELECT id, entry_date, summary, manual_notes, created_at FROM logbook_entries
+             WHERE (?1 IS NULL OR entry_date < ?1 OR (entry_date = ?1 AND id < ?2))
+             ORDER BY entry_date DESC, id DESC LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![before_key, before_id, fetch_limit(limit)],
+        logbook_entry_from_row,
+    )?;
     let mut entries = Vec::new();
-    if let Some(limit) = limit {
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, entry_date, summary, created_at FROM logbook_entries ORDER BY entry_date DESC LIMIT ?1",
-            )
-            .map_err(|e| e.to_string())?;
-        let rows = stmt
-            .query_map([limit as i64], |row| {
-                Ok(LogbookEntry {
-                    id: row.get(0)?,
-                    entry_date: row.get(1)?,
-                    summary: row.get(2)?,
-                    created_at: row.get(3)?,
-                })
-            })
-            .map_err(|e| e.to_string())?;
-        for row in rows {
-            entries.push(row.map_err(|e| e.to_string())?);
+    for row in rows {
+        entries.push(row?);
+    }
+    Ok(Page::from_rows(entries, limit, |entry| Cursor {
+        key: entry.entry_date.clone(),
+        id: entry.id.clone(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct UpdateLogbookNoteInput {
+    pub entry_date: String,
+    /// Replacement addendum; blank text clears it.
+    pub text: String,
+}
+
+/// Set the hand-written note on an existing day's logbook entry. The AI
+/// summary is left as is, and later digests keep the note.
+#[tauri::command]
+pub fn update_logbook_note(
+    state: State<ApiState>,
+    input: UpdateLogbookNoteInput,
+) -> Result<LogbookEntry, IpcError> {
+    let entry_date = validation::parse_date("entry_date", &input.entry_date)?.to_string();
+    let text = input.text.trim();
+    let manual_notes = (!text.is_empty()).then_some(text);
+    let conn = state.db.get()?;
+    let updated = conn.execute(
+        "UPDATE logbook_entries SET manual_notes = ?2 WHERE entry_date = ?1",
+        params![entry_date, manual_notes],
+    )?;
+    if updated == 0 {
+        return Err(InkOsError::InvalidField {
+            field: "entry_date".into(),
+            reason: format!("no logbook entry exists for {entry_date}"),
         }
-        return Ok(entries);
+        .into());
     }
+    let entry = conn.query_row(
+        "SELECT id, entry_date, summary, manual_notes, created_at FROM logbook_entries
+             WHERE entry_date = ?1",
+        params![entry_date],
+        logbook_entry_from_row,
+    )?;
+    Ok(entry)
+}
+
+/// Full-text search over logbook summaries and manual notes, optionally
+/// within an inclusive date range.
+#[tauri::command]
+pub fn search_logbook(
+    state: State<ApiState>,
+    query: String,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<LogbookSearchHit>, IpcError> {
+    let query = validation::required_text("query", &query)?;
+    let from = from_date
+        .map(|value| validation::parse_date("from_date", &value))
+        .transpose()?;
+    let to = to_date
+        .map(|value| validation::parse_date("to_date", &value))
+        .transpose()?;
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    let conn = state.db.get()?;
+    Ok(workers::search_logbook(&conn, &query, from, to, limit)?)
+}
+
+/// Weekly or monthly rollup of logbook entries.
+#[derive(Serialize)]
+pub struct RollupEntry {
+    pub id: String,
+    pub period: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub summary: String,
+    pub entry_count: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
 
+/// Return a page of rollup entries, newest period first, optionally
+/// restricted to a `weekly`/`monthly` period. Pages hold 50 entries unless
+/// `limit` asks for 1–200.
+#[tauri::command]
+pub fn list_rollup_entries(
+    state: State<ApiState>,
+    period: Option<String>,
+    limit: Option<usize>,
+    before: Option<Cursor<String>>,
+) -> Result<Page<RollupEntry, String>, IpcError> {
+    let conn = state.db.get()?;
+    let limit = Some(limit.unwrap_or(50).clamp(1, 200));
+    let (before_key, before_id) = cursor_params(&before);
     let mut stmt = conn
-        .prepare("SELECT id, entry_date, summary, created_at FROM logbook_entries ORDER BY entry_date DESC")
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map([], |row| {
-            Ok(LogbookEntry {
+        .prepare(
+            "SELECT id, period, period_start, period_end, summary, entry_count, created_at, updated_at FROM rollup_entries
+             WHERE (?1 IS NULL OR period = ?1)
+               AND (?3 IS NULL OR period_start < ?3 OR (period_start = ?3 AND id < ?4))
+             ORDER BY period_start DESC, id DESC LIMIT ?2",
+        )?;
+    let rows = stmt.query_map(
+        params![period, fetch_limit(limit), before_key, before_id],
+        |row| {
+            Ok(RollupEntry {
                 id: row.get(0)?,
-                entry_date: row.get(1)?,
-                summary: row.get(2)?,
-                created_at: row.get(3)?,
+                period: row.get(1)?,
+                period_start: row.get(2)?,
+                period_end: row.get(3)?,
+                summary: row.get(4)?,
+                entry_count: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
             })
-        })
-        .map_err(|e| e.to_string())?;
+        },
+    )?;
+    let mut entries = Vec::new();
     for row in rows {
-        entries.push(row.map_err(|e| e.to_string())?);
+        entries.push(row?);
     }
-    Ok(entries)
+    Ok(Page::from_rows(entries, limit, |entry| Cursor {
+        key: entry.period_start.clone(),
+        id: entry.id.clone(),
+    }))
 }
 
 /// Timeline event DTO surfaced to the frontend.
@@ -224,51 +1040,123 @@ pub struct TimelineEvent {
     pub kind: String,
     pub title: String,
     pub detail: Option<String>,
+    /// `derived` for digest-generated events, `manual` for user-added ones.
+    pub source: String,
     pub created_at: i64,
 }
 
+fn map_timeline_event(
+    row: &r2d2_sqlite::rusqlite::Row,
+) -> r2d2_sqlite::rusqlite::Result<TimelineEvent> {
+    Ok(TimelineEvent {
+        id: row.get(0)?,
+        entry_date: row.get(1)?,
+        event_time: row.get(2)?,
+        kind: row.get(3)?,
+        title: row.get(4)?,
+        detail: row.get(5)?,
+        source: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
 /// Fetch timeline events for a specific day.
 #[tauri::command]
 pub fn list_timeline_events(
     state: State<ApiState>,
     date: Option<String>,
-) -> Result<Vec<TimelineEvent>, String> {
+) -> Result<Vec<TimelineEvent>, IpcError> {
+    let requested = date
+        .map(|value| validation::parse_date("date", &value))
+        .transpose()?;
     ensure_today_digest(&state)?;
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+    let conn = state.db.get()?;
 
-    let resolved_date = if let Some(value) = date {
-        Date::parse(&value, &format_description!("[year]-[month]-[day]"))
-            .map_err(|e| e.to_string())?
-    } else {
-        OffsetDateTime::now_utc().date()
+    let resolved_date = match requested {
+        Some(date) => date,
+        None => state.scheduler.local_today_blocking()?,
     };
     let date_key = resolved_date.to_string();
 
     let mut stmt = conn
-        .prepare("SELECT id, entry_date, event_time, kind, title, detail, created_at FROM timeline_events WHERE entry_date = ?1 ORDER BY event_time ASC")
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map([date_key.as_str()], |row| {
-            let detail: Option<String> = row.get(5)?;
-            Ok(TimelineEvent {
-                id: row.get(0)?,
-                entry_date: row.get(1)?,
-                event_time: row.get(2)?,
-                kind: row.get(3)?,
-                title: row.get(4)?,
-                detail,
-                created_at: row.get(6)?,
-            })
-        })
-        .map_err(|e| e.to_string())?;
+        .prepare("SELECT id, entry_date, event_time, kind, title, detail, source, created_at FROM timeline_events WHERE entry_date = ?1 ORDER BY event_time ASC")?;
+    let rows = stmt.query_map([date_key.as_str()], map_timeline_event)?;
 
     let mut events = Vec::new();
     for row in rows {
-        events.push(row.map_err(|e| e.to_string())?);
+        events.push(row?);
     }
     Ok(events)
 }
 
+#[derive(Deserialize)]
+pub struct AddTimelineEventInput {
+    pub entry_date: String,
+    pub kind: String,
+    pub title: String,
+    pub detail: Option<String>,
+    /// Unix seconds; defaults to now.
+    pub event_time: Option<i64>,
+}
+
+/// Add a user-authored event, such as a release marker, to a day's timeline.
+/// Manual events survive digest re-runs, which only rebuild derived events.
+#[tauri::command]
+pub fn add_timeline_event(
+    state: State<ApiState>,
+    input: AddTimelineEventInput,
+) -> Result<TimelineEvent, IpcError> {
+    let entry_date = validation::parse_date("entry_date", &input.entry_date)?.to_string();
+    let kind = validation::required_text("kind", &input.kind)?;
+    let title = validation::required_text("title", &input.title)?;
+    let detail = input
+        .detail
+        .map(|detail| detail.trim().to_string())
+        .filter(|detail| !detail.is_empty());
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let event = TimelineEvent {
+        id: Uuid::new_v4().to_string(),
+        entry_date,
+        event_time: input.event_time.unwrap_or(now),
+        kind,
+        title,
+        detail,
+        source: "manual".to_string(),
+        created_at: now,
+    };
+    let conn = state.db.get()?;
+    conn.execute(
+        "INSERT INTO timeline_events (id, entry_date, event_time, kind, title, detail, source, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            event.id,
+            event.entry_date,
+            event.event_time,
+            event.kind,
+            event.title,
+            event.detail,
+            event.source,
+            event.created_at
+        ],
+    )?;
+    Ok(event)
+}
+
+/// Delete a manual timeline event. Derived events are owned by the digest and
+/// cannot be removed this way.
+#[tauri::command]
+pub fn delete_timeline_event(state: State<ApiState>, id: String) -> Result<(), IpcError> {
+    let conn = state.db.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM timeline_events WHERE id = ?1 AND source = 'manual'",
+        params![id],
+    )?;
+    if deleted == 0 {
+        return Err(InkOsError::TimelineEventNotFound.into());
+    }
+    Ok(())
+}
+
 /// Structured AI runtime event surfaced in the debugger UI.
 #[derive(Serialize)]
 pub struct AiRuntimeEvent {
@@ -281,42 +1169,144 @@ pub struct AiRuntimeEvent {
     pub data: Option<serde_json::Value>,
 }
 
-/// Return recent AI runtime events for diagnostics.
+/// Return a page of recent AI runtime events for diagnostics.
 #[tauri::command]
 pub fn list_ai_events(
     state: State<ApiState>,
     limit: Option<usize>,
-) -> Result<Vec<AiRuntimeEvent>, String> {
-    let conn = state.db.get().map_err(|e| e.to_string())?;
+    before: Option<Cursor>,
+    request_id: Option<String>,
+) -> Result<Page<AiRuntimeEvent>, IpcError> {
+    let conn = state.db.get()?;
+    let (before_key, before_id) = cursor_params(&before);
 
+    let mut stmt = conn.prepare(
+        "SELECT id, ts, level, code, message, explain, data FROM event_log
+             WHERE module = 'ai.runtime' AND (?1 IS NULL OR ts < ?1 OR (ts = ?1 AND id < ?2))
+               AND (?4 IS NULL OR (json_valid(data) AND json_extract(data, '$.request_id') = ?4))
+             ORDER BY ts DESC, id DESC LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![before_key, before_id, fetch_limit(limit), request_id],
+        map_ai_event,
+    )?;
     let mut events = Vec::new();
-    if let Some(limit) = limit {
-        let mut stmt = conn
-            .prepare(
-                "SELECT id, ts, level, code, message, explain, data FROM event_log WHERE module = 'ai.runtime' ORDER BY ts DESC LIMIT ?1",
-            )
-            .map_err(|e| e.to_string())?;
-        let rows = stmt
-            .query_map([limit as i64], map_ai_event)
-            .map_err(|e| e.to_string())?;
-        for row in rows {
-            events.push(row.map_err(|e| e.to_string())?);
-        }
-        return Ok(events);
+    for row in rows {
+        events.push(row?);
     }
+    Ok(Page::from_rows(events, limit, |event| Cursor {
+        key: event.ts,
+        id: event.id.clone(),
+    }))
+}
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, ts, level, code, message, explain, data FROM event_log WHERE module = 'ai.runtime' ORDER BY ts DESC",
-        )
-        .map_err(|e| e.to_string())?;
-    let rows = stmt
-        .query_map([], map_ai_event)
-        .map_err(|e| e.to_string())?;
+/// Set the minimum level persisted to the event log (`logging.min_level`).
+#[tauri::command]
+pub fn set_log_level(state: State<ApiState>, level: String) -> Result<String, IpcError> {
+    let level = level.trim().to_lowercase();
+    if !crate::logging::LEVELS.contains(&level.as_str()) {
+        return Err(InkOsError::ValidationFailed(format!(
+            "Unknown log level '{level}'; expected one of {}",
+            crate::logging::LEVELS.join(", ")
+        ))
+        .into());
+    }
+    let conn = state.db.get()?;
+    crate::logging::set_min_level(&conn, &level)?;
+    Ok(level)
+}
+
+/// Turn capture of raw provider replies (`ai.capture_raw`) on or off.
+/// Turning it off discards everything captured so far.
+#[tauri::command]
+pub fn set_raw_capture(state: State<ApiState>, enabled: bool) -> Result<bool, IpcError> {
+    let conn = state.db.get()?;
+    crate::response_log::set_capture(&conn, enabled)?;
+    Ok(enabled)
+}
+
+/// Let chats download a missing Ollama model (`ollama.auto_pull`) instead
+/// of falling back to another provider.
+#[tauri::command]
+pub fn set_ollama_auto_pull(state: State<ApiState>, enabled: bool) -> Result<bool, IpcError> {
+    let conn = state.db.get()?;
+    crate::model_manager::set_auto_pull(&conn, enabled)?;
+    Ok(enabled)
+}
+
+/// Return the redacted raw replies captured for a chat request.
+#[tauri::command]
+pub fn get_ai_raw_response(
+    state: State<ApiState>,
+    request_id: String,
+) -> Result<Vec<crate::response_log::RawResponse>, IpcError> {
+    let request_id = request_id.trim();
+    if request_id.is_empty() {
+        return Err(InkOsError::ValidationFailed("request_id is required".into()).into());
+    }
+    let conn = state.db.get()?;
+    Ok(crate::response_log::get(&conn, request_id)?)
+}
+
+#[derive(Deserialize, Default)]
+pub struct ListEventsInput {
+    /// Exact module, or a prefix such as `jobs` matching `jobs.daily`.
+    pub module: Option<String>,
+    pub level: Option<String>,
+    pub code: Option<String>,
+    /// Inclusive lower bound on the event timestamp.
+    pub since: Option<i64>,
+    /// Exclusive upper bound on the event timestamp.
+    pub until: Option<i64>,
+    /// Only events logged for this chat request.
+    pub request_id: Option<String>,
+    pub before: Option<Cursor>,
+    pub limit: Option<usize>,
+}
+
+/// Return a page of event log rows filtered by module, level, code, and time.
+#[tauri::command]
+pub fn list_events(
+    state: State<ApiState>,
+    input: Option<ListEventsInput>,
+) -> Result<Page<AiRuntimeEvent>, IpcError> {
+    let input = input.unwrap_or_default();
+    let conn = state.db.get()?;
+    let (before_key, before_id) = cursor_params(&input.before);
+
+    let mut stmt = conn.prepare(
+        "SELECT id, ts, level, code, message, explain, data FROM event_log
+         WHERE (?1 IS NULL OR module = ?1 OR module LIKE ?1 || '.%')
+           AND (?2 IS NULL OR level = ?2)
+           AND (?3 IS NULL OR code = ?3)
+           AND (?4 IS NULL OR ts >= ?4)
+           AND (?5 IS NULL OR ts < ?5)
+           AND (?6 IS NULL OR ts < ?6 OR (ts = ?6 AND id < ?7))
+           AND (?9 IS NULL OR (json_valid(data) AND json_extract(data, '$.request_id') = ?9))
+         ORDER BY ts DESC, id DESC LIMIT ?8",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            input.module,
+            input.level,
+            input.code,
+            input.since,
+            input.until,
+            before_key,
+            before_id,
+            fetch_limit(input.limit),
+            input.request_id
+        ],
+        map_ai_event,
+    )?;
+    let mut events = Vec::new();
     for row in rows {
-        events.push(row.map_err(|e| e.to_string())?);
+        events.push(row?);
     }
-    Ok(events)
+    Ok(Page::from_rows(events, input.limit, |event| Cursor {
+        key: event.ts,
+        id: event.id.clone(),
+    }))
 }
 
 fn map_ai_event(row: &r2d2_sqlite::rusqlite::Row) -> r2d2_sqlite::rusqlite::Result<AiRuntimeEvent> {
@@ -333,12 +1323,227 @@ fn map_ai_event(row: &r2d2_sqlite::rusqlite::Row) -> r2d2_sqlite::rusqlite::Resu
     })
 }
 
+/// Snapshot the live database to `dest_path` using the online backup API.
+#[tauri::command]
+pub async fn backup_db(
+    state: State<'_, ApiState>,
+    dest_path: String,
+) -> Result<BackupReport, IpcError> {
+    let pool = state.db.clone();
+    let report = spawn_blocking(move || {
+        let conn = pool.get()?;
+        let report = db_backup::backup_db(&conn, Path::new(dest_path.trim()))?;
+        log_event(
+            &conn,
+            "info",
+            Some("DB-BACKUP"),
+            "db",
+            "Database backup written",
+            None,
+            Some(json!({ "path": report.path, "bytes": report.bytes, "sha256": report.sha256 })),
+        )?;
+        Ok::<_, IpcError>(report)
+    })
+    .await??;
+    Ok(report)
+}
+
+/// Validate the backup at `src_path` and stage it to replace the database on
+/// the next start.
+#[tauri::command]
+pub async fn restore_db(
+    state: State<'_, ApiState>,
+    src_path: String,
+) -> Result<RestoreReport, IpcError> {
+    let pool = state.db.clone();
+    let report = spawn_blocking(move || {
+        let conn = pool.get()?;
+        let report = db_backup::restore_db(&conn, Path::new(src_path.trim()))?;
+        log_event(
+            &conn,
+            "info",
+            Some("DB-RESTORE"),
+            "db",
+            "Database restore staged",
+            Some("The backup replaces the current database when the app restarts."),
+            Some(json!({ "source": src_path, "bytes": report.bytes, "sha256": report.sha256 })),
+        )?;
+        Ok::<_, IpcError>(report)
+    })
+    .await??;
+    Ok(report)
+}
+
+/// Change the passphrase of an encrypted workspace (`sqlcipher` builds only).
+#[tauri::command]
+pub async fn rekey_db(state: State<'_, ApiState>, new_key: String) -> Result<(), IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        db_encryption::rekey(&conn, &new_key)?;
+        log_event(
+            &conn,
+            "info",
+            Some("DB-REKEY"),
+            "db",
+            "Database passphrase changed",
+            Some("Restart the app so every connection uses the new passphrase."),
+            None,
+        )?;
+        Ok::<_, IpcError>(())
+    })
+    .await??;
+    Ok(())
+}
+
+/// Background job DTO surfaced to the operations panel.
+#[derive(Serialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub kind: String,
+    pub state: String,
+    pub payload: serde_json::Value,
+    pub result: Option<serde_json::Value>,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub run_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// When the latest attempt started running.
+    pub started_at: Option<i64>,
+}
+
+const JOB_COLUMNS: &str =
+    "id, kind, state, payload, result, attempts, max_attempts, run_at, created_at, updated_at, started_at";
+
+/// Return a page of jobs, newest first, optionally filtered by state and
+/// kind. Pages hold 50 jobs unless `limit` asks for 1–200.
+#[tauri::command]
+pub fn list_jobs(
+    state: State<ApiState>,
+    job_state: Option<String>,
+    kind: Option<String>,
+    limit: Option<usize>,
+    before: Option<Cursor>,
+) -> Result<Page<JobRecord>, IpcError> {
+    let conn = state.db.get()?;
+    let limit = Some(limit.unwrap_or(50).clamp(1, 200));
+    let (before_key, before_id) = cursor_params(&before);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {JOB_COLUMNS} FROM jobs WHERE (?1 IS NULL OR state = ?1) AND (?2 IS NULL OR kind = ?2)
+               AND (?4 IS NULL OR created_at < ?4 OR (created_at = ?4 AND id < ?5))
+             ORDER BY created_at DESC, id DESC LIMIT ?3"
+        ))?;
+    let rows = stmt.query_map(
+        params![job_state, kind, fetch_limit(limit), before_key, before_id],
+        map_job,
+    )?;
+    let mut jobs = Vec::new();
+    for row in rows {
+        jobs.push(row?);
+    }
+    Ok(Page::from_rows(jobs, limit, |job| Cursor {
+        key: job.created_at,
+        id: job.id.clone(),
+    }))
+}
+
+/// Fetch a single job by id.
+#[tauri::command]
+pub fn get_job(state: State<ApiState>, job_id: String) -> Result<Option<JobRecord>, IpcError> {
+    let conn = state.db.get()?;
+    conn.query_row(
+        &format!("SELECT {JOB_COLUMNS} FROM jobs WHERE id = ?1"),
+        params![job_id],
+        map_job,
+    )
+    .optional()
+    .map_err(IpcError::from)
+}
+
+fn map_job(row: &r2d2_sqlite::rusqlite::Row) -> r2d2_sqlite::rusqlite::Result<JobRecord> {
+    let payload_str: String = row.get(3)?;
+    let result_str: Option<String> = row.get(4)?;
+    // Failed jobs store the bare error message rather than JSON.
+    let result =
+        result_str.map(|raw| serde_json::from_str(&raw).unwrap_or(serde_json::Value::String(raw)));
+    Ok(JobRecord {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        state: row.get(2)?,
+        payload: serde_json::from_str(&payload_str).unwrap_or(serde_json::Value::Null),
+        result,
+        attempts: row.get(5)?,
+        max_attempts: row.get(6)?,
+        run_at: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        started_at: row.get(10)?,
+    })
+}
+
+/// Cancel a queued job.
+#[tauri::command]
+pub async fn cancel_job(state: State<'_, ApiState>, job_id: String) -> Result<(), IpcError> {
+    state
+        .scheduler
+        .cancel_job(&job_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Queue a copy of a failed or cancelled job, returning the new job id.
+#[tauri::command]
+pub async fn requeue_job(
+    state: State<'_, ApiState>,
+    job_id: String,
+    run_at: Option<i64>,
+) -> Result<String, IpcError> {
+    state
+        .scheduler
+        .requeue_job(&job_id, run_at)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Stop background job dispatch; due jobs stay queued until resumed.
+#[tauri::command]
+pub async fn scheduler_pause(state: State<'_, ApiState>) -> Result<SchedulerStatus, IpcError> {
+    state.scheduler.pause().await.map_err(IpcError::from)
+}
+
+/// Resume background job dispatch.
+#[tauri::command]
+pub async fn scheduler_resume(state: State<'_, ApiState>) -> Result<SchedulerStatus, IpcError> {
+    state.scheduler.resume().await.map_err(IpcError::from)
+}
+
+/// Report whether the scheduler is paused and how many jobs are waiting.
+#[tauri::command]
+pub async fn scheduler_status(state: State<'_, ApiState>) -> Result<SchedulerStatus, IpcError> {
+    state.scheduler.status().await.map_err(IpcError::from)
+}
+
+/// Set how many due jobs the scheduler runs in parallel (1–8).
+#[tauri::command]
+pub async fn scheduler_set_workers(
+    state: State<'_, ApiState>,
+    workers: usize,
+) -> Result<SchedulerStatus, IpcError> {
+    state
+        .scheduler
+        .set_worker_count(workers)
+        .await
+        .map_err(IpcError::from)
+}
+
 /// Trigger the daily digest worker immediately.
 #[tauri::command]
 pub async fn run_daily_digest(
     state: State<'_, ApiState>,
     date: Option<String>,
-) -> Result<JobRunResult, String> {
+) -> Result<JobRunResult, IpcError> {
     let payload = if let Some(value) = date {
         json!({ "date": value })
     } else {
@@ -348,28 +1553,139 @@ pub async fn run_daily_digest(
         .scheduler
         .run_now("workspace.daily_digest", payload)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(IpcError::from)
+}
+
+/// Return the nightly digest schedule (local time and IANA timezone).
+#[tauri::command]
+pub async fn get_digest_schedule(state: State<'_, ApiState>) -> Result<DigestSchedule, IpcError> {
+    state
+        .scheduler
+        .digest_schedule()
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Update the nightly digest time/timezone and re-queue the next run.
+#[tauri::command]
+pub async fn update_digest_schedule(
+    state: State<'_, ApiState>,
+    input: DigestSchedule,
+) -> Result<DigestSchedule, IpcError> {
+    state
+        .scheduler
+        .update_digest_schedule(input)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Return the job completion webhook settings.
+#[tauri::command]
+pub async fn get_webhook_config(state: State<'_, ApiState>) -> Result<WebhookConfig, IpcError> {
+    state
+        .scheduler
+        .webhook_config()
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Set the webhook URL and the job states that trigger it.
+#[tauri::command]
+pub async fn update_webhook_config(
+    state: State<'_, ApiState>,
+    input: WebhookConfig,
+) -> Result<WebhookConfig, IpcError> {
+    state
+        .scheduler
+        .update_webhook_config(input)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Send a sample job notification to the configured webhook.
+#[tauri::command]
+pub async fn test_webhook(state: State<'_, ApiState>) -> Result<u16, IpcError> {
+    state.scheduler.test_webhook().await.map_err(IpcError::from)
+}
+
+/// List recurring job schedules, including the seeded nightly digest.
+#[tauri::command]
+pub async fn list_schedules(state: State<'_, ApiState>) -> Result<Vec<JobSchedule>, IpcError> {
+    state
+        .scheduler
+        .list_schedules()
+        .await
+        .map_err(IpcError::from)
+}
+
+#[derive(Deserialize)]
+pub struct CreateScheduleInput {
+    pub kind: String,
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+    pub cron_expr: String,
+}
+
+/// Define a recurring job from a cron expression.
+#[tauri::command]
+pub async fn create_schedule(
+    state: State<'_, ApiState>,
+    input: CreateScheduleInput,
+) -> Result<JobSchedule, IpcError> {
+    state
+        .scheduler
+        .create_schedule(
+            &input.kind,
+            input.payload.unwrap_or_else(|| json!({})),
+            &input.cron_expr,
+        )
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Delete a schedule and its queued job.
+#[tauri::command]
+pub async fn delete_schedule(
+    state: State<'_, ApiState>,
+    schedule_id: String,
+) -> Result<(), IpcError> {
+    state
+        .scheduler
+        .delete_schedule(&schedule_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Enable or disable a schedule.
+#[tauri::command]
+pub async fn toggle_schedule(
+    state: State<'_, ApiState>,
+    schedule_id: String,
+    enabled: bool,
+) -> Result<JobSchedule, IpcError> {
+    state
+        .scheduler
+        .toggle_schedule(&schedule_id, enabled)
+        .await
+        .map_err(IpcError::from)
 }
 
 /// Ensure the daily digest job has been scheduled for the current day.
-fn ensure_today_digest(state: &State<ApiState>) -> Result<(), String> {
-    let today = OffsetDateTime::now_utc().date().to_string();
+fn ensure_today_digest(state: &State<ApiState>) -> Result<(), IpcError> {
+    let today = state.scheduler.local_today_blocking()?.to_string();
     let missing = {
-        let conn = state.db.get().map_err(|e| e.to_string())?;
-        let mut stmt = conn
-            .prepare("SELECT id FROM logbook_entries WHERE entry_date = ?1 LIMIT 1")
-            .map_err(|e| e.to_string())?;
+        let conn = state.db.get()?;
+        let mut stmt =
+            conn.prepare("SELECT id FROM logbook_entries WHERE entry_date = ?1 LIMIT 1")?;
         let existing: Option<String> = stmt
             .query_row([today.as_str()], |row| row.get(0))
-            .optional()
-            .map_err(|e| e.to_string())?;
+            .optional()?;
         existing.is_none()
     };
     if missing {
         let _ = state
             .scheduler
-            .run_now_blocking("workspace.daily_digest", json!({ "date": today }))
-            .map_err(|e| e.to_string())?;
+            .run_now_blocking("workspace.daily_digest", json!({ "date": today }))?;
     }
     Ok(())
 }
@@ -378,31 +1694,29 @@ fn ensure_today_digest(state: &State<ApiState>) -> Result<(), String> {
 #[tauri::command]
 pub async fn ai_list_providers(
     state: State<'_, ApiState>,
-) -> Result<Vec<config::AiProviderInfo>, String> {
-    state
-        .model_manager
-        .list_providers()
-        .map_err(|e| e.to_string())
+) -> Result<Vec<config::AiProviderInfo>, IpcError> {
+    state.model_manager.list_providers().map_err(IpcError::from)
 }
 
 /// Fetch the current AI settings snapshot via a blocking thread pool.
 #[tauri::command]
-pub async fn ai_get_settings(state: State<'_, ApiState>) -> Result<AiSettingsView, String> {
+pub async fn ai_get_settings(state: State<'_, ApiState>) -> Result<AiSettingsView, IpcError> {
     let pool = state.db.clone();
     let snapshot = spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| e.to_string())?;
-        config::get_settings(&conn).map_err(|e| e.to_string())
+        let conn = pool.get()?;
+        config::get_settings(&conn).map_err(IpcError::from)
     })
-    .await
-    .map_err(|e| e.to_string())??;
+    .await??;
 
-    let summarizer_config = state.summarizer.load_config().map_err(|e| e.to_string())?;
+    let summarizer_config = state.summarizer.load_config()?;
 
     Ok(AiSettingsView {
         snapshot,
         warn_ratio: summarizer_config.warn_ratio,
         force_ratio: summarizer_config.force_ratio,
         summarizer_model: summarizer_config.summarizer_model,
+        rollover_tail: summarizer_config.rollover_tail,
+        rollover_tail_auto: summarizer_config.rollover_tail_auto,
     })
 }
 
@@ -415,6 +1729,8 @@ pub struct AiUpdateSettingsInput {
     pub warn_ratio: Option<f32>,
     pub force_ratio: Option<f32>,
     pub summarizer_model: Option<String>,
+    pub rollover_tail: Option<usize>,
+    pub rollover_tail_auto: Option<bool>,
 }
 
 /// Update AI provider settings from the UI.
@@ -422,15 +1738,21 @@ pub struct AiUpdateSettingsInput {
 pub async fn ai_update_settings(
     state: State<'_, ApiState>,
     input: AiUpdateSettingsInput,
-) -> Result<AiSettingsView, String> {
+) -> Result<AiSettingsView, IpcError> {
     let pool = state.db.clone();
-    let summarizer_config = state.summarizer.load_config().map_err(|e| e.to_string())?;
+    let summarizer_config = state.summarizer.load_config()?;
     let warn_ratio = input.warn_ratio.unwrap_or(summarizer_config.warn_ratio);
     let force_ratio = input.force_ratio.unwrap_or(summarizer_config.force_ratio);
     let summarizer_model = input
         .summarizer_model
         .clone()
         .or(summarizer_config.summarizer_model.clone());
+    let rollover_tail = input
+        .rollover_tail
+        .unwrap_or(summarizer_config.rollover_tail);
+    let rollover_tail_auto = input
+        .rollover_tail_auto
+        .unwrap_or(summarizer_config.rollover_tail_auto);
 
     let provider_id = input.provider_id.clone();
     let model = input.model.clone();
@@ -438,7 +1760,7 @@ pub async fn ai_update_settings(
     let base_url = input.base_url.clone();
 
     let snapshot = spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| e.to_string())?;
+        let conn = pool.get()?;
         let snapshot = config::update_settings(
             &conn,
             AiSettingsUpdate {
@@ -447,27 +1769,76 @@ pub async fn ai_update_settings(
                 api_key,
                 base_url,
             },
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
         config::audit_settings_change(&conn, "AI settings updated");
-        Ok(snapshot)
+        Ok::<_, IpcError>(snapshot)
     })
-    .await
-    .map_err(|e| e.to_string())??;
+    .await??;
 
-    let summarizer_state = state
-        .summarizer
-        .update_config(warn_ratio, force_ratio, summarizer_model)
-        .map_err(|e| e.to_string())?;
+    let summarizer_state = state.summarizer.update_config(
+        warn_ratio,
+        force_ratio,
+        summarizer_model,
+        rollover_tail,
+        rollover_tail_auto,
+    )?;
 
     Ok(AiSettingsView {
         snapshot,
         warn_ratio: summarizer_state.warn_ratio,
         force_ratio: summarizer_state.force_ratio,
         summarizer_model: summarizer_state.summarizer_model,
+        rollover_tail: summarizer_state.rollover_tail,
+        rollover_tail_auto: summarizer_state.rollover_tail_auto,
     })
 }
 
+/// Return the rollover thresholds and summariser model.
+#[tauri::command]
+pub fn get_summarizer_config(state: State<'_, ApiState>) -> Result<SummarizerConfig, IpcError> {
+    state.summarizer.load_config().map_err(IpcError::from)
+}
+
+#[derive(Deserialize)]
+pub struct UpdateSummarizerConfigInput {
+    pub warn_ratio: Option<f32>,
+    pub force_ratio: Option<f32>,
+    /// An empty string clears the override.
+    pub summarizer_model: Option<String>,
+    pub rollover_tail: Option<usize>,
+    pub rollover_tail_auto: Option<bool>,
+    pub prune_after_rollover: Option<bool>,
+}
+
+/// Update summariser settings without touching the active provider.
+///
+/// Omitted fields keep their current value.
+#[tauri::command]
+pub fn update_summarizer_config(
+    state: State<'_, ApiState>,
+    input: UpdateSummarizerConfigInput,
+) -> Result<SummarizerConfig, IpcError> {
+    let current = state.summarizer.load_config()?;
+    // Validate and store the ratios first so a rejected update changes nothing.
+    let updated = state.summarizer.update_config(
+        input.warn_ratio.unwrap_or(current.warn_ratio),
+        input.force_ratio.unwrap_or(current.force_ratio),
+        match input.summarizer_model {
+            Some(model) if model.trim().is_empty() => None,
+            Some(model) => Some(model.trim().to_string()),
+            None => current.summarizer_model,
+        },
+        input.rollover_tail.unwrap_or(current.rollover_tail),
+        input
+            .rollover_tail_auto
+            .unwrap_or(current.rollover_tail_auto),
+    )?;
+    match input.prune_after_rollover {
+        Some(enabled) => Ok(state.summarizer.set_prune_after_rollover(enabled)?),
+        None => Ok(updated),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct AiChatMessageInput {
     pub role: String,
@@ -478,8 +1849,43 @@ pub struct AiChatMessageInput {
 pub struct AiChatCommandInput {
     pub messages: Vec<AiChatMessageInput>,
     pub temperature: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Extended thinking (Anthropic) or reasoning effort (OpenAI-compatible).
+    pub reasoning_effort: Option<ReasoningEffort>,
+    pub provider_id: Option<String>,
+    pub model: Option<String>,
+    /// Conversation the reply belongs to; provider usage is held until the
+    /// reply is appended via `chat_append_and_maybe_rollover`.
+    pub conversation_id: Option<String>,
+    /// Race fallback providers concurrently instead of trying them in turn.
+    #[serde(default)]
+    pub race: bool,
+    /// Note attachments whose images are sent with the last user message.
+    #[serde(default)]
+    pub attachment_ids: Vec<String>,
+    /// Answer an identical earlier request from the response cache.
+    #[serde(default)]
+    pub cache: bool,
+    /// Fail with the chosen provider's error instead of trying others.
+    #[serde(default)]
+    pub no_fallback: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AiChatJsonInput {
+    pub messages: Vec<AiChatMessageInput>,
+    pub temperature: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Extended thinking (Anthropic) or reasoning effort (OpenAI-compatible).
+    pub reasoning_effort: Option<ReasoningEffort>,
     pub provider_id: Option<String>,
     pub model: Option<String>,
+    /// Optional JSON Schema the reply should match.
+    pub schema: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
@@ -487,6 +1893,8 @@ pub struct ChatCreateConversationInput {
     pub title: Option<String>,
     pub provider_id: Option<String>,
     pub model_id: Option<String>,
+    pub system_prompt: Option<String>,
+    pub default_temperature: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -500,6 +1908,50 @@ pub struct ChatAppendInput {
     pub conversation_id: String,
     pub content: String,
     pub role: Option<String>,
+    pub usage: Option<AiUsageMetrics>,
+}
+
+#[derive(Deserialize)]
+pub struct AiSetModelContextInput {
+    pub provider_id: String,
+    pub model_id: String,
+    /// Window in tokens; `None` removes the override.
+    pub context_window: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct AiConversationUsageInput {
+    pub conversation_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct ExportConversationInput {
+    pub conversation_id: String,
+    pub format: ExportFormat,
+}
+
+#[derive(Deserialize)]
+pub struct ForkConversationInput {
+    pub conversation_id: String,
+    /// Last message copied into the fork.
+    pub up_to_message_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct EditMessageInput {
+    pub message_id: String,
+    pub new_body: String,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteConversationInput {
+    pub conversation_id: String,
+    /// Close and hide the conversation instead of removing its rows.
+    #[serde(default)]
+    pub soft: bool,
+    /// Apply to every thread in the conversation's rollover chain.
+    #[serde(default)]
+    pub chain: bool,
 }
 
 #[derive(Deserialize)]
@@ -514,23 +1966,55 @@ pub struct AiSetModelInput {
     pub model_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct SetConversationPromptInput {
+    pub conversation_id: String,
+    pub system_prompt: Option<String>,
+    pub default_temperature: Option<f32>,
+}
+
 #[derive(Deserialize)]
 pub struct AiSummarizeInput {
     pub target_type: String,
     pub target_id: String,
 }
 
+#[derive(Deserialize)]
+pub struct AiSummarizeTextInput {
+    pub content: String,
+    /// Cache key for the summary; both default to a hash of `content`.
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AiSummarizeRangeInput {
+    /// First local date, `YYYY-MM-DD`.
+    pub start_date: String,
+    /// Last local date, inclusive.
+    pub end_date: String,
+}
+
 #[derive(Deserialize)]
 pub struct AiSummaryLookupInput {
     pub summary_id: String,
 }
 
-/// Execute a chat completion via the orchestrator and record the result.
+#[derive(Deserialize)]
+pub struct AiSummaryHistoryInput {
+    pub target_type: String,
+    pub target_id: String,
+}
+
+/// Request strict JSON from the model and return it parsed.
+///
+/// Replies that are not valid JSON fail with `AI-JSON-INVALID` so the
+/// caller can retry.
 #[tauri::command]
-pub async fn ai_chat(
+pub async fn ai_chat_json(
     state: State<'_, ApiState>,
-    input: AiChatCommandInput,
-) -> Result<AiChatResponse, String> {
+    input: AiChatJsonInput,
+) -> Result<serde_json::Value, IpcError> {
     let ai_input = AiChatInput {
         messages: input
             .messages
@@ -538,159 +2022,1079 @@ pub async fn ai_chat(
             .map(|m| AiChatMessage {
                 role: m.role.clone(),
                 content: m.content.clone(),
+                images: Vec::new(),
             })
             .collect(),
         temperature: input.temperature,
+        response_format: Some(ResponseFormat::Json {
+            schema: input.schema,
+        }),
+        stop: input.stop,
+        top_p: input.top_p,
+        max_tokens: input.max_tokens,
+        reasoning_effort: input.reasoning_effort,
+        request_id: Some(Uuid::new_v4().to_string()),
     };
-
-    state
+    let response = state
         .model_manager
-        .chat(
-            ai_input,
-            input.provider_id.clone(),
-            input.model.clone(),
-            false,
-        )
-        .await
-        .map_err(|e| e.to_string())
+        .chat(ai_input, input.provider_id, input.model, false, false)
+        .await?;
+    Ok(parse_json_content(
+        &response.provider_id,
+        &response.content,
+    )?)
+}
+
+/// Execute a chat completion via the orchestrator and record the result.
+#[tauri::command]
+pub async fn ai_chat(
+    state: State<'_, ApiState>,
+    input: AiChatCommandInput,
+) -> Result<AiChatResponse, IpcError> {
+    let mut ai_input = AiChatInput {
+        messages: input
+            .messages
+            .iter()
+            .map(|m| AiChatMessage {
+                role: m.role.clone(),
+                content: m.content.clone(),
+                images: Vec::new(),
+            })
+            .collect(),
+        temperature: input.temperature,
+        response_format: None,
+        stop: input.stop.clone(),
+        top_p: input.top_p,
+        max_tokens: input.max_tokens,
+        reasoning_effort: input.reasoning_effort,
+        request_id: Some(Uuid::new_v4().to_string()),
+    };
+    if let Some(conversation_id) = input.conversation_id.clone() {
+        let summarizer = Arc::clone(&state.summarizer);
+        let conversation =
+            spawn_blocking(move || summarizer.get_conversation(&conversation_id)).await??;
+        if let Some(conversation) = conversation {
+            conversation.apply_chat_defaults(&mut ai_input);
+        }
+    }
+    if !input.attachment_ids.is_empty() {
+        let pool = state.db.clone();
+        let ids = input.attachment_ids.clone();
+        let images = spawn_blocking(move || {
+            let conn = pool.get()?;
+            let attached = attachments::get_many(&conn, &ids)?;
+            Ok::<_, IpcError>(attachments::load_images(&attached)?)
+        })
+        .await??;
+        if let Some(message) = ai_input
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|message| message.role == "user")
+        {
+            message.images = images;
+        }
+    }
+
+    let manager = &state.model_manager;
+    let response = if input.cache {
+        manager
+            .chat_cached(
+                ai_input,
+                input.provider_id.clone(),
+                input.model.clone(),
+                false,
+                input.race,
+                input.no_fallback,
+            )
+            .await
+    } else if input.race {
+        manager
+            .chat_raced(
+                ai_input,
+                input.provider_id.clone(),
+                input.model.clone(),
+                false,
+                input.no_fallback,
+            )
+            .await
+    } else {
+        manager
+            .chat(
+                ai_input,
+                input.provider_id.clone(),
+                input.model.clone(),
+                false,
+                input.no_fallback,
+            )
+            .await
+    }?;
+
+    let Some(conversation_id) = input.conversation_id else {
+        return Ok(response);
+    };
+    let summarizer = Arc::clone(&state.summarizer);
+    spawn_blocking(move || {
+        summarizer.record_pending_usage(&conversation_id, &response)?;
+        Ok(response)
+    })
+    .await?
 }
 
 #[tauri::command]
 pub async fn ai_list_models(
     state: State<'_, ApiState>,
-) -> Result<Vec<config::AiProviderInfo>, String> {
+) -> Result<Vec<config::AiProviderInfo>, IpcError> {
     ai_list_providers(state).await
 }
 
+/// Register a self-hosted or third-party provider.
+#[tauri::command]
+pub async fn add_provider(
+    state: State<'_, ApiState>,
+    input: config::CustomProviderInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        let provider = config::add_provider(&conn, input)?;
+        config::audit_settings_change(&conn, "Custom AI provider added");
+        Ok(provider)
+    })
+    .await?
+}
+
+/// Replace the definition of a custom provider.
+#[tauri::command]
+pub async fn update_provider(
+    state: State<'_, ApiState>,
+    input: config::CustomProviderInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        let provider = config::update_provider(&conn, input)?;
+        config::audit_settings_change(&conn, "Custom AI provider updated");
+        Ok(provider)
+    })
+    .await?
+}
+
+/// Remove a custom provider and its stored credentials.
+#[tauri::command]
+pub async fn remove_provider(
+    state: State<'_, ApiState>,
+    provider_id: String,
+) -> Result<(), IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::remove_provider(&conn, &provider_id)?;
+        config::audit_settings_change(&conn, "Custom AI provider removed");
+        Ok(())
+    })
+    .await?
+}
+
+/// Report providers the circuit breaker is currently skipping or probing.
+#[tauri::command]
+pub async fn ai_breaker_states(state: State<'_, ApiState>) -> Result<Vec<BreakerStatus>, IpcError> {
+    Ok(state.model_manager.breaker_states())
+}
+
+/// Report calls made and queued for each rate-limited provider.
+#[tauri::command]
+pub async fn ai_rate_limit_states(
+    state: State<'_, ApiState>,
+) -> Result<Vec<RateLimitStatus>, IpcError> {
+    Ok(state.model_manager.rate_limit_states())
+}
+
+/// Probe the local Ollama and LM Studio endpoints for running runtimes.
+/// Results are cached briefly; pass `refresh` to probe again immediately.
+#[tauri::command]
+pub async fn discover_local_providers(
+    state: State<'_, ApiState>,
+    refresh: Option<bool>,
+) -> Result<Vec<LocalRuntime>, IpcError> {
+    state
+        .model_manager
+        .discover_local_providers(refresh.unwrap_or(false))
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Return the stored fallback chain ordering (`ai.fallback_order`).
+#[tauri::command]
+pub async fn get_fallback_order(state: State<'_, ApiState>) -> Result<FallbackOrder, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        model_manager::fallback_order(&conn).map_err(IpcError::from)
+    })
+    .await?
+}
+
+/// Store the fallback chain ordering and excluded providers.
+#[tauri::command]
+pub async fn set_fallback_order(
+    state: State<'_, ApiState>,
+    input: FallbackOrder,
+) -> Result<FallbackOrder, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        model_manager::set_fallback_order(&conn, &input)?;
+        config::audit_settings_change(&conn, "Updated fallback order");
+        Ok(input)
+    })
+    .await?
+}
+
+/// Store or clear a per-model context window override.
+#[tauri::command]
+pub async fn ai_set_model_context(
+    state: State<'_, ApiState>,
+    input: AiSetModelContextInput,
+) -> Result<(), IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::set_model_context_window(
+            &conn,
+            &input.provider_id,
+            &input.model_id,
+            input.context_window,
+        )
+        .map_err(IpcError::from)
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AiSetProviderTimeoutInput {
+    pub provider_id: String,
+    /// `None` restores the default (seeded value for built-in providers).
+    pub timeout_secs: Option<u64>,
+}
+
+/// Store or clear the HTTP request timeout for a provider.
+#[tauri::command]
+pub async fn ai_set_provider_timeout(
+    state: State<'_, ApiState>,
+    input: AiSetProviderTimeoutInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::set_provider_timeout(&conn, &input.provider_id, input.timeout_secs)
+            .map_err(IpcError::from)
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AiSetProviderSamplingInput {
+    pub provider_id: String,
+    /// `None` restores the bundled default (custom providers: 0.2).
+    pub default_temperature: Option<f32>,
+    /// `None` restores the bundled default (custom providers: omitted).
+    pub default_top_p: Option<f32>,
+}
+
+/// Store or clear the default temperature and top-p for a provider.
+#[tauri::command]
+pub async fn ai_set_provider_sampling(
+    state: State<'_, ApiState>,
+    input: AiSetProviderSamplingInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::set_provider_sampling(
+            &conn,
+            &input.provider_id,
+            input.default_temperature,
+            input.default_top_p,
+        )
+        .map_err(IpcError::from)
+    })
+    .await?
+}
+
+/// What a provider supports, so the UI need not interpret raw tags.
+#[tauri::command]
+pub async fn ai_provider_capabilities(
+    state: State<'_, ApiState>,
+    provider_id: String,
+) -> Result<Capabilities, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::provider_capabilities(&conn, &provider_id).map_err(IpcError::from)
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AiSetProviderRateLimitInput {
+    pub provider_id: String,
+    /// `None` removes the limit.
+    pub rate_limit_rpm: Option<u32>,
+}
+
+/// Store or clear the requests-per-minute limit for a provider.
+#[tauri::command]
+pub async fn ai_set_provider_rate_limit(
+    state: State<'_, ApiState>,
+    input: AiSetProviderRateLimitInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        config::set_provider_rate_limit(&conn, &input.provider_id, input.rate_limit_rpm)
+            .map_err(IpcError::from)
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AiSetProviderHeadersInput {
+    pub provider_id: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Replace the extra HTTP headers sent with a provider's requests.
+#[tauri::command]
+pub async fn ai_set_provider_headers(
+    state: State<'_, ApiState>,
+    input: AiSetProviderHeadersInput,
+) -> Result<config::AiProviderInfo, IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        let provider = config::set_provider_headers(&conn, &input.provider_id, input.headers)?;
+        config::audit_settings_change(
+            &conn,
+            &format!("Updated request headers for {}", input.provider_id),
+        );
+        Ok(provider)
+    })
+    .await?
+}
+
+#[derive(Deserialize)]
+pub struct AiSetEmbeddingModelInput {
+    /// Leave both fields unset to use the active provider's default.
+    pub provider_id: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Choose the provider/model used for note embeddings.
+#[tauri::command]
+pub async fn ai_set_embedding_model(
+    state: State<'_, ApiState>,
+    input: AiSetEmbeddingModelInput,
+) -> Result<(), IpcError> {
+    let pool = state.db.clone();
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        let selection = match (input.provider_id.as_deref(), input.model.as_deref()) {
+            (Some(provider_id), Some(model)) => Some((provider_id, model)),
+            (None, None) => None,
+            _ => {
+                return Err(InkOsError::ValidationFailed(
+                    "provider_id and model must be set together".into(),
+                )
+                .into())
+            }
+        };
+        config::set_embedding_model(&conn, selection)?;
+        config::audit_settings_change(&conn, "Updated embedding model");
+        Ok(())
+    })
+    .await?
+}
+
 #[tauri::command]
 pub async fn chat_create_conversation(
     state: State<'_, ApiState>,
     input: ChatCreateConversationInput,
-) -> Result<ConversationRecord, String> {
+) -> Result<ConversationRecord, IpcError> {
     state
         .summarizer
-        .create_conversation(input.title, input.provider_id, input.model_id)
-        .map_err(|e| e.to_string())
+        .create_conversation(
+            input.title,
+            input.provider_id,
+            input.model_id,
+            input.system_prompt,
+            input.default_temperature,
+        )
+        .map_err(IpcError::from)
 }
 
 #[tauri::command]
 pub async fn chat_list_conversations(
     state: State<'_, ApiState>,
     limit: Option<usize>,
-) -> Result<Vec<ConversationRecord>, String> {
+    before: Option<Cursor<PinnedKey>>,
+    include_closed: Option<bool>,
+    pinned_only: Option<bool>,
+) -> Result<Page<ConversationRecord, PinnedKey>, IpcError> {
+    state
+        .summarizer
+        .list_conversations(
+            limit,
+            before,
+            include_closed.unwrap_or(false),
+            pinned_only.unwrap_or(false),
+        )
+        .map_err(IpcError::from)
+}
+
+#[derive(Deserialize)]
+pub struct SetConversationPinnedInput {
+    pub conversation_id: String,
+    pub pinned: bool,
+}
+
+/// Pin or unpin a conversation so it sorts ahead of the rest.
+#[tauri::command]
+pub async fn set_conversation_pinned(
+    state: State<'_, ApiState>,
+    input: SetConversationPinnedInput,
+) -> Result<ConversationRecord, IpcError> {
     state
         .summarizer
-        .list_conversations(limit)
-        .map_err(|e| e.to_string())
+        .set_conversation_pinned(&input.conversation_id, input.pinned)
+        .map_err(IpcError::from)
 }
 
 #[tauri::command]
 pub async fn chat_get_messages(
     state: State<'_, ApiState>,
     input: ChatMessagesInput,
-) -> Result<Vec<MessageRecord>, String> {
+) -> Result<Vec<MessageRecord>, IpcError> {
     state
         .summarizer
         .list_messages(&input.conversation_id, input.limit)
-        .map_err(|e| e.to_string())
+        .map_err(IpcError::from)
 }
 
 #[tauri::command]
 pub async fn chat_append_and_maybe_rollover(
     state: State<'_, ApiState>,
     input: ChatAppendInput,
-) -> Result<AppendResult, String> {
+) -> Result<AppendResult, IpcError> {
     let role = input.role.unwrap_or_else(|| "user".to_string());
+    let result = state.summarizer.append_and_maybe_rollover(
+        &input.conversation_id,
+        &role,
+        &input.content,
+        input.usage.as_ref(),
+    )?;
+    if role == "assistant" {
+        // Titling runs after the reply is stored; failures are in the AI log.
+        let summarizer = Arc::clone(&state.summarizer);
+        let conversation_id = input.conversation_id;
+        tauri::async_runtime::spawn(async move {
+            let _ = summarizer.auto_title_if_untitled(&conversation_id).await;
+        });
+    }
+    Ok(result)
+}
+
+/// Full-text search across conversation messages, grouped by conversation.
+#[tauri::command]
+pub async fn search_messages(
+    state: State<'_, ApiState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ConversationMatches>, IpcError> {
+    let limit = limit.unwrap_or(50).clamp(1, 200);
+    let summarizer = Arc::clone(&state.summarizer);
+    spawn_blocking(move || Ok(summarizer.search_messages(&query, limit)?)).await?
+}
+
+#[derive(Deserialize)]
+pub struct RenameConversationInput {
+    pub conversation_id: String,
+    pub title: String,
+}
+
+/// Rename a conversation.
+#[tauri::command]
+pub async fn rename_conversation(
+    state: State<'_, ApiState>,
+    input: RenameConversationInput,
+) -> Result<ConversationRecord, IpcError> {
+    state
+        .summarizer
+        .rename_conversation(&input.conversation_id, &input.title)
+        .map_err(IpcError::from)
+}
+
+/// Generate and store a short title from a conversation's opening messages.
+#[tauri::command]
+pub async fn auto_title_conversation(
+    state: State<'_, ApiState>,
+    conversation_id: String,
+) -> Result<ConversationRecord, IpcError> {
+    state
+        .summarizer
+        .auto_title_conversation(&conversation_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Aggregate token counts and estimated spend for a conversation.
+#[tauri::command]
+pub async fn ai_conversation_usage(
+    state: State<'_, ApiState>,
+    input: AiConversationUsageInput,
+) -> Result<ConversationUsage, IpcError> {
+    state
+        .summarizer
+        .conversation_usage(&input.conversation_id)
+        .map_err(IpcError::from)
+}
+
+/// Delete or close a conversation, returning the affected thread ids.
+#[tauri::command]
+pub async fn delete_conversation(
+    state: State<'_, ApiState>,
+    input: DeleteConversationInput,
+) -> Result<Vec<String>, IpcError> {
+    state
+        .summarizer
+        .delete_conversation(&input.conversation_id, input.soft, input.chain)
+        .map_err(IpcError::from)
+}
+
+/// Branch a conversation at a message and return the new thread.
+#[tauri::command]
+pub async fn fork_conversation(
+    state: State<'_, ApiState>,
+    input: ForkConversationInput,
+) -> Result<ConversationRecord, IpcError> {
     state
         .summarizer
-        .append_and_maybe_rollover(&input.conversation_id, &role, &input.content)
-        .map_err(|e| e.to_string())
+        .fork_conversation(&input.conversation_id, &input.up_to_message_id)
+        .map_err(IpcError::from)
+}
+
+/// Delete a rolled-over conversation's messages beyond the retained tail.
+#[tauri::command]
+pub async fn prune_conversation(
+    state: State<'_, ApiState>,
+    conversation_id: String,
+) -> Result<usize, IpcError> {
+    let summarizer = Arc::clone(&state.summarizer);
+    spawn_blocking(move || {
+        summarizer
+            .prune_conversation(&conversation_id)
+            .map_err(IpcError::from)
+    })
+    .await?
+}
+
+/// Rewrite a message and drop the messages that followed it.
+#[tauri::command]
+pub async fn edit_message(
+    state: State<'_, ApiState>,
+    input: EditMessageInput,
+) -> Result<HistoryUpdate, IpcError> {
+    let summarizer = Arc::clone(&state.summarizer);
+    spawn_blocking(move || {
+        summarizer
+            .edit_message(&input.message_id, &input.new_body)
+            .map_err(IpcError::from)
+    })
+    .await?
+}
+
+/// Replace the last assistant reply with a freshly generated one.
+#[tauri::command]
+pub async fn regenerate_last(
+    state: State<'_, ApiState>,
+    conversation_id: String,
+) -> Result<HistoryUpdate, IpcError> {
+    state
+        .summarizer
+        .regenerate_last(&conversation_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Export a conversation and its rollover chain as Markdown or JSON.
+#[tauri::command]
+pub async fn export_conversation(
+    state: State<'_, ApiState>,
+    input: ExportConversationInput,
+) -> Result<String, IpcError> {
+    state
+        .summarizer
+        .export_conversation(&input.conversation_id, input.format)
+        .map_err(IpcError::from)
 }
 
 #[tauri::command]
 pub async fn ai_rollover_chat(
     state: State<'_, ApiState>,
     input: AiRolloverInput,
-) -> Result<RolloverOutcome, String> {
+) -> Result<RolloverOutcome, IpcError> {
     state
         .summarizer
         .rollover(&input.conversation_id)
-        .map_err(|e| e.to_string())
+        .map_err(IpcError::from)
+}
+
+/// Show the summary and token savings a rollover would produce, without
+/// rolling over.
+#[tauri::command]
+pub async fn ai_preview_rollover(
+    state: State<'_, ApiState>,
+    input: AiRolloverInput,
+) -> Result<RolloverPreview, IpcError> {
+    let summarizer = Arc::clone(&state.summarizer);
+    spawn_blocking(move || Ok(summarizer.preview_rollover(&input.conversation_id)?)).await?
 }
 
 #[tauri::command]
 pub async fn ai_set_model(
     state: State<'_, ApiState>,
     input: AiSetModelInput,
-) -> Result<ConversationRecord, String> {
+) -> Result<ConversationRecord, IpcError> {
     state
         .summarizer
         .set_conversation_model(&input.conversation_id, input.provider_id, input.model_id)
-        .map_err(|e| e.to_string())
+        .map_err(IpcError::from)
+}
+
+/// Replace a conversation's stored system prompt and default temperature.
+#[tauri::command]
+pub async fn set_conversation_prompt(
+    state: State<'_, ApiState>,
+    input: SetConversationPromptInput,
+) -> Result<ConversationRecord, IpcError> {
+    state
+        .summarizer
+        .set_conversation_prompt(
+            &input.conversation_id,
+            input.system_prompt,
+            input.default_temperature,
+        )
+        .map_err(IpcError::from)
 }
 
 #[tauri::command]
 pub async fn ai_summarize(
     state: State<'_, ApiState>,
     input: AiSummarizeInput,
-) -> Result<SummaryRecord, String> {
+) -> Result<SummaryRecord, IpcError> {
     match input.target_type.as_str() {
         "note" => {
             let pool = state.db.clone();
             let target_id = input.target_id.clone();
             let (title, body): (String, String) = spawn_blocking(move || {
-                let conn = pool.get().map_err(|e| e.to_string())?;
-                let mut stmt = conn
-                    .prepare("SELECT title, body FROM notes WHERE id = ?1")
-                    .map_err(|e| e.to_string())?;
-                let result = stmt
-                    .query_row([target_id.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))
-                    .map_err(|e| e.to_string())?;
-                Ok(result)
+                let conn = pool.get()?;
+                let mut stmt = conn.prepare("SELECT title, body FROM notes WHERE id = ?1")?;
+                let result =
+                    stmt.query_row([target_id.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                Ok::<_, IpcError>(result)
             })
-            .await
-            .map_err(|e| e.to_string())??;
+            .await??;
             let content = format!("# {title}\n\n{body}");
             state
                 .summarizer
                 .summarise("note", &input.target_id, &content)
-                .map_err(|e| e.to_string())
+                .await
+                .map_err(IpcError::from)
         }
         "conversation" => state
             .summarizer
             .summarise_conversation(&input.target_id)
-            .map_err(|e| e.to_string()),
+            .await
+            .map_err(IpcError::from),
         "day" => {
             let pool = state.db.clone();
             let target_id = input.target_id.clone();
             let summary_text: String = spawn_blocking(move || {
-                let conn = pool.get().map_err(|e| e.to_string())?;
-                let mut stmt = conn
-                    .prepare("SELECT summary FROM logbook_entries WHERE entry_date = ?1")
-                    .map_err(|e| e.to_string())?;
-                let result = stmt
-                    .query_row([target_id.as_str()], |row| row.get(0))
-                    .map_err(|e| e.to_string())?;
-                Ok(result)
+                let conn = pool.get()?;
+                let mut stmt =
+                    conn.prepare("SELECT summary FROM logbook_entries WHERE entry_date = ?1")?;
+                let result = stmt.query_row([target_id.as_str()], |row| row.get(0))?;
+                Ok::<_, IpcError>(result)
             })
-            .await
-            .map_err(|e| e.to_string())??;
+            .await??;
             state
                 .summarizer
                 .summarise("day", &input.target_id, &summary_text)
-                .map_err(|e| e.to_string())
+                .await
+                .map_err(IpcError::from)
+        }
+        other => {
+            Err(InkOsError::ValidationFailed(format!("Unsupported summary target: {other}")).into())
         }
-        other => Err(format!("Unsupported summary target: {other}")),
     }
 }
 
+/// Summarise text that is not stored as a note, conversation or day.
+#[tauri::command]
+pub async fn ai_summarize_text(
+    state: State<'_, ApiState>,
+    input: AiSummarizeTextInput,
+) -> Result<SummaryRecord, IpcError> {
+    state
+        .summarizer
+        .summarise_text(&input.content, input.target_type, input.target_id)
+        .await
+        .map_err(IpcError::from)
+}
+
+/// Summarise notes, AI activity and logbook entries across a date range.
+#[tauri::command]
+pub async fn ai_summarize_range(
+    state: State<'_, ApiState>,
+    input: AiSummarizeRangeInput,
+) -> Result<SummaryRecord, IpcError> {
+    let start = validation::parse_date("start_date", &input.start_date)?;
+    let end = validation::parse_date("end_date", &input.end_date)?;
+    state
+        .scheduler
+        .summarize_range(start, end)
+        .await
+        .map_err(IpcError::from)
+}
+
 #[tauri::command]
 pub async fn ai_get_summary(
     state: State<'_, ApiState>,
     input: AiSummaryLookupInput,
-) -> Result<Option<SummaryRecord>, String> {
+) -> Result<Option<SummaryRecord>, IpcError> {
     state
         .summarizer
         .fetch_summary(&input.summary_id)
-        .map_err(|e| e.to_string())
+        .map_err(IpcError::from)
+}
+
+/// List every stored version of a summary target, newest first.
+#[tauri::command]
+pub async fn ai_list_summaries(
+    state: State<'_, ApiState>,
+    input: AiSummaryHistoryInput,
+) -> Result<Vec<SummaryRecord>, IpcError> {
+    state
+        .summarizer
+        .list_summaries(&input.target_type, &input.target_id)
+        .map_err(IpcError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri::test::{mock_app, MockRuntime};
+    use tauri::{App, Manager};
+
+    fn test_app() -> (App<MockRuntime>, std::path::PathBuf) {
+        let (model_manager, db, path) = crate::model_manager::file_backed_models();
+        let summarizer = Summarizer::new(db.clone(), Arc::clone(&model_manager));
+        let scheduler = JobScheduler::new(db.clone(), Arc::clone(&summarizer));
+        let app = mock_app();
+        app.manage(ApiState {
+            db,
+            model_manager,
+            summarizer,
+            scheduler,
+        });
+        (app, path)
+    }
+
+    fn input<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Assert the command was rejected with `VAL-1001` naming `field`.
+    fn assert_rejected<T>(result: Result<T, IpcError>, field: &str) {
+        let Err(err) = result else {
+            panic!("expected '{field}' to be rejected");
+        };
+        assert_eq!(err.code, "VAL-1001");
+        assert_eq!(err.field.as_deref(), Some(field));
+        assert!(err.message.starts_with(&format!("{field}: ")));
+    }
+
+    #[test]
+    fn create_note_rejects_a_blank_title() {
+        let (app, path) = test_app();
+        let result = create_note(app.state(), input(json!({ "title": "  " })));
+        assert_rejected(result, "title");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn update_note_rejects_a_blank_title() {
+        let (app, path) = test_app();
+        let created = create_note(app.state(), input(json!({ "title": "Plans" }))).unwrap();
+        let result = update_note(
+            app.state(),
+            input(json!({ "id": created.id, "title": "\t" })),
+        );
+        assert_rejected(result, "title");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn set_note_tags_rejects_a_comma() {
+        let (app, path) = test_app();
+        let created = create_note(app.state(), input(json!({ "title": "Plans" }))).unwrap();
+        let result = set_note_tags(
+            app.state(),
+            input(json!({ "note_id": created.id, "tags": ["work", "q1,q2"] })),
+        );
+        assert_rejected(result, "tags");
+        let tags = set_note_tags(
+            app.state(),
+            input(json!({ "note_id": created.id, "tags": [" Work ", "q1"] })),
+        )
+        .unwrap();
+        assert_eq!(tags, ["q1", "work"]);
+
+        delete_note(app.state(), created.id.clone()).unwrap();
+        let err = set_note_tags(
+            app.state(),
+            input(json!({ "note_id": created.id, "tags": ["archive"] })),
+        )
+        .unwrap_err();
+        assert_eq!(err.code, "NTE-1001");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn list_notes_pages_stay_put_when_the_cursor_note_is_pinned() {
+        let (app, path) = test_app();
+        let state: State<ApiState> = app.state();
+        for (id, updated_at, pinned) in [("a", 10, 0), ("b", 30, 0), ("c", 5, 1), ("d", 20, 1)] {
+            state
+                .db
+                .get()
+                .unwrap()
+                .execute(
+                    "INSERT INTO notes (id, title, body, created_at, updated_at, pinned) VALUES (?1, ?1, '', 0, ?2, ?3)",
+                    params![id, updated_at, pinned],
+                )
+                .unwrap();
+        }
+        let ids = |page: &Page<serde_json::Value, PinnedKey>| -> Vec<String> {
+            page.items
+                .iter()
+                .map(|note| note["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let first = list_notes(app.state(), Some(input(json!({ "limit": 3 })))).unwrap();
+        assert_eq!(ids(&first), ["d", "c", "b"]);
+        state
+            .db
+            .get()
+            .unwrap()
+            .execute("UPDATE notes SET pinned = 1 WHERE id = 'b'", [])
+            .unwrap();
+        let rest = list_notes(
+            app.state(),
+            Some(input(json!({ "limit": 3, "before": first.next_cursor }))),
+        )
+        .unwrap();
+        assert_eq!(ids(&rest), ["a"]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn list_jobs_returns_bounded_pages() {
+        let (app, path) = test_app();
+        let state: State<ApiState> = app.state();
+        let conn = state.db.get().unwrap();
+        for n in 0..60 {
+            conn.execute(
+                "INSERT INTO jobs (id, kind, state, payload, created_at, updated_at, run_at) VALUES (?1, 'notes.embed', 'succeeded', '{}', ?2, ?2, ?2)",
+                params![format!("job-{n:02}"), n],
+            )
+            .unwrap();
+        }
+
+        let embeds = || Some("notes.embed".to_string());
+        let first = list_jobs(app.state(), None, embeds(), None, None).unwrap();
+        assert_eq!(first.items.len(), 50);
+        assert_eq!(first.items[0].id, "job-59");
+        let rest = list_jobs(app.state(), None, embeds(), None, first.next_cursor).unwrap();
+        assert_eq!(rest.items.len(), 10);
+        assert_eq!(rest.items[0].id, "job-09");
+        assert!(rest.next_cursor.is_none());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn list_rollup_entries_returns_bounded_pages() {
+        let (app, path) = test_app();
+        let state: State<ApiState> = app.state();
+        let conn = state.db.get().unwrap();
+        let monday = time::Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+        for week in 0..55 {
+            let start = monday + time::Duration::weeks(week);
+            conn.execute(
+                "INSERT INTO rollup_entries (id, period, period_start, period_end, summary, created_at, updated_at) VALUES (?1, 'weekly', ?2, ?3, 'Busy week.', 0, 0)",
+                params![
+                    format!("w{week:02}"),
+                    start.to_string(),
+                    (start + time::Duration::days(6)).to_string()
+                ],
+            )
+            .unwrap();
+        }
+
+        let first = list_rollup_entries(app.state(), None, None, None).unwrap();
+        assert_eq!(first.items.len(), 50);
+        assert_eq!(first.items[0].id, "w54");
+        let rest = list_rollup_entries(app.state(), None, None, first.next_cursor).unwrap();
+        let ids: Vec<&str> = rest.items.iter().map(|entry| entry.id.as_str()).collect();
+        assert_eq!(ids, ["w04", "w03", "w02", "w01", "w00"]);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn delete_and_restore_check_the_note_state() {
+        let (app, path) = test_app();
+        let created = create_note(app.state(), input(json!({ "title": "Plans" }))).unwrap();
+        let code = |result: Result<(), IpcError>| result.unwrap_err().code;
+
+        assert_eq!(
+            code(restore_note(app.state(), created.id.clone())),
+            "VAL-1001"
+        );
+        delete_note(app.state(), created.id.clone()).unwrap();
+        assert_eq!(
+            code(delete_note(app.state(), created.id.clone())),
+            "NTE-1001"
+        );
+        assert_eq!(
+            get_note(app.state(), created.id.clone())
+                .err()
+                .unwrap()
+                .code,
+            "NTE-1001"
+        );
+        restore_note(app.state(), created.id.clone()).unwrap();
+        assert_eq!(
+            get_note(app.state(), created.id.clone()).unwrap().title,
+            "Plans"
+        );
+        assert_eq!(
+            code(restore_note(app.state(), "missing".into())),
+            "NTE-1001"
+        );
+        assert_eq!(code(delete_note(app.state(), "missing".into())), "NTE-1001");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn list_timeline_events_rejects_a_malformed_date() {
+        let (app, path) = test_app();
+        assert_rejected(
+            list_timeline_events(app.state(), Some("2024-02-30".into())),
+            "date",
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn ai_summarize_range_rejects_malformed_dates() {
+        let (app, path) = test_app();
+        let result = ai_summarize_range(
+            app.state(),
+            input(json!({ "start_date": "last week", "end_date": "2024-01-07" })),
+        )
+        .await;
+        assert_rejected(result, "start_date");
+        let result = ai_summarize_range(
+            app.state(),
+            input(json!({ "start_date": "2024-01-01", "end_date": "" })),
+        )
+        .await;
+        assert_rejected(result, "end_date");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn chat_append_rejects_a_blank_body() {
+        let (app, path) = test_app();
+        let state: State<ApiState> = app.state();
+        state
+            .db
+            .get()
+            .unwrap()
+            .execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('c1', 'ollama', 'llama3', 0, 0)",
+                [],
+            )
+            .unwrap();
+        let result = chat_append_and_maybe_rollover(
+            app.state(),
+            input(json!({ "conversation_id": "c1", "content": " \n " })),
+        )
+        .await;
+        assert_rejected(result, "content");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn update_logbook_note_rejects_a_malformed_date() {
+        let (app, path) = test_app();
+        let result = update_logbook_note(
+            app.state(),
+            input(json!({ "entry_date": "05/01/2024", "text": "note" })),
+        );
+        assert_rejected(result, "entry_date");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn add_timeline_event_rejects_malformed_dates_and_blank_text() {
+        let (app, path) = test_app();
+        let event = |entry_date: &str, kind: &str, title: &str| {
+            add_timeline_event(
+                app.state(),
+                input(json!({ "entry_date": entry_date, "kind": kind, "title": title })),
+            )
+        };
+        assert_rejected(event("2024/01/05", "release", "Beta"), "entry_date");
+        assert_rejected(event("2024-01-05", " ", "Beta"), "kind");
+        assert_rejected(event("2024-01-05", "release", ""), "title");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn search_logbook_rejects_a_blank_query_and_malformed_dates() {
+        let (app, path) = test_app();
+        assert_rejected(
+            search_logbook(app.state(), " ".into(), None, None, None),
+            "query",
+        );
+        assert_rejected(
+            search_logbook(
+                app.state(),
+                "beta".into(),
+                Some("2024-13-01".into()),
+                None,
+                None,
+            ),
+            "from_date",
+        );
+        assert_rejected(
+            search_logbook(app.state(), "beta".into(), None, Some("soon".into()), None),
+            "to_date",
+        );
+        let _ = std::fs::remove_file(path);
+    }
 }
@@ -0,0 +1,248 @@
+//! Files attached to notes.
+//!
+//! Attachment bytes are written under `<workspace>/attachments/<note_id>/`,
+//! next to the database file, and indexed in the `attachments` table with a
+//! workspace-relative path. Image attachments can be handed to vision-capable
+//! providers as [`AiImage`] parts.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as B64_ENGINE;
+use base64::Engine;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::agents::AiImage;
+use crate::errors::InkOsError;
+
+const ATTACHMENTS_DIR: &str = "attachments";
+const ATTACHMENT_COLUMNS: &str = "id, note_id, filename, mime, path, size_bytes, created_at";
+/// Largest single attachment accepted, in bytes.
+pub const MAX_ATTACHMENT_BYTES: usize = 20 * 1024 * 1024;
+/// Images sent with one AI request; later attachments are left out.
+pub const MAX_IMAGES_PER_REQUEST: usize = 4;
+
+/// Metadata for a stored attachment.
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteAttachment {
+    pub id: String,
+    pub note_id: String,
+    pub filename: String,
+    pub mime: String,
+    /// Absolute path of the stored file.
+    pub path: String,
+    pub size_bytes: i64,
+    pub created_at: i64,
+}
+
+/// Directory holding the database file, which roots attachment storage.
+fn workspace_dir(conn: &Connection) -> Result<PathBuf> {
+    conn.path()
+        .filter(|path| !path.is_empty())
+        .and_then(|path| Path::new(path).parent().map(Path::to_path_buf))
+        .ok_or_else(|| {
+            InkOsError::ValidationFailed("attachments need a file-backed workspace".into()).into()
+        })
+}
+
+/// Reduce a user-supplied name to a single safe path component.
+fn sanitize_filename(filename: &str) -> String {
+    let base = filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let cleaned: String = base
+        .chars()
+        .map(|c| if c.is_control() || c == ':' { '_' } else { c })
+        .collect();
+    if cleaned.is_empty() || cleaned.chars().all(|c| c == '.') {
+        "attachment".into()
+    } else {
+        cleaned
+    }
+}
+
+/// Store `bytes` as an attachment of a live note.
+pub fn add(
+    conn: &Connection,
+    note_id: &str,
+    filename: &str,
+    mime: &str,
+    bytes: &[u8],
+) -> Result<NoteAttachment> {
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(InkOsError::ValidationFailed(format!(
+            "attachments are limited to {} MiB",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ))
+        .into());
+    }
+    let live: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![note_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if live.is_none() {
+        return Err(InkOsError::NoteNotFound.into());
+    }
+
+    let workspace = workspace_dir(conn)?;
+    let id = Uuid::new_v4().to_string();
+    let filename = sanitize_filename(filename);
+    let relative = Path::new(ATTACHMENTS_DIR)
+        .join(note_id)
+        .join(format!("{id}-{filename}"));
+    let absolute = workspace.join(&relative);
+    if let Some(dir) = absolute.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&absolute, bytes)?;
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let relative = relative.to_string_lossy().into_owned();
+    if let Err(err) = conn.execute(
+        "INSERT INTO attachments (id, note_id, filename, mime, path, size_bytes, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![id, note_id, filename, mime, relative, bytes.len() as i64, now],
+    ) {
+        let _ = std::fs::remove_file(&absolute);
+        return Err(err.into());
+    }
+    Ok(NoteAttachment {
+        id,
+        note_id: note_id.to_string(),
+        filename,
+        mime: mime.to_string(),
+        path: absolute.to_string_lossy().into_owned(),
+        size_bytes: bytes.len() as i64,
+        created_at: now,
+    })
+}
+
+/// Attachments of a note, oldest first.
+pub fn list(conn: &Connection, note_id: &str) -> Result<Vec<NoteAttachment>> {
+    let workspace = workspace_dir(conn)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE note_id = ?1 ORDER BY created_at ASC, rowid ASC"
+    ))?;
+    let rows = stmt.query_map(params![note_id], |row| row_to_attachment(row, &workspace))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Look up attachments by id, in the order given. Unknown ids are an error.
+pub fn get_many(conn: &Connection, ids: &[String]) -> Result<Vec<NoteAttachment>> {
+    let workspace = workspace_dir(conn)?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {ATTACHMENT_COLUMNS} FROM attachments WHERE id = ?1"
+    ))?;
+    ids.iter()
+        .map(|id| {
+            stmt.query_row(params![id], |row| row_to_attachment(row, &workspace))
+                .optional()?
+                .ok_or_else(|| {
+                    InkOsError::ValidationFailed(format!("attachment '{id}' does not exist")).into()
+                })
+        })
+        .collect()
+}
+
+fn row_to_attachment(
+    row: &rusqlite::Row<'_>,
+    workspace: &Path,
+) -> rusqlite::Result<NoteAttachment> {
+    let relative: String = row.get(4)?;
+    Ok(NoteAttachment {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        filename: row.get(2)?,
+        mime: row.get(3)?,
+        path: workspace.join(relative).to_string_lossy().into_owned(),
+        size_bytes: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Remove an attachment row and its file.
+pub fn delete(conn: &Connection, attachment_id: &str) -> Result<()> {
+    let relative: Option<String> = conn
+        .query_row(
+            "SELECT path FROM attachments WHERE id = ?1",
+            params![attachment_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(relative) = relative else {
+        return Err(InkOsError::ValidationFailed(format!(
+            "attachment '{attachment_id}' does not exist"
+        ))
+        .into());
+    };
+    conn.execute(
+        "DELETE FROM attachments WHERE id = ?1",
+        params![attachment_id],
+    )?;
+    let absolute = workspace_dir(conn)?.join(relative);
+    match std::fs::remove_file(&absolute) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Load the given attachments that are images, base64-encoded for a chat
+/// request. At most [`MAX_IMAGES_PER_REQUEST`] are returned.
+pub fn load_images(attachments: &[NoteAttachment]) -> Result<Vec<AiImage>> {
+    attachments
+        .iter()
+        .filter(|attachment| attachment.mime.starts_with("image/"))
+        .take(MAX_IMAGES_PER_REQUEST)
+        .map(|attachment| -> Result<AiImage> {
+            Ok(AiImage {
+                mime_type: attachment.mime.clone(),
+                data: B64_ENGINE.encode(std::fs::read(&attachment.path)?),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn attachments_are_stored_beside_the_database() {
+        let dir = std::env::temp_dir().join(format!("inkos-attach-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let conn = Connection::open(dir.join("inkos.db")).unwrap();
+        apply_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n1', 't', '', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        let shot = add(&conn, "n1", "../../shot.png", "image/png", b"png").unwrap();
+        assert_eq!(shot.filename, "shot.png");
+        assert!(Path::new(&shot.path).starts_with(dir.join(ATTACHMENTS_DIR).join("n1")));
+        let doc = add(&conn, "n1", "notes.txt", "text/plain", b"text").unwrap();
+        assert!(add(&conn, "missing", "a.png", "image/png", b"x").is_err());
+
+        let listed = list(&conn, "n1").unwrap();
+        assert_eq!(listed.len(), 2);
+        let images = load_images(&listed).unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].data, B64_ENGINE.encode(b"png"));
+        assert!(get_many(&conn, &[doc.id.clone(), "nope".into()]).is_err());
+
+        delete(&conn, &shot.id).unwrap();
+        assert!(!Path::new(&shot.path).exists());
+        assert_eq!(list(&conn, "n1").unwrap().len(), 1);
+        drop(conn);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
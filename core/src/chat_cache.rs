@@ -0,0 +1,168 @@
+//! Response cache for repeated chat requests.
+//!
+//! Entries live in `chat_cache`, keyed by a SHA-256 of the provider, model,
+//! and normalised request (messages, sampling parameters, token cap,
+//! reasoning effort, response format). Callers opt in per request through
+//! [`ModelManager::chat_cached`](crate::model_manager::ModelManager::chat_cached);
+//! entries expire after `ai.cache.ttl_secs`.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::agents::{AiChatInput, AiChatResponse};
+use crate::settings;
+
+/// Setting key holding the cache lifetime in seconds; `0` disables storing.
+pub const TTL_KEY: &str = "ai.cache.ttl_secs";
+const DEFAULT_TTL_SECS: i64 = 24 * 60 * 60;
+
+/// Cache key for `input` sent to `provider_id`/`model`.
+///
+/// Roles are lowercased and message text trimmed, so whitespace-only edits
+/// still hit. Images are reduced to a digest of their data.
+pub fn key(provider_id: &str, model: &str, input: &AiChatInput) -> String {
+    let messages: Vec<_> = input
+        .messages
+        .iter()
+        .map(|message| {
+            let images: Vec<String> = message
+                .images
+                .iter()
+                .map(|image| {
+                    format!(
+                        "{}:{:x}",
+                        image.mime_type,
+                        Sha256::digest(image.data.as_bytes())
+                    )
+                })
+                .collect();
+            json!({
+                "role": message.role.trim().to_lowercase(),
+                "content": message.content.trim(),
+                "images": images,
+            })
+        })
+        .collect();
+    let canonical = json!({
+        "provider": provider_id,
+        "model": model,
+        "messages": messages,
+        "temperature": input.temperature,
+        "top_p": input.top_p,
+        "stop": input.stop,
+        "response_format": input.response_format,
+        "max_tokens": input.max_tokens,
+        "reasoning_effort": input.reasoning_effort,
+    });
+    format!("{:x}", Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+/// Return the unexpired response stored under `key`, marked as cached.
+///
+/// Usage is dropped because a cache hit bills no tokens.
+pub fn lookup(conn: &Connection, key: &str, now: i64) -> Result<Option<AiChatResponse>> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT response_json FROM chat_cache WHERE key = ?1 AND expires_at > ?2",
+            params![key, now],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    let mut response: AiChatResponse = serde_json::from_str(&stored)?;
+    response.cached = true;
+    response.usage = None;
+    Ok(Some(response))
+}
+
+/// Store a response under `key` and drop expired entries. Empty replies are
+/// never stored so that a retry reaches the provider again.
+pub fn store(conn: &Connection, key: &str, response: &AiChatResponse, now: i64) -> Result<()> {
+    let ttl = read_ttl(conn)?;
+    if ttl <= 0 || response.content.trim().is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "DELETE FROM chat_cache WHERE expires_at <= ?1",
+        params![now],
+    )?;
+    conn.execute(
+        "INSERT OR REPLACE INTO chat_cache (key, provider_id, model, response_json, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            key,
+            response.provider_id,
+            response.model,
+            serde_json::to_string(response)?,
+            now,
+            now + ttl
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_ttl(conn: &Connection) -> Result<i64> {
+    Ok(settings::read(conn, TTL_KEY)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_TTL_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::AiChatMessage;
+    use crate::db::apply_migrations;
+
+    fn input(content: &str, temperature: Option<f32>) -> AiChatInput {
+        AiChatInput {
+            messages: vec![AiChatMessage {
+                role: "user".into(),
+                content: content.into(),
+                images: Vec::new(),
+            }],
+            temperature,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn cached_responses_expire_and_skip_empty_replies() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        let cache_key = key("ollama", "m", &input("Hello ", Some(0.2)));
+        assert_eq!(cache_key, key("ollama", "m", &input("Hello", Some(0.2))));
+        assert_ne!(cache_key, key("ollama", "m", &input("Hello", Some(0.7))));
+        assert_ne!(cache_key, key("ollama", "m2", &input("Hello", Some(0.2))));
+
+        let mut response = AiChatResponse {
+            provider_id: "ollama".into(),
+            model: "m".into(),
+            content: "  ".into(),
+            usage: None,
+            raw: json!({}),
+            cached: false,
+            reasoning: None,
+            request_id: None,
+        };
+        store(&conn, &cache_key, &response, 100).unwrap();
+        assert!(lookup(&conn, &cache_key, 100).unwrap().is_none());
+
+        response.content = "Hi there".into();
+        store(&conn, &cache_key, &response, 100).unwrap();
+        let hit = lookup(&conn, &cache_key, 101).unwrap().unwrap();
+        assert!(hit.cached);
+        assert_eq!(hit.content, "Hi there");
+        assert!(lookup(&conn, &cache_key, 100 + DEFAULT_TTL_SECS)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! Workspace snapshots built on SQLite's online backup API.
+//!
+//! Backups copy pages from a live connection, so they stay consistent while
+//! the scheduler keeps writing. Restores are staged next to the live file and
+//! swapped in by [`apply_pending_restore`] before the pool opens on the next
+//! start.
+
+use anyhow::{Context, Result};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{encryption, MIGRATIONS};
+use crate::errors::InkOsError;
+
+/// Result of a completed backup.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupReport {
+    pub path: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// Result of staging a restore; the swap happens on the next start.
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub staged_path: String,
+    pub bytes: u64,
+    pub sha256: String,
+    pub migrations: usize,
+}
+
+/// Copy the database behind `conn` to `dest`, overwriting any existing file.
+pub fn backup_db(conn: &Connection, dest: &Path) -> Result<BackupReport> {
+    if dest.as_os_str().is_empty() {
+        return Err(InkOsError::ValidationFailed("destination path is required".into()).into());
+    }
+    if conn.path().is_some_and(|live| Path::new(live) == dest) {
+        return Err(InkOsError::ValidationFailed(
+            "destination must differ from the live database".into(),
+        )
+        .into());
+    }
+    copy_database(conn, dest)
+        .with_context(|| format!("failed to back up database to {}", dest.display()))?;
+    let (bytes, sha256) = checksum(dest)?;
+    Ok(BackupReport {
+        path: dest.display().to_string(),
+        bytes,
+        sha256,
+    })
+}
+
+/// Validate the backup at `src` and stage it to replace the live database.
+pub fn restore_db(conn: &Connection, src: &Path) -> Result<RestoreReport> {
+    let live = conn
+        .path()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| InkOsError::ValidationFailed("live database has no file path".into()))?;
+    if !src.is_file() {
+        return Err(InkOsError::ValidationFailed(format!(
+            "backup file {} does not exist",
+            src.display()
+        ))
+        .into());
+    }
+
+    let source = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("failed to open backup {}", src.display()))?;
+    encryption::apply_key(&source)?;
+    encryption::verify(&source)?;
+    let migrations = validate_schema(&source)?;
+
+    let staged = sidecar(&live, ".restore");
+    if staged.exists() {
+        std::fs::remove_file(&staged)?;
+    }
+    copy_database(&source, &staged).context("failed to stage restore")?;
+    let (bytes, sha256) = checksum(&staged)?;
+    Ok(RestoreReport {
+        staged_path: staged.display().to_string(),
+        bytes,
+        sha256,
+        migrations,
+    })
+}
+
+/// Swap a staged restore into place. Must run before any connection opens.
+///
+/// The replaced database (and its WAL files) is kept as `*.pre-restore`.
+/// Returns whether a restore was applied.
+pub(crate) fn apply_pending_restore(db_path: &Path) -> Result<bool> {
+    let staged = sidecar(db_path, ".restore");
+    if !staged.exists() {
+        return Ok(false);
+    }
+    let previous = sidecar(db_path, ".pre-restore");
+    for suffix in ["", "-wal", "-shm"] {
+        let old = sidecar(&previous, suffix);
+        if old.exists() {
+            std::fs::remove_file(&old)?;
+        }
+        let current = sidecar(db_path, suffix);
+        if current.exists() {
+            std::fs::rename(&current, &old)
+                .with_context(|| format!("failed to move aside {}", current.display()))?;
+        }
+    }
+    std::fs::rename(&staged, db_path).context("failed to swap in restored database")?;
+    Ok(true)
+}
+
+/// Check the backup is intact and only uses migrations this build knows.
+fn validate_schema(conn: &Connection) -> Result<usize> {
+    let integrity: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if integrity != "ok" {
+        return Err(InkOsError::ValidationFailed(format!(
+            "backup failed integrity check: {integrity}"
+        ))
+        .into());
+    }
+    let versioned = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?
+        .is_some();
+    if !versioned {
+        return Err(InkOsError::ValidationFailed(
+            "backup has no schema version and cannot be restored".into(),
+        )
+        .into());
+    }
+    let recorded: HashSet<String> = conn
+        .prepare("SELECT name FROM schema_migrations")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let known: HashSet<&str> = MIGRATIONS.iter().map(|(name, _)| *name).collect();
+    if let Some(unknown) = recorded.iter().find(|name| !known.contains(name.as_str())) {
+        return Err(InkOsError::ValidationFailed(format!(
+            "backup was created by a newer version (migration {unknown})"
+        ))
+        .into());
+    }
+    if !recorded.contains(MIGRATIONS[0].0) {
+        return Err(InkOsError::ValidationFailed("backup is not an InkOS workspace".into()).into());
+    }
+    Ok(recorded.len())
+}
+
+/// Page-copy `src` into `dest`, keying the destination like the live
+/// database so encrypted workspaces stay encrypted.
+fn copy_database(src: &Connection, dest: &Path) -> rusqlite::Result<()> {
+    let mut dst = Connection::open(dest)?;
+    encryption::apply_key(&dst)?;
+    let backup = Backup::new(src, &mut dst)?;
+    backup.run_to_completion(256, Duration::from_millis(50), None)
+}
+
+fn checksum(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let bytes = std::io::copy(&mut file, &mut hasher)?;
+    Ok((bytes, format!("{:x}", hasher.finalize())))
+}
+
+fn sidecar(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn backups_round_trip_through_a_staged_restore() {
+        let dir = std::env::temp_dir().join(format!("inkos-backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let live_path = dir.join("inkos.db");
+        let live = Connection::open(&live_path).unwrap();
+        apply_migrations(&live).unwrap();
+        live.execute(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n1', 'a', '', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        let report = backup_db(&live, &dir.join("snapshot.db")).unwrap();
+        assert!(report.bytes > 0);
+        assert_eq!(report.sha256.len(), 64);
+
+        live.execute("DELETE FROM notes", []).unwrap();
+        let staged = restore_db(&live, &dir.join("snapshot.db")).unwrap();
+        assert_eq!(staged.migrations, MIGRATIONS.len());
+        drop(live);
+
+        assert!(apply_pending_restore(&live_path).unwrap());
+        assert!(dir.join("inkos.db.pre-restore").exists());
+        let restored = Connection::open(&live_path).unwrap();
+        let count: i64 = restored
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        restored
+            .execute(
+                "INSERT INTO schema_migrations (name, applied_at) VALUES ('9999_future.sql', 0)",
+                [],
+            )
+            .unwrap();
+        assert!(restore_db(&restored, &live_path).is_err());
+
+        drop(restored);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
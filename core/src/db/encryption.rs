@@ -0,0 +1,91 @@
+//! Optional encryption at rest via SQLCipher.
+//!
+//! Only active when the crate is built with the `sqlcipher` feature. The
+//! passphrase comes from `INKOS_DB_KEY` or, failing that, a prompt callback
+//! registered by the host before `init_db` runs. Without the feature every
+//! helper here is a no-op and the database stays plaintext.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use std::sync::{OnceLock, RwLock};
+
+use crate::errors::InkOsError;
+
+/// Environment variable holding the workspace passphrase.
+pub const KEY_ENV: &str = "INKOS_DB_KEY";
+
+/// Callback asked for the passphrase when the environment variable is unset.
+pub type KeyPrompt = fn() -> Option<String>;
+
+static PROMPT: OnceLock<KeyPrompt> = OnceLock::new();
+static CURRENT_KEY: RwLock<Option<String>> = RwLock::new(None);
+
+/// Register the passphrase prompt. Only the first registration takes effect.
+pub fn set_key_prompt(prompt: KeyPrompt) {
+    let _ = PROMPT.set(prompt);
+}
+
+/// Whether this build can open encrypted databases.
+pub fn supported() -> bool {
+    cfg!(feature = "sqlcipher")
+}
+
+/// Resolve the passphrase for this process and remember it for new
+/// connections.
+pub(crate) fn load_key() {
+    if !supported() {
+        return;
+    }
+    let key = std::env::var(KEY_ENV)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .or_else(|| PROMPT.get().and_then(|prompt| prompt()));
+    *CURRENT_KEY.write().unwrap() = key;
+}
+
+/// Key a freshly opened connection. Must run before any other statement.
+pub(crate) fn apply_key(conn: &Connection) -> rusqlite::Result<()> {
+    if let Some(key) = CURRENT_KEY.read().unwrap().as_deref() {
+        conn.pragma_update(None, "key", key)?;
+    }
+    Ok(())
+}
+
+/// Read the schema once so a wrong or missing key surfaces as `DB-ENCRYPT`
+/// instead of SQLite's "file is not a database".
+pub(crate) fn verify(conn: &Connection) -> Result<()> {
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    }) {
+        Ok(_) => Ok(()),
+        Err(err) if err.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) => {
+            Err(InkOsError::DbEncryption.into())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Re-encrypt the database behind `conn` with `new_key`.
+///
+/// Connections opened afterwards use the new key; connections already in the
+/// pool keep the old one, so the app should be restarted after rekeying.
+pub fn rekey(conn: &Connection, new_key: &str) -> Result<()> {
+    if !supported() {
+        return Err(InkOsError::ValidationFailed(
+            "this build does not support database encryption".into(),
+        )
+        .into());
+    }
+    if new_key.is_empty() {
+        return Err(InkOsError::ValidationFailed("passphrase must not be empty".into()).into());
+    }
+    if CURRENT_KEY.read().unwrap().is_none() {
+        return Err(InkOsError::ValidationFailed(format!(
+            "database is not encrypted; start with {KEY_ENV} set to create an encrypted workspace"
+        ))
+        .into());
+    }
+    conn.pragma_update(None, "rekey", new_key)?;
+    *CURRENT_KEY.write().unwrap() = Some(new_key.to_string());
+    Ok(())
+}
@@ -6,68 +6,562 @@
 use anyhow::{Context, Result};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::Connection;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::json;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::time::Duration;
+use time::OffsetDateTime;
 
 use crate::agents::config as ai_config;
+use crate::logging::log_event;
+
+pub mod backup;
+pub mod encryption;
 
 /// Shared connection pool type for the SQLite database.
 pub type DbPool = Pool<SqliteConnectionManager>;
 
+/// Connection pool sizing for [`init_db`].
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    /// Upper bound on open connections, including those checked out.
+    pub max_connections: u32,
+    /// Idle connections kept warm; `None` keeps up to `max_connections`.
+    pub min_idle: Option<u32>,
+    /// How long `pool.get()` waits for a free connection before failing.
+    pub connection_timeout: Duration,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 16,
+            min_idle: Some(2),
+            connection_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
 /// Initialise the workspace database inside the supplied directory.
 ///
 /// This helper ensures the directory exists, opens an `r2d2` pool, runs all
 /// migrations, and seeds the AI provider tables with sensible defaults. The
 /// resulting pool can then be injected into the Tauri state container.
-pub fn init_db(workspace_dir: PathBuf) -> Result<DbPool> {
+pub fn init_db(workspace_dir: PathBuf, config: DbConfig) -> Result<DbPool> {
     std::fs::create_dir_all(&workspace_dir)?;
     let db_path = workspace_dir.join("inkos.db");
-    let mgr = SqliteConnectionManager::file(&db_path);
-    let pool = Pool::new(mgr)?;
+    let restored = backup::apply_pending_restore(&db_path)?;
+    encryption::load_key();
+    {
+        let conn = Connection::open(&db_path)?;
+        encryption::apply_key(&conn)?;
+        encryption::verify(&conn)?;
+    }
+    let mgr = SqliteConnectionManager::file(&db_path).with_init(configure_connection);
+    let pool = Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(config.min_idle)
+        .connection_timeout(config.connection_timeout)
+        .build(mgr)?;
     {
         let conn = pool.get()?;
-        apply_migrations(&conn)?;
+        let applied = apply_migrations(&conn)?;
         ai_config::seed_defaults(&conn)?;
+        if !applied.is_empty() {
+            log_event(
+                &conn,
+                "info",
+                Some("DB-MIGRATE"),
+                "db",
+                "Applied database migrations",
+                Some("New schema migrations ran at startup."),
+                Some(json!({ "migrations": applied })),
+            )?;
+        }
+        if restored {
+            log_event(
+                &conn,
+                "info",
+                Some("DB-RESTORE"),
+                "db",
+                "Restored database from backup",
+                Some("The previous database was kept alongside as inkos.db.pre-restore."),
+                None,
+            )?;
+        }
     }
     Ok(pool)
 }
 
-/// Apply all embedded SQL migrations in order.
-fn apply_migrations(conn: &Connection) -> Result<()> {
-    let migrations: &[(&str, &str)] = &[
-        (
-            "0001_init.sql",
-            include_str!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/../migrations/0001_init.sql"
-            )),
-        ),
-        (
-            "0002_ai_settings.sql",
-            include_str!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/../migrations/0002_ai_settings.sql"
-            )),
-        ),
-        (
-            "0003_logbook_timeline.sql",
-            include_str!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/../migrations/0003_logbook_timeline.sql"
-            )),
-        ),
-        (
-            "0004_conversations_summaries.sql",
-            include_str!(concat!(
-                env!("CARGO_MANIFEST_DIR"),
-                "/../migrations/0004_conversations_summaries.sql"
-            )),
-        ),
-    ];
-
-    for (name, sql) in migrations {
-        conn.execute_batch(sql)
+/// Per-connection pragmas applied to every pooled connection.
+///
+/// WAL lets IPC reads proceed while the scheduler writes, and the busy
+/// timeout makes competing writers wait instead of failing with
+/// `database is locked`. Encrypted workspaces are keyed first, as SQLCipher
+/// requires.
+fn configure_connection(conn: &mut Connection) -> rusqlite::Result<()> {
+    encryption::apply_key(conn)?;
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+    conn.busy_timeout(Duration::from_millis(5000))?;
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(())
+}
+
+/// Embedded migrations in application order, keyed by file name.
+pub(crate) const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "0001_init.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0001_init.sql"
+        )),
+    ),
+    (
+        "0002_ai_settings.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0002_ai_settings.sql"
+        )),
+    ),
+    (
+        "0003_logbook_timeline.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0003_logbook_timeline.sql"
+        )),
+    ),
+    (
+        "0004_conversations_summaries.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0004_conversations_summaries.sql"
+        )),
+    ),
+    (
+        "0005_usage.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0005_usage.sql"
+        )),
+    ),
+    (
+        "0006_rollup_entries.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0006_rollup_entries.sql"
+        )),
+    ),
+    (
+        "0007_job_attempts.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0007_job_attempts.sql"
+        )),
+    ),
+    (
+        "0008_note_soft_delete.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0008_note_soft_delete.sql"
+        )),
+    ),
+    (
+        "0009_tags.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0009_tags.sql"
+        )),
+    ),
+    (
+        "0010_summary_source.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0010_summary_source.sql"
+        )),
+    ),
+    (
+        "0011_model_context.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0011_model_context.sql"
+        )),
+    ),
+    (
+        "0012_custom_providers.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0012_custom_providers.sql"
+        )),
+    ),
+    (
+        "0013_event_log_index.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0013_event_log_index.sql"
+        )),
+    ),
+    (
+        "0014_provider_timeouts.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0014_provider_timeouts.sql"
+        )),
+    ),
+    (
+        "0015_provider_headers.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0015_provider_headers.sql"
+        )),
+    ),
+    (
+        "0016_note_embeddings.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0016_note_embeddings.sql"
+        )),
+    ),
+    (
+        "0017_summary_coverage.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0017_summary_coverage.sql"
+        )),
+    ),
+    (
+        "0018_note_revisions.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0018_note_revisions.sql"
+        )),
+    ),
+    (
+        "0019_note_attachments.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0019_note_attachments.sql"
+        )),
+    ),
+    (
+        "0020_pinned.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0020_pinned.sql"
+        )),
+    ),
+    (
+        "0021_message_search.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0021_message_search.sql"
+        )),
+    ),
+    (
+        "0022_conversation_prompt.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0022_conversation_prompt.sql"
+        )),
+    ),
+    (
+        "0023_provider_rate_limits.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0023_provider_rate_limits.sql"
+        )),
+    ),
+    (
+        "0024_chat_cache.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0024_chat_cache.sql"
+        )),
+    ),
+    (
+        "0025_schedules.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0025_schedules.sql"
+        )),
+    ),
+    (
+        "0026_job_started_at.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0026_job_started_at.sql"
+        )),
+    ),
+    (
+        "0027_provider_sampling_defaults.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0027_provider_sampling_defaults.sql"
+        )),
+    ),
+    (
+        "0028_note_links.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0028_note_links.sql"
+        )),
+    ),
+    (
+        "0029_ai_response_log.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0029_ai_response_log.sql"
+        )),
+    ),
+    (
+        "0030_logbook_manual_notes.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0030_logbook_manual_notes.sql"
+        )),
+    ),
+    (
+        "0031_timeline_event_source.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0031_timeline_event_source.sql"
+        )),
+    ),
+    (
+        "0032_logbook_search.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0032_logbook_search.sql"
+        )),
+    ),
+    (
+        "0033_summary_covered_message.sql",
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../migrations/0033_summary_covered_message.sql"
+        )),
+    ),
+];
+
+/// Apply embedded SQL migrations that have not run yet, in order.
+///
+/// Applied names are recorded in `schema_migrations`, so files containing
+/// `ALTER TABLE` statements run exactly once; each new file runs in its own
+/// transaction together with its record. Returns the names applied by this
+/// call.
+pub(crate) fn apply_migrations(conn: &Connection) -> Result<Vec<String>> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+           name TEXT PRIMARY KEY,
+           applied_at INTEGER NOT NULL
+         )",
+    )
+    .context("failed to create schema_migrations")?;
+    let recorded: HashSet<String> = conn
+        .prepare("SELECT name FROM schema_migrations")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    // Databases created before versioning ran every file at each startup, so
+    // they have no records yet but may already contain any migration.
+    let legacy = recorded.is_empty()
+        && conn
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'notes'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+            .is_some();
+
+    let mut applied = Vec::new();
+    for (name, sql) in MIGRATIONS {
+        if recorded.contains(*name) {
+            continue;
+        }
+        let sql = if legacy {
+            without_existing_columns(conn, sql)?
+        } else {
+            Cow::Borrowed(*sql)
+        };
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&sql)
             .with_context(|| format!("failed to apply migration {name}"))?;
+        record_migration(&tx, name)?;
+        tx.commit()
+            .with_context(|| format!("failed to commit migration {name}"))?;
+        applied.push(name.to_string());
     }
+    Ok(applied)
+}
+
+fn record_migration(conn: &Connection, name: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO schema_migrations (name, applied_at) VALUES (?1, ?2)",
+        params![name, OffsetDateTime::now_utc().unix_timestamp()],
+    )?;
     Ok(())
 }
+
+/// Drop `ALTER TABLE .. ADD COLUMN` lines whose column already exists.
+///
+/// A legacy database may have applied the file before; every other
+/// statement in it still runs, so nothing after the ALTER is skipped.
+fn without_existing_columns<'a>(conn: &Connection, sql: &'a str) -> Result<Cow<'a, str>> {
+    let mut kept = Vec::new();
+    let mut dropped = false;
+    for line in sql.lines() {
+        if let Some((table, column)) = added_column(line) {
+            if column_exists(conn, table, column)? {
+                dropped = true;
+                continue;
+            }
+        }
+        kept.push(line);
+    }
+    Ok(if dropped {
+        Cow::Owned(kept.join("\n"))
+    } else {
+        Cow::Borrowed(sql)
+    })
+}
+
+/// Table and column of a single-line `ALTER TABLE t ADD COLUMN c ...;`.
+fn added_column(line: &str) -> Option<(&str, &str)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [alter, table_kw, table, add, column_kw, column, ..]
+            if alter.eq_ignore_ascii_case("ALTER")
+                && table_kw.eq_ignore_ascii_case("TABLE")
+                && add.eq_ignore_ascii_case("ADD")
+                && column_kw.eq_ignore_ascii_case("COLUMN") =>
+        {
+            Some((table, column.trim_end_matches(';')))
+        }
+        _ => None,
+    }
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    Ok(conn
+        .query_row(
+            "SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2",
+            params![table, column],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// Turn free text into an FTS5 query of quoted words joined by `joiner`
+/// (`" "` to require every word, `" OR "` to accept any), so operators and
+/// quotes in the text are matched literally. Empty for blank text.
+pub(crate) fn fts_phrase_query(text: &str, joiner: &str) -> String {
+    text.split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(joiner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        column_exists(conn, table, column).unwrap()
+    }
+
+    #[test]
+    fn fts_phrase_query_quotes_every_word() {
+        assert_eq!(
+            fts_phrase_query(" say \"hi\" OR ", " "),
+            "\"say\" \"\"\"hi\"\"\" \"OR\""
+        );
+        assert_eq!(fts_phrase_query("a b", " OR "), "\"a\" OR \"b\"");
+        assert!(fts_phrase_query("  ", " ").is_empty());
+    }
+
+    #[test]
+    fn migrations_run_once_and_are_recorded() {
+        let conn = Connection::open_in_memory().unwrap();
+        let first = apply_migrations(&conn).unwrap();
+        assert!(first.contains(&"0001_init.sql".to_string()));
+        assert!(apply_migrations(&conn).unwrap().is_empty());
+        assert!(has_column(&conn, "jobs", "max_attempts"));
+    }
+
+    #[test]
+    fn pooled_connections_interleave_reads_and_writes() {
+        let dir = std::env::temp_dir().join(format!("inkos-wal-{}", uuid::Uuid::new_v4()));
+        let pool = init_db(dir.clone(), DbConfig::default()).unwrap();
+        let writer = pool.get().unwrap();
+        let reader = pool.get().unwrap();
+
+        let mode: String = writer
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        let tx = writer.unchecked_transaction().unwrap();
+        tx.execute(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n1', 'a', '', 0, 0)",
+            [],
+        )
+        .unwrap();
+        // The reader sees the last committed state while the write is open.
+        let before: i64 = reader
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(before, 0);
+        tx.commit().unwrap();
+
+        reader
+            .execute(
+                "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n2', 'b', '', 0, 0)",
+                [],
+            )
+            .unwrap();
+        let after: i64 = writer
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(after, 2);
+
+        drop((writer, reader, pool));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn legacy_databases_are_adopted() {
+        // Before versioning every file re-ran at startup; simulate a database
+        // that applied 0007 only partly, stopped inside 0008 after its ALTER
+        // and recorded nothing.
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        conn.execute_batch(
+            "DROP TABLE schema_migrations;
+             ALTER TABLE jobs DROP COLUMN max_attempts;
+             DROP INDEX idx_notes_deleted_at;
+             DROP TRIGGER notes_ad;
+             DROP TRIGGER notes_au;",
+        )
+        .unwrap();
+
+        let applied = apply_migrations(&conn).unwrap();
+        assert!(applied.contains(&"0008_note_soft_delete.sql".to_string()));
+        assert!(has_column(&conn, "jobs", "result"));
+        assert!(has_column(&conn, "jobs", "attempts"));
+        assert!(has_column(&conn, "jobs", "max_attempts"));
+        assert!(has_column(&conn, "notes", "deleted_at"));
+        let schema_objects: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master
+                 WHERE name IN ('idx_notes_deleted_at', 'notes_ad', 'notes_au')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(schema_objects, 3);
+        assert!(apply_migrations(&conn).unwrap().is_empty());
+    }
+}
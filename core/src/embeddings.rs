@@ -0,0 +1,210 @@
+//! Note embeddings for semantic search.
+//!
+//! Each live note keeps at most one vector in `note_embeddings`, stored as a
+//! little-endian `f32` blob alongside the provider/model that produced it.
+//! Vectors are refreshed by the `notes.embed` background job and compared by
+//! cosine similarity in [`search`]; only vectors from the same model are
+//! ranked against a query.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::agents::config;
+use crate::model_manager::ModelManager;
+
+/// A note ranked by similarity to a query.
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticHit {
+    pub id: String,
+    pub title: String,
+    pub score: f32,
+}
+
+/// Serialise a vector as little-endian `f32`s.
+pub fn encode(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Inverse of [`encode`]; trailing bytes that do not form an `f32` are ignored.
+pub fn decode(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+/// Cosine similarity in `[-1, 1]`; mismatched or zero vectors score 0.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+/// Recompute the embedding for one note, skipping unchanged content.
+///
+/// Deleted notes lose their vector. When no embedding-capable provider is
+/// configured the job succeeds without doing anything.
+pub fn embed_note(conn: &Connection, models: &ModelManager, note_id: &str) -> Result<Value> {
+    let note: Option<(String, String)> = conn
+        .query_row(
+            "SELECT title, body FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![note_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((title, body)) = note else {
+        conn.execute(
+            "DELETE FROM note_embeddings WHERE note_id = ?1",
+            params![note_id],
+        )?;
+        return Ok(json!({ "note_id": note_id, "skipped": "note not found" }));
+    };
+    let Some(selection) = config::resolve_embedding_runtime(conn)? else {
+        return Ok(json!({ "note_id": note_id, "skipped": "no embedding model configured" }));
+    };
+
+    let text = format!("{title}\n\n{body}");
+    let hash = format!("{:x}", Sha256::digest(text.as_bytes()));
+    let current: Option<(String, String, String)> = conn
+        .query_row(
+            "SELECT provider_id, model, content_hash FROM note_embeddings WHERE note_id = ?1",
+            params![note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    if current.as_ref().is_some_and(|(provider, model, stored)| {
+        provider == &selection.provider.id && model == &selection.model && stored == &hash
+    }) {
+        return Ok(json!({ "note_id": note_id, "skipped": "unchanged" }));
+    }
+
+    let vector = models
+        .embed_blocking(&selection, &[text])?
+        .pop()
+        .unwrap_or_default();
+    store(
+        conn,
+        note_id,
+        &selection.provider.id,
+        &selection.model,
+        &hash,
+        &vector,
+    )?;
+    Ok(json!({
+        "note_id": note_id,
+        "provider": selection.provider.id,
+        "model": selection.model,
+        "dims": vector.len(),
+    }))
+}
+
+/// Insert or replace a note's vector.
+pub fn store(
+    conn: &Connection,
+    note_id: &str,
+    provider_id: &str,
+    model: &str,
+    content_hash: &str,
+    vector: &[f32],
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO note_embeddings (note_id, provider_id, model, dims, vector, content_hash, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(note_id) DO UPDATE SET
+             provider_id = excluded.provider_id,
+             model = excluded.model,
+             dims = excluded.dims,
+             vector = excluded.vector,
+             content_hash = excluded.content_hash,
+             updated_at = excluded.updated_at",
+        params![
+            note_id,
+            provider_id,
+            model,
+            vector.len() as i64,
+            encode(vector),
+            content_hash,
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Rank live notes embedded by `provider_id`/`model` against `query`.
+pub fn search(
+    conn: &Connection,
+    provider_id: &str,
+    model: &str,
+    query: &[f32],
+    k: usize,
+) -> Result<Vec<SemanticHit>> {
+    let mut stmt = conn.prepare(
+        "SELECT n.id, n.title, e.vector FROM note_embeddings e
+         JOIN notes n ON n.id = e.note_id
+         WHERE n.deleted_at IS NULL AND e.provider_id = ?1 AND e.model = ?2 AND e.dims = ?3",
+    )?;
+    let rows = stmt.query_map(params![provider_id, model, query.len() as i64], |row| {
+        let blob: Vec<u8> = row.get(2)?;
+        Ok(SemanticHit {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            score: cosine(query, &decode(&blob)),
+        })
+    })?;
+    let mut hits = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(k);
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn search_ranks_live_notes_by_cosine_similarity() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        for (id, deleted) in [("near", None), ("far", None), ("gone", Some(1))] {
+            conn.execute(
+                "INSERT INTO notes (id, title, body, created_at, updated_at, deleted_at) VALUES (?1, ?1, '', 0, 0, ?2)",
+                params![id, deleted],
+            )
+            .unwrap();
+        }
+        store(&conn, "near", "ollama", "m", "h", &[0.9, 0.1]).unwrap();
+        store(&conn, "far", "ollama", "m", "h", &[0.0, 1.0]).unwrap();
+        store(&conn, "gone", "ollama", "m", "h", &[1.0, 0.0]).unwrap();
+
+        assert_eq!(decode(&encode(&[0.5, -2.0])), vec![0.5, -2.0]);
+        let hits = search(&conn, "ollama", "m", &[1.0, 0.0], 5).unwrap();
+        let ids: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+        assert_eq!(ids, ["near", "far"]);
+        assert!(hits[0].score > 0.99 && hits[1].score.abs() < 1e-6);
+
+        assert!(search(&conn, "openai", "m", &[1.0, 0.0], 5)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            search(&conn, "ollama", "m", &[1.0, 0.0], 1).unwrap().len(),
+            1
+        );
+    }
+}
@@ -4,15 +4,52 @@
 //! stable code and explanation so the UI can surface meaningful guidance to
 //! the user.
 
+use serde::Serialize;
+use std::sync::Arc;
 use thiserror::Error;
 
+use crate::agents::OrchestratorError;
+
 /// Canonical error variants emitted by the core service.
 #[derive(Debug, Error)]
 pub enum InkOsError {
     #[error("Database unavailable")]
     DbUnavailable,
+    #[error("Database key is missing or incorrect")]
+    DbEncryption,
     #[error("Note not found")]
     NoteNotFound,
+    #[error("Note was modified by another writer")]
+    NoteConflict,
+    #[error("No AI provider is configured")]
+    ProviderNotConfigured,
+    #[error("Model '{model}' is not offered by this provider; available: {}", .available.join(", "))]
+    ModelNotFound {
+        model: String,
+        available: Vec<String>,
+    },
+    #[error("Conversation not found")]
+    ConversationNotFound,
+    #[error("Message not found")]
+    MessageNotFound,
+    #[error("Conversation was rolled over")]
+    ConversationRolled,
+    #[error("Summary not found")]
+    SummaryNotFound,
+    #[error("Job not found")]
+    JobNotFound,
+    #[error("{0}")]
+    JobInvalidState(String),
+    #[error("Timeline event not found")]
+    TimelineEventNotFound,
+    #[error("{0}")]
+    ValidationFailed(String),
+    #[error("{field}: {reason}")]
+    InvalidField { field: String, reason: String },
+    #[error("Conversation rolled over {recent} times in the last {window_mins} minutes")]
+    RolloverLoop { recent: usize, window_mins: i64 },
+    #[error("Rate limited")]
+    RateLimited,
     #[error("Unknown error")]
     Unknown,
 }
@@ -23,7 +60,21 @@ impl InkOsError {
     pub fn code(&self) -> &'static str {
         match self {
             Self::DbUnavailable => "DB-1001",
+            Self::DbEncryption => "DB-ENCRYPT",
             Self::NoteNotFound => "NTE-1001",
+            Self::NoteConflict => "NTE-CONFLICT",
+            Self::ProviderNotConfigured => "AI-2001",
+            Self::ModelNotFound { .. } => "AI-2002",
+            Self::ConversationNotFound => "CNV-1001",
+            Self::MessageNotFound => "MSG-1001",
+            Self::ConversationRolled => "CNV-ROLLED",
+            Self::SummaryNotFound => "SUM-1001",
+            Self::JobNotFound => "JOB-1001",
+            Self::JobInvalidState(_) => "JOB-STATE",
+            Self::TimelineEventNotFound => "TML-1001",
+            Self::ValidationFailed(_) | Self::InvalidField { .. } => "VAL-1001",
+            Self::RolloverLoop { .. } => "AI-CTX-LOOP",
+            Self::RateLimited => "GEN-1429",
             Self::Unknown => "GEN-1000",
         }
     }
@@ -32,8 +83,174 @@ impl InkOsError {
     pub fn explain(&self) -> &'static str {
         match self {
             Self::DbUnavailable => "The application could not access the SQLite database.",
+            Self::DbEncryption => {
+                "The workspace database is encrypted. Supply the correct passphrase via INKOS_DB_KEY."
+            }
             Self::NoteNotFound => "No note exists for the requested ID.",
+            Self::NoteConflict => {
+                "The note changed since it was loaded. Reload it and reapply your edit."
+            }
+            Self::ProviderNotConfigured => {
+                "Select an AI provider in Settings before using AI features."
+            }
+            Self::ModelNotFound { .. } => {
+                "Pick one of the provider's listed models, or add the model to the provider."
+            }
+            Self::ConversationNotFound => "No conversation exists for the requested ID.",
+            Self::MessageNotFound => "No message exists for the requested ID.",
+            Self::ConversationRolled => {
+                "This conversation continues in a newer one after reaching its context limit. Carry on there."
+            }
+            Self::SummaryNotFound => "No summary exists for the requested target.",
+            Self::JobNotFound => "No background job exists for the requested ID.",
+            Self::JobInvalidState(_) => {
+                "The job's state does not allow this action. Refresh the job list and try again."
+            }
+            Self::TimelineEventNotFound => {
+                "No manual timeline event exists for the requested ID. Derived events are rebuilt by the daily digest and cannot be deleted."
+            }
+            Self::ValidationFailed(_) | Self::InvalidField { .. } => {
+                "The request was rejected because an input is invalid."
+            }
+            Self::RolloverLoop { .. } => {
+                "The conversation keeps exceeding its context window. Start a new conversation or use a model with a larger context window."
+            }
+            Self::RateLimited => "Too many requests were made. Wait a moment and try again.",
             Self::Unknown => "An unspecified error occurred.",
         }
     }
 }
+
+/// Error object returned by every IPC command.
+///
+/// The frontend switches on `code`; `message` carries the specifics and
+/// `explain` a remediation hint. `field` names the rejected input when a
+/// validation error concerns a single field.
+#[derive(Debug, Clone, Serialize)]
+pub struct IpcError {
+    pub code: String,
+    pub message: String,
+    pub explain: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl From<InkOsError> for IpcError {
+    fn from(err: InkOsError) -> Self {
+        let field = match &err {
+            InkOsError::InvalidField { field, .. } => Some(field.clone()),
+            _ => None,
+        };
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            explain: err.explain().to_string(),
+            field,
+        }
+    }
+}
+
+impl From<OrchestratorError> for IpcError {
+    fn from(err: OrchestratorError) -> Self {
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            explain: err.explain().to_string(),
+            field: None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for IpcError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<InkOsError>() {
+            Ok(catalogued) => return catalogued.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<OrchestratorError>() {
+            Ok(classified) => return classified.into(),
+            Err(err) => err,
+        };
+        if err.downcast_ref::<r2d2::Error>().is_some() {
+            return InkOsError::DbUnavailable.into();
+        }
+        unknown(err.to_string())
+    }
+}
+
+impl From<r2d2::Error> for IpcError {
+    fn from(_: r2d2::Error) -> Self {
+        InkOsError::DbUnavailable.into()
+    }
+}
+
+impl From<r2d2_sqlite::rusqlite::Error> for IpcError {
+    fn from(err: r2d2_sqlite::rusqlite::Error) -> Self {
+        unknown(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for IpcError {
+    fn from(err: serde_json::Error) -> Self {
+        unknown(err.to_string())
+    }
+}
+
+impl From<tauri::Error> for IpcError {
+    fn from(err: tauri::Error) -> Self {
+        unknown(err.to_string())
+    }
+}
+
+impl From<time::error::Parse> for IpcError {
+    fn from(err: time::error::Parse) -> Self {
+        InkOsError::ValidationFailed(err.to_string()).into()
+    }
+}
+
+/// Uncatalogued failure: keep the underlying message under the generic code.
+fn unknown(message: String) -> IpcError {
+    IpcError {
+        code: InkOsError::Unknown.code().to_string(),
+        message,
+        explain: InkOsError::Unknown.explain().to_string(),
+        field: None,
+    }
+}
+
+/// Recover an error from a computation whose result was shared between
+/// waiters: the last holder gets the original, others a formatted copy.
+pub(crate) fn unshare_error(err: Arc<anyhow::Error>) -> anyhow::Error {
+    Arc::try_unwrap(err).unwrap_or_else(|shared| anyhow::anyhow!("{shared:#}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anyhow_errors_keep_their_catalogue_code() {
+        let err: anyhow::Error = InkOsError::ConversationNotFound.into();
+        assert_eq!(IpcError::from(err).code, "CNV-1001");
+
+        let plain = IpcError::from(anyhow::anyhow!("disk full"));
+        assert_eq!(plain.code, "GEN-1000");
+        assert_eq!(plain.message, "disk full");
+    }
+
+    #[test]
+    fn rollover_loops_are_catalogued() {
+        let err: anyhow::Error = InkOsError::RolloverLoop {
+            recent: 3,
+            window_mins: 10,
+        }
+        .into();
+        let ipc = IpcError::from(err);
+        assert_eq!(ipc.code, "AI-CTX-LOOP");
+        assert_eq!(
+            ipc.message,
+            "Conversation rolled over 3 times in the last 10 minutes"
+        );
+        assert!(ipc.explain.contains("larger context window"));
+    }
+}
@@ -4,16 +4,44 @@
 //! between responsibilities remain obvious when exploring the codebase:
 //! - [`agents`] handles AI provider configuration and the runtime orchestrator.
 //! - [`api`] exposes the IPC surface that the Tauri UI invokes.
+//! - [`attachments`] stores files attached to notes in the workspace directory.
+//! - [`chat_cache`] stores replies for chat requests that opt into caching.
 //! - [`db`] initialises the SQLite database and applies migrations.
+//! - [`embeddings`] stores note vectors and ranks them for semantic search.
 //! - [`errors`] keeps the central error catalogue with human friendly metadata.
 //! - [`logging`] writes structured diagnostics to the event log table.
+//! - [`note_links`] records explicit links and backlinks between notes.
+//! - [`note_markdown`] imports and exports notes as Markdown files.
+//! - [`pagination`] provides the keyset cursor shared by list commands.
+//! - [`redact`] scrubs API keys and tokens before anything is logged.
+//! - [`response_log`] keeps redacted raw provider replies when capture is on.
+//! - [`revisions`] keeps earlier versions of edited notes for restore.
+//! - [`settings`] reads and writes key/value rows in `app_settings`.
+//! - [`tags`] normalises tag names and attaches them to notes.
+//! - [`tokens`] counts tokens for context budgeting, optionally via BPE tables.
+//! - [`validation`] checks IPC inputs and reports the offending field.
 //! - [`workers`] implements synchronous background jobs such as the daily digest.
 
 pub mod agents;
 pub mod api;
+pub mod attachments;
+pub mod chat_cache;
 pub mod db;
+pub mod embeddings;
 pub mod errors;
 pub mod logging;
+#[cfg(test)]
+mod mock_provider;
 pub mod model_manager;
+pub mod note_links;
+pub mod note_markdown;
+pub mod pagination;
+pub mod redact;
+pub mod response_log;
+pub mod revisions;
+pub mod settings;
 pub mod summarizer;
+pub mod tags;
+pub mod tokens;
+pub mod validation;
 pub mod workers;
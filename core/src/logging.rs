@@ -2,17 +2,174 @@
 //! `event_log` table. The log stream powers the AI debugger UI and the
 //! daily digest worker, so keeping the API small and predictable is useful.
 
-use r2d2_sqlite::rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use r2d2_sqlite::rusqlite::{params, Connection, Transaction};
+use serde::Serialize;
 use serde_json::Value;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+use crate::redact::{redact_json, redact_text};
+use crate::settings;
+
+/// `app_settings` key holding the minimum level that is persisted.
+pub const MIN_LEVEL_KEY: &str = "logging.min_level";
+/// Levels accepted by [`set_min_level`], from most to least verbose.
+pub const LEVELS: &[&str] = &["debug", "info", "warn", "error"];
+const DEFAULT_MIN_LEVEL: &str = "info";
+
+/// Tauri event carrying each [`LoggedEvent`] as it is written.
+pub const EVENT_LOG_NEW_EVENT: &str = "event-log://new";
+
+/// An `event_log` row as stored, after redaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggedEvent {
+    pub id: String,
+    pub ts: i64,
+    pub level: String,
+    pub code: Option<String>,
+    pub module: String,
+    pub message: String,
+    pub explain: Option<String>,
+    pub data: Option<Value>,
+}
+
+/// Callback told about every persisted event, usually forwarding it to the
+/// UI as a Tauri event.
+pub type EventSink = Arc<dyn Fn(&LoggedEvent) + Send + Sync>;
+
+static SINK: OnceLock<EventSink> = OnceLock::new();
+
+/// Events logged inside a still-open transaction, keyed by the address of
+/// their connection, until it commits or rolls back.
+static PENDING: OnceLock<Mutex<HashMap<usize, Vec<LoggedEvent>>>> = OnceLock::new();
+
+/// Events whose transaction has started to commit. They are published once
+/// the commit is confirmed and dropped if it turns into a rollback.
+static COMMITTING: OnceLock<Mutex<HashMap<usize, Vec<LoggedEvent>>>> = OnceLock::new();
+
+/// Register the sink [`log_event`] publishes to. Only the first registration
+/// takes effect; without one, events are only written to the table.
+pub fn set_event_sink(sink: EventSink) {
+    let _ = SINK.set(sink);
+}
+
+fn pending() -> &'static Mutex<HashMap<usize, Vec<LoggedEvent>>> {
+    PENDING.get_or_init(Default::default)
+}
+
+fn committing() -> &'static Mutex<HashMap<usize, Vec<LoggedEvent>>> {
+    COMMITTING.get_or_init(Default::default)
+}
+
+fn connection_key(conn: &Connection) -> usize {
+    conn as *const Connection as usize
+}
+
+fn take_events(events: &Mutex<HashMap<usize, Vec<LoggedEvent>>>, key: usize) -> Vec<LoggedEvent> {
+    events
+        .lock()
+        .ok()
+        .and_then(|mut events| events.remove(&key))
+        .unwrap_or_default()
+}
+
+/// Publish the events of `conn`'s last commit. Only call this once the
+/// commit is known to have gone through.
+fn publish_committed(key: usize) {
+    let events = take_events(committing(), key);
+    if let Some(sink) = SINK.get() {
+        for event in &events {
+            sink(event);
+        }
+    }
+}
+
+/// Hand `event` to the sink once its row is durable: right away outside a
+/// transaction, otherwise after the transaction commits. Events from a
+/// rolled-back transaction are dropped with their rows.
+fn publish(conn: &Connection, event: LoggedEvent) {
+    let Some(sink) = SINK.get() else {
+        return;
+    };
+    let key = connection_key(conn);
+    if conn.is_autocommit() {
+        // Back in autocommit without a rollback, so an earlier commit that
+        // was not finished through `commit` went through.
+        publish_committed(key);
+        sink(&event);
+        return;
+    }
+    let first = pending()
+        .lock()
+        .map(|mut pending| {
+            let events = pending.entry(key).or_default();
+            events.push(event);
+            events.len() == 1
+        })
+        .unwrap_or(false);
+    if !first {
+        return;
+    }
+    // The hooks fire before SQLite finishes the commit, which can still
+    // fail; they only stage the events or drop them.
+    conn.commit_hook(Some(move || {
+        let events = take_events(pending(), key);
+        if let Ok(mut committing) = committing().lock() {
+            committing.entry(key).or_default().extend(events);
+        }
+        false
+    }));
+    conn.rollback_hook(Some(move || {
+        take_events(pending(), key);
+        take_events(committing(), key);
+    }));
+}
+
+/// Commit `tx` and publish the events logged inside it. Transactions that
+/// log events commit through this, so the events go out as soon as the
+/// commit is confirmed.
+pub fn commit(tx: Transaction<'_>) -> rusqlite::Result<()> {
+    let key = connection_key(&tx);
+    tx.commit()?;
+    publish_committed(key);
+    Ok(())
+}
+
+fn level_rank(level: &str) -> usize {
+    match level {
+        "warning" => 2,
+        other => LEVELS.iter().position(|l| *l == other).unwrap_or(1),
+    }
+}
+
+/// Read the configured minimum level, defaulting to `info`.
+///
+/// Databases without an `app_settings` table (e.g. in tests) use the default.
+pub fn min_level(conn: &Connection) -> String {
+    settings::read(conn, MIN_LEVEL_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_MIN_LEVEL.to_string())
+}
+
+/// Persist the minimum level; callers validate against [`LEVELS`].
+pub fn set_min_level(conn: &Connection, level: &str) -> anyhow::Result<()> {
+    settings::upsert(conn, MIN_LEVEL_KEY, level)
+}
+
 /// Insert a structured event into the `event_log` table.
 ///
 /// The function accepts optional metadata so that callers can provide
 /// machine-readable error codes alongside human-readable explanations.
 /// `data` is stored as raw JSON to keep the schema flexible while still
-/// allowing downstream analysis.
+/// allowing downstream analysis. Events below `logging.min_level` are
+/// dropped without error. Message, explanation, and data are passed through
+/// [`crate::redact`] so credentials never reach the table. Stored events are
+/// also handed to the sink registered with [`set_event_sink`], if any, once
+/// the surrounding transaction (if there is one) commits.
 pub fn log_event(
     conn: &Connection,
     level: &str,
@@ -22,12 +179,185 @@ pub fn log_event(
     explain: Option<&str>,
     data: Option<Value>,
 ) -> rusqlite::Result<()> {
+    if level_rank(level) < level_rank(&min_level(conn)) {
+        return Ok(());
+    }
     let id = Uuid::new_v4().to_string();
     let ts = OffsetDateTime::now_utc().unix_timestamp();
-    let data_str = data.map(|v| v.to_string());
+    let message = redact_text(message);
+    let explain = explain.map(redact_text);
+    let data = data.map(|mut v| {
+        redact_json(&mut v);
+        v
+    });
+    let data_str = data.as_ref().map(Value::to_string);
     conn.execute(
         "INSERT INTO event_log (id, ts, level, code, module, message, explain, data) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
         params![id, ts, level, code, module, message, explain, data_str],
     )?;
+    publish(
+        conn,
+        LoggedEvent {
+            id,
+            ts,
+            level: level.to_string(),
+            code: code.map(str::to_string),
+            module: module.to_string(),
+            message,
+            explain,
+            data,
+        },
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_below_threshold_are_dropped() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE app_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at INTEGER NOT NULL);
+             CREATE TABLE event_log (id TEXT PRIMARY KEY, ts INTEGER, level TEXT, code TEXT, module TEXT, message TEXT, explain TEXT, data TEXT);",
+        )
+        .unwrap();
+        set_min_level(&conn, "warn").unwrap();
+        log_event(
+            &conn,
+            "info",
+            Some("AI-0200"),
+            "ai.runtime",
+            "ok",
+            None,
+            None,
+        )
+        .unwrap();
+        log_event(
+            &conn,
+            "warn",
+            Some("AI-0201"),
+            "ai.runtime",
+            "failed",
+            None,
+            None,
+        )
+        .unwrap();
+        let levels: Vec<String> = conn
+            .prepare("SELECT level FROM event_log")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(levels, vec!["warn".to_string()]);
+    }
+
+    /// Events seen by the process-wide sink, which only the first
+    /// registration sets, filtered to `module`.
+    fn sunk(module: &str) -> Vec<LoggedEvent> {
+        static SEEN: Mutex<Vec<LoggedEvent>> = Mutex::new(Vec::new());
+        set_event_sink(Arc::new(|event: &LoggedEvent| {
+            SEEN.lock().unwrap().push(event.clone());
+        }));
+        SEEN.lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.module == module)
+            .cloned()
+            .collect()
+    }
+
+    fn event_log_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE event_log (id TEXT PRIMARY KEY, ts INTEGER, level TEXT, code TEXT, module TEXT, message TEXT, explain TEXT, data TEXT);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn stored_events_reach_the_sink_redacted() {
+        let conn = event_log_db();
+        sunk("test.sink");
+        log_event(
+            &conn,
+            "warn",
+            Some("AI-0201"),
+            "test.sink",
+            "401: Bearer sk-live-abc123 rejected",
+            None,
+            Some(serde_json::json!({ "request_id": "r1" })),
+        )
+        .unwrap();
+
+        let seen = sunk("test.sink");
+        assert_eq!(seen.len(), 1);
+        let stored: String = conn
+            .query_row("SELECT id FROM event_log", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(seen[0].id, stored);
+        assert_eq!(seen[0].code.as_deref(), Some("AI-0201"));
+        assert!(!seen[0].message.contains("sk-live-abc123"));
+        assert_eq!(seen[0].data.as_ref().unwrap()["request_id"], "r1");
+    }
+
+    #[test]
+    fn transactional_events_reach_the_sink_only_on_commit() {
+        let mut conn = event_log_db();
+        sunk("test.tx");
+        let log = |conn: &Connection, message: &str| {
+            log_event(conn, "info", None, "test.tx", message, None, None).unwrap();
+        };
+
+        let tx = conn.transaction().unwrap();
+        log(&tx, "rolled back");
+        assert!(sunk("test.tx").is_empty());
+        drop(tx);
+        assert!(sunk("test.tx").is_empty());
+
+        let tx = conn.transaction().unwrap();
+        log(&tx, "first");
+        log(&tx, "second");
+        assert!(sunk("test.tx").is_empty());
+        commit(tx).unwrap();
+        let messages: Vec<String> = sunk("test.tx")
+            .into_iter()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(messages, ["first", "second"]);
+    }
+
+    #[test]
+    fn events_of_a_failed_commit_never_reach_the_sink() {
+        sunk("test.failed");
+        let path = std::env::temp_dir().join(format!("inkos-log-{}.db", Uuid::new_v4()));
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE event_log (id TEXT PRIMARY KEY, ts INTEGER, level TEXT, code TEXT, module TEXT, message TEXT, explain TEXT, data TEXT);",
+        )
+        .unwrap();
+        // A reader holding its snapshot keeps the rollback-journal commit
+        // from getting its exclusive lock.
+        conn.busy_timeout(std::time::Duration::ZERO).unwrap();
+        let reader = Connection::open(&path).unwrap();
+        reader
+            .execute_batch("BEGIN; SELECT COUNT(*) FROM event_log;")
+            .unwrap();
+
+        let tx = conn.transaction().unwrap();
+        log_event(&tx, "info", None, "test.failed", "busy", None, None).unwrap();
+        assert!(commit(tx).is_err());
+        reader.execute_batch("COMMIT").unwrap();
+        log_event(&conn, "info", None, "test.failed", "later", None, None).unwrap();
+        let messages: Vec<String> = sunk("test.failed")
+            .into_iter()
+            .map(|event| event.message)
+            .collect();
+        assert_eq!(messages, ["later"]);
+        drop((conn, reader));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -0,0 +1,85 @@
+//! Local HTTP server standing in for an AI provider in tests.
+//!
+//! Every connection carries a single request: the server reads it, answers
+//! from the test's responder and closes the connection.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// One request received by [`serve`].
+pub(crate) struct MockRequest {
+    /// Request path, such as `/api/chat`.
+    pub path: String,
+    /// Raw request body; empty for requests without one.
+    pub body: String,
+}
+
+impl MockRequest {
+    /// The request body parsed as JSON.
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// Serve requests on a free local port and return its base URL.
+///
+/// `respond` receives the zero-based request index and the request, and
+/// returns the status (such as `"200 OK"`) and JSON body to send back.
+/// Returning `None` drops that connection unanswered and stops the server.
+pub(crate) fn serve<F>(mut respond: F) -> String
+where
+    F: FnMut(usize, &MockRequest) -> Option<(&'static str, String)> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&stream);
+            let Some((status, body)) = respond(index, &request) else {
+                break;
+            };
+            let _ = stream.write_all(
+                format!(
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .as_bytes(),
+            );
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// Ollama `/api/chat` reply body carrying `content`.
+pub(crate) fn ollama_reply(content: &str) -> String {
+    serde_json::json!({ "message": { "role": "assistant", "content": content } }).to_string()
+}
+
+fn read_request(stream: &TcpStream) -> MockRequest {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).unwrap();
+    let path = request_line
+        .split(' ')
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+            content_length = value.trim().parse().unwrap();
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+    MockRequest {
+        path,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    }
+}
@@ -6,28 +6,167 @@
 //! can simply request a completion without caring which backend ultimately
 //! fulfils it.
 
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
-use tokio::task::spawn_blocking;
+use serde::{Deserialize, Serialize};
+use tokio::task::{spawn_blocking, JoinSet};
+use tokio::time::{sleep_until, Instant};
+use uuid::Uuid;
 
-use crate::agents::config::{self, AiProviderInfo, AiRuntimeSelection};
-use crate::agents::{AiChatInput, AiChatResponse, AiOrchestrator};
+use crate::agents::capabilities::speaks_ollama;
+use crate::agents::config::{self, AiProviderInfo, AiRuntimeSelection, AiSettingsSnapshot};
+use crate::agents::discovery::{self, LocalRuntime};
+use crate::agents::{AiChatInput, AiChatResponse, AiOrchestrator, OrchestratorError, PullProgress};
+use crate::chat_cache;
 use crate::db::DbPool;
+use crate::errors::InkOsError;
 use crate::logging::log_event;
+use crate::response_log;
+use crate::settings;
+
+/// Setting key that lets a chat download a missing Ollama model (`true`)
+/// instead of falling back; off by default because models are large.
+pub const AUTO_PULL_KEY: &str = "ollama.auto_pull";
+
+/// Tauri event carrying [`PullProgress`] while a missing model downloads.
+pub const PULL_PROGRESS_EVENT: &str = "ollama://pull-progress";
+
+/// Callback that forwards model download progress to the UI.
+pub type PullEmitter = Arc<dyn Fn(&PullProgress) + Send + Sync>;
+
+/// Setting key holding the user's [`FallbackOrder`].
+pub const FALLBACK_ORDER_KEY: &str = "ai.fallback_order";
+
+/// Setting key holding the race head-start delay in milliseconds.
+const RACE_HEAD_START_KEY: &str = "ai.race.head_start_ms";
+/// How long a raced candidate runs alone before the next one is started.
+const DEFAULT_RACE_HEAD_START_MS: u64 = 5_000;
+
+/// Failures inside [`BREAKER_WINDOW`] that trip a provider's breaker.
+const BREAKER_FAILURE_THRESHOLD: usize = 3;
+const BREAKER_WINDOW: Duration = Duration::from_secs(120);
+/// How long a tripped provider is skipped before a probe call is allowed.
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Span over which `rate_limit_rpm` is enforced.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Longest a throttled call queues before failing with `AI-1002`.
+const RATE_QUEUE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long local runtime discovery results are reused.
+pub const DISCOVERY_TTL: Duration = Duration::from_secs(60);
+
+/// Last local discovery result and when it was probed.
+type DiscoverySnapshot = (Instant, Vec<LocalRuntime>);
 
 /// Wrapper that owns the orchestrator alongside access to provider metadata.
 #[derive(Clone)]
 pub struct ModelManager {
     pool: DbPool,
     orchestrator: Arc<AiOrchestrator>,
+    breakers: Arc<Mutex<CircuitBreakers>>,
+    limiter: RateLimiter,
+    pull_progress: Arc<OnceLock<PullEmitter>>,
+    local_runtimes: Arc<tokio::sync::Mutex<Option<DiscoverySnapshot>>>,
 }
 
 impl ModelManager {
     /// Construct a new manager backed by the given pool and orchestrator.
     pub fn new(pool: DbPool, orchestrator: Arc<AiOrchestrator>) -> Arc<Self> {
-        Arc::new(Self { pool, orchestrator })
+        Arc::new(Self {
+            pool,
+            orchestrator,
+            breakers: Arc::new(Mutex::new(CircuitBreakers::default())),
+            limiter: RateLimiter::default(),
+            pull_progress: Arc::new(OnceLock::new()),
+            local_runtimes: Arc::new(tokio::sync::Mutex::new(None)),
+        })
+    }
+
+    /// Register the emitter notified while a missing Ollama model is pulled.
+    /// Only the first registration takes effect.
+    pub fn set_pull_emitter(&self, emitter: PullEmitter) {
+        let _ = self.pull_progress.set(emitter);
+    }
+
+    /// Snapshot of every provider breaker that has seen a recent failure.
+    pub fn breaker_states(&self) -> Vec<BreakerStatus> {
+        self.lock_breakers().statuses(std::time::Instant::now())
+    }
+
+    /// Calls made and queued for every rate-limited provider used so far.
+    pub fn rate_limit_states(&self) -> Vec<RateLimitStatus> {
+        self.limiter.statuses(std::time::Instant::now())
+    }
+
+    /// HTTP client shared with the orchestrator.
+    pub fn http_client(&self) -> reqwest::Client {
+        self.orchestrator.http_client()
+    }
+
+    /// Probe the local Ollama and LM Studio endpoints.
+    ///
+    /// Results are reused for [`DISCOVERY_TTL`] unless `refresh` is set;
+    /// concurrent callers wait on a single probe rather than each issuing
+    /// their own.
+    pub async fn discover_local_providers(&self, refresh: bool) -> Result<Vec<LocalRuntime>> {
+        let mut cached = self.local_runtimes.lock().await;
+        if let Some((probed_at, runtimes)) = cached.as_ref() {
+            if !refresh && probed_at.elapsed() < DISCOVERY_TTL {
+                return Ok(runtimes.clone());
+            }
+        }
+
+        let pool = self.pool.clone();
+        let providers = spawn_blocking(move || {
+            let conn = pool.get()?;
+            config::list_providers(&conn)
+        })
+        .await
+        .map_err(|err| anyhow!("provider lookup task failed: {err}"))??;
+        let runtimes =
+            discovery::probe_all(&self.http_client(), discovery::probe_targets(&providers)).await;
+        *cached = Some((Instant::now(), runtimes.clone()));
+        Ok(runtimes)
+    }
+
+    /// Discover local runtimes and make the first reachable one active when
+    /// no cloud provider is configured. Returns the new settings on a switch.
+    pub async fn select_local_provider_if_unconfigured(
+        &self,
+    ) -> Result<Option<AiSettingsSnapshot>> {
+        let runtimes = self.discover_local_providers(false).await?;
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            let selected =
+                discovery::select_if_unconfigured(&conn, &runtimes, config::process_env)?;
+            if let Some(settings) = &selected {
+                log_event(
+                    &conn,
+                    "info",
+                    Some("AI-LOCAL-SELECT"),
+                    "ai.runtime",
+                    &format!(
+                        "Selected local provider {}",
+                        settings.active_provider_id.as_deref().unwrap_or("?")
+                    ),
+                    Some(
+                        "No cloud provider has a key, so a running local runtime was made active.",
+                    ),
+                    Some(serde_json::json!({
+                        "provider_id": settings.active_provider_id,
+                        "model": settings.active_model,
+                    })),
+                )?;
+            }
+            Ok(selected)
+        })
+        .await
+        .map_err(|err| anyhow!("provider selection task failed: {err}"))?
     }
 
     /// Return a clone of the underlying connection pool.
@@ -54,66 +193,451 @@ impl ModelManager {
 
     /// Execute a chat completion asynchronously, optionally overriding the
     /// provider/model. When `prefer_local` is true, local runtimes will be
-    /// prioritised ahead of cloud providers during fallback selection.
+    /// prioritised ahead of cloud providers during fallback selection. With
+    /// `no_fallback` only the requested provider is tried and its error is
+    /// returned as is.
     pub async fn chat(
         &self,
-        input: AiChatInput,
+        mut input: AiChatInput,
+        provider_override: Option<String>,
+        model_override: Option<String>,
+        prefer_local: bool,
+        no_fallback: bool,
+    ) -> Result<AiChatResponse> {
+        let request_id = ensure_request_id(&mut input);
+        let attempts = self
+            .candidates(provider_override, model_override, prefer_local, no_fallback)
+            .await?;
+
+        let mut queue = CandidateQueue::new(attempts);
+        let mut last_err: Option<anyhow::Error> = None;
+        while let Some(selection) = queue.next(self) {
+            match self.attempt(&selection, &input, &request_id).await {
+                Ok(response) => {
+                    self.record_success(&selection.provider.id);
+                    log_invocation_success(&self.pool, &selection, &response, &request_id);
+                    return Ok(response);
+                }
+                Err(err) => {
+                    self.record_failure(&selection.provider.id, &err);
+                    log_invocation_failure(&self.pool, &selection, &err, Some(&request_id));
+                    last_err = Some(err.into());
+                    continue;
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| anyhow!("no AI runtime available")))
+    }
+
+    /// Call one candidate. When Ollama reports the model missing and
+    /// [`pull_missing_model`](Self::pull_missing_model) fetches it, the call
+    /// is retried once.
+    async fn attempt(
+        &self,
+        selection: &AiRuntimeSelection,
+        input: &AiChatInput,
+        request_id: &str,
+    ) -> Result<AiChatResponse, OrchestratorError> {
+        let mut result = match self.limiter.acquire(&selection.provider).await {
+            Ok(()) => self.orchestrator.chat(selection, input.clone()).await,
+            Err(err) => Err(err),
+        };
+        if matches!(result, Err(OrchestratorError::ModelNotFound(_)))
+            && self.pull_missing_model(selection, request_id).await
+        {
+            result = match self.limiter.acquire(&selection.provider).await {
+                Ok(()) => self.orchestrator.chat(selection, input.clone()).await,
+                Err(err) => Err(err),
+            };
+        }
+        result
+    }
+
+    /// Download a model Ollama reported missing when `ollama.auto_pull` is
+    /// on. Returns whether the model is now available to retry with.
+    async fn pull_missing_model(&self, selection: &AiRuntimeSelection, request_id: &str) -> bool {
+        if !speaks_ollama(&selection.provider) {
+            return false;
+        }
+        let pool = self.pool.clone();
+        let enabled = spawn_blocking(move || {
+            let conn = pool.get()?;
+            auto_pull_enabled(&conn)
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))
+        .and_then(|enabled| enabled)
+        .unwrap_or(false);
+        if !enabled {
+            return false;
+        }
+
+        let emitter = self.pull_progress.get().cloned();
+        let progress = move |step: &PullProgress| {
+            if let Some(emit) = &emitter {
+                emit(step);
+            }
+        };
+        let result = self
+            .orchestrator
+            .pull_ollama_model(&selection.provider, &selection.model, &progress)
+            .await;
+        let pulled = result.is_ok();
+        let data = serde_json::json!({
+            "provider": selection.provider.id,
+            "model": selection.model,
+            "request_id": request_id,
+            "error": result.err().map(|err| err.to_string()),
+        });
+        let pool = self.pool.clone();
+        tokio::spawn(async move {
+            if let Ok(conn) = pool.get() {
+                let (level, message) = if pulled {
+                    ("info", "Missing Ollama model pulled")
+                } else {
+                    ("warn", "Pulling missing Ollama model failed")
+                };
+                let _ = log_event(
+                    &conn,
+                    level,
+                    Some("AI-MODEL-PULL"),
+                    "ai.runtime",
+                    message,
+                    Some("ollama.auto_pull is on"),
+                    Some(data),
+                );
+            }
+        });
+        pulled
+    }
+
+    /// Like [`chat`](Self::chat) (or [`chat_raced`](Self::chat_raced) with
+    /// `race`), but answer repeated requests from the response cache.
+    ///
+    /// The cache key uses the primary provider/model, so a reply that came
+    /// from a fallback is still found on the next identical request. Hits
+    /// come back with `cached` set and are logged as `AI-CACHE-HIT`.
+    pub async fn chat_cached(
+        &self,
+        mut input: AiChatInput,
         provider_override: Option<String>,
         model_override: Option<String>,
         prefer_local: bool,
+        race: bool,
+        no_fallback: bool,
     ) -> Result<AiChatResponse> {
-        let mut attempts = Vec::new();
-        attempts.push(self.resolve_runtime(
+        let request_id = ensure_request_id(&mut input);
+        let primary = self.primary_runtime(
             provider_override.clone(),
             model_override.clone(),
             prefer_local,
-        )?);
+            no_fallback,
+        )?;
+        let key = chat_cache::key(&primary.provider.id, &primary.model, &input);
+        let pool = self.pool.clone();
+        let lookup_key = key.clone();
+        let hit_request_id = request_id.clone();
+        let hit = spawn_blocking(move || {
+            let conn = pool.get()?;
+            let hit = chat_cache::lookup(&conn, &lookup_key, unix_now())?;
+            if let Some(response) = &hit {
+                log_event(
+                    &conn,
+                    "info",
+                    Some("AI-CACHE-HIT"),
+                    "ai.runtime",
+                    "AI chat served from cache",
+                    None,
+                    Some(serde_json::json!({
+                        "provider": response.provider_id,
+                        "model": response.model,
+                        "request_id": hit_request_id,
+                    })),
+                )
+                .ok();
+            }
+            Ok::<_, anyhow::Error>(hit)
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))??;
+        if let Some(mut response) = hit {
+            response.request_id = Some(request_id);
+            return Ok(response);
+        }
 
-        // Gather any additional candidates up front so we only touch the
-        // database once from the async context.
+        let response = if race {
+            self.chat_raced(
+                input,
+                provider_override,
+                model_override,
+                prefer_local,
+                no_fallback,
+            )
+            .await?
+        } else {
+            self.chat(
+                input,
+                provider_override,
+                model_override,
+                prefer_local,
+                no_fallback,
+            )
+            .await?
+        };
         let pool = self.pool.clone();
-        let extra = spawn_blocking(move || {
+        let stored = response.clone();
+        // A failed write only costs a future cache miss.
+        let _ = spawn_blocking(move || {
+            let conn = pool.get()?;
+            chat_cache::store(&conn, &key, &stored, unix_now())
+        })
+        .await;
+        Ok(response)
+    }
+
+    /// Execute a chat completion by racing candidates instead of waiting on
+    /// each in turn.
+    ///
+    /// The first candidate gets a head start (`ai.race.head_start_ms`); if it
+    /// has not answered by then, the next one is started alongside it, and
+    /// so on. A failure starts the next candidate immediately. The first
+    /// success wins and the remaining requests are cancelled. This may bill
+    /// several cloud providers for one reply, so [`chat`](Self::chat) stays
+    /// the default. Missing Ollama models are pulled as in `chat`, and a
+    /// cancelled racer hands back the half-open probe it was holding.
+    pub async fn chat_raced(
+        &self,
+        mut input: AiChatInput,
+        provider_override: Option<String>,
+        model_override: Option<String>,
+        prefer_local: bool,
+        no_fallback: bool,
+    ) -> Result<AiChatResponse> {
+        let request_id = ensure_request_id(&mut input);
+        let attempts = self
+            .candidates(provider_override, model_override, prefer_local, no_fallback)
+            .await?;
+        let candidate_count = attempts.len();
+        let pool = self.pool.clone();
+        let head_start = spawn_blocking(move || {
             let conn = pool.get()?;
-            collect_alternative_runtimes(&conn, provider_override, model_override, prefer_local)
+            read_race_head_start(&conn)
         })
         .await
         .map_err(|err| anyhow!(err.to_string()))??;
-        attempts.extend(extra);
 
+        let mut pending = CandidateQueue::new(attempts);
+        let mut running = JoinSet::new();
+        let mut raced = 0usize;
+        let mut next_launch = Instant::now();
         let mut last_err: Option<anyhow::Error> = None;
-        for selection in attempts {
-            let provider_id = selection.provider.id.clone();
-            let model_name = selection.model.clone();
-            match self.orchestrator.chat(&selection, input.clone()).await {
-                Ok(response) => {
-                    log_invocation_success(&self.pool, &provider_id, &model_name, &response);
-                    return Ok(response);
-                }
-                Err(err) => {
-                    log_invocation_failure(&self.pool, &provider_id, &model_name, &err);
-                    last_err = Some(err);
+
+        loop {
+            if running.is_empty() || Instant::now() >= next_launch {
+                if let Some(selection) = pending.next(self) {
+                    let manager = self.clone();
+                    let input = input.clone();
+                    let request_id = request_id.clone();
+                    running.spawn(async move {
+                        let probe = ProbeGuard::new(&manager.breakers, &selection.provider.id);
+                        let result = manager.attempt(&selection, &input, &request_id).await;
+                        probe.disarm();
+                        (selection, result)
+                    });
+                    raced += 1;
+                    next_launch = Instant::now() + head_start;
                     continue;
                 }
+                if running.is_empty() {
+                    break;
+                }
+            }
+
+            let more_pending = pending.has_more();
+            tokio::select! {
+                joined = running.join_next() => match joined {
+                    Some(Ok((selection, Ok(response)))) => {
+                        running.abort_all();
+                        self.record_success(&selection.provider.id);
+                        log_invocation_success(&self.pool, &selection, &response, &request_id);
+                        log_race_outcome(
+                            &self.pool,
+                            &selection.provider.id,
+                            &selection.model,
+                            raced,
+                            candidate_count,
+                            &request_id,
+                        );
+                        return Ok(response);
+                    }
+                    Some(Ok((selection, Err(err)))) => {
+                        self.record_failure(&selection.provider.id, &err);
+                        log_invocation_failure(&self.pool, &selection, &err, Some(&request_id));
+                        last_err = Some(err.into());
+                        next_launch = Instant::now();
+                    }
+                    Some(Err(join_err)) => {
+                        last_err = Some(anyhow!(join_err.to_string()));
+                        next_launch = Instant::now();
+                    }
+                    None => {}
+                },
+                _ = sleep_until(next_launch), if more_pending => {}
             }
         }
 
         Err(last_err.unwrap_or_else(|| anyhow!("no AI runtime available")))
     }
 
-    /// Blocking helper that wraps [`chat`] for synchronous callers.
+    /// Resolve the runtime a call starts with. Without fallback the
+    /// requested provider must itself be usable.
+    fn primary_runtime(
+        &self,
+        provider_override: Option<String>,
+        model_override: Option<String>,
+        prefer_local: bool,
+        no_fallback: bool,
+    ) -> Result<AiRuntimeSelection> {
+        if no_fallback {
+            let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            return config::resolve_runtime(&conn, provider_override, model_override);
+        }
+        self.resolve_runtime(provider_override, model_override, prefer_local)
+    }
+
+    /// Resolve the primary runtime followed by every fallback candidate, or
+    /// only the primary one when `no_fallback` is set. Breakers are checked
+    /// by [`CandidateQueue`] as each one is about to be called.
+    async fn candidates(
+        &self,
+        provider_override: Option<String>,
+        model_override: Option<String>,
+        prefer_local: bool,
+        no_fallback: bool,
+    ) -> Result<Vec<AiRuntimeSelection>> {
+        let mut attempts = vec![self.primary_runtime(
+            provider_override.clone(),
+            model_override.clone(),
+            prefer_local,
+            no_fallback,
+        )?];
+        if no_fallback {
+            return Ok(attempts);
+        }
+
+        // Gather any additional candidates up front so we only touch the
+        // database once from the async context.
+        let pool = self.pool.clone();
+        let extra = spawn_blocking(move || {
+            let conn = pool.get()?;
+            collect_alternative_runtimes(
+                &conn,
+                provider_override,
+                prefer_local,
+                config::process_env,
+            )
+        })
+        .await
+        .map_err(|err| anyhow!(err.to_string()))??;
+        attempts.extend(extra);
+        Ok(attempts)
+    }
+
+    fn lock_breakers(&self) -> std::sync::MutexGuard<'_, CircuitBreakers> {
+        self.breakers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn record_success(&self, provider_id: &str) {
+        self.lock_breakers().record_success(provider_id);
+    }
+
+    /// Count `err` against the provider's breaker when
+    /// [`counts_against_breaker`] says it reflects the provider's health;
+    /// otherwise only release a half-open probe the call was holding.
+    fn record_failure(&self, provider_id: &str, err: &OrchestratorError) {
+        if !counts_against_breaker(err) {
+            self.lock_breakers()
+                .release_probe(provider_id, std::time::Instant::now());
+            return;
+        }
+        let tripped = self
+            .lock_breakers()
+            .record_failure(provider_id, std::time::Instant::now());
+        if tripped {
+            log_breaker_open(&self.pool, provider_id);
+        }
+    }
+
+    /// Embed `texts` with an explicit embedding runtime.
+    ///
+    /// Failures count against the provider's breaker like chat calls do.
+    pub async fn embed(
+        &self,
+        selection: &AiRuntimeSelection,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>> {
+        let result = match self.limiter.acquire(&selection.provider).await {
+            Ok(()) => self.orchestrator.embed(selection, texts).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(vectors) => {
+                self.record_success(&selection.provider.id);
+                Ok(vectors)
+            }
+            Err(err) => {
+                self.record_failure(&selection.provider.id, &err);
+                log_invocation_failure(&self.pool, selection, &err, None);
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Blocking helper that wraps [`embed`](Self::embed).
+    pub fn embed_blocking(
+        &self,
+        selection: &AiRuntimeSelection,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>> {
+        tauri::async_runtime::block_on(self.embed(selection, texts))
+    }
+
+    /// Blocking helper that wraps [`chat`](Self::chat), or
+    /// [`chat_cached`](Self::chat_cached) when `cache` is set, for
+    /// synchronous callers.
     pub fn chat_blocking(
         &self,
         input: AiChatInput,
         provider_override: Option<String>,
         model_override: Option<String>,
         prefer_local: bool,
+        cache: bool,
     ) -> Result<AiChatResponse> {
-        tauri::async_runtime::block_on(self.chat(
-            input,
-            provider_override,
-            model_override,
-            prefer_local,
-        ))
+        tauri::async_runtime::block_on(async {
+            if cache {
+                self.chat_cached(
+                    input,
+                    provider_override,
+                    model_override,
+                    prefer_local,
+                    false,
+                    false,
+                )
+                .await
+            } else {
+                self.chat(
+                    input,
+                    provider_override,
+                    model_override,
+                    prefer_local,
+                    false,
+                )
+                .await
+            }
+        })
     }
 }
 
@@ -123,48 +647,74 @@ fn resolve_with_fallback(
     model_override: Option<String>,
     prefer_local: bool,
 ) -> Result<AiRuntimeSelection> {
-    if let Ok(selection) =
-        config::resolve_runtime(conn, provider_override.clone(), model_override.clone())
-    {
-        return Ok(selection);
+    match config::resolve_runtime(conn, provider_override.clone(), model_override.clone()) {
+        Ok(selection) => return Ok(selection),
+        // Running a different provider would not honour the requested model.
+        Err(err) if matches!(err.downcast_ref(), Some(InkOsError::ModelNotFound { .. })) => {
+            return Err(err)
+        }
+        Err(_) => {}
     }
 
     let candidates =
-        collect_alternative_runtimes(conn, provider_override, model_override, prefer_local)?;
+        collect_alternative_runtimes(conn, provider_override, prefer_local, config::process_env)?;
     candidates
         .into_iter()
         .next()
-        .ok_or_else(|| anyhow!("No AI provider is configured"))
+        .ok_or_else(|| InkOsError::ProviderNotConfigured.into())
 }
 
+/// Fallback runtimes for every other usable provider.
+///
+/// The requested provider (the override, or the active one) is excluded
+/// because it is the primary attempt, as is every provider the user
+/// excluded in [`FallbackOrder`]. Providers listed in its `order` come
+/// first. Model overrides name a model of that provider, so fallbacks
+/// always run on their own default model. Providers that need a key count
+/// when they have a stored one or an environment key read through `env`.
 fn collect_alternative_runtimes(
     conn: &rusqlite::Connection,
     provider_override: Option<String>,
-    model_override: Option<String>,
     prefer_local: bool,
+    env: config::EnvLookup,
 ) -> Result<Vec<AiRuntimeSelection>> {
     let providers = config::list_providers(conn)?;
     let mut attempts = Vec::new();
     let mut seen: HashSet<String> = HashSet::new();
 
-    if let Some(pid) = &provider_override {
-        seen.insert(pid.clone());
+    let requested = match provider_override {
+        Some(pid) => Some(pid),
+        None => config::active_provider_id(conn)?,
+    };
+    if let Some(pid) = requested {
+        seen.insert(pid);
     }
 
+    let preference = fallback_order(conn)?;
     let mut ordered = providers;
+    ordered.retain(|p| !preference.excluded.contains(&p.id));
     if prefer_local {
         ordered.sort_by_key(|p| if p.kind == "local" { 0 } else { 1 });
     }
+    // Stable, so unlisted providers keep the `prefer_local` order behind the
+    // listed ones.
+    ordered.sort_by_key(|p| {
+        preference
+            .order
+            .iter()
+            .position(|id| id == &p.id)
+            .unwrap_or(usize::MAX)
+    });
 
     for provider in ordered {
-        if provider.requires_api_key && !provider.has_credentials {
+        if provider.requires_api_key && !config::has_secret(&provider, env) {
             continue;
         }
         if seen.contains(&provider.id) {
             continue;
         }
         if let Ok(selection) =
-            config::resolve_runtime(conn, Some(provider.id.clone()), model_override.clone())
+            config::resolve_runtime_with(conn, Some(provider.id.clone()), None, env)
         {
             seen.insert(provider.id.clone());
             attempts.push(selection);
@@ -174,18 +724,452 @@ fn collect_alternative_runtimes(
     Ok(attempts)
 }
 
+/// Candidates handed out one at a time, so a half-open breaker's probe is
+/// only spent on a provider that is actually called.
+struct CandidateQueue {
+    pending: VecDeque<AiRuntimeSelection>,
+    skipped: Vec<AiRuntimeSelection>,
+    tried: bool,
+    forced: bool,
+}
+
+impl CandidateQueue {
+    fn new(attempts: Vec<AiRuntimeSelection>) -> Self {
+        Self {
+            pending: attempts.into(),
+            skipped: Vec::new(),
+            tried: false,
+            forced: false,
+        }
+    }
+
+    /// The next candidate whose breaker admits a call. When every breaker
+    /// is open, the skipped candidates are tried anyway rather than failing
+    /// without a single call.
+    fn next(&mut self, manager: &ModelManager) -> Option<AiRuntimeSelection> {
+        while let Some(selection) = self.pending.pop_front() {
+            if self.forced
+                || manager
+                    .lock_breakers()
+                    .allow(&selection.provider.id, std::time::Instant::now())
+            {
+                self.tried = true;
+                return Some(selection);
+            }
+            self.skipped.push(selection);
+        }
+        if self.tried || self.skipped.is_empty() {
+            return None;
+        }
+        self.forced = true;
+        self.pending = std::mem::take(&mut self.skipped).into();
+        self.next(manager)
+    }
+
+    fn has_more(&self) -> bool {
+        !self.pending.is_empty()
+    }
+}
+
+/// Circuit breaker position for a single provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls flow normally.
+    Closed,
+    /// Recent failures tripped the breaker; the provider is skipped.
+    Open,
+    /// The cooldown elapsed and a single probe call is allowed through.
+    HalfOpen,
+}
+
+/// Breaker view returned to the UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub provider_id: String,
+    pub state: BreakerState,
+    pub recent_failures: usize,
+    /// Seconds until a probe is allowed while the breaker is open.
+    pub retry_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct ProviderBreaker {
+    failures: Vec<std::time::Instant>,
+    opened_at: Option<std::time::Instant>,
+    probing: bool,
+}
+
+impl ProviderBreaker {
+    fn state(&self, now: std::time::Instant) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(_) if self.probing => BreakerState::HalfOpen,
+            Some(opened) if now.duration_since(opened) < BREAKER_COOLDOWN => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// A probe that has not reported back within a cooldown is abandoned.
+    fn probe_in_flight(&self, now: std::time::Instant) -> bool {
+        self.probing
+            && self
+                .opened_at
+                .is_some_and(|at| now.duration_since(at) < BREAKER_COOLDOWN)
+    }
+}
+
+/// In-memory failure tracking keyed by provider id.
+#[derive(Debug, Default)]
+struct CircuitBreakers {
+    providers: HashMap<String, ProviderBreaker>,
+}
+
+impl CircuitBreakers {
+    /// Whether a call to `provider_id` should be attempted. A half-open
+    /// breaker lets one probe through until that probe reports back.
+    fn allow(&mut self, provider_id: &str, now: std::time::Instant) -> bool {
+        let Some(breaker) = self.providers.get_mut(provider_id) else {
+            return true;
+        };
+        match breaker.state(now) {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen if breaker.probe_in_flight(now) => false,
+            BreakerState::HalfOpen => {
+                breaker.probing = true;
+                breaker.opened_at = Some(now);
+                true
+            }
+        }
+    }
+
+    fn record_success(&mut self, provider_id: &str) {
+        self.providers.remove(provider_id);
+    }
+
+    /// End a half-open probe whose outcome says nothing about the provider,
+    /// leaving the breaker half-open so the next call probes again.
+    fn release_probe(&mut self, provider_id: &str, now: std::time::Instant) {
+        let Some(breaker) = self.providers.get_mut(provider_id) else {
+            return;
+        };
+        if breaker.probing {
+            breaker.probing = false;
+            breaker.opened_at = now.checked_sub(BREAKER_COOLDOWN).or(breaker.opened_at);
+        }
+    }
+
+    /// Record a failure and return true when it trips the breaker open.
+    fn record_failure(&mut self, provider_id: &str, now: std::time::Instant) -> bool {
+        let breaker = self.providers.entry(provider_id.to_string()).or_default();
+        breaker
+            .failures
+            .retain(|at| now.duration_since(*at) < BREAKER_WINDOW);
+        breaker.failures.push(now);
+
+        let was_open = breaker.opened_at.is_some();
+        if breaker.probing || breaker.failures.len() >= BREAKER_FAILURE_THRESHOLD {
+            breaker.opened_at = Some(now);
+            breaker.probing = false;
+            return !was_open;
+        }
+        false
+    }
+
+    fn statuses(&self, now: std::time::Instant) -> Vec<BreakerStatus> {
+        let mut statuses: Vec<BreakerStatus> = self
+            .providers
+            .iter()
+            .map(|(provider_id, breaker)| {
+                let state = breaker.state(now);
+                let retry_in_secs = match (state, breaker.opened_at) {
+                    (BreakerState::Open, Some(opened)) => Some(
+                        BREAKER_COOLDOWN
+                            .saturating_sub(now.duration_since(opened))
+                            .as_secs(),
+                    ),
+                    _ => None,
+                };
+                BreakerStatus {
+                    provider_id: provider_id.clone(),
+                    state,
+                    recent_failures: breaker
+                        .failures
+                        .iter()
+                        .filter(|at| now.duration_since(**at) < BREAKER_WINDOW)
+                        .count(),
+                    retry_in_secs,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.provider_id.cmp(&b.provider_id));
+        statuses
+    }
+}
+
+/// Releases a racer's half-open probe if the racer is aborted before it
+/// reports back; a racer that finishes disarms it and records its outcome.
+struct ProbeGuard {
+    breakers: Arc<Mutex<CircuitBreakers>>,
+    provider_id: String,
+    armed: bool,
+}
+
+impl ProbeGuard {
+    fn new(breakers: &Arc<Mutex<CircuitBreakers>>, provider_id: &str) -> Self {
+        Self {
+            breakers: Arc::clone(breakers),
+            provider_id: provider_id.to_string(),
+            armed: true,
+        }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if self.armed {
+            self.breakers
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .release_probe(&self.provider_id, std::time::Instant::now());
+        }
+    }
+}
+
+/// Throttling view returned to the UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    pub provider_id: String,
+    pub rate_limit_rpm: u32,
+    /// Calls started within the last minute.
+    pub recent_calls: usize,
+    /// Calls currently waiting for a slot.
+    pub queued: usize,
+}
+
+#[derive(Debug, Default)]
+struct RateWindow {
+    rpm: u32,
+    starts: VecDeque<std::time::Instant>,
+    queued: usize,
+}
+
+impl RateWindow {
+    fn prune(&mut self, now: std::time::Instant) {
+        while self
+            .starts
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= RATE_WINDOW)
+        {
+            self.starts.pop_front();
+        }
+    }
+
+    /// Claim a slot, or return when the next one frees up.
+    fn try_start(&mut self, now: std::time::Instant) -> Option<std::time::Instant> {
+        self.prune(now);
+        if self.starts.len() < self.rpm as usize {
+            self.starts.push_back(now);
+            return None;
+        }
+        self.starts.front().map(|oldest| *oldest + RATE_WINDOW)
+    }
+}
+
+/// Process-wide sliding-window limiter keyed by provider id.
+///
+/// Calls over a provider's `rate_limit_rpm` wait for a slot instead of
+/// failing, up to [`RATE_QUEUE_TIMEOUT`].
+#[derive(Clone, Default)]
+struct RateLimiter {
+    windows: Arc<Mutex<HashMap<String, RateWindow>>>,
+}
+
+impl RateLimiter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, RateWindow>> {
+        self.windows
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Wait until `provider` may start another call.
+    async fn acquire(&self, provider: &AiProviderInfo) -> Result<(), OrchestratorError> {
+        let Some(rpm) = provider.rate_limit_rpm.filter(|rpm| *rpm > 0) else {
+            return Ok(());
+        };
+        let deadline = std::time::Instant::now() + RATE_QUEUE_TIMEOUT;
+        let mut queued: Option<QueuedCall<'_>> = None;
+        loop {
+            let now = std::time::Instant::now();
+            let next_slot = {
+                let mut windows = self.lock();
+                let window = windows.entry(provider.id.clone()).or_default();
+                window.rpm = rpm;
+                match window.try_start(now) {
+                    None => return Ok(()),
+                    Some(next_slot) => next_slot,
+                }
+            };
+            if next_slot > deadline {
+                return Err(OrchestratorError::RateLimited {
+                    message: format!(
+                        "{} is over its limit of {rpm} requests per minute",
+                        provider.display_name
+                    )
+                    .into(),
+                    retry_after_secs: Some(next_slot.saturating_duration_since(now).as_secs()),
+                    local: true,
+                });
+            }
+            if queued.is_none() {
+                queued = Some(QueuedCall::new(self, &provider.id));
+            }
+            tokio::time::sleep(next_slot.saturating_duration_since(now)).await;
+        }
+    }
+
+    fn statuses(&self, now: std::time::Instant) -> Vec<RateLimitStatus> {
+        let mut windows = self.lock();
+        let mut statuses: Vec<RateLimitStatus> = windows
+            .iter_mut()
+            .map(|(provider_id, window)| {
+                window.prune(now);
+                RateLimitStatus {
+                    provider_id: provider_id.clone(),
+                    rate_limit_rpm: window.rpm,
+                    recent_calls: window.starts.len(),
+                    queued: window.queued,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.provider_id.cmp(&b.provider_id));
+        statuses
+    }
+}
+
+/// Counts a waiting call in its provider's queue depth until dropped, so
+/// cancelled waits (for example a lost race) are released too.
+struct QueuedCall<'a> {
+    limiter: &'a RateLimiter,
+    provider_id: &'a str,
+}
+
+impl<'a> QueuedCall<'a> {
+    fn new(limiter: &'a RateLimiter, provider_id: &'a str) -> Self {
+        if let Some(window) = limiter.lock().get_mut(provider_id) {
+            window.queued += 1;
+        }
+        Self {
+            limiter,
+            provider_id,
+        }
+    }
+}
+
+impl Drop for QueuedCall<'_> {
+    fn drop(&mut self) {
+        if let Some(window) = self.limiter.lock().get_mut(self.provider_id) {
+            window.queued = window.queued.saturating_sub(1);
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Whether chats may download a missing Ollama model.
+pub fn auto_pull_enabled(conn: &rusqlite::Connection) -> Result<bool> {
+    Ok(settings::read(conn, AUTO_PULL_KEY)?.as_deref() == Some("true"))
+}
+
+/// Turn automatic model downloads on or off.
+pub fn set_auto_pull(conn: &rusqlite::Connection, enabled: bool) -> Result<()> {
+    settings::upsert(conn, AUTO_PULL_KEY, &enabled.to_string())
+}
+
+/// User-chosen ordering of the fallback chain, stored under
+/// [`FALLBACK_ORDER_KEY`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FallbackOrder {
+    /// Provider ids tried first, in this order.
+    #[serde(default)]
+    pub order: Vec<String>,
+    /// Provider ids never used as a fallback.
+    #[serde(default)]
+    pub excluded: Vec<String>,
+}
+
+/// The stored fallback ordering, or an empty one when none is set.
+pub fn fallback_order(conn: &rusqlite::Connection) -> Result<FallbackOrder> {
+    match settings::read(conn, FALLBACK_ORDER_KEY)? {
+        Some(raw) => Ok(serde_json::from_str(&raw)?),
+        None => Ok(FallbackOrder::default()),
+    }
+}
+
+/// Validate and store the fallback ordering. Every id must name a
+/// registered provider and may appear only once across both lists.
+pub fn set_fallback_order(conn: &rusqlite::Connection, preference: &FallbackOrder) -> Result<()> {
+    let known: HashSet<String> = config::list_providers(conn)?
+        .into_iter()
+        .map(|p| p.id)
+        .collect();
+    let mut seen = HashSet::new();
+    for id in preference.order.iter().chain(&preference.excluded) {
+        if !known.contains(id) {
+            return Err(InkOsError::ValidationFailed(format!("Unknown provider '{id}'")).into());
+        }
+        if !seen.insert(id) {
+            return Err(InkOsError::ValidationFailed(format!(
+                "Provider '{id}' is listed more than once"
+            ))
+            .into());
+        }
+    }
+    settings::upsert(
+        conn,
+        FALLBACK_ORDER_KEY,
+        &serde_json::to_string(preference)?,
+    )
+}
+
+fn read_race_head_start(conn: &rusqlite::Connection) -> Result<Duration> {
+    let millis = settings::read(conn, RACE_HEAD_START_KEY)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RACE_HEAD_START_MS);
+    Ok(Duration::from_millis(millis))
+}
+
+/// Give the request a correlation id unless the caller already set one.
+fn ensure_request_id(input: &mut AiChatInput) -> String {
+    input
+        .request_id
+        .get_or_insert_with(|| Uuid::new_v4().to_string())
+        .clone()
+}
+
 fn log_invocation_success(
     pool: &DbPool,
-    provider_id: &str,
-    model: &str,
+    selection: &AiRuntimeSelection,
     response: &AiChatResponse,
+    request_id: &str,
 ) {
     let preview = response.content.chars().take(200).collect::<String>();
     let pool = pool.clone();
-    let provider = provider_id.to_string();
-    let model = model.to_string();
+    let provider = selection.provider.id.clone();
+    let model = selection.model.clone();
+    let secret_source = selection.secret_source;
+    let request_id = request_id.to_string();
+    let raw = response.raw.clone();
     tokio::spawn(async move {
         if let Ok(conn) = pool.get() {
+            let _ = response_log::record(&conn, &request_id, &provider, &model, "ok", &raw);
             let _ = log_event(
                 &conn,
                 "info",
@@ -196,20 +1180,62 @@ fn log_invocation_success(
                 Some(serde_json::json!({
                     "provider": provider,
                     "model": model,
+                    "secret_source": secret_source,
                     "preview": preview,
+                    "request_id": request_id,
                 })),
             );
         }
     });
 }
 
-fn log_invocation_failure(pool: &DbPool, provider_id: &str, model: &str, error: &anyhow::Error) {
+fn log_invocation_failure(
+    pool: &DbPool,
+    selection: &AiRuntimeSelection,
+    error: &OrchestratorError,
+    request_id: Option<&str>,
+) {
+    let mut data = serde_json::json!({
+        "provider": selection.provider.id,
+        "model": selection.model,
+        "secret_source": selection.secret_source,
+        "error": error.to_string(),
+        "error_code": error.code(),
+        "hint": error.explain(),
+        "request_id": request_id,
+    });
+    if !selection.provider.headers.is_empty() {
+        // Header values may hold tokens; only their names are recorded.
+        let names: serde_json::Map<String, serde_json::Value> = selection
+            .provider
+            .headers
+            .keys()
+            .map(|name| (name.clone(), "***".into()))
+            .collect();
+        data["headers"] = names.into();
+    }
     let pool = pool.clone();
-    let provider = provider_id.to_string();
-    let model = model.to_string();
-    let message = error.to_string();
+    let capture = request_id.map(|request_id| {
+        let mut raw = serde_json::json!({ "error": error.to_string(), "code": error.code() });
+        if let Some(response) = error.response() {
+            // The provider's own reply; `response_log::record` redacts it.
+            raw["status"] = response.status.into();
+            raw["body"] = response.body.clone();
+        }
+        (request_id.to_string(), raw)
+    });
     tokio::spawn(async move {
         if let Ok(conn) = pool.get() {
+            if let Some((request_id, raw)) = capture {
+                let _ = response_log::record(
+                    &conn,
+                    &request_id,
+                    data["provider"].as_str().unwrap_or_default(),
+                    data["model"].as_str().unwrap_or_default(),
+                    "error",
+                    &raw,
+                );
+            }
             let _ = log_event(
                 &conn,
                 "warn",
@@ -217,10 +1243,70 @@ fn log_invocation_failure(pool: &DbPool, provider_id: &str, model: &str, error:
                 "ai.runtime",
                 "AI provider invocation failed",
                 Some("Attempting fallback"),
+                Some(data),
+            );
+        }
+    });
+}
+
+fn log_race_outcome(
+    pool: &DbPool,
+    provider_id: &str,
+    model: &str,
+    raced: usize,
+    candidates: usize,
+    request_id: &str,
+) {
+    let pool = pool.clone();
+    let provider = provider_id.to_string();
+    let model = model.to_string();
+    let request_id = request_id.to_string();
+    tokio::spawn(async move {
+        if let Ok(conn) = pool.get() {
+            let _ = log_event(
+                &conn,
+                "info",
+                Some("AI-RACE"),
+                "ai.runtime",
+                "Raced AI providers for a chat completion",
+                Some("The first candidate to succeed was used; the rest were cancelled."),
                 Some(serde_json::json!({
-                    "provider": provider,
+                    "winner": provider,
                     "model": model,
-                    "error": message,
+                    "raced": raced,
+                    "candidates": candidates,
+                    "request_id": request_id,
+                })),
+            );
+        }
+    });
+}
+
+/// Whether `err` says anything about the provider's health. The local rate
+/// limiter's refusals never reached the provider, and an invalid JSON reply
+/// came from a provider that answered; the model ignored the instruction.
+fn counts_against_breaker(err: &OrchestratorError) -> bool {
+    !matches!(
+        err,
+        OrchestratorError::RateLimited { local: true, .. } | OrchestratorError::InvalidJson(_)
+    )
+}
+
+fn log_breaker_open(pool: &DbPool, provider_id: &str) {
+    let pool = pool.clone();
+    let provider = provider_id.to_string();
+    tokio::spawn(async move {
+        if let Ok(conn) = pool.get() {
+            let _ = log_event(
+                &conn,
+                "warn",
+                Some("AI-BREAKER"),
+                "ai.runtime",
+                "AI provider temporarily skipped",
+                Some("Repeated failures opened the circuit breaker; the provider will be probed again after a cooldown."),
+                Some(serde_json::json!({
+                    "provider": provider,
+                    "cooldown_secs": BREAKER_COOLDOWN.as_secs(),
                 })),
             );
         }
@@ -229,3 +1315,481 @@ fn log_invocation_failure(pool: &DbPool, provider_id: &str, model: &str, error:
 
 // Allow synchronous access to rusqlite without importing from the caller.
 use r2d2_sqlite::rusqlite;
+
+/// A [`ModelManager`] over a migrated database file in the temp directory,
+/// for tests whose connections cross threads. Callers remove the file.
+#[cfg(test)]
+pub(crate) fn file_backed_models() -> (Arc<ModelManager>, DbPool, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("inkos-test-{}.db", Uuid::new_v4()));
+    // Foreign keys are on, as in `db::init_db`, so cascades behave the same.
+    let manager = r2d2_sqlite::SqliteConnectionManager::file(&path)
+        .with_init(|conn| conn.pragma_update(None, "foreign_keys", "ON"));
+    let pool = r2d2::Pool::builder().build(manager).unwrap();
+    crate::db::apply_migrations(&pool.get().unwrap()).unwrap();
+    let orchestrator = Arc::new(AiOrchestrator::new(Default::default()).unwrap());
+    (ModelManager::new(pool.clone(), orchestrator), pool, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+    use std::time::Instant as StdInstant;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn fallbacks_include_providers_keyed_from_the_environment() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        config::seed_defaults(&conn).unwrap();
+
+        let ids = |env: config::EnvLookup| -> Vec<String> {
+            collect_alternative_runtimes(&conn, Some("openai".into()), false, env)
+                .unwrap()
+                .into_iter()
+                .map(|s| s.provider.id)
+                .collect()
+        };
+        assert!(!ids(no_env).contains(&"anthropic".to_string()));
+
+        let env: config::EnvLookup =
+            |var| (var == "INKOS_ANTHROPIC_API_KEY").then(|| "sk-env".into());
+        let fallbacks =
+            collect_alternative_runtimes(&conn, Some("openai".into()), false, env).unwrap();
+        let anthropic = fallbacks
+            .iter()
+            .find(|s| s.provider.id == "anthropic")
+            .expect("anthropic fallback");
+        assert_eq!(anthropic.secret.as_deref(), Some("sk-env"));
+        assert_eq!(
+            anthropic.secret_source,
+            Some(config::SecretSource::Environment)
+        );
+    }
+
+    #[test]
+    fn fallbacks_ignore_model_override_for_other_providers() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        config::seed_defaults(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO ai_credentials (provider_id, secret, created_at, updated_at) VALUES ('anthropic', 'c2stdGVzdA==', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        let primary =
+            resolve_with_fallback(&conn, Some("openai".into()), Some("gpt-4o".into()), false)
+                .unwrap();
+        assert_eq!(primary.model, "gpt-4o");
+
+        let fallbacks =
+            collect_alternative_runtimes(&conn, Some("openai".into()), false, no_env).unwrap();
+        assert!(fallbacks.iter().all(|s| s.provider.id != "openai"));
+        let anthropic = fallbacks
+            .iter()
+            .find(|s| s.provider.id == "anthropic")
+            .expect("anthropic fallback");
+        assert!(anthropic.model.starts_with("claude-"));
+        assert_ne!(anthropic.model, "gpt-4o");
+    }
+
+    #[test]
+    fn fallbacks_follow_the_configured_order_and_skip_excluded() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        config::seed_defaults(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO ai_credentials (provider_id, secret, created_at, updated_at) VALUES ('anthropic', 'c2stdGVzdA==', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        let preference = FallbackOrder {
+            order: vec!["lmstudio".into(), "anthropic".into()],
+            excluded: vec!["ollama".into()],
+        };
+        set_fallback_order(&conn, &preference).unwrap();
+        assert_eq!(fallback_order(&conn).unwrap(), preference);
+
+        let fallbacks =
+            collect_alternative_runtimes(&conn, Some("openai".into()), true, no_env).unwrap();
+        let ids: Vec<&str> = fallbacks.iter().map(|s| s.provider.id.as_str()).collect();
+        assert_eq!(&ids[..2], ["lmstudio", "anthropic"]);
+        assert!(!ids.contains(&"ollama"));
+
+        let duplicate = FallbackOrder {
+            order: vec!["anthropic".into()],
+            excluded: vec!["anthropic".into()],
+        };
+        assert!(set_fallback_order(&conn, &duplicate).is_err());
+    }
+
+    #[test]
+    fn breaker_opens_then_half_opens_for_a_single_probe() {
+        let mut breakers = CircuitBreakers::default();
+        let start = StdInstant::now();
+        assert!(!breakers.record_failure("openai", start));
+        assert!(!breakers.record_failure("openai", start));
+        assert!(breakers.record_failure("openai", start));
+        assert!(!breakers.allow("openai", start));
+        assert!(breakers.allow("anthropic", start));
+
+        let later = start + BREAKER_COOLDOWN;
+        assert!(breakers.allow("openai", later));
+        assert!(!breakers.allow("openai", later));
+
+        breakers.record_failure("openai", later);
+        assert_eq!(breakers.statuses(later)[0].state, BreakerState::Open);
+
+        let recovered = later + BREAKER_COOLDOWN;
+        assert!(breakers.allow("openai", recovered));
+        breakers.record_success("openai");
+        assert!(breakers.statuses(recovered).is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fallback_probes_are_only_spent_when_the_fallback_is_called() {
+        let (manager, pool, path) = file_backed_models();
+        config::seed_defaults(&pool.get().unwrap()).unwrap();
+        let attempts = manager
+            .candidates(Some("ollama".into()), None, true, false)
+            .await
+            .unwrap();
+        let fallback = attempts[1].provider.id.clone();
+        // The fallback tripped a cooldown ago, so its probe is due.
+        let tripped = StdInstant::now() - BREAKER_COOLDOWN;
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            manager.lock_breakers().record_failure(&fallback, tripped);
+        }
+
+        let mut queue = CandidateQueue::new(attempts);
+        assert_eq!(queue.next(&manager).unwrap().provider.id, "ollama");
+        assert!(manager.lock_breakers().allow(&fallback, StdInstant::now()));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn local_rate_limits_do_not_count_against_the_breaker() {
+        let pool = r2d2::Pool::builder()
+            .build(r2d2_sqlite::SqliteConnectionManager::memory())
+            .unwrap();
+        let manager = ModelManager::new(
+            pool,
+            Arc::new(AiOrchestrator::new(Default::default()).unwrap()),
+        );
+        let limited = |local| OrchestratorError::RateLimited {
+            message: "over the limit".into(),
+            retry_after_secs: None,
+            local,
+        };
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            manager.record_failure("openai", &limited(true));
+        }
+        assert!(manager.breaker_states().is_empty());
+
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            manager.record_failure("openai", &limited(false));
+        }
+        let states = manager.breaker_states();
+        assert_eq!(states[0].state, BreakerState::Open);
+        assert_eq!(states[0].recent_failures, BREAKER_FAILURE_THRESHOLD);
+    }
+
+    #[test]
+    fn uncounted_probe_failures_release_the_probe() {
+        let pool = r2d2::Pool::builder()
+            .build(r2d2_sqlite::SqliteConnectionManager::memory())
+            .unwrap();
+        let manager = ModelManager::new(
+            pool,
+            Arc::new(AiOrchestrator::new(Default::default()).unwrap()),
+        );
+        let tripped = StdInstant::now() - BREAKER_COOLDOWN;
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            manager.lock_breakers().record_failure("openai", tripped);
+        }
+        assert!(manager.lock_breakers().allow("openai", StdInstant::now()));
+
+        manager.record_failure("openai", &OrchestratorError::InvalidJson("not JSON".into()));
+        let states = manager.breaker_states();
+        assert_eq!(states[0].state, BreakerState::HalfOpen);
+        assert_eq!(states[0].recent_failures, BREAKER_FAILURE_THRESHOLD);
+        assert!(manager.lock_breakers().allow("openai", StdInstant::now()));
+    }
+
+    #[test]
+    fn aborted_racers_release_their_probe() {
+        let breakers = Arc::new(Mutex::new(CircuitBreakers::default()));
+        let tripped = StdInstant::now() - BREAKER_COOLDOWN;
+        let take_probe = || breakers.lock().unwrap().allow("openai", StdInstant::now());
+        for _ in 0..BREAKER_FAILURE_THRESHOLD {
+            breakers.lock().unwrap().record_failure("openai", tripped);
+        }
+        assert!(take_probe());
+
+        ProbeGuard::new(&breakers, "openai").disarm();
+        assert!(!take_probe());
+        drop(ProbeGuard::new(&breakers, "openai"));
+        assert!(take_probe());
+    }
+
+    #[test]
+    fn invalid_json_replies_do_not_count_against_the_breaker() {
+        assert!(!counts_against_breaker(&OrchestratorError::InvalidJson(
+            "not JSON".into()
+        )));
+        assert!(counts_against_breaker(&OrchestratorError::ServerError(
+            "500".into()
+        )));
+    }
+
+    #[test]
+    fn rate_window_queues_calls_over_the_per_minute_limit() {
+        let mut window = RateWindow {
+            rpm: 2,
+            ..Default::default()
+        };
+        let start = StdInstant::now();
+        assert!(window.try_start(start).is_none());
+        assert!(window.try_start(start + Duration::from_secs(10)).is_none());
+        assert_eq!(
+            window.try_start(start + Duration::from_secs(20)),
+            Some(start + RATE_WINDOW)
+        );
+        assert!(window.try_start(start + RATE_WINDOW).is_none());
+
+        let limiter = RateLimiter::default();
+        limiter.lock().insert("openai".into(), window);
+        let queued = QueuedCall::new(&limiter, "openai");
+        let statuses = limiter.statuses(start + RATE_WINDOW);
+        assert_eq!((statuses[0].recent_calls, statuses[0].queued), (2, 1));
+        drop(queued);
+        assert_eq!(limiter.statuses(start + RATE_WINDOW)[0].queued, 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn failed_attempts_are_logged_under_the_request_id() {
+        let (manager, pool, path) = file_backed_models();
+        {
+            let conn = pool.get().unwrap();
+            config::seed_defaults(&conn).unwrap();
+            // Nothing listens on the discard port, so every attempt fails fast.
+            conn.execute(
+                "UPDATE ai_providers SET base_url = 'http://127.0.0.1:9' WHERE kind = 'local'",
+                [],
+            )
+            .unwrap();
+        }
+        let input = AiChatInput {
+            messages: Vec::new(),
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: Some("req-42".into()),
+        };
+        assert!(manager
+            .chat(input, Some("ollama".into()), None, true, false)
+            .await
+            .is_err());
+
+        let logged = || -> i64 {
+            pool.get()
+                .unwrap()
+                .query_row(
+                    "SELECT COUNT(*) FROM event_log WHERE code = 'AI-0201' AND json_extract(data, '$.request_id') = 'req-42'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        for _ in 0..50 {
+            if logged() > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(logged() > 0);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn captured_failures_keep_the_provider_reply() {
+        let base_url = crate::mock_provider::serve(|_, _| {
+            let body = r#"{"error":"overloaded","api_key":"sk-live-secret"}"#;
+            Some(("500 Internal Server Error", body.to_string()))
+        });
+
+        let (manager, pool, path) = file_backed_models();
+        {
+            let conn = pool.get().unwrap();
+            config::seed_defaults(&conn).unwrap();
+            conn.execute(
+                "UPDATE ai_providers SET base_url = ?1 WHERE id = 'ollama'",
+                params![base_url],
+            )
+            .unwrap();
+            response_log::set_capture(&conn, true).unwrap();
+        }
+        let input = AiChatInput {
+            messages: Vec::new(),
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: Some("req-500".into()),
+        };
+        assert!(manager
+            .chat(input, Some("ollama".into()), None, true, true)
+            .await
+            .is_err());
+
+        let captured = || response_log::get(&pool.get().unwrap(), "req-500").unwrap();
+        for _ in 0..50 {
+            if !captured().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let raw = &captured()[0].raw;
+        assert_eq!(raw["code"], "AI-1006");
+        assert_eq!(raw["status"], 500);
+        assert_eq!(raw["body"]["error"], "overloaded");
+        assert_eq!(raw["body"]["api_key"], "***");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn no_fallback_only_tries_the_requested_provider() {
+        let (manager, pool, path) = file_backed_models();
+        {
+            let conn = pool.get().unwrap();
+            config::seed_defaults(&conn).unwrap();
+        }
+
+        let with_fallback = manager
+            .candidates(Some("ollama".into()), None, true, false)
+            .await
+            .unwrap();
+        assert!(with_fallback.len() > 1);
+
+        let alone = manager
+            .candidates(Some("ollama".into()), None, true, true)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = alone.iter().map(|s| s.provider.id.as_str()).collect();
+        assert_eq!(ids, ["ollama"]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn missing_ollama_model_is_pulled_then_retried() {
+        let script = [
+            (
+                "404 Not Found",
+                r#"{"error":"model \"phi3\" not found, try pulling it first"}"#.to_string(),
+            ),
+            (
+                "200 OK",
+                [
+                    r#"{"status":"pulling manifest"}"#,
+                    r#"{"status":"pulling abc","completed":5,"total":10}"#,
+                    r#"{"status":"success"}"#,
+                ]
+                .join("\n"),
+            ),
+            (
+                "200 OK",
+                r#"{"message":{"role":"assistant","content":"hi"}}"#.to_string(),
+            ),
+        ];
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&paths);
+        let base_url = crate::mock_provider::serve(move |index, request| {
+            recorded.lock().unwrap().push(request.path.clone());
+            script.get(index % script.len()).cloned()
+        });
+
+        let (manager, pool, path) = file_backed_models();
+        {
+            let conn = pool.get().unwrap();
+            config::seed_defaults(&conn).unwrap();
+            conn.execute(
+                "UPDATE ai_providers SET base_url = ?1 WHERE id = 'ollama'",
+                params![base_url],
+            )
+            .unwrap();
+            set_auto_pull(&conn, true).unwrap();
+        }
+        let steps = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&steps);
+        manager.set_pull_emitter(Arc::new(move |step: &PullProgress| {
+            seen.lock()
+                .unwrap()
+                .push((step.status.clone(), step.completed));
+        }));
+        let input = AiChatInput {
+            messages: Vec::new(),
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        };
+
+        let response = manager
+            .chat(
+                input.clone(),
+                Some("ollama".into()),
+                Some("phi3".into()),
+                true,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (response.provider_id.as_str(), response.content.as_str()),
+            ("ollama", "hi")
+        );
+        assert_eq!(
+            *paths.lock().unwrap(),
+            ["/api/chat", "/api/pull", "/api/chat"]
+        );
+        assert_eq!(
+            *steps.lock().unwrap(),
+            [
+                ("pulling manifest".to_string(), None),
+                ("pulling abc".to_string(), Some(5)),
+                ("success".to_string(), None),
+            ]
+        );
+
+        // Raced candidates pull the same way.
+        let raced = manager
+            .chat_raced(
+                input,
+                Some("ollama".into()),
+                Some("phi3".into()),
+                true,
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(raced.content, "hi");
+        assert_eq!(
+            paths.lock().unwrap()[3..],
+            ["/api/chat", "/api/pull", "/api/chat"]
+        );
+        let _ = std::fs::remove_file(path);
+    }
+}
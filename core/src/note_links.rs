@@ -0,0 +1,178 @@
+//! Explicit links between notes.
+//!
+//! Note-to-note links are rows in the shared `links` table with
+//! `src_type = 'note'` and `dst_type = 'note'`. A link is directed, so a note
+//! lists both the links it makes and the backlinks pointing at it.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::errors::InkOsError;
+
+/// Relation used when a link is created without one.
+pub const DEFAULT_REL: &str = "related";
+
+/// A link as seen from one note, with the other note's title.
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteLink {
+    pub link_id: String,
+    /// The note at the other end of the link.
+    pub note_id: String,
+    pub title: String,
+    pub rel: String,
+    /// `outgoing` when the listed note is the source, `incoming` for a backlink.
+    pub direction: String,
+    pub created_at: i64,
+}
+
+fn ensure_live_note(conn: &Connection, note_id: &str) -> Result<()> {
+    let live: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+            params![note_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match live {
+        Some(_) => Ok(()),
+        None => Err(InkOsError::NoteNotFound.into()),
+    }
+}
+
+fn normalise_rel(rel: Option<&str>) -> String {
+    rel.map(str::trim)
+        .filter(|rel| !rel.is_empty())
+        .unwrap_or(DEFAULT_REL)
+        .to_string()
+}
+
+/// Link `src_id` to `dst_id`. Both notes must be live; a repeated
+/// (source, target, relation) triple is rejected.
+pub fn link(conn: &Connection, src_id: &str, dst_id: &str, rel: Option<&str>) -> Result<NoteLink> {
+    if src_id == dst_id {
+        return Err(InkOsError::ValidationFailed("a note cannot link to itself".into()).into());
+    }
+    ensure_live_note(conn, src_id)?;
+    ensure_live_note(conn, dst_id)?;
+    let rel = normalise_rel(rel);
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM links WHERE src_id = ?1 AND src_type = 'note' AND dst_id = ?2 AND dst_type = 'note' AND rel = ?3",
+            params![src_id, dst_id, rel],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_some() {
+        return Err(
+            InkOsError::ValidationFailed(format!("notes are already linked as '{rel}'")).into(),
+        );
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    conn.execute(
+        "INSERT INTO links (id, src_id, src_type, dst_id, dst_type, rel, created_at) VALUES (?1, ?2, 'note', ?3, 'note', ?4, ?5)",
+        params![id, src_id, dst_id, rel, now],
+    )?;
+    let title: String = conn.query_row(
+        "SELECT title FROM notes WHERE id = ?1",
+        params![dst_id],
+        |row| row.get(0),
+    )?;
+    Ok(NoteLink {
+        link_id: id,
+        note_id: dst_id.to_string(),
+        title,
+        rel,
+        direction: "outgoing".into(),
+        created_at: now,
+    })
+}
+
+/// Links made by a note followed by its backlinks, oldest first within each
+/// group. Links to deleted notes are left out.
+pub fn list(conn: &Connection, note_id: &str) -> Result<Vec<NoteLink>> {
+    ensure_live_note(conn, note_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT l.id, n.id, n.title, l.rel, 'outgoing' AS direction, l.created_at AS created_at
+         FROM links l JOIN notes n ON n.id = l.dst_id
+         WHERE l.src_id = ?1 AND l.src_type = 'note' AND l.dst_type = 'note' AND n.deleted_at IS NULL
+         UNION ALL
+         SELECT l.id, n.id, n.title, l.rel, 'incoming' AS direction, l.created_at AS created_at
+         FROM links l JOIN notes n ON n.id = l.src_id
+         WHERE l.dst_id = ?1 AND l.dst_type = 'note' AND l.src_type = 'note' AND n.deleted_at IS NULL
+         ORDER BY direction DESC, created_at ASC",
+    )?;
+    let rows = stmt.query_map(params![note_id], |row| {
+        Ok(NoteLink {
+            link_id: row.get(0)?,
+            note_id: row.get(1)?,
+            title: row.get(2)?,
+            rel: row.get(3)?,
+            direction: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Remove the link from `src_id` to `dst_id`, or every relation between them
+/// in that direction when `rel` is `None`. Returns how many links were removed.
+pub fn unlink(conn: &Connection, src_id: &str, dst_id: &str, rel: Option<&str>) -> Result<usize> {
+    let rel = rel.map(str::trim).filter(|rel| !rel.is_empty());
+    let removed = conn.execute(
+        "DELETE FROM links WHERE src_id = ?1 AND src_type = 'note' AND dst_id = ?2 AND dst_type = 'note' AND (?3 IS NULL OR rel = ?3)",
+        params![src_id, dst_id, rel],
+    )?;
+    if removed == 0 {
+        return Err(InkOsError::ValidationFailed("the notes are not linked".into()).into());
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn links_are_listed_from_both_ends_without_duplicates() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('a', 'Alpha', '', 0, 0);
+             INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('b', 'Beta', '', 0, 0);
+             INSERT INTO notes (id, title, body, created_at, updated_at, deleted_at) VALUES ('gone', 'Gone', '', 0, 0, 1);",
+        )
+        .unwrap();
+
+        let made = link(&conn, "a", "b", None).unwrap();
+        assert_eq!(
+            (made.title.as_str(), made.rel.as_str()),
+            ("Beta", DEFAULT_REL)
+        );
+        assert!(link(&conn, "a", "b", Some(" related ")).is_err());
+        link(&conn, "a", "b", Some("follows")).unwrap();
+        assert!(link(&conn, "a", "a", None).is_err());
+        assert!(link(&conn, "a", "gone", None).is_err());
+
+        let from_a = list(&conn, "a").unwrap();
+        assert_eq!(from_a.len(), 2);
+        assert!(from_a
+            .iter()
+            .all(|l| l.direction == "outgoing" && l.note_id == "b"));
+        let from_b = list(&conn, "b").unwrap();
+        assert_eq!(from_b.len(), 2);
+        assert!(from_b
+            .iter()
+            .all(|l| l.direction == "incoming" && l.title == "Alpha"));
+
+        assert_eq!(unlink(&conn, "a", "b", Some("follows")).unwrap(), 1);
+        assert_eq!(unlink(&conn, "a", "b", None).unwrap(), 1);
+        assert!(unlink(&conn, "a", "b", None).is_err());
+        assert!(list(&conn, "b").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,466 @@
+//! Bulk import and export of notes as Markdown files.
+//!
+//! Every `.md` file under a directory becomes one note, so an Obsidian vault
+//! can be brought over in one go. A leading `# Heading` becomes the title,
+//! falling back to the file name, and a `tags` entry in the YAML front matter
+//! is attached as tags. Hidden directories such as `.obsidian` are skipped.
+//!
+//! Exports write the same shape into a zip archive, plus a `manifest.json`
+//! mapping note ids to file names.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::errors::InkOsError;
+use crate::tags;
+
+/// Event emitted after each file while an import runs.
+pub const IMPORT_PROGRESS_EVENT: &str = "notes://import-progress";
+
+/// Progress payload for [`IMPORT_PROGRESS_EVENT`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    /// Files handled so far, imported or failed.
+    pub processed: usize,
+    pub total: usize,
+    pub imported: usize,
+}
+
+/// A file that could not be imported.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportFailure {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome of an import; one failing file does not stop the others.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub note_ids: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
+
+/// Result of a completed export.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub bytes: u64,
+    pub notes: usize,
+}
+
+/// Name of the id-to-file index written at the root of an export.
+pub const MANIFEST_NAME: &str = "manifest.json";
+
+/// A Markdown file split into note fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MarkdownNote {
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+/// Import every Markdown file under `dir`, calling `progress` after each.
+///
+/// Notes keep the file's modification time as their creation and update
+/// time, so imported history lands on the right days in the logbook.
+pub fn import_dir(
+    conn: &mut Connection,
+    dir: &Path,
+    progress: &dyn Fn(ImportProgress),
+) -> Result<ImportReport> {
+    if !dir.is_dir() {
+        return Err(
+            InkOsError::ValidationFailed(format!("{} is not a directory", dir.display())).into(),
+        );
+    }
+    let files = markdown_files(dir)?;
+    let total = files.len();
+    let mut report = ImportReport::default();
+    for (index, path) in files.iter().enumerate() {
+        match import_file(conn, path) {
+            Ok(id) => {
+                report.imported += 1;
+                report.note_ids.push(id);
+            }
+            Err(err) => report.failed.push(ImportFailure {
+                path: path.display().to_string(),
+                error: format!("{err:#}"),
+            }),
+        }
+        progress(ImportProgress {
+            processed: index + 1,
+            total,
+            imported: report.imported,
+        });
+    }
+    Ok(report)
+}
+
+fn import_file(conn: &mut Connection, path: &Path) -> Result<String> {
+    let text = fs::read_to_string(path).context("failed to read file")?;
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let note = parse(&stem, &text);
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let modified = fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or(now);
+
+    let id = Uuid::new_v4().to_string();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+        params![id, note.title, note.body, modified],
+    )?;
+    tags::replace(&tx, &id, &note.tags, now)?;
+    tx.commit()?;
+    Ok(id)
+}
+
+/// `.md` files under `dir` in path order, skipping hidden entries and symlinks.
+fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries =
+            fs::read_dir(&dir).with_context(|| format!("failed to read {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn parse(file_stem: &str, text: &str) -> MarkdownNote {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let (front_matter, rest) = split_front_matter(text);
+    let tags = front_matter.map(front_matter_tags).unwrap_or_default();
+    let rest = rest.trim_start();
+    let heading = rest.strip_prefix("# ").map(|heading| {
+        let (line, after) = heading.split_once('\n').unwrap_or((heading, ""));
+        (line.trim(), after)
+    });
+    let (title, body) = match heading {
+        Some((title, after)) if !title.is_empty() => {
+            (title, after.trim_start_matches(['\r', '\n']))
+        }
+        _ => (file_stem.trim(), rest),
+    };
+    MarkdownNote {
+        title: title.to_string(),
+        body: body.trim_end().to_string(),
+        tags,
+    }
+}
+
+/// Split off a `---` delimited front-matter block at the start of `text`.
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let Some(after) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in after.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&after[..offset]), &after[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// Tags from a front-matter `tags:` (or `tag:`) entry, written inline
+/// (`tags: [a, b]`, `tags: a b`) or as a block list of `- a` items.
+fn front_matter_tags(front_matter: &str) -> Vec<String> {
+    let mut raw = Vec::new();
+    let mut in_list = false;
+    for line in front_matter.lines() {
+        if in_list {
+            if let Some(item) = line.trim_start().strip_prefix("- ") {
+                raw.push(item.to_string());
+                continue;
+            }
+            in_list = false;
+        }
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !matches!(key.trim(), "tags" | "tag") {
+            continue;
+        }
+        let value = value.trim();
+        if value.is_empty() {
+            in_list = true;
+            continue;
+        }
+        let value = value
+            .strip_prefix('[')
+            .and_then(|inner| inner.strip_suffix(']'))
+            .unwrap_or(value);
+        raw.extend(value.split([',', ' ']).map(str::to_string));
+    }
+    let cleaned: Vec<String> = raw
+        .iter()
+        .map(|tag| {
+            tag.trim()
+                .trim_matches(['"', '\''])
+                .trim_start_matches('#')
+                .to_string()
+        })
+        .collect();
+    tags::normalize(&cleaned)
+}
+
+/// Write every live note to a zip archive at `dest`, overwriting any
+/// existing file.
+///
+/// Each note becomes `<title>.md` with the title as an H1 and its tags as
+/// front matter, so the archive can be imported again. Titles that clash
+/// after sanitising get the start of the note id appended.
+pub fn export_zip(conn: &Connection, dest: &Path) -> Result<ExportReport> {
+    let file =
+        File::create(dest).with_context(|| format!("failed to create {}", dest.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut stmt = conn.prepare(
+        "SELECT n.id, n.title, n.body, n.created_at, n.updated_at,
+                (SELECT GROUP_CONCAT(name, ',') FROM (
+                    SELECT t.name FROM note_tags nt JOIN tags t ON t.id = nt.tag_id
+                    WHERE nt.note_id = n.id ORDER BY t.name))
+         FROM notes n WHERE n.deleted_at IS NULL ORDER BY n.created_at ASC, n.id ASC",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut used = HashSet::new();
+    let mut manifest = Vec::new();
+    while let Some(row) = rows.next()? {
+        let id: String = row.get(0)?;
+        let title: String = row.get(1)?;
+        let body: String = row.get(2)?;
+        let tags: Option<String> = row.get(5)?;
+        let tags: Vec<&str> = tags
+            .as_deref()
+            .map(|t| t.split(',').collect())
+            .unwrap_or_default();
+
+        let file_name = unique_file_name(&mut used, &title, &id);
+        zip.start_file(file_name.as_str(), options)?;
+        zip.write_all(to_markdown(&title, &body, &tags).as_bytes())?;
+        manifest.push(json!({
+            "id": id,
+            "file": file_name,
+            "title": title,
+            "created_at": row.get::<_, i64>(3)?,
+            "updated_at": row.get::<_, i64>(4)?,
+        }));
+    }
+
+    let notes = manifest.len();
+    zip.start_file(MANIFEST_NAME, options)?;
+    let manifest = json!({
+        "version": 1,
+        "exported_at": OffsetDateTime::now_utc().unix_timestamp(),
+        "notes": manifest,
+    });
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish()?.sync_all()?;
+
+    Ok(ExportReport {
+        path: dest.display().to_string(),
+        bytes: fs::metadata(dest)?.len(),
+        notes,
+    })
+}
+
+fn to_markdown(title: &str, body: &str, tags: &[&str]) -> String {
+    let mut out = String::new();
+    if !tags.is_empty() {
+        out.push_str(&format!("---\ntags: [{}]\n---\n\n", tags.join(", ")));
+    }
+    out.push_str(&format!("# {}\n", title.trim()));
+    if !body.trim().is_empty() {
+        out.push('\n');
+        out.push_str(body.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// A file name for `title` not yet in `used`, compared case-insensitively
+/// because archives are often unpacked on case-insensitive file systems.
+fn unique_file_name(used: &mut HashSet<String>, title: &str, id: &str) -> String {
+    let stem = sanitize_file_stem(title);
+    let short_id: String = id.chars().take(8).collect();
+    let name = [
+        format!("{stem}.md"),
+        format!("{stem}-{short_id}.md"),
+        format!("{stem}-{id}.md"),
+    ]
+    .into_iter()
+    .find(|name| !used.contains(&name.to_lowercase()))
+    .unwrap_or_else(|| format!("{id}.md"));
+    used.insert(name.to_lowercase());
+    name
+}
+
+/// Replace characters that are unsafe in file names on common platforms.
+fn sanitize_file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+    use std::cell::RefCell;
+
+    #[test]
+    fn imports_markdown_tree_with_titles_and_tags() {
+        let dir = std::env::temp_dir().join(format!("inkos-import-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("projects")).unwrap();
+        fs::create_dir_all(dir.join(".obsidian")).unwrap();
+        fs::write(
+            dir.join("alpha.md"),
+            "---\ntitle: ignored\ntags: [Work, \"#plans\"]\n---\n\n# Kickoff notes\n\nAgenda first.\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("projects/beta.md"),
+            "---\ntags:\n  - ideas\n  - work\n---\nNo heading here.",
+        )
+        .unwrap();
+        fs::write(dir.join(".obsidian/workspace.md"), "skipped").unwrap();
+        fs::write(dir.join("readme.txt"), "skipped").unwrap();
+        fs::write(dir.join("broken.md"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        let seen = RefCell::new(Vec::new());
+        let report = import_dir(&mut conn, &dir, &|p| {
+            seen.borrow_mut().push((p.processed, p.total))
+        })
+        .unwrap();
+
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].path.ends_with("broken.md"));
+        assert_eq!(*seen.borrow(), vec![(1, 3), (2, 3), (3, 3)]);
+
+        let note = |title: &str| -> (String, String) {
+            conn.query_row(
+                "SELECT n.body, COALESCE(GROUP_CONCAT(t.name, ','), '') FROM notes n
+                 LEFT JOIN note_tags nt ON nt.note_id = n.id LEFT JOIN tags t ON t.id = nt.tag_id
+                 WHERE n.title = ?1 GROUP BY n.id",
+                params![title],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        let (body, tags) = note("Kickoff notes");
+        assert_eq!(body, "Agenda first.");
+        assert_eq!(tags.split(',').count(), 2);
+        assert!(tags.contains("plans") && tags.contains("work"));
+        let (body, tags) = note("beta");
+        assert_eq!(body, "No heading here.");
+        assert!(tags.contains("ideas") && tags.contains("work"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn exports_live_notes_with_unique_names_and_manifest() {
+        use std::io::Read;
+
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('aaaaaaaa-1', 'Plan: Q3', 'Ship it.', 1, 1);
+             INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('bbbbbbbb-2', 'plan- q3', '', 2, 2);
+             INSERT INTO notes (id, title, body, created_at, updated_at, deleted_at) VALUES ('gone', 'Gone', '', 3, 3, 4);",
+        )
+        .unwrap();
+        tags::replace(&conn, "aaaaaaaa-1", &["work".into(), "q3".into()], 0).unwrap();
+
+        let dest = std::env::temp_dir().join(format!("inkos-export-{}.zip", Uuid::new_v4()));
+        let report = export_zip(&conn, &dest).unwrap();
+        assert_eq!(report.notes, 2);
+        assert_eq!(report.bytes, fs::metadata(&dest).unwrap().len());
+
+        let mut archive = zip::ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+        let first = read("Plan- Q3.md");
+        assert_eq!(
+            parse("Plan- Q3", &first),
+            MarkdownNote {
+                title: "Plan: Q3".into(),
+                body: "Ship it.".into(),
+                tags: vec!["q3".into(), "work".into()],
+            }
+        );
+        assert!(read("plan- q3-bbbbbbbb.md").starts_with("# plan- q3"));
+        let manifest: serde_json::Value = serde_json::from_str(&read(MANIFEST_NAME)).unwrap();
+        assert_eq!(manifest["notes"][1]["id"], "bbbbbbbb-2");
+        assert_eq!(manifest["notes"][1]["file"], "plan- q3-bbbbbbbb.md");
+        drop(archive);
+        fs::remove_file(dest).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+//! Keyset pagination shared by list commands.
+//!
+//! Pages are ordered newest first by a sort key with the row id as a
+//! tiebreaker. Callers pass the `next_cursor` of the previous page back as
+//! `before` to continue, which avoids the cost of large `OFFSET` scans.
+
+use serde::{Deserialize, Serialize};
+
+/// Position of the last row seen: its sort key plus id as a tiebreaker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cursor<K = i64> {
+    pub key: K,
+    pub id: String,
+}
+
+/// Sort key for lists that show pinned rows first.
+///
+/// The cursor carries the row's `pinned` flag as it was when the page was
+/// read, so pinning or deleting that row before the next page loads does not
+/// skip or repeat rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedKey {
+    pub pinned: bool,
+    pub updated_at: i64,
+}
+
+/// One page of results and the cursor for the next page, if any.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T, K = i64> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<Cursor<K>>,
+}
+
+impl<T, K> Page<T, K> {
+    /// Build a page from rows fetched with [`fetch_limit`].
+    ///
+    /// The extra look-ahead row, when present, is dropped and signals that a
+    /// further page exists.
+    pub fn from_rows(
+        mut rows: Vec<T>,
+        limit: Option<usize>,
+        cursor_of: impl Fn(&T) -> Cursor<K>,
+    ) -> Self {
+        let next_cursor = match limit {
+            Some(limit) if rows.len() > limit => {
+                rows.truncate(limit);
+                rows.last().map(cursor_of)
+            }
+            _ => None,
+        };
+        Self {
+            items: rows,
+            next_cursor,
+        }
+    }
+}
+
+/// SQL `LIMIT` value that fetches one look-ahead row; `-1` means unbounded.
+pub fn fetch_limit(limit: Option<usize>) -> i64 {
+    limit.map(|l| l as i64 + 1).unwrap_or(-1)
+}
+
+/// Split an optional cursor into nullable SQL parameters.
+pub fn cursor_params<K: Clone>(before: &Option<Cursor<K>>) -> (Option<K>, Option<String>) {
+    match before {
+        Some(cursor) => (Some(cursor.key.clone()), Some(cursor.id.clone())),
+        None => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_look_ahead_row_and_sets_cursor() {
+        let rows = vec![(3, "c"), (2, "b"), (1, "a")];
+        let page = Page::from_rows(rows, Some(2), |(ts, id)| Cursor {
+            key: *ts,
+            id: id.to_string(),
+        });
+        assert_eq!(page.items.len(), 2);
+        let cursor = page.next_cursor.unwrap();
+        assert_eq!((cursor.key, cursor.id.as_str()), (2, "b"));
+
+        let last = Page::from_rows(vec![(1, "a")], Some(2), |(ts, id)| Cursor {
+            key: *ts,
+            id: id.to_string(),
+        });
+        assert!(last.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Secret scrubbing for anything that reaches the event log.
+//!
+//! Provider errors can echo request details back to us: bearer tokens,
+//! `x-api-key` headers, or Gemini's `?key=` query parameter. Every event
+//! passes through [`redact_text`] / [`redact_json`] before it is stored.
+
+use serde_json::Value;
+
+/// Replacement written in place of a secret.
+pub const MASK: &str = "***";
+
+/// Object keys whose values are always masked, compared case-insensitively.
+const SENSITIVE_KEYS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "api-key",
+    "api_key",
+    "apikey",
+    "secret",
+    "password",
+];
+
+/// Markers in free text that are followed by a secret. `key=` only counts as
+/// a query parameter (after `?` or `&`).
+const TEXT_MARKERS: &[&str] = &[
+    "bearer ",
+    "x-api-key",
+    "x-goog-api-key",
+    "api-key",
+    "api_key",
+    "apikey",
+    "?key=",
+    "&key=",
+];
+
+/// Mask secrets that follow a known marker in free text.
+pub fn redact_text(input: &str) -> String {
+    let lower = input.to_ascii_lowercase();
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(input.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let Some(marker) = TEXT_MARKERS
+            .iter()
+            .find(|m| lower.as_bytes()[i..].starts_with(m.as_bytes()))
+        else {
+            i += 1;
+            continue;
+        };
+        let mut start = i + marker.len();
+        while start < bytes.len() && matches!(bytes[start], b' ' | b':' | b'=' | b'"' | b'\'') {
+            start += 1;
+        }
+        let mut end = start;
+        while end < bytes.len() && !ends_secret(bytes[end]) {
+            end += 1;
+        }
+        if end > start && &input[start..end] != MASK {
+            out.push_str(&input[copied..start]);
+            out.push_str(MASK);
+            copied = end;
+        }
+        i = end.max(i + 1);
+    }
+    out.push_str(&input[copied..]);
+    out
+}
+
+/// Mask sensitive object fields and scrub every string in a JSON value.
+pub fn redact_json(value: &mut Value) {
+    match value {
+        Value::String(text) => *text = redact_text(text),
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if is_sensitive_key(key) && !item.is_null() {
+                    *item = Value::String(MASK.into());
+                } else {
+                    redact_json(item);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mask sensitive object fields only, leaving other strings untouched.
+///
+/// Used on provider responses kept for the debugger, where rewriting the
+/// model's own text would corrupt the reply.
+pub fn strip_auth_fields(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(strip_auth_fields),
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                if is_sensitive_key(key) && !item.is_null() {
+                    *item = Value::String(MASK.into());
+                } else {
+                    strip_auth_fields(item);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    SENSITIVE_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key))
+}
+
+fn ends_secret(byte: u8) -> bool {
+    byte.is_ascii_whitespace()
+        || matches!(
+            byte,
+            b'"' | b'\'' | b'&' | b',' | b';' | b'}' | b']' | b')' | b'<' | b'>'
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRETS: &[&str] = &[
+        "sk-live-abc123",
+        "sk-ant-api03-xyz",
+        "AIzaSyD-gemini",
+        "tok_rotating_9",
+    ];
+
+    #[test]
+    fn text_payloads_lose_every_secret() {
+        let samples = [
+            "openai returned 401 Unauthorized: {\"error\":\"Incorrect API key\"} Authorization: Bearer sk-live-abc123",
+            "anthropic returned 400: request headers x-api-key: sk-ant-api03-xyz, anthropic-version: 2023-06-01",
+            "google request failed: error sending request for url (https://generativelanguage.googleapis.com/v1beta/models/gemini:generateContent?key=AIzaSyD-gemini)",
+            "gateway echoed {\"api_key\": \"tok_rotating_9\"}",
+        ];
+        for sample in samples {
+            let scrubbed = redact_text(sample);
+            for secret in SECRETS {
+                assert!(
+                    !scrubbed.contains(secret),
+                    "{secret} survived in {scrubbed}"
+                );
+            }
+            assert!(scrubbed.contains(MASK));
+        }
+        assert_eq!(redact_text("no secrets here"), "no secrets here");
+    }
+
+    #[test]
+    fn json_payloads_mask_auth_fields_and_embedded_tokens() {
+        let mut data = json!({
+            "provider": "openai",
+            "error": "401: Bearer sk-live-abc123 rejected",
+            "request": {
+                "headers": { "Authorization": "Bearer sk-live-abc123", "X-API-Key": "sk-ant-api03-xyz" },
+                "url": "https://example.test/v1?alt=json&key=AIzaSyD-gemini"
+            },
+            "echo": [{ "apiKey": "tok_rotating_9" }]
+        });
+        redact_json(&mut data);
+        let serialised = data.to_string();
+        for secret in SECRETS {
+            assert!(
+                !serialised.contains(secret),
+                "{secret} survived in {serialised}"
+            );
+        }
+        assert_eq!(data["provider"], "openai");
+
+        let mut raw = json!({
+            "choices": [{ "message": { "content": "Use Bearer tokens for auth" } }],
+            "echo": { "x-api-key": "sk-ant-api03-xyz" }
+        });
+        strip_auth_fields(&mut raw);
+        assert_eq!(raw["echo"]["x-api-key"], MASK);
+        assert_eq!(
+            raw["choices"][0]["message"]["content"],
+            "Use Bearer tokens for auth"
+        );
+    }
+}
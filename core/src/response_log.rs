@@ -0,0 +1,137 @@
+//! Raw provider replies kept for debugging.
+//!
+//! While the `ai.capture_raw` setting is on, every chat attempt stores its
+//! redacted raw reply (or the error it produced) in `ai_response_log`, keyed
+//! by the request's correlation id. Capture is off by default and rows
+//! expire after [`TTL_SECS`], so the table stays small.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::redact::redact_json;
+use crate::settings;
+
+/// Setting key that turns capture on (`true`) or off.
+pub const CAPTURE_KEY: &str = "ai.capture_raw";
+/// How long a captured reply is kept.
+pub const TTL_SECS: i64 = 24 * 60 * 60;
+
+/// One captured provider attempt.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawResponse {
+    pub id: String,
+    pub request_id: String,
+    pub provider_id: String,
+    pub model: String,
+    /// `ok` for a reply, `error` for a failed attempt.
+    pub outcome: String,
+    pub raw: Value,
+    pub created_at: i64,
+}
+
+/// Whether raw replies are being captured.
+pub fn capture_enabled(conn: &Connection) -> Result<bool> {
+    Ok(settings::read(conn, CAPTURE_KEY)?.as_deref() == Some("true"))
+}
+
+/// Turn capture on or off. Turning it off also drops everything captured.
+pub fn set_capture(conn: &Connection, enabled: bool) -> Result<()> {
+    settings::upsert(conn, CAPTURE_KEY, &enabled.to_string())?;
+    if !enabled {
+        conn.execute("DELETE FROM ai_response_log", [])?;
+    }
+    Ok(())
+}
+
+/// Store `raw` for `request_id` when capture is on, after redacting secrets,
+/// and drop expired rows.
+pub fn record(
+    conn: &Connection,
+    request_id: &str,
+    provider_id: &str,
+    model: &str,
+    outcome: &str,
+    raw: &Value,
+) -> Result<()> {
+    if !capture_enabled(conn)? {
+        return Ok(());
+    }
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut raw = raw.clone();
+    redact_json(&mut raw);
+    conn.execute(
+        "DELETE FROM ai_response_log WHERE expires_at <= ?1",
+        params![now],
+    )?;
+    conn.execute(
+        "INSERT INTO ai_response_log (id, request_id, provider_id, model, outcome, raw_json, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            Uuid::new_v4().to_string(),
+            request_id,
+            provider_id,
+            model,
+            outcome,
+            raw.to_string(),
+            now,
+            now + TTL_SECS
+        ],
+    )?;
+    Ok(())
+}
+
+/// Unexpired captures for a request, in the order the attempts finished.
+pub fn get(conn: &Connection, request_id: &str) -> Result<Vec<RawResponse>> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let mut stmt = conn.prepare(
+        "SELECT id, request_id, provider_id, model, outcome, raw_json, created_at FROM ai_response_log
+         WHERE request_id = ?1 AND expires_at > ?2 ORDER BY created_at ASC, rowid ASC",
+    )?;
+    let rows = stmt.query_map(params![request_id, now], |row| {
+        let raw: String = row.get(5)?;
+        Ok(RawResponse {
+            id: row.get(0)?,
+            request_id: row.get(1)?,
+            provider_id: row.get(2)?,
+            model: row.get(3)?,
+            outcome: row.get(4)?,
+            raw: serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+            created_at: row.get(6)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+    use serde_json::json;
+
+    #[test]
+    fn captures_are_off_by_default_and_redacted() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        let raw = json!({
+            "choices": [{ "message": { "content": "hi" } }],
+            "api_key": "sk-live-secret",
+        });
+
+        record(&conn, "req-1", "openai", "gpt-4o", "ok", &raw).unwrap();
+        assert!(get(&conn, "req-1").unwrap().is_empty());
+
+        set_capture(&conn, true).unwrap();
+        record(&conn, "req-1", "openai", "gpt-4o", "ok", &raw).unwrap();
+        let captured = get(&conn, "req-1").unwrap();
+        assert_eq!(captured.len(), 1);
+        assert_eq!(captured[0].raw["api_key"], "***");
+        assert_eq!(captured[0].raw["choices"][0]["message"]["content"], "hi");
+
+        set_capture(&conn, false).unwrap();
+        assert!(get(&conn, "req-1").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+//! Revision history for edited notes.
+//!
+//! Every edit stores the note's previous title and body in `note_revisions`
+//! before the new text is written, so earlier versions can be listed and
+//! restored. Each note keeps at most `notes.revision_cap` revisions (default
+//! 50); older ones are pruned as new ones arrive.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::settings;
+
+const REVISION_CAP_KEY: &str = "notes.revision_cap";
+const DEFAULT_REVISION_CAP: usize = 50;
+
+/// A previous version of a note.
+#[derive(Debug, Clone, Serialize)]
+pub struct NoteRevision {
+    pub id: String,
+    pub note_id: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+/// Read `notes.revision_cap` from `app_settings`, defaulting to 50.
+pub fn read_revision_cap(conn: &Connection) -> Result<usize> {
+    Ok(settings::read(conn, REVISION_CAP_KEY)?
+        .and_then(|v| v.parse().ok())
+        .filter(|cap| *cap > 0)
+        .unwrap_or(DEFAULT_REVISION_CAP))
+}
+
+/// Store the current title/body of `note_id` as a revision, then prune the
+/// oldest revisions beyond the configured cap.
+///
+/// Returns `false` without writing anything when the note does not exist.
+pub fn snapshot(conn: &Connection, note_id: &str, now: i64) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT INTO note_revisions (id, note_id, title, body, created_at)
+         SELECT ?1, id, title, body, ?3 FROM notes WHERE id = ?2",
+        params![Uuid::new_v4().to_string(), note_id, now],
+    )?;
+    if inserted == 0 {
+        return Ok(false);
+    }
+    let cap = read_revision_cap(conn)?;
+    conn.execute(
+        "DELETE FROM note_revisions WHERE note_id = ?1 AND id NOT IN (
+             SELECT id FROM note_revisions WHERE note_id = ?1
+             ORDER BY created_at DESC, rowid DESC LIMIT ?2
+         )",
+        params![note_id, cap as i64],
+    )?;
+    Ok(true)
+}
+
+/// Revisions of a note, newest first.
+pub fn list(conn: &Connection, note_id: &str) -> Result<Vec<NoteRevision>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, note_id, title, body, created_at FROM note_revisions
+         WHERE note_id = ?1 ORDER BY created_at DESC, rowid DESC",
+    )?;
+    let rows = stmt.query_map(params![note_id], row_to_revision)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Load one revision, scoped to its note.
+pub fn get(conn: &Connection, note_id: &str, revision_id: &str) -> Result<Option<NoteRevision>> {
+    Ok(conn
+        .query_row(
+            "SELECT id, note_id, title, body, created_at FROM note_revisions
+             WHERE id = ?1 AND note_id = ?2",
+            params![revision_id, note_id],
+            row_to_revision,
+        )
+        .optional()?)
+}
+
+fn row_to_revision(row: &rusqlite::Row<'_>) -> rusqlite::Result<NoteRevision> {
+    Ok(NoteRevision {
+        id: row.get(0)?,
+        note_id: row.get(1)?,
+        title: row.get(2)?,
+        body: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn snapshots_are_listed_newest_first_and_capped() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n1', 'v0', '', 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, '3', 0)",
+            params![REVISION_CAP_KEY],
+        )
+        .unwrap();
+
+        for version in 1..=4 {
+            assert!(snapshot(&conn, "n1", version).unwrap());
+            conn.execute(
+                "UPDATE notes SET title = ?1 WHERE id = 'n1'",
+                params![format!("v{version}")],
+            )
+            .unwrap();
+        }
+        assert!(!snapshot(&conn, "missing", 5).unwrap());
+
+        let revisions = list(&conn, "n1").unwrap();
+        let titles: Vec<&str> = revisions.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["v3", "v2", "v1"]);
+        assert!(get(&conn, "n1", &revisions[0].id).unwrap().is_some());
+        assert!(get(&conn, "other", &revisions[0].id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,35 @@
+//! Key/value application settings stored in the `app_settings` table.
+//!
+//! Values are kept as text; each owner parses its own keys and decides what
+//! an unset key falls back to.
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use time::OffsetDateTime;
+
+/// Raw value stored under `key`, or `None` when it is unset.
+pub fn read(conn: &Connection, key: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Store `value` under `key`, replacing any previous value.
+pub fn upsert(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value, OffsetDateTime::now_utc().unix_timestamp()],
+    )?;
+    Ok(())
+}
+
+/// Unset `key` so its owner falls back to the default.
+pub fn remove(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])?;
+    Ok(())
+}
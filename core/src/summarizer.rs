@@ -5,30 +5,208 @@
 //! summaries for reuse, records provenance in the event log, and coordinates
 //! conversation rollover when token thresholds are exceeded.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use anyhow::{anyhow, Result};
-use r2d2_sqlite::rusqlite::{params, OptionalExtension};
-use serde::Serialize;
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use r2d2_sqlite::rusqlite::{params, OptionalExtension, TransactionBehavior};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tokio::task::spawn_blocking;
 use uuid::Uuid;
 
-use crate::agents::{AiChatInput, AiChatMessage};
+use crate::agents::capabilities::DEFAULT_CONTEXT_WINDOW;
+use crate::agents::providers::price_for_model;
+use crate::agents::{
+    AiChatInput, AiChatMessage, AiChatResponse, AiImage, AiUsageMetrics, Capabilities,
+};
+use crate::attachments;
 use crate::db::DbPool;
-use crate::logging::log_event;
+use crate::errors::{unshare_error, InkOsError};
+use crate::logging::{self, log_event};
 use crate::model_manager::ModelManager;
+use crate::pagination::{cursor_params, fetch_limit, Cursor, Page, PinnedKey};
+use crate::settings;
+use crate::tokens::{counter_for, HeuristicCounter, TokenCounter};
+use crate::validation;
 
+pub use crate::tokens::approx_tokens;
+
+/// `SummaryRecord::source` for summaries written by a model.
+pub const SOURCE_AI: &str = "ai";
+/// `SummaryRecord::source` for deterministic text stored when the model failed.
+pub const SOURCE_FALLBACK: &str = "fallback";
+const SUMMARY_COLUMNS: &str =
+    "id, target_type, target_id, version, body, token_est, model_id, created_at, COALESCE(source, 'ai'), covered_through, covered_message_id";
+const ROLLOVER_LOOP_WINDOW_SECS: i64 = 600;
+/// Times a rollover is summarised again when the conversation changes while
+/// its summary is being written.
+const ROLLOVER_ATTEMPTS: usize = 3;
+const DEFAULT_MAX_ROLLOVERS_PER_WINDOW: u32 = 3;
+const DEFAULT_ROLLOVER_TAIL: usize = 12;
+const SEED_OVERSIZED_FLAG: &str = "seed_oversized";
+const SEED_PREFIX: &str = "Summary of previous thread:\n";
+/// `"on"` prunes rolled-over threads down to their tail automatically.
+const PRUNE_AFTER_ROLLOVER_KEY: &str = "ai.rollover.prune";
+/// Share of the summariser model's context window one prompt may use.
+const SUMMARY_PROMPT_SHARE: f32 = 0.6;
+const MIN_SUMMARY_BUDGET: usize = 256;
+const MAX_REDUCE_DEPTH: usize = 3;
+/// Older messages pulled back into a rollover prompt by keyword match.
+const MAX_BACK_REFERENCES: usize = 6;
+/// Keywords present in more than this share of older messages are ignored.
+const MAX_KEYWORD_SHARE: f32 = 0.25;
+/// Common English words long enough to pass the keyword length filter.
+/// Extend this list rather than adding special cases to [`extract_keywords`].
+const STOPWORDS: &[&str] = &[
+    "about",
+    "above",
+    "after",
+    "again",
+    "against",
+    "almost",
+    "along",
+    "already",
+    "although",
+    "always",
+    "among",
+    "another",
+    "anyone",
+    "anything",
+    "around",
+    "because",
+    "before",
+    "being",
+    "below",
+    "between",
+    "could",
+    "didn't",
+    "doesn't",
+    "doing",
+    "don't",
+    "during",
+    "either",
+    "enough",
+    "every",
+    "everyone",
+    "everything",
+    "first",
+    "going",
+    "gonna",
+    "great",
+    "hasn't",
+    "haven't",
+    "having",
+    "hello",
+    "however",
+    "instead",
+    "isn't",
+    "itself",
+    "little",
+    "might",
+    "maybe",
+    "myself",
+    "never",
+    "nothing",
+    "often",
+    "other",
+    "others",
+    "ourselves",
+    "perhaps",
+    "please",
+    "pretty",
+    "quite",
+    "rather",
+    "really",
+    "right",
+    "should",
+    "shouldn't",
+    "since",
+    "something",
+    "sometimes",
+    "still",
+    "thank",
+    "thanks",
+    "their",
+    "theirs",
+    "themselves",
+    "there",
+    "these",
+    "thing",
+    "things",
+    "think",
+    "those",
+    "though",
+    "through",
+    "today",
+    "together",
+    "under",
+    "until",
+    "using",
+    "usually",
+    "wasn't",
+    "where",
+    "whether",
+    "which",
+    "while",
+    "whose",
+    "within",
+    "without",
+    "won't",
+    "would",
+    "wouldn't",
+    "years",
+    "yesterday",
+    "you're",
+    "yours",
+    "yourself",
+];
+/// Incremental passes shorter than this share of the previous summary are
+/// assumed to have dropped earlier context and are redone in full.
+const INCREMENTAL_MIN_RETAINED: f32 = 0.5;
+const INCREMENTAL_PREFIX: &str = "Previous summary (update it with the messages that follow, keeping earlier points that still matter):\n";
+/// Messages from the start of a conversation shown to the title model.
+const AUTO_TITLE_MESSAGES: usize = 6;
+const AUTO_TITLE_MAX_WORDS: usize = 6;
+const RANGE_PROMPT: &str = "The material covers a range of days: lead with the main themes and progress across the whole range rather than retelling each day, then note recurring problems and open threads.";
+const AUTO_TITLE_PROMPT: &str = "Write a 3-6 word title for the conversation below. Reply with the title only: no quotes, no trailing punctuation.";
 const SUMMARISER_PROMPT: &str = "You are InkOS' summariser. Craft a concise, factual markdown summary highlighting key actions, decisions, and next steps. Keep the tone warm yet professional. Where appropriate, group related points together and avoid redundant phrasing.";
 
+const DEFAULT_WARN_RATIO: f32 = 0.75;
+const DEFAULT_FORCE_RATIO: f32 = 0.9;
+/// Context window assumed when nothing usable is configured for a model.
+const DEFAULT_CONTEXT_LIMIT: usize = DEFAULT_CONTEXT_WINDOW;
+
+/// Summary target type for text passed in directly rather than stored.
+const TEXT_TARGET: &str = "text";
+/// Summary target type for on-demand date range summaries.
+const RANGE_TARGET: &str = "range";
+
 /// Cached configuration for the summariser thresholds and model selection.
 #[derive(Clone, Debug, Serialize)]
 pub struct SummarizerConfig {
     pub warn_ratio: f32,
     pub force_ratio: f32,
     pub summarizer_model: Option<String>,
+    /// Rollovers allowed within [`ROLLOVER_LOOP_WINDOW_SECS`] before
+    /// `AI-CTX-LOOP` is raised.
+    pub max_rollovers_per_window: u32,
+    /// Newest messages always included in a rollover summary prompt.
+    pub rollover_tail: usize,
+    /// Size the tail to the summariser's context budget instead of using
+    /// `rollover_tail`.
+    pub rollover_tail_auto: bool,
+    /// Delete a rolled-over thread's messages, except the newest
+    /// `rollover_tail`, once its summary is linked.
+    pub prune_after_rollover: bool,
+    /// Extra instruction appended to the summariser prompt for one request.
+    #[serde(skip)]
+    pub focus: Option<&'static str>,
 }
 
 /// Persisted summary metadata returned to callers.
@@ -42,7 +220,14 @@ pub struct SummaryRecord {
     pub token_est: Option<i64>,
     pub model_id: Option<String>,
     pub created_at: i64,
+    /// Whether a stored summary was served instead of writing a new one.
     pub reused: bool,
+    /// `"ai"` for model output, `"fallback"` for the deterministic text.
+    pub source: String,
+    /// For conversation summaries, `created_at` of the newest message covered.
+    pub covered_through: Option<i64>,
+    /// For conversation summaries, id of the newest message covered.
+    pub covered_message_id: Option<String>,
 }
 
 /// Representation of a conversation row returned through the API.
@@ -59,6 +244,43 @@ pub struct ConversationRecord {
     pub closed_at: Option<i64>,
     pub quality_flags: Option<String>,
     pub total_tokens: i64,
+    /// Context window of the conversation's model, in tokens.
+    pub context_limit: usize,
+    /// `total_tokens` at which the context warning is raised.
+    pub warn_threshold: i64,
+    /// `total_tokens` at which the conversation rolls over.
+    pub force_threshold: i64,
+    pub pinned: bool,
+    /// Prepended to `ai_chat` requests for this conversation. It is not
+    /// stored as a message, so it never counts toward rollover thresholds.
+    pub system_prompt: Option<String>,
+    /// Temperature used when a chat request does not set one.
+    pub default_temperature: Option<f32>,
+}
+
+impl ConversationRecord {
+    /// Fill in the conversation's system prompt and temperature where the
+    /// request leaves them out. A request that starts with its own system
+    /// message keeps it.
+    pub fn apply_chat_defaults(&self, input: &mut AiChatInput) {
+        if input.temperature.is_none() {
+            input.temperature = self.default_temperature;
+        }
+        let has_system = input
+            .messages
+            .first()
+            .is_some_and(|message| message.role == "system");
+        if let (Some(prompt), false) = (&self.system_prompt, has_system) {
+            input.messages.insert(
+                0,
+                AiChatMessage {
+                    role: "system".into(),
+                    content: prompt.clone(),
+                    images: Vec::new(),
+                },
+            );
+        }
+    }
 }
 
 /// Normalised chat message returned to the UI.
@@ -73,6 +295,49 @@ pub struct MessageRecord {
     pub quality_flags: Option<String>,
 }
 
+/// A message matching a [`Summarizer::search_messages`] query.
+#[derive(Clone, Debug, Serialize)]
+pub struct MessageSearchHit {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub role: String,
+    /// Excerpt around the match with matched terms wrapped in `**`.
+    pub snippet: String,
+    pub created_at: i64,
+}
+
+/// Search hits from one conversation, best match first.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConversationMatches {
+    pub conversation_id: String,
+    pub title: Option<String>,
+    pub match_count: usize,
+    pub messages: Vec<MessageSearchHit>,
+}
+
+/// Output format accepted by [`Summarizer::export_conversation`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// A full conversation history, oldest thread first.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConversationExport {
+    pub exported_at: i64,
+    pub threads: Vec<ExportedThread>,
+}
+
+/// One conversation in a rollover chain with its messages and summaries.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedThread {
+    pub conversation: ConversationRecord,
+    pub messages: Vec<MessageRecord>,
+    pub summaries: Vec<SummaryRecord>,
+}
+
 /// Outcome returned after appending a message and checking rollover.
 #[derive(Clone, Debug, Serialize)]
 pub struct AppendResult {
@@ -82,6 +347,10 @@ pub struct AppendResult {
     pub new_conversation: Option<ConversationRecord>,
     pub summary: Option<SummaryRecord>,
     pub total_tokens: i64,
+    /// Context window of the conversation's model, in tokens.
+    pub context_limit: usize,
+    pub warn_threshold: i64,
+    pub force_threshold: i64,
 }
 
 /// Details describing an explicit rollover request.
@@ -92,17 +361,65 @@ pub struct RolloverOutcome {
     pub summary: Option<SummaryRecord>,
 }
 
+/// Conversation state after a message was edited or a reply regenerated.
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryUpdate {
+    /// The conversation with its recomputed token total and context flags.
+    pub conversation: ConversationRecord,
+    pub messages: Vec<MessageRecord>,
+    pub rolled: bool,
+    pub new_conversation: Option<ConversationRecord>,
+}
+
+/// What a rollover would produce, computed without rolling over.
+#[derive(Clone, Debug, Serialize)]
+pub struct RolloverPreview {
+    pub conversation_id: String,
+    /// Candidate summary; cached, so a rollover forced next reuses it.
+    pub summary: SummaryRecord,
+    pub excerpt_count: usize,
+    /// Tokens currently held by the conversation's messages.
+    pub current_tokens: i64,
+    /// Tokens of the seed message the new thread would start with.
+    pub seed_tokens: i64,
+    /// `current_tokens - seed_tokens`: context freed by rolling over.
+    pub token_delta: i64,
+}
+
+/// Aggregate token usage and estimated spend for a single conversation.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConversationUsage {
+    pub conversation_id: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    pub recorded_calls: i64,
+    pub estimated_calls: i64,
+    pub estimated_cost_usd: f64,
+    pub unpriced_tokens: i64,
+}
+
+/// Summary computation shared by every caller asking for the same source.
+type PendingSummary = Shared<BoxFuture<'static, Result<SummaryRecord, Arc<anyhow::Error>>>>;
+/// `(target_type, target_id, source_hash)` of an in-flight summary.
+type SummaryKey = (String, String, String);
+
 /// Primary entry point for the summarisation and rollover subsystem.
 #[derive(Clone)]
 pub struct Summarizer {
     pool: DbPool,
     models: Arc<ModelManager>,
+    in_flight: Arc<Mutex<HashMap<SummaryKey, PendingSummary>>>,
 }
 
 impl Summarizer {
     /// Construct a new summariser bound to the SQLite pool and model manager.
     pub fn new(pool: DbPool, models: Arc<ModelManager>) -> Arc<Self> {
-        Arc::new(Self { pool, models })
+        Arc::new(Self {
+            pool,
+            models,
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        })
     }
 
     /// Provide synchronous access to the underlying connection pool.
@@ -110,6 +427,11 @@ impl Summarizer {
         self.pool.clone()
     }
 
+    /// Model manager used for completions, shared with background jobs.
+    pub fn models(&self) -> Arc<ModelManager> {
+        Arc::clone(&self.models)
+    }
+
     /// Read the persisted configuration from `app_settings`.
     pub fn load_config(&self) -> Result<SummarizerConfig> {
         let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
@@ -117,14 +439,42 @@ impl Summarizer {
     }
 
     /// Persist updated thresholds and optional summariser model override.
+    ///
+    /// Both ratios must lie in `(0, 1]` with `warn_ratio` below `force_ratio`.
     pub fn update_config(
         &self,
         warn_ratio: f32,
         force_ratio: f32,
         summarizer_model: Option<String>,
+        rollover_tail: usize,
+        rollover_tail_auto: bool,
     ) -> Result<SummarizerConfig> {
+        if rollover_tail == 0 {
+            return Err(
+                InkOsError::ValidationFailed("rollover_tail must be at least 1".into()).into(),
+            );
+        }
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        write_config(
+            &conn,
+            warn_ratio,
+            force_ratio,
+            summarizer_model,
+            rollover_tail,
+            rollover_tail_auto,
+        )?;
+        read_config(&conn)
+    }
+
+    /// Turn automatic pruning of rolled-over threads on or off.
+    pub fn set_prune_after_rollover(&self, enabled: bool) -> Result<SummarizerConfig> {
         let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
-        write_config(&conn, warn_ratio, force_ratio, summarizer_model)?;
+        let value = if enabled { "on" } else { "off" };
+        settings::upsert(
+            &conn,
+            PRUNE_AFTER_ROLLOVER_KEY,
+            &serde_json::to_string(value)?,
+        )?;
         read_config(&conn)
     }
 
@@ -134,7 +484,11 @@ impl Summarizer {
         title: Option<String>,
         provider_override: Option<String>,
         model_override: Option<String>,
+        system_prompt: Option<String>,
+        default_temperature: Option<f32>,
     ) -> Result<ConversationRecord> {
+        let (system_prompt, default_temperature) =
+            validate_chat_defaults(system_prompt, default_temperature)?;
         let selection = self
             .models
             .resolve_runtime(provider_override, model_override, true)?;
@@ -142,12 +496,14 @@ impl Summarizer {
         let now = OffsetDateTime::now_utc().unix_timestamp();
         let id = Uuid::new_v4().to_string();
         conn.execute(
-            "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, 0, 0, ?5, ?5)",
+            "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, system_prompt, default_temperature, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, 0, 0, ?5, ?6, ?7, ?7)",
             params![
                 id,
                 title,
                 selection.provider.id,
                 selection.model,
+                system_prompt,
+                default_temperature,
                 now,
             ],
         )?;
@@ -155,10 +511,102 @@ impl Summarizer {
             .ok_or_else(|| anyhow!("conversation missing after creation"))
     }
 
-    /// Return conversations ordered by most recent activity.
-    pub fn list_conversations(&self, limit: Option<usize>) -> Result<Vec<ConversationRecord>> {
+    /// Return a page of conversations, pinned first, then by most recent
+    /// activity.
+    ///
+    /// Closed conversations (soft-deleted or rolled over) are skipped unless
+    /// `include_closed` is set; `pinned_only` drops unpinned ones.
+    pub fn list_conversations(
+        &self,
+        limit: Option<usize>,
+        before: Option<Cursor<PinnedKey>>,
+        include_closed: bool,
+        pinned_only: bool,
+    ) -> Result<Page<ConversationRecord, PinnedKey>> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        list_conversations(&conn, limit, before, include_closed, pinned_only)
+    }
+
+    /// Pin or unpin a conversation. Pinning does not count as activity, so
+    /// `updated_at` is left alone.
+    pub fn set_conversation_pinned(
+        &self,
+        conversation_id: &str,
+        pinned: bool,
+    ) -> Result<ConversationRecord> {
         let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
-        list_conversations(&conn, limit)
+        conn.execute(
+            "UPDATE conversations SET pinned = ?2 WHERE id = ?1",
+            params![conversation_id, pinned],
+        )?;
+        fetch_conversation(&conn, conversation_id)?
+            .ok_or_else(|| InkOsError::ConversationNotFound.into())
+    }
+
+    /// Delete a conversation, returning the ids of the affected threads.
+    ///
+    /// With `soft` the thread is only closed and hidden from the default
+    /// listing. A hard delete removes messages, summaries, and links; usage
+    /// rows are kept, detached from the thread, so spend reports stay whole.
+    /// Threads that belong to a rollover chain are refused unless `chain` is
+    /// set, in which case every thread in the chain is removed together.
+    pub fn delete_conversation(
+        &self,
+        conversation_id: &str,
+        soft: bool,
+        chain: bool,
+    ) -> Result<Vec<String>> {
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        fetch_conversation(&conn, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        let threads = rollover_chain(&conn, conversation_id)?;
+        let targets = if chain {
+            threads
+        } else if soft || threads.len() == 1 {
+            vec![conversation_id.to_string()]
+        } else {
+            return Err(InkOsError::ValidationFailed(format!(
+                "conversation is part of a rollover chain of {} threads; delete the whole chain or close it instead",
+                threads.len()
+            ))
+            .into());
+        };
+
+        let tx = conn.transaction()?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for id in &targets {
+            if soft {
+                tx.execute(
+                    "UPDATE conversations SET closed_at = COALESCE(closed_at, ?2), updated_at = ?2 WHERE id = ?1",
+                    params![id, now],
+                )?;
+            } else {
+                purge_conversation(&tx, id)?;
+            }
+        }
+        log_event(
+            &tx,
+            "info",
+            Some("AI-CONV-DELETE"),
+            "ai.context",
+            if soft {
+                "Conversation closed"
+            } else {
+                "Conversation deleted"
+            },
+            Some(if soft {
+                "The thread is hidden from the default conversation list."
+            } else {
+                "Messages, summaries, and links were removed; usage was kept."
+            }),
+            Some(json!({
+                "conversation_id": conversation_id,
+                "threads": targets,
+                "soft": soft,
+            })),
+        )
+        .ok();
+        logging::commit(tx)?;
+        Ok(targets)
     }
 
     /// Fetch messages for a conversation.
@@ -177,6 +625,29 @@ impl Summarizer {
         fetch_conversation(&conn, conversation_id)
     }
 
+    /// Replace a conversation's system prompt and default temperature.
+    /// `None` clears a value.
+    pub fn set_conversation_prompt(
+        &self,
+        conversation_id: &str,
+        system_prompt: Option<String>,
+        default_temperature: Option<f32>,
+    ) -> Result<ConversationRecord> {
+        let (system_prompt, default_temperature) =
+            validate_chat_defaults(system_prompt, default_temperature)?;
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let updated = conn.execute(
+            "UPDATE conversations SET system_prompt = ?2, default_temperature = ?3, updated_at = ?4 WHERE id = ?1",
+            params![conversation_id, system_prompt, default_temperature, now],
+        )?;
+        if updated == 0 {
+            return Err(InkOsError::ConversationNotFound.into());
+        }
+        fetch_conversation(&conn, conversation_id)?
+            .ok_or_else(|| InkOsError::ConversationNotFound.into())
+    }
+
     /// Override the provider/model used for a conversation.
     pub fn set_conversation_model(
         &self,
@@ -207,7 +678,226 @@ impl Summarizer {
             })),
         )
         .ok();
-        fetch_conversation(&conn, conversation_id)?.ok_or_else(|| anyhow!("conversation not found"))
+        fetch_conversation(&conn, conversation_id)?
+            .ok_or_else(|| InkOsError::ConversationNotFound.into())
+    }
+
+    /// Branch a conversation at `up_to_message_id`.
+    ///
+    /// The fork copies every message up to and including that one, keeps the
+    /// source provider/model, and starts with fresh context flags. It is
+    /// linked back to the source with a `forked_from` relation.
+    pub fn fork_conversation(
+        &self,
+        conversation_id: &str,
+        up_to_message_id: &str,
+    ) -> Result<ConversationRecord> {
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let tx = conn.transaction()?;
+        let source =
+            fetch_conversation(&tx, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        let messages = list_messages(&tx, conversation_id, None)?;
+        let Some(cut) = messages
+            .iter()
+            .position(|message| message.id == up_to_message_id)
+        else {
+            return Err(InkOsError::ValidationFailed(format!(
+                "message '{up_to_message_id}' does not belong to conversation '{conversation_id}'"
+            ))
+            .into());
+        };
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let id = Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, system_prompt, default_temperature, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, 0, 0, ?5, ?6, ?7, ?7)",
+            params![
+                id,
+                source.title,
+                source.provider_id,
+                source.model_id,
+                source.system_prompt,
+                source.default_temperature,
+                now
+            ],
+        )?;
+        for message in &messages[..=cut] {
+            tx.execute(
+                "INSERT INTO messages (id, conversation_id, role, body, token_est, quality_flags, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    id,
+                    message.role,
+                    message.body,
+                    message.token_est,
+                    message.quality_flags,
+                    message.created_at,
+                ],
+            )?;
+        }
+        insert_link(
+            &tx,
+            &id,
+            "conversation",
+            conversation_id,
+            "conversation",
+            "forked_from",
+        )?;
+        log_event(
+            &tx,
+            "info",
+            Some("AI-CONV-FORK"),
+            "ai.context",
+            "Conversation forked",
+            None,
+            Some(json!({
+                "conversation_id": id,
+                "source_id": conversation_id,
+                "up_to_message_id": up_to_message_id,
+                "messages": cut + 1,
+            })),
+        )
+        .ok();
+        let record = fetch_conversation(&tx, &id)?
+            .ok_or_else(|| anyhow!("conversation missing after fork"))?;
+        logging::commit(tx)?;
+        Ok(record)
+    }
+
+    /// Full-text search over message bodies, grouped by conversation.
+    ///
+    /// Every word of `query` must appear in a message; FTS operators are
+    /// treated as plain text. Groups are ordered by their best hit and at
+    /// most `limit` messages are returned in total. Closed and rolled-over
+    /// threads are included.
+    pub fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<ConversationMatches>> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        search_messages(&conn, query, limit)
+    }
+
+    /// Set a conversation's title and bump `updated_at`.
+    pub fn rename_conversation(
+        &self,
+        conversation_id: &str,
+        title: &str,
+    ) -> Result<ConversationRecord> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(InkOsError::ValidationFailed("title must not be empty".into()).into());
+        }
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        store_title(&conn, conversation_id, title)
+    }
+
+    /// Ask the conversation's model for a short title based on its opening
+    /// messages, store it, and return the updated record.
+    pub async fn auto_title_conversation(
+        &self,
+        conversation_id: &str,
+    ) -> Result<ConversationRecord> {
+        let pool = self.pool.clone();
+        let id = conversation_id.to_string();
+        let (conversation, messages) = spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            let conversation =
+                fetch_conversation(&conn, &id)?.ok_or(InkOsError::ConversationNotFound)?;
+            let messages = list_messages(&conn, &id, Some(AUTO_TITLE_MESSAGES))?;
+            Ok::<_, anyhow::Error>((conversation, messages))
+        })
+        .await??;
+        if messages.is_empty() {
+            return Err(InkOsError::ValidationFailed(
+                "conversation has no messages to title".into(),
+            )
+            .into());
+        }
+
+        let transcript = messages
+            .iter()
+            .map(|message| format!("{}: {}", message.role, message.body))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let input = AiChatInput {
+            messages: vec![
+                AiChatMessage {
+                    role: "system".into(),
+                    content: AUTO_TITLE_PROMPT.into(),
+                    images: Vec::new(),
+                },
+                AiChatMessage {
+                    role: "user".into(),
+                    content: transcript,
+                    images: Vec::new(),
+                },
+            ],
+            temperature: Some(0.2),
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        };
+        let response = self
+            .models
+            .chat(
+                input,
+                Some(conversation.provider_id.clone()),
+                Some(conversation.model_id.clone()),
+                true,
+                false,
+            )
+            .await?;
+        let title = clean_title(&response.content)
+            .ok_or_else(|| anyhow!("model returned an empty title"))?;
+
+        let pool = self.pool.clone();
+        let id = conversation_id.to_string();
+        spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            let record = store_title(&conn, &id, &title)?;
+            log_event(
+                &conn,
+                "info",
+                Some("AI-AUTOTITLE"),
+                "ai.context",
+                "Conversation titled automatically",
+                None,
+                Some(json!({ "conversation_id": id, "title": title })),
+            )
+            .ok();
+            Ok(record)
+        })
+        .await?
+    }
+
+    /// Auto-title a conversation that is still untitled after its first
+    /// assistant reply. Returns `None` when no title was needed.
+    pub async fn auto_title_if_untitled(
+        &self,
+        conversation_id: &str,
+    ) -> Result<Option<ConversationRecord>> {
+        let pool = self.pool.clone();
+        let id = conversation_id.to_string();
+        let needed = spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            let needed: Option<bool> = conn
+                .query_row(
+                    "SELECT title IS NULL AND (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id AND m.role = 'assistant') = 1
+                     FROM conversations c WHERE c.id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok::<_, anyhow::Error>(needed.unwrap_or(false))
+        })
+        .await??;
+        if !needed {
+            return Ok(None);
+        }
+        self.auto_title_conversation(conversation_id)
+            .await
+            .map(Some)
     }
 
     /// Retrieve a previously cached summary by id.
@@ -216,83 +906,273 @@ impl Summarizer {
         load_summary(&conn, summary_id)
     }
 
+    /// Every stored version of a target's summary, newest first.
+    pub fn list_summaries(&self, target_type: &str, target_id: &str) -> Result<Vec<SummaryRecord>> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        list_summaries(&conn, target_type, target_id)
+    }
+
     /// Generate or return a cached conversation summary without rolling over.
-    pub fn summarise_conversation(&self, conversation_id: &str) -> Result<SummaryRecord> {
+    pub async fn summarise_conversation(&self, conversation_id: &str) -> Result<SummaryRecord> {
+        let pool = self.pool.clone();
+        let id = conversation_id.to_string();
+        let models = Arc::clone(&self.models);
+        let (messages, previous, tail) = spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            fetch_conversation(&conn, &id)?.ok_or(InkOsError::ConversationNotFound)?;
+            let messages = list_messages(&conn, &id, None)?;
+            let previous = latest_covered_summary(&conn, &id)?;
+            let config = read_config(&conn)?;
+            let tail = rollover_tail_len(&conn, &models, &config, &messages, None);
+            Ok::<_, anyhow::Error>((messages, previous, tail))
+        })
+        .await??;
+        let covered = messages
+            .last()
+            .map(|message| (message.id.clone(), message.created_at));
+
+        // Resume after the covered message; if it was edited away or pruned,
+        // fall through to a full pass.
+        let resumable = previous.and_then(|(previous, covered_id)| {
+            let position = messages
+                .iter()
+                .position(|message| message.id == covered_id)?;
+            Some((previous, &messages[position + 1..]))
+        });
+        if let Some((previous, fresh)) = resumable {
+            if fresh.is_empty() {
+                return Ok(SummaryRecord {
+                    reused: true,
+                    ..previous
+                });
+            }
+            let mut excerpts = vec![format!("{INCREMENTAL_PREFIX}{}", previous.body)];
+            excerpts.extend(
+                fresh
+                    .iter()
+                    .map(|message| format!("{}: {}", message.role, message.body)),
+            );
+            let summary = self
+                .coalesced_summary("conversation", conversation_id, excerpts)
+                .await?;
+            let retained =
+                summary.body.chars().count() as f32 / previous.body.chars().count().max(1) as f32;
+            if retained >= INCREMENTAL_MIN_RETAINED {
+                return self.record_coverage(summary, covered).await;
+            }
+            self.discard_incremental(&summary, retained).await?;
+        }
+
+        let excerpts = select_conversation_excerpts(&messages, None, tail);
+        let summary = self
+            .coalesced_summary("conversation", conversation_id, excerpts)
+            .await?;
+        self.record_coverage(summary, covered).await
+    }
+
+    /// Stamp a conversation summary with the newest message it covers.
+    async fn record_coverage(
+        &self,
+        mut summary: SummaryRecord,
+        covered: Option<(String, i64)>,
+    ) -> Result<SummaryRecord> {
+        let Some((message_id, created_at)) = covered else {
+            return Ok(summary);
+        };
+        let pool = self.pool.clone();
+        let id = summary.id.clone();
+        let covered_id = message_id.clone();
+        spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            conn.execute(
+                "UPDATE summaries SET covered_through = ?2, covered_message_id = ?3 WHERE id = ?1",
+                params![id, created_at, covered_id],
+            )?;
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+        summary.covered_through = Some(created_at);
+        summary.covered_message_id = Some(message_id);
+        Ok(summary)
+    }
+
+    /// Drop an incremental summary that lost too much of its predecessor.
+    async fn discard_incremental(&self, summary: &SummaryRecord, retained: f32) -> Result<()> {
+        let pool = self.pool.clone();
+        let summary = summary.clone();
+        spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            if !summary.reused {
+                conn.execute("DELETE FROM summaries WHERE id = ?1", params![summary.id])?;
+            }
+            log_event(
+                &conn,
+                "warn",
+                Some("AI-SUMMARY-FULL"),
+                "ai.summary",
+                "Incremental summary discarded",
+                Some("The update was much shorter than the previous summary; re-summarising the whole thread."),
+                Some(json!({
+                    "target_id": summary.target_id,
+                    "retained_ratio": retained,
+                })),
+            )
+            .ok();
+            Ok::<_, anyhow::Error>(())
+        })
+        .await?
+    }
+
+    /// Serialise a conversation, including every thread in its rollover
+    /// chain, as Markdown or a JSON bundle.
+    pub fn export_conversation(
+        &self,
+        conversation_id: &str,
+        format: ExportFormat,
+    ) -> Result<String> {
         let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
-        let conversation = fetch_conversation(&conn, conversation_id)?
-            .ok_or_else(|| anyhow!("conversation not found"))?;
-        let messages = list_messages(&conn, conversation_id, None)?;
-        let mut excerpts = select_conversation_excerpts(&messages, None);
-        let config = read_config(&conn)?;
-        store_or_create_summary(
+        let export = build_conversation_export(&conn, conversation_id)?;
+        match format {
+            ExportFormat::Markdown => render_markdown_export(&export),
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(&export)?),
+        }
+    }
+
+    /// Record provider-reported usage for a chat call made on behalf of a
+    /// conversation. The row is claimed by the next assistant message
+    /// appended to that conversation.
+    pub fn record_pending_usage(
+        &self,
+        conversation_id: &str,
+        response: &AiChatResponse,
+    ) -> Result<()> {
+        let Some(usage) = response.usage.as_ref() else {
+            return Ok(());
+        };
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let prompt = usage.prompt_tokens.unwrap_or(0) as i64;
+        let completion = usage.completion_tokens.unwrap_or(0) as i64;
+        let total = usage
+            .total_tokens
+            .map(|v| v as i64)
+            .unwrap_or(prompt + completion);
+        insert_usage(
             &conn,
-            self.models.as_ref(),
-            "conversation",
-            conversation_id,
-            &mut excerpts,
-            &config,
+            None,
+            Some(conversation_id),
+            &response.provider_id,
+            &response.model,
+            (prompt, completion, total),
+            false,
         )
     }
 
+    /// Aggregate token usage and estimated cost for a conversation.
+    pub fn conversation_usage(&self, conversation_id: &str) -> Result<ConversationUsage> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        conversation_usage(&conn, conversation_id)
+    }
+
     /// Append a new message and evaluate rollover thresholds.
+    ///
+    /// The body is trimmed and must not be empty. Assistant messages also
+    /// record token usage: explicit `usage` wins, then any pending usage
+    /// captured by `ai_chat`, and finally an [`approx_tokens`] based
+    /// estimate. A pending row is claimed even when `usage` is given, so the
+    /// reply stays attributed to the provider/model that actually answered.
+    ///
+    /// A rollover runs once the message is committed, so if it fails the
+    /// message is kept and the next append tries the rollover again.
     pub fn append_and_maybe_rollover(
         &self,
         conversation_id: &str,
         role: &str,
         body: &str,
+        usage: Option<&AiUsageMetrics>,
     ) -> Result<AppendResult> {
-        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        self.append_message(conversation_id, role, body, usage, None, None)
+    }
+
+    /// [`append_and_maybe_rollover`](Self::append_and_maybe_rollover) for a
+    /// reply from a known `responder` (provider, model). It also deletes
+    /// `replacing` in the same transaction, so a failed append leaves the
+    /// replaced message in place.
+    fn append_message(
+        &self,
+        conversation_id: &str,
+        role: &str,
+        body: &str,
+        usage: Option<&AiUsageMetrics>,
+        responder: Option<(&str, &str)>,
+        replacing: Option<&str>,
+    ) -> Result<AppendResult> {
+        let body = validation::required_text("content", body)?;
+        let body = body.as_str();
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
         let config = read_config(&conn)?;
-        let mut tx = conn.transaction()?;
-        let conversation = fetch_conversation(&tx, conversation_id)?
-            .ok_or_else(|| anyhow!("conversation not found"))?;
+        // Take the write lock up front: upgrading a read transaction fails at
+        // once with `database is locked` if another connection is writing.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let conversation =
+            fetch_conversation(&tx, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
         if conversation.ctx_force {
-            return Err(anyhow!("conversation already rolled"));
+            return Err(InkOsError::ConversationRolled.into());
+        }
+        if let Some(old) = replacing {
+            // Keep the replaced reply's billed usage; the foreign key would
+            // otherwise cascade the delete onto it.
+            tx.execute(
+                "UPDATE usage SET message_id = NULL WHERE message_id = ?1",
+                params![old],
+            )?;
+            tx.execute(
+                "DELETE FROM messages WHERE id = ?1 AND conversation_id = ?2",
+                params![old, conversation_id],
+            )?;
+        }
+        let counter = counter_for(&tx, &conversation.provider_id, &conversation.model_id);
+        let prior_tokens = sum_tokens(&tx, conversation_id)?;
+        let message = insert_message(&tx, counter.as_ref(), conversation_id, role, body)?;
+        if role == "assistant" {
+            record_message_usage(&tx, &conversation, &message, usage, responder, prior_tokens)?;
         }
-        let message = insert_message(&tx, conversation_id, role, body)?;
         let total_tokens = sum_tokens(&tx, conversation_id)?;
-        let context_limit =
-            context_limit_from_tags(&tx, &conversation.provider_id, &conversation.model_id)?;
-        let warn_threshold = (context_limit as f32 * config.warn_ratio) as i64;
-        let force_threshold = (context_limit as f32 * config.force_ratio) as i64;
+        let (context_limit, warn_threshold, force_threshold) = context_gauge(
+            &tx,
+            &conversation.provider_id,
+            &conversation.model_id,
+            &config,
+        )?;
         let mut warn = conversation.ctx_warn;
         if total_tokens >= warn_threshold && !conversation.ctx_warn {
-            mark_ctx_warn(&tx, conversation_id)?;
+            raise_ctx_warn(&tx, conversation_id, total_tokens, warn_threshold)?;
             warn = true;
-            log_event(
-                &tx,
-                "warn",
-                Some("AI-CTX-WARN"),
-                "ai.context",
-                "Conversation approaching context limit",
-                Some("A warning banner should be shown in the UI."),
-                Some(json!({
-                    "conversation_id": conversation_id,
-                    "total_tokens": total_tokens,
-                    "threshold": warn_threshold,
-                })),
-            )
-            .ok();
         }
         if total_tokens >= force_threshold {
-            let outcome = perform_rollover(
-                &mut tx,
-                &conversation,
-                self.models.as_ref(),
-                &config,
-                Some((role, body)),
-            )?;
-            tx.commit()?;
+            if let Some(recent) = recent_rollovers(&tx, &conversation, &config)? {
+                drop(tx);
+                return Err(rollover_loop_error(&conn, conversation_id, recent));
+            }
+            logging::commit(tx)?;
+            let outcome =
+                self.roll_over_committed(&mut conn, &config, conversation_id, Some((role, body)))?;
+            self.prune_after_rollover(&mut conn, &config, conversation_id);
+            let (new_conversation, summary) = outcome
+                .map(|outcome| (outcome.new_conversation, outcome.summary))
+                .unwrap_or_default();
             return Ok(AppendResult {
                 message,
                 warn: true,
                 rolled: true,
-                new_conversation: outcome.new_conversation,
-                summary: outcome.summary,
+                new_conversation,
+                summary,
                 total_tokens,
+                context_limit,
+                warn_threshold,
+                force_threshold,
             });
         }
-        tx.commit()?;
+        logging::commit(tx)?;
         Ok(AppendResult {
             message,
             warn,
@@ -300,39 +1180,479 @@ impl Summarizer {
             new_conversation: None,
             summary: None,
             total_tokens,
+            context_limit,
+            warn_threshold,
+            force_threshold,
         })
     }
 
-    /// Force a rollover for the supplied conversation.
-    pub fn rollover(&self, conversation_id: &str) -> Result<RolloverOutcome> {
-        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+    /// Replace a message's body and remove every message after it, so the
+    /// conversation continues from the edit. The body is trimmed and must
+    /// not be empty, as on append.
+    ///
+    /// The token total is recomputed afterwards: the context warning is
+    /// cleared once the conversation is back under it, and reaching the
+    /// force threshold rolls over after the edit commits, as an append would.
+    pub fn edit_message(&self, message_id: &str, new_body: &str) -> Result<HistoryUpdate> {
+        let new_body = validation::required_text("new_body", new_body)?;
+        let new_body = new_body.as_str();
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
         let config = read_config(&conn)?;
-        let mut tx = conn.transaction()?;
-        let conversation = fetch_conversation(&tx, conversation_id)?
-            .ok_or_else(|| anyhow!("conversation not found"))?;
-        let outcome =
-            perform_rollover(&mut tx, &conversation, self.models.as_ref(), &config, None)?;
-        tx.commit()?;
-        Ok(outcome)
-    }
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let target: Option<(String, i64, i64)> = tx
+            .query_row(
+                "SELECT conversation_id, created_at, rowid FROM messages WHERE id = ?1",
+                params![message_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((conversation_id, created_at, rowid)) = target else {
+            return Err(InkOsError::MessageNotFound.into());
+        };
+        let conversation =
+            fetch_conversation(&tx, &conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        if conversation.ctx_force {
+            return Err(InkOsError::ConversationRolled.into());
+        }
 
-    /// Summarise arbitrary source material.
-    pub fn summarise(
-        &self,
-        target_type: &str,
-        target_id: &str,
-        content: &str,
-    ) -> Result<SummaryRecord> {
-        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
-        summarise_text(
-            &conn,
-            self.models.as_ref(),
-            target_type,
-            target_id,
-            content,
+        let counter = counter_for(&tx, &conversation.provider_id, &conversation.model_id);
+        let tokens = counter.count(new_body) as i64;
+        tx.execute(
+            "UPDATE messages SET body = ?2, token_est = ?3 WHERE id = ?1",
+            params![message_id, new_body, tokens],
+        )?;
+        // Summaries covering the edited message or anything after it no
+        // longer describe the thread, so they are not resumed from.
+        tx.execute(
+            "UPDATE summaries SET covered_through = NULL, covered_message_id = NULL
+             WHERE target_type = 'conversation' AND target_id = ?1 AND covered_message_id IN (
+               SELECT id FROM messages WHERE conversation_id = ?1 AND (created_at > ?2 OR (created_at = ?2 AND rowid >= ?3)))",
+            params![conversation_id, created_at, rowid],
+        )?;
+        // Same ordering as `list_messages`. Usage of the removed messages is
+        // kept, detached from them, so the conversation's spend stays intact.
+        tx.execute(
+            "UPDATE usage SET message_id = NULL WHERE message_id IN (
+               SELECT id FROM messages WHERE conversation_id = ?1 AND (created_at > ?2 OR (created_at = ?2 AND rowid > ?3)))",
+            params![conversation_id, created_at, rowid],
+        )?;
+        let truncated = tx.execute(
+            "DELETE FROM messages WHERE conversation_id = ?1 AND (created_at > ?2 OR (created_at = ?2 AND rowid > ?3))",
+            params![conversation_id, created_at, rowid],
+        )?;
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        tx.execute(
+            "UPDATE conversations SET updated_at = ?2 WHERE id = ?1",
+            params![conversation_id, now],
+        )?;
+        log_event(
+            &tx,
+            "info",
+            Some("AI-EDIT"),
+            "ai.context",
+            "Conversation message edited",
             None,
-        )
-    }
+            Some(json!({
+                "conversation_id": conversation_id,
+                "message_id": message_id,
+                "token_est": tokens,
+                "truncated": truncated,
+            })),
+        )
+        .ok();
+
+        let total_tokens = sum_tokens(&tx, &conversation_id)?;
+        let (_, warn_threshold, force_threshold) = context_gauge(
+            &tx,
+            &conversation.provider_id,
+            &conversation.model_id,
+            &config,
+        )?;
+        if total_tokens < warn_threshold && conversation.ctx_warn {
+            tx.execute(
+                "UPDATE conversations SET ctx_warn = 0 WHERE id = ?1",
+                params![conversation_id],
+            )?;
+        } else if total_tokens >= warn_threshold && !conversation.ctx_warn {
+            raise_ctx_warn(&tx, &conversation_id, total_tokens, warn_threshold)?;
+        }
+        let mut new_conversation = None;
+        let rolled = total_tokens >= force_threshold;
+        if rolled {
+            if let Some(recent) = recent_rollovers(&tx, &conversation, &config)? {
+                drop(tx);
+                return Err(rollover_loop_error(&conn, &conversation_id, recent));
+            }
+            logging::commit(tx)?;
+            new_conversation = self
+                .roll_over_committed(&mut conn, &config, &conversation_id, None)?
+                .and_then(|outcome| outcome.new_conversation);
+            self.prune_after_rollover(&mut conn, &config, &conversation_id);
+        } else {
+            logging::commit(tx)?;
+        }
+        let conversation = fetch_conversation(&conn, &conversation_id)?
+            .ok_or_else(|| anyhow!("conversation missing after edit"))?;
+        let messages = list_messages(&conn, &conversation_id, None)?;
+        Ok(HistoryUpdate {
+            conversation,
+            messages,
+            rolled,
+            new_conversation,
+        })
+    }
+
+    /// Replace the conversation's last assistant reply with a new one.
+    ///
+    /// The reply is requested from the conversation's provider/model with
+    /// the history before it, and its system prompt and temperature applied.
+    /// The old reply is deleted in the transaction that stores the new one,
+    /// so it stays if the model call or the append fails. A history
+    /// that already ends with a user message, as after
+    /// [`edit_message`](Self::edit_message), simply gets a reply.
+    pub async fn regenerate_last(&self, conversation_id: &str) -> Result<HistoryUpdate> {
+        let pool = self.pool.clone();
+        let id = conversation_id.to_string();
+        let (conversation, mut messages) = spawn_blocking(move || {
+            let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+            let conversation =
+                fetch_conversation(&conn, &id)?.ok_or(InkOsError::ConversationNotFound)?;
+            let messages = list_messages(&conn, &id, None)?;
+            Ok::<_, anyhow::Error>((conversation, messages))
+        })
+        .await??;
+        if conversation.ctx_force {
+            return Err(InkOsError::ConversationRolled.into());
+        }
+        let replaced = if messages
+            .last()
+            .is_some_and(|message| message.role == "assistant")
+        {
+            messages.pop().map(|message| message.id)
+        } else {
+            None
+        };
+        if messages.last().is_none_or(|message| message.role != "user") {
+            return Err(InkOsError::ValidationFailed(
+                "conversation has no user message to reply to".into(),
+            )
+            .into());
+        }
+
+        let mut input = AiChatInput {
+            messages: messages
+                .iter()
+                .map(|message| AiChatMessage {
+                    role: message.role.clone(),
+                    content: message.body.clone(),
+                    images: Vec::new(),
+                })
+                .collect(),
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        };
+        conversation.apply_chat_defaults(&mut input);
+        let response = self
+            .models
+            .chat(
+                input,
+                Some(conversation.provider_id.clone()),
+                Some(conversation.model_id.clone()),
+                false,
+                false,
+            )
+            .await?;
+
+        let summarizer = self.clone();
+        let id = conversation_id.to_string();
+        spawn_blocking(move || {
+            let conn = summarizer
+                .pool
+                .get()
+                .map_err(|err| anyhow!(err.to_string()))?;
+            let appended = summarizer.append_message(
+                &id,
+                "assistant",
+                &response.content,
+                response.usage.as_ref(),
+                Some((&response.provider_id, &response.model)),
+                replaced.as_deref(),
+            )?;
+            log_event(
+                &conn,
+                "info",
+                Some("AI-REGEN"),
+                "ai.context",
+                "Assistant reply regenerated",
+                None,
+                Some(json!({
+                    "conversation_id": id,
+                    "replaced_message_id": replaced,
+                    "message_id": appended.message.id,
+                    "provider": response.provider_id,
+                    "model": response.model,
+                    "request_id": response.request_id,
+                })),
+            )
+            .ok();
+            let conversation = fetch_conversation(&conn, &id)?
+                .ok_or_else(|| anyhow!("conversation missing after regenerate"))?;
+            let messages = list_messages(&conn, &id, None)?;
+            Ok(HistoryUpdate {
+                conversation,
+                messages,
+                rolled: appended.rolled,
+                new_conversation: appended.new_conversation,
+            })
+        })
+        .await?
+    }
+
+    /// Force a rollover for the supplied conversation.
+    ///
+    /// A conversation that has already rolled over fails with
+    /// [`InkOsError::ConversationRolled`].
+    pub fn rollover(&self, conversation_id: &str) -> Result<RolloverOutcome> {
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let config = read_config(&conn)?;
+        let conversation =
+            fetch_conversation(&conn, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        if let Some(recent) = recent_rollovers(&conn, &conversation, &config)? {
+            return Err(rollover_loop_error(&conn, conversation_id, recent));
+        }
+        let outcome = self
+            .roll_over_committed(&mut conn, &config, conversation_id, None)?
+            .ok_or(InkOsError::ConversationRolled)?;
+        self.prune_after_rollover(&mut conn, &config, conversation_id);
+        Ok(outcome)
+    }
+
+    /// Roll over a conversation whose latest changes are already committed.
+    ///
+    /// The summary is generated first, without holding the write lock, so a
+    /// slow model never blocks other writers. The rollover itself then runs
+    /// in a short transaction that first checks the conversation is still
+    /// the one summarised; if it changed meanwhile, it is summarised again.
+    /// Returns `None` when another caller rolled it over first.
+    fn roll_over_committed(
+        &self,
+        conn: &mut rusqlite::Connection,
+        config: &SummarizerConfig,
+        conversation_id: &str,
+        pending_message: Option<(&str, &str)>,
+    ) -> Result<Option<RolloverOutcome>> {
+        for _ in 0..ROLLOVER_ATTEMPTS {
+            let conversation = fetch_conversation(conn, conversation_id)?
+                .ok_or(InkOsError::ConversationNotFound)?;
+            if conversation.ctx_force {
+                return Ok(None);
+            }
+            let stamp = source_stamp(conn, "conversation", conversation_id)?;
+            let summary =
+                summarise_for_rollover(conn, &conversation, &self.models, config, pending_message)?;
+
+            let mut tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let current = fetch_conversation(&tx, conversation_id)?
+                .ok_or(InkOsError::ConversationNotFound)?;
+            if current.ctx_force {
+                return Ok(None);
+            }
+            if source_stamp(&tx, "conversation", conversation_id)? != stamp {
+                continue;
+            }
+            let outcome =
+                perform_rollover(&mut tx, &current, self.models.as_ref(), config, summary)?;
+            logging::commit(tx)?;
+            return Ok(Some(outcome));
+        }
+        Err(anyhow!(
+            "conversation '{conversation_id}' kept changing while it was being rolled over"
+        ))
+    }
+
+    /// Delete a rolled-over conversation's messages except its retained
+    /// tail, after summarising them into the record kept in their place.
+    /// Returns how many messages were removed.
+    pub fn prune_conversation(&self, conversation_id: &str) -> Result<usize> {
+        let mut conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let config = read_config(&conn)?;
+        let conversation =
+            fetch_conversation(&conn, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        if !conversation.ctx_force {
+            return Err(InkOsError::ValidationFailed(
+                "only rolled-over conversations can be pruned".into(),
+            )
+            .into());
+        }
+        prune_messages(&mut conn, &self.models, &config, conversation_id)
+    }
+
+    /// Prune a conversation that was just rolled over, when configured to.
+    ///
+    /// Runs after the rollover commits. Pruning is best-effort: a failure
+    /// keeps the messages and never undoes the rollover.
+    fn prune_after_rollover(
+        &self,
+        conn: &mut rusqlite::Connection,
+        config: &SummarizerConfig,
+        conversation_id: &str,
+    ) {
+        if !config.prune_after_rollover {
+            return;
+        }
+        if let Err(err) = prune_messages(conn, &self.models, config, conversation_id) {
+            log_event(
+                conn,
+                "warn",
+                Some("AI-CTX-PRUNE"),
+                "ai.context",
+                "Rolled-over conversation could not be pruned",
+                Some("The messages were kept; prune_conversation can retry."),
+                Some(json!({
+                    "conversation_id": conversation_id,
+                    "error": err.to_string(),
+                })),
+            )
+            .ok();
+        }
+    }
+
+    /// Summarise a conversation the way [`rollover`](Self::rollover) would,
+    /// without closing it, creating its successor, or linking the two.
+    pub fn preview_rollover(&self, conversation_id: &str) -> Result<RolloverPreview> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let config = read_config(&conn)?;
+        let conversation =
+            fetch_conversation(&conn, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+        let messages = list_messages(&conn, conversation_id, None)?;
+        let tail = rollover_tail_len(&conn, &self.models, &config, &messages, None);
+        let mut excerpts = select_conversation_excerpts(&messages, None, tail);
+        let excerpt_count = excerpts.len();
+        let summary = store_or_create_summary(
+            &conn,
+            &self.models,
+            "conversation",
+            conversation_id,
+            &mut excerpts,
+            &[],
+            &config,
+        )?;
+        let counter = counter_for(&conn, &conversation.provider_id, &conversation.model_id);
+        let seed_tokens = counter.count(&format!("{SEED_PREFIX}{}", summary.body)) as i64;
+        let current_tokens = sum_tokens(&conn, conversation_id)?;
+        Ok(RolloverPreview {
+            conversation_id: conversation_id.to_string(),
+            summary,
+            excerpt_count,
+            current_tokens,
+            seed_tokens,
+            token_delta: current_tokens - seed_tokens,
+        })
+    }
+
+    /// Summarise arbitrary source material.
+    pub async fn summarise(
+        &self,
+        target_type: &str,
+        target_id: &str,
+        content: &str,
+    ) -> Result<SummaryRecord> {
+        self.coalesced_summary(target_type, target_id, vec![content.to_string()])
+            .await
+    }
+
+    /// Summarise text that is not stored anywhere, such as a pasted
+    /// selection.
+    ///
+    /// Without a target the summary is cached under type `text` and an id
+    /// derived from the content, so the same text is only summarised once.
+    pub async fn summarise_text(
+        &self,
+        content: &str,
+        target_type: Option<String>,
+        target_id: Option<String>,
+    ) -> Result<SummaryRecord> {
+        if content.trim().is_empty() {
+            return Err(InkOsError::ValidationFailed("content must not be empty".into()).into());
+        }
+        let target_type = target_type
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| TEXT_TARGET.to_string());
+        let target_id = target_id
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| format!("text-{}", &hash_strings(&[content.to_string()])[..16]));
+        self.summarise(&target_type, &target_id, content).await
+    }
+
+    /// Run [`store_or_create_summary`] once per `(target, source hash)`.
+    ///
+    /// Callers arriving while the same summary is being computed await that
+    /// computation instead of starting another one, and receive its record
+    /// with `reused` set.
+    async fn coalesced_summary(
+        &self,
+        target_type: &str,
+        target_id: &str,
+        excerpts: Vec<String>,
+    ) -> Result<SummaryRecord> {
+        let key = (
+            target_type.to_string(),
+            target_id.to_string(),
+            hash_strings(&excerpts),
+        );
+        let mut in_flight = self.in_flight.lock().await;
+        if let Some(pending) = in_flight.get(&key).cloned() {
+            drop(in_flight);
+            let mut summary = pending.await.map_err(unshare_error)?;
+            summary.reused = true;
+            return Ok(summary);
+        }
+
+        let pool = self.pool.clone();
+        let models = Arc::clone(&self.models);
+        let registry = Arc::clone(&self.in_flight);
+        let task_key = key.clone();
+        // The entry is removed by the computation itself, so it is cleared
+        // even if the first caller stops waiting.
+        let pending = async move {
+            let (target_type, target_id, _) = task_key.clone();
+            let result = spawn_blocking(move || {
+                let conn = pool.get().map_err(|err| anyhow!(err.to_string()))?;
+                let config = read_config(&conn)?;
+                let mut excerpts = excerpts;
+                let images = if target_type == "note" {
+                    note_attachment_context(&conn, &target_id, &mut excerpts)?
+                } else {
+                    Vec::new()
+                };
+                store_or_create_summary(
+                    &conn,
+                    models.as_ref(),
+                    &target_type,
+                    &target_id,
+                    &mut excerpts,
+                    &images,
+                    &config,
+                )
+            })
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result)
+            .map_err(Arc::new);
+            registry.lock().await.remove(&task_key);
+            result
+        }
+        .boxed()
+        .shared();
+        in_flight.insert(key, pending.clone());
+        drop(in_flight);
+        pending.await.map_err(unshare_error)
+    }
 
     /// Summarise a daily digest payload with caching.
     pub fn summarise_daily_digest(
@@ -351,101 +1671,172 @@ impl Summarizer {
             Some(facts),
         )
     }
-}
 
-/// Estimate tokens using a simple character based heuristic.
-pub fn approx_tokens(text: &str) -> usize {
-    let chars = text.chars().count() as f32;
-    let words = text.split_whitespace().count() as f32;
-    let char_est = (chars / 4.0).ceil();
-    let word_est = (words * 1.1).ceil();
-    char_est.max(word_est).max(1.0) as usize
+    /// Summarise an arbitrary date range, keyed `start..end`, with caching.
+    pub fn summarise_range(
+        &self,
+        range_key: &str,
+        facts: serde_json::Value,
+        fallback: &str,
+    ) -> Result<SummaryRecord> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        let mut config = read_config(&conn)?;
+        config.focus = Some(RANGE_PROMPT);
+        let mut excerpts = vec![fallback.to_string(), facts.to_string()];
+        store_or_create_summary(
+            &conn,
+            self.models.as_ref(),
+            RANGE_TARGET,
+            range_key,
+            &mut excerpts,
+            &[],
+            &config,
+        )
+    }
+
+    /// Summarise a weekly or monthly rollup of logbook entries with caching.
+    pub fn summarise_rollup(
+        &self,
+        target_type: &str,
+        period_start: &str,
+        facts: serde_json::Value,
+        fallback: &str,
+    ) -> Result<SummaryRecord> {
+        let conn = self.pool.get().map_err(|err| anyhow!(err.to_string()))?;
+        summarise_text(
+            &conn,
+            self.models.as_ref(),
+            target_type,
+            period_start,
+            fallback,
+            Some(facts),
+        )
+    }
 }
 
 fn read_config(conn: &rusqlite::Connection) -> Result<SummarizerConfig> {
-    let warn_ratio = read_setting(conn, "ai.rollover.warn_ratio")?.unwrap_or(0.75);
-    let force_ratio = read_setting(conn, "ai.rollover.force_ratio")?.unwrap_or(0.9);
-    let summarizer_model = read_string_setting(conn, "ai.summarizer_model")?;
+    let mut warn_ratio =
+        read_setting(conn, "ai.rollover.warn_ratio")?.unwrap_or(DEFAULT_WARN_RATIO);
+    let mut force_ratio =
+        read_setting(conn, "ai.rollover.force_ratio")?.unwrap_or(DEFAULT_FORCE_RATIO);
+    // Ratios stored before validation existed may be unusable.
+    if validate_ratios(warn_ratio, force_ratio).is_err() {
+        (warn_ratio, force_ratio) = (DEFAULT_WARN_RATIO, DEFAULT_FORCE_RATIO);
+    }
+    // The seeded value is an empty string, meaning "use the active model".
+    let summarizer_model =
+        read_string_setting(conn, "ai.summarizer_model")?.filter(|model| !model.is_empty());
+    let max_rollovers_per_window = read_setting(conn, "ai.rollover.max_per_window")?
+        .map(|v| v.max(1.0) as u32)
+        .unwrap_or(DEFAULT_MAX_ROLLOVERS_PER_WINDOW);
+    let rollover_tail = read_setting(conn, "ai.rollover.tail")?
+        .map(|v| v.max(1.0) as usize)
+        .unwrap_or(DEFAULT_ROLLOVER_TAIL);
+    let rollover_tail_auto =
+        read_string_setting(conn, "ai.rollover.tail_mode")?.as_deref() == Some("auto");
+    let prune_after_rollover =
+        read_string_setting(conn, PRUNE_AFTER_ROLLOVER_KEY)?.is_some_and(|v| v == "on");
     Ok(SummarizerConfig {
         warn_ratio,
         force_ratio,
         summarizer_model,
+        max_rollovers_per_window,
+        rollover_tail,
+        rollover_tail_auto,
+        prune_after_rollover,
+        focus: None,
     })
 }
 
+/// Reject rollover ratios that would stop the warning or rollover from firing.
+fn validate_ratios(warn_ratio: f32, force_ratio: f32) -> Result<()> {
+    for (name, ratio) in [("warn_ratio", warn_ratio), ("force_ratio", force_ratio)] {
+        if !(ratio > 0.0 && ratio <= 1.0) {
+            return Err(InkOsError::ValidationFailed(format!(
+                "{name} must be greater than 0 and at most 1, got {ratio}"
+            ))
+            .into());
+        }
+    }
+    if warn_ratio >= force_ratio {
+        return Err(InkOsError::ValidationFailed(format!(
+            "warn_ratio ({warn_ratio}) must be below force_ratio ({force_ratio})"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
 fn write_config(
     conn: &rusqlite::Connection,
     warn_ratio: f32,
     force_ratio: f32,
     summarizer_model: Option<String>,
+    rollover_tail: usize,
+    rollover_tail_auto: bool,
 ) -> Result<()> {
-    let now = OffsetDateTime::now_utc().unix_timestamp();
-    upsert_setting(conn, "ai.rollover.warn_ratio", warn_ratio.to_string(), now)?;
-    upsert_setting(
+    validate_ratios(warn_ratio, force_ratio)?;
+    settings::upsert(conn, "ai.rollover.warn_ratio", &warn_ratio.to_string())?;
+    settings::upsert(conn, "ai.rollover.force_ratio", &force_ratio.to_string())?;
+    let summarizer_value = serde_json::to_string(&summarizer_model)?;
+    settings::upsert(conn, "ai.summarizer_model", &summarizer_value)?;
+    settings::upsert(conn, "ai.rollover.tail", &rollover_tail.to_string())?;
+    let tail_mode = if rollover_tail_auto { "auto" } else { "fixed" };
+    settings::upsert(
         conn,
-        "ai.rollover.force_ratio",
-        force_ratio.to_string(),
-        now,
+        "ai.rollover.tail_mode",
+        &serde_json::to_string(tail_mode)?,
     )?;
-    let summarizer_value = serde_json::to_string(&summarizer_model)?;
-    upsert_setting(conn, "ai.summarizer_model", summarizer_value, now)?;
     Ok(())
 }
 
 fn read_setting(conn: &rusqlite::Connection, key: &str) -> Result<Option<f32>> {
-    let value: Option<String> = conn
-        .query_row(
-            "SELECT value FROM app_settings WHERE key = ?1",
-            params![key],
-            |row| row.get(0),
-        )
-        .optional()?;
-    if let Some(value) = value {
-        Ok(value.parse::<f32>().ok())
-    } else {
-        Ok(None)
-    }
+    Ok(settings::read(conn, key)?.and_then(|value| value.parse::<f32>().ok()))
 }
 
 fn read_string_setting(conn: &rusqlite::Connection, key: &str) -> Result<Option<String>> {
-    let value: Option<String> = conn
-        .query_row(
-            "SELECT value FROM app_settings WHERE key = ?1",
-            params![key],
-            |row| row.get(0),
-        )
-        .optional()?;
-    Ok(value.and_then(|v| serde_json::from_str(&v).ok()))
-}
-
-fn upsert_setting(conn: &rusqlite::Connection, key: &str, value: String, now: i64) -> Result<()> {
-    conn.execute(
-        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
-         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
-        params![key, value, now],
-    )?;
-    Ok(())
+    Ok(settings::read(conn, key)?.and_then(|v| serde_json::from_str(&v).ok()))
 }
 
 fn list_conversations(
     conn: &rusqlite::Connection,
     limit: Option<usize>,
-) -> Result<Vec<ConversationRecord>> {
-    let mut sql = "SELECT id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at, closed_at, quality_flags FROM conversations ORDER BY updated_at DESC".to_string();
-    if limit.is_some() {
-        sql.push_str(" LIMIT ?1");
-    }
-    let mut stmt = conn.prepare(&sql)?;
-    let rows = if let Some(limit) = limit {
-        stmt.query_map(params![limit as i64], |row| row_to_conversation(conn, row))?
-    } else {
-        stmt.query_map([], |row| row_to_conversation(conn, row))?
-    };
+    before: Option<Cursor<PinnedKey>>,
+    include_closed: bool,
+    pinned_only: bool,
+) -> Result<Page<ConversationRecord, PinnedKey>> {
+    let (before_key, before_id) = cursor_params(&before);
+    let (before_pinned, before_updated) =
+        before_key.map(|key| (key.pinned, key.updated_at)).unzip();
+    let mut stmt = conn.prepare(
+        "SELECT id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at, closed_at, quality_flags, pinned, system_prompt, default_temperature FROM conversations
+         WHERE (?1 IS NULL OR (pinned, updated_at, id) < (?6, ?1, ?2))
+           AND (?4 OR closed_at IS NULL)
+           AND (NOT ?5 OR pinned = 1)
+         ORDER BY pinned DESC, updated_at DESC, id DESC LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            before_updated,
+            before_id,
+            fetch_limit(limit),
+            include_closed,
+            pinned_only,
+            before_pinned
+        ],
+        |row| row_to_conversation(conn, row),
+    )?;
     let mut conversations = Vec::new();
     for row in rows {
         conversations.push(row?);
     }
-    Ok(conversations)
+    Ok(Page::from_rows(conversations, limit, |c| Cursor {
+        key: PinnedKey {
+            pinned: c.pinned,
+            updated_at: c.updated_at,
+        },
+        id: c.id.clone(),
+    }))
 }
 
 fn row_to_conversation(
@@ -453,12 +1844,24 @@ fn row_to_conversation(
     row: &rusqlite::Row<'_>,
 ) -> rusqlite::Result<ConversationRecord> {
     let id: String = row.get(0)?;
+    let provider_id: String = row.get(2)?;
+    let model_id: String = row.get(3)?;
     let total_tokens = sum_tokens(conn, &id).unwrap_or(0);
+    let (context_limit, warn_threshold, force_threshold) = read_config(conn)
+        .and_then(|config| context_gauge(conn, &provider_id, &model_id, &config))
+        .unwrap_or_else(|_| {
+            let limit = DEFAULT_CONTEXT_LIMIT as f32;
+            (
+                DEFAULT_CONTEXT_LIMIT,
+                (limit * DEFAULT_WARN_RATIO) as i64,
+                (limit * DEFAULT_FORCE_RATIO) as i64,
+            )
+        });
     Ok(ConversationRecord {
         id,
         title: row.get(1)?,
-        provider_id: row.get(2)?,
-        model_id: row.get(3)?,
+        provider_id,
+        model_id,
         ctx_warn: row.get::<_, i64>(4)? != 0,
         ctx_force: row.get::<_, i64>(5)? != 0,
         created_at: row.get(6)?,
@@ -466,6 +1869,12 @@ fn row_to_conversation(
         closed_at: row.get(8)?,
         quality_flags: row.get(9)?,
         total_tokens,
+        context_limit,
+        warn_threshold,
+        force_threshold,
+        pinned: row.get::<_, i64>(10)? != 0,
+        system_prompt: row.get(11)?,
+        default_temperature: row.get::<_, Option<f64>>(12)?.map(|t| t as f32),
     })
 }
 
@@ -474,7 +1883,7 @@ fn fetch_conversation(
     conversation_id: &str,
 ) -> Result<Option<ConversationRecord>> {
     let mut stmt = conn.prepare(
-        "SELECT id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at, closed_at, quality_flags FROM conversations WHERE id = ?1",
+        "SELECT id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at, closed_at, quality_flags, pinned, system_prompt, default_temperature FROM conversations WHERE id = ?1",
     )?;
     let row = stmt
         .query_row([conversation_id], |row| row_to_conversation(conn, row))
@@ -482,12 +1891,117 @@ fn fetch_conversation(
     Ok(row)
 }
 
+fn search_messages(
+    conn: &rusqlite::Connection,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<ConversationMatches>> {
+    let terms = crate::db::fts_phrase_query(query, " ");
+    if terms.is_empty() {
+        return Err(InkOsError::ValidationFailed("query must not be empty".into()).into());
+    }
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.conversation_id, m.role, snippet(fts_messages, 0, '**', '**', '…', 16), m.created_at, c.title
+         FROM fts_messages
+         JOIN messages m ON m.rowid = fts_messages.rowid
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE fts_messages MATCH ?1
+         ORDER BY bm25(fts_messages) LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![terms, limit as i64], |row| {
+        Ok((
+            MessageSearchHit {
+                message_id: row.get(0)?,
+                conversation_id: row.get(1)?,
+                role: row.get(2)?,
+                snippet: row.get(3)?,
+                created_at: row.get(4)?,
+            },
+            row.get::<_, Option<String>>(5)?,
+        ))
+    })?;
+    let mut groups: Vec<ConversationMatches> = Vec::new();
+    for row in rows {
+        let (hit, title) = row?;
+        match groups
+            .iter_mut()
+            .find(|group| group.conversation_id == hit.conversation_id)
+        {
+            Some(group) => {
+                group.match_count += 1;
+                group.messages.push(hit);
+            }
+            None => groups.push(ConversationMatches {
+                conversation_id: hit.conversation_id.clone(),
+                title,
+                match_count: 1,
+                messages: vec![hit],
+            }),
+        }
+    }
+    Ok(groups)
+}
+
+/// Normalise per-conversation chat defaults: blank prompts are dropped and
+/// temperatures must fall within `0.0..=2.0`.
+fn validate_chat_defaults(
+    system_prompt: Option<String>,
+    default_temperature: Option<f32>,
+) -> Result<(Option<String>, Option<f32>)> {
+    if let Some(temperature) = default_temperature {
+        if !(0.0..=2.0).contains(&temperature) {
+            return Err(InkOsError::ValidationFailed(
+                "default_temperature must be between 0 and 2".into(),
+            )
+            .into());
+        }
+    }
+    let system_prompt = system_prompt
+        .map(|prompt| prompt.trim().to_string())
+        .filter(|prompt| !prompt.is_empty());
+    Ok((system_prompt, default_temperature))
+}
+
+fn store_title(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+    title: &str,
+) -> Result<ConversationRecord> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let updated = conn.execute(
+        "UPDATE conversations SET title = ?2, updated_at = ?3 WHERE id = ?1",
+        params![conversation_id, title, now],
+    )?;
+    if updated == 0 {
+        return Err(InkOsError::ConversationNotFound.into());
+    }
+    fetch_conversation(conn, conversation_id)?
+        .ok_or_else(|| InkOsError::ConversationNotFound.into())
+}
+
+/// Reduce a model reply to a one-line title of at most
+/// [`AUTO_TITLE_MAX_WORDS`] words.
+fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.trim_start_matches(['#', '*']).trim();
+    let line = line.strip_prefix("Title:").unwrap_or(line);
+    let title = line
+        .split_whitespace()
+        .take(AUTO_TITLE_MAX_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let title = title
+        .trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | '*' | '.' | ':' | '!'))
+        .trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
 fn list_messages(
     conn: &rusqlite::Connection,
     conversation_id: &str,
     limit: Option<usize>,
 ) -> Result<Vec<MessageRecord>> {
-    let mut sql = "SELECT id, conversation_id, role, body, token_est, quality_flags, created_at FROM messages WHERE conversation_id = ?1 ORDER BY created_at ASC".to_string();
+    let mut sql = "SELECT id, conversation_id, role, body, token_est, quality_flags, created_at FROM messages WHERE conversation_id = ?1 ORDER BY created_at ASC, rowid ASC".to_string();
     if let Some(limit) = limit {
         sql.push_str(" LIMIT ");
         sql.push_str(&limit.to_string());
@@ -513,12 +2027,13 @@ fn list_messages(
 
 fn insert_message(
     conn: &rusqlite::Connection,
+    counter: &dyn TokenCounter,
     conversation_id: &str,
     role: &str,
     body: &str,
 ) -> Result<MessageRecord> {
     let id = Uuid::new_v4().to_string();
-    let tokens = approx_tokens(body) as i64;
+    let tokens = counter.count(body) as i64;
     let created_at = OffsetDateTime::now_utc().unix_timestamp();
     conn.execute(
         "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
@@ -548,77 +2063,308 @@ fn sum_tokens(conn: &rusqlite::Connection, conversation_id: &str) -> Result<i64>
     Ok(total)
 }
 
-fn mark_ctx_warn(conn: &rusqlite::Connection, conversation_id: &str) -> Result<()> {
-    conn.execute(
-        "UPDATE conversations SET ctx_warn = 1 WHERE id = ?1",
-        params![conversation_id],
-    )?;
-    Ok(())
+/// Attach usage to an assistant message, preferring real provider counts.
+fn record_message_usage(
+    conn: &rusqlite::Connection,
+    conversation: &ConversationRecord,
+    message: &MessageRecord,
+    usage: Option<&AiUsageMetrics>,
+    responder: Option<(&str, &str)>,
+    prior_tokens: i64,
+) -> Result<()> {
+    let completion_est = message.token_est.unwrap_or(0);
+    // Without a known responder, the row `ai_chat` left for this reply names
+    // the provider that answered, which may be a fallback.
+    let pending: Option<String> = match responder {
+        Some(_) => None,
+        None => conn
+            .query_row(
+                "SELECT id FROM usage WHERE conversation_id = ?1 AND message_id IS NULL
+                 ORDER BY created_at DESC LIMIT 1",
+                params![conversation.id],
+                |row| row.get(0),
+            )
+            .optional()?,
+    };
+    let (provider_id, model_id) =
+        responder.unwrap_or((&conversation.provider_id, &conversation.model_id));
+    if let Some(usage) = usage {
+        let prompt = usage
+            .prompt_tokens
+            .map(|v| v as i64)
+            .unwrap_or(prior_tokens);
+        let completion = usage
+            .completion_tokens
+            .map(|v| v as i64)
+            .unwrap_or(completion_est);
+        let total = usage
+            .total_tokens
+            .map(|v| v as i64)
+            .unwrap_or(prompt + completion);
+        if let Some(pending) = pending {
+            conn.execute(
+                "UPDATE usage SET message_id = ?2, prompt_tokens = ?3, completion_tokens = ?4, total_tokens = ?5, estimated = 0 WHERE id = ?1",
+                params![pending, message.id, prompt, completion, total],
+            )?;
+            return Ok(());
+        }
+        return insert_usage(
+            conn,
+            Some(&message.id),
+            Some(&conversation.id),
+            provider_id,
+            model_id,
+            (prompt, completion, total),
+            false,
+        );
+    }
+
+    if let Some(pending) = pending {
+        conn.execute(
+            "UPDATE usage SET message_id = ?2 WHERE id = ?1",
+            params![pending, message.id],
+        )?;
+        return Ok(());
+    }
+
+    insert_usage(
+        conn,
+        Some(&message.id),
+        Some(&conversation.id),
+        provider_id,
+        model_id,
+        (prior_tokens, completion_est, prior_tokens + completion_est),
+        true,
+    )
 }
 
-fn mark_ctx_force(conn: &rusqlite::Connection, conversation_id: &str) -> Result<()> {
+fn insert_usage(
+    conn: &rusqlite::Connection,
+    message_id: Option<&str>,
+    conversation_id: Option<&str>,
+    provider_id: &str,
+    model_id: &str,
+    (prompt, completion, total): (i64, i64, i64),
+    estimated: bool,
+) -> Result<()> {
+    let id = Uuid::new_v4().to_string();
     let now = OffsetDateTime::now_utc().unix_timestamp();
     conn.execute(
-        "UPDATE conversations SET ctx_force = 1, closed_at = ?2 WHERE id = ?1",
-        params![conversation_id, now],
+        "INSERT INTO usage (id, message_id, conversation_id, provider_id, model_id, prompt_tokens, completion_tokens, total_tokens, estimated, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            id,
+            message_id,
+            conversation_id,
+            provider_id,
+            model_id,
+            prompt,
+            completion,
+            total,
+            estimated as i64,
+            now,
+        ],
     )?;
     Ok(())
 }
 
-fn context_limit_from_tags(
+fn conversation_usage(
     conn: &rusqlite::Connection,
-    provider_id: &str,
-    model_id: &str,
-) -> Result<usize> {
-    let providers = crate::agents::config::list_providers(conn)?;
-    if let Some(provider) = providers.into_iter().find(|p| p.id == provider_id) {
-        for tag in provider.capability_tags {
-            if let Some(limit) = parse_context_tag(&tag) {
-                return Ok(limit);
-            }
+    conversation_id: &str,
+) -> Result<ConversationUsage> {
+    let mut stmt = conn.prepare(
+        "SELECT provider_id, model_id, SUM(prompt_tokens), SUM(completion_tokens), SUM(total_tokens), COUNT(*), SUM(estimated)
+         FROM usage WHERE conversation_id = ?1 GROUP BY provider_id, model_id",
+    )?;
+    let rows = stmt.query_map([conversation_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+            row.get::<_, i64>(5)?,
+            row.get::<_, i64>(6)?,
+        ))
+    })?;
+    let local_providers: HashSet<String> = crate::agents::config::list_providers(conn)?
+        .into_iter()
+        .filter(|p| p.kind == "local")
+        .map(|p| p.id)
+        .collect();
+
+    let mut usage = ConversationUsage {
+        conversation_id: conversation_id.to_string(),
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        total_tokens: 0,
+        recorded_calls: 0,
+        estimated_calls: 0,
+        estimated_cost_usd: 0.0,
+        unpriced_tokens: 0,
+    };
+    for row in rows {
+        let (provider_id, model_id, prompt, completion, total, calls, estimated) = row?;
+        usage.prompt_tokens += prompt;
+        usage.completion_tokens += completion;
+        usage.total_tokens += total;
+        usage.recorded_calls += calls;
+        usage.estimated_calls += estimated;
+        if local_providers.contains(&provider_id) {
+            continue;
+        }
+        match estimate_cost(&model_id, prompt, completion) {
+            Some(cost) => usage.estimated_cost_usd += cost,
+            None => usage.unpriced_tokens += total,
         }
     }
-    if model_id.to_lowercase().contains("32k") {
-        return Ok(32_000);
-    }
-    Ok(4096)
+    Ok(usage)
+}
+
+/// Estimate spend in USD for the given token counts using the price table.
+pub(crate) fn estimate_cost(
+    model_id: &str,
+    prompt_tokens: i64,
+    completion_tokens: i64,
+) -> Option<f64> {
+    let price = price_for_model(model_id)?;
+    Some(
+        (prompt_tokens as f64 * price.input_per_mtok
+            + completion_tokens as f64 * price.output_per_mtok)
+            / 1_000_000.0,
+    )
+}
+
+/// Set `ctx_warn` once the token total reaches the warn threshold.
+fn raise_ctx_warn(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+    total_tokens: i64,
+    warn_threshold: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE conversations SET ctx_warn = 1 WHERE id = ?1",
+        params![conversation_id],
+    )?;
+    log_event(
+        conn,
+        "warn",
+        Some("AI-CTX-WARN"),
+        "ai.context",
+        "Conversation approaching context limit",
+        Some("A warning banner should be shown in the UI."),
+        Some(json!({
+            "conversation_id": conversation_id,
+            "total_tokens": total_tokens,
+            "threshold": warn_threshold,
+        })),
+    )
+    .ok();
+    Ok(())
+}
+
+fn mark_ctx_force(conn: &rusqlite::Connection, conversation_id: &str) -> Result<()> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    conn.execute(
+        "UPDATE conversations SET ctx_force = 1, closed_at = ?2 WHERE id = ?1",
+        params![conversation_id, now],
+    )?;
+    Ok(())
+}
+
+/// Token counts at which a conversation warns and rolls over.
+///
+/// Both are at least one token, and a zero `context_limit` is treated as
+/// [`DEFAULT_CONTEXT_LIMIT`], so a misconfigured model cannot roll over on
+/// its first message.
+fn rollover_thresholds(context_limit: usize, config: &SummarizerConfig) -> (i64, i64) {
+    let limit = if context_limit == 0 {
+        DEFAULT_CONTEXT_LIMIT
+    } else {
+        context_limit
+    } as f32;
+    let threshold = |ratio: f32| ((limit * ratio) as i64).max(1);
+    (threshold(config.warn_ratio), threshold(config.force_ratio))
+}
+
+/// Context window of a conversation's model with its warn and force
+/// thresholds, as reported to the UI and enforced on append.
+fn context_gauge(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    model_id: &str,
+    config: &SummarizerConfig,
+) -> Result<(usize, i64, i64)> {
+    let context_limit = context_limit_from_tags(conn, provider_id, model_id)?;
+    let (warn_threshold, force_threshold) = rollover_thresholds(context_limit, config);
+    Ok((context_limit, warn_threshold, force_threshold))
 }
 
-fn parse_context_tag(tag: &str) -> Option<usize> {
-    if let Some(rest) = tag.strip_prefix("ctx-") {
-        if rest.ends_with('k') {
-            let digits: String = rest[..rest.len() - 1]
-                .chars()
-                .filter(|c| c.is_ascii_digit())
-                .collect();
-            if let Ok(value) = digits.parse::<usize>() {
-                return Some(value * 1000);
+/// Resolve a model's context window: stored override, provider metadata for
+/// the default model, `ctx-*` capability tags, then a name heuristic.
+/// Zero-sized windows from overrides, provider defaults, or tags are skipped
+/// as misconfigured.
+fn context_limit_from_tags(
+    conn: &rusqlite::Connection,
+    provider_id: &str,
+    model_id: &str,
+) -> Result<usize> {
+    if let Some(window) = crate::agents::config::model_context_window(conn, provider_id, model_id)?
+        .filter(|window| *window > 0)
+    {
+        return Ok(window);
+    }
+    let providers = crate::agents::config::list_providers(conn)?;
+    if let Some(provider) = providers.into_iter().find(|p| p.id == provider_id) {
+        if provider.default_model.as_deref() == Some(model_id) {
+            if let Some(window) = provider.context_window.filter(|window| *window > 0) {
+                return Ok(window);
             }
-        } else if let Ok(value) = rest.parse::<usize>() {
-            return Some(value);
         }
+        let tagged = Capabilities::from_tags(&provider.capability_tags).context_window;
+        if tagged > 0 {
+            return Ok(tagged);
+        }
+    }
+    if model_id.to_lowercase().contains("32k") {
+        return Ok(32_000);
     }
-    None
+    Ok(DEFAULT_CONTEXT_LIMIT)
 }
 
-fn perform_rollover(
-    conn: &mut rusqlite::Transaction<'_>,
+/// Summary seeding a conversation's successor, as a rollover would write it.
+///
+/// This may call the model several times, so it runs before any write
+/// transaction opens; [`perform_rollover`] then only stores the result.
+fn summarise_for_rollover(
+    conn: &rusqlite::Connection,
     conversation: &ConversationRecord,
     models: &ModelManager,
     config: &SummarizerConfig,
     pending_message: Option<(&str, &str)>,
-) -> Result<RolloverOutcome> {
-    mark_ctx_force(conn, &conversation.id)?;
+) -> Result<SummaryRecord> {
     let messages = list_messages(conn, &conversation.id, None)?;
-    let mut excerpts = select_conversation_excerpts(&messages, pending_message);
-    let summary = store_or_create_summary(
+    let tail = rollover_tail_len(conn, models, config, &messages, pending_message);
+    let mut excerpts = select_conversation_excerpts(&messages, pending_message, tail);
+    store_or_create_summary(
         conn,
         models,
         "conversation",
         &conversation.id,
         &mut excerpts,
+        &[],
         config,
-    )?;
+    )
+}
+
+/// Close `conversation` and open its successor seeded with `summary`.
+fn perform_rollover(
+    conn: &mut rusqlite::Transaction<'_>,
+    conversation: &ConversationRecord,
+    models: &ModelManager,
+    config: &SummarizerConfig,
+    summary: SummaryRecord,
+) -> Result<RolloverOutcome> {
+    mark_ctx_force(conn, &conversation.id)?;
 
     let selection = models.resolve_runtime(
         Some(conversation.provider_id.clone()),
@@ -627,24 +2373,55 @@ fn perform_rollover(
     )?;
     let now = OffsetDateTime::now_utc().unix_timestamp();
     let new_id = Uuid::new_v4().to_string();
+
+    // A seed that already crosses the warn threshold would trigger another
+    // rollover almost immediately, so trim it and flag the new thread.
+    let counter = counter_for(conn, &selection.provider.id, &selection.model);
+    let new_limit = context_limit_from_tags(conn, &selection.provider.id, &selection.model)?;
+    let (warn_threshold, _) = rollover_thresholds(new_limit, config);
+    let warn_threshold = warn_threshold as usize;
+    let mut seed = format!("{SEED_PREFIX}{}", summary.body);
+    let mut quality_flags = None;
+    if counter.count(&seed) >= warn_threshold {
+        let budget = (warn_threshold / 2).max(1);
+        let trimmed = split_to_budget(&summary.body, budget, counter.as_ref())
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        seed = format!("{SEED_PREFIX}{}\n\n[summary truncated]", trimmed.trim_end());
+        quality_flags = Some(SEED_OVERSIZED_FLAG);
+        log_event(
+            conn,
+            "warn",
+            Some("AI-CTX-SEED"),
+            "ai.context",
+            "Rollover summary truncated",
+            Some("The summary was too large for the new thread's context window."),
+            Some(json!({
+                "previous_conversation": conversation.id,
+                "new_conversation": new_id,
+                "summary_tokens": counter.count(&summary.body),
+                "warn_threshold": warn_threshold,
+            })),
+        )
+        .ok();
+    }
+
     conn.execute(
-        "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, 0, 0, ?5, ?5)",
+        "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, quality_flags, system_prompt, default_temperature, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, 0, 0, ?5, ?6, ?7, ?8, ?8)",
         params![
             new_id,
             conversation.title.clone(),
             selection.provider.id,
             selection.model,
+            quality_flags,
+            conversation.system_prompt,
+            conversation.default_temperature,
             now,
         ],
     )?;
 
-    let summary_body = summary.body.clone();
-    insert_message(
-        conn,
-        &new_id,
-        "system",
-        &format!("Summary of previous thread:\n{}", summary_body),
-    )?;
+    insert_message(conn, counter.as_ref(), &new_id, "system", &seed)?;
 
     insert_link(
         conn,
@@ -687,32 +2464,245 @@ fn perform_rollover(
     })
 }
 
+/// Delete a conversation's messages except its retained tail.
+///
+/// The messages to delete are summarised first, map-reduced when they exceed
+/// the summariser's budget, and that summary is linked to the conversation
+/// with `summarised_as` as their record. Nothing is removed unless the model
+/// wrote it: fallback text is provisional. Usage rows are detached from the
+/// deleted messages rather than dropped with them, keeping the
+/// conversation's spend intact.
+///
+/// The model is asked before any transaction opens; only the link and the
+/// deletes hold the write lock.
+fn prune_messages(
+    conn: &mut rusqlite::Connection,
+    models: &ModelManager,
+    config: &SummarizerConfig,
+    conversation_id: &str,
+) -> Result<usize> {
+    let messages = list_messages(conn, conversation_id, None)?;
+    let keep = rollover_tail_len(conn, models, config, &messages, None);
+    let pruned = &messages[..messages.len().saturating_sub(keep)];
+    if pruned.is_empty() {
+        return Ok(0);
+    }
+    let mut excerpts: Vec<String> = pruned
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.body))
+        .collect();
+    let summary = store_or_create_summary(
+        conn,
+        models,
+        "conversation",
+        conversation_id,
+        &mut excerpts,
+        &[],
+        config,
+    )?;
+    if summary.source != SOURCE_AI {
+        return Err(InkOsError::ValidationFailed(format!(
+            "the model could not summarise the messages to prune from conversation '{conversation_id}'; they are kept"
+        ))
+        .into());
+    }
+
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    insert_link(
+        &tx,
+        conversation_id,
+        "conversation",
+        &summary.id,
+        "summary",
+        "summarised_as",
+    )?;
+    let mut removed = 0;
+    for message in pruned {
+        tx.execute(
+            "UPDATE usage SET message_id = NULL WHERE message_id = ?1",
+            params![message.id],
+        )?;
+        removed += tx.execute("DELETE FROM messages WHERE id = ?1", params![message.id])?;
+    }
+    if removed > 0 {
+        log_event(
+            &tx,
+            "info",
+            Some("AI-CTX-PRUNE"),
+            "ai.context",
+            "Rolled-over conversation pruned",
+            Some("Messages beyond the retained tail were summarised and removed; the summary remains the record."),
+            Some(json!({
+                "conversation_id": conversation_id,
+                "summary_id": summary.id,
+                "removed": removed,
+                "kept": keep,
+            })),
+        )
+        .ok();
+    }
+    logging::commit(tx)?;
+    Ok(removed)
+}
+
+/// Number of rollovers in this conversation's chain within the loop window,
+/// when it has already reached the configured cap.
+fn recent_rollovers(
+    conn: &rusqlite::Connection,
+    conversation: &ConversationRecord,
+    config: &SummarizerConfig,
+) -> Result<Option<usize>> {
+    let since = OffsetDateTime::now_utc().unix_timestamp() - ROLLOVER_LOOP_WINDOW_SECS;
+    let chain = rollover_chain(conn, &conversation.id)?;
+    let mut recent = 0;
+    for id in chain.iter().skip(1) {
+        let created_at: Option<i64> = conn
+            .query_row(
+                "SELECT created_at FROM conversations WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if created_at.is_some_and(|ts| ts >= since) {
+            recent += 1;
+        }
+    }
+    if recent >= config.max_rollovers_per_window as usize {
+        Ok(Some(recent))
+    } else {
+        Ok(None)
+    }
+}
+
+fn rollover_loop_error(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+    recent: usize,
+) -> anyhow::Error {
+    log_event(
+        conn,
+        "error",
+        Some("AI-CTX-LOOP"),
+        "ai.context",
+        "Rollover loop detected",
+        Some("The conversation keeps exceeding its context window; no new thread was created."),
+        Some(json!({
+            "conversation_id": conversation_id,
+            "recent_rollovers": recent,
+            "window_secs": ROLLOVER_LOOP_WINDOW_SECS,
+        })),
+    )
+    .ok();
+    InkOsError::RolloverLoop {
+        recent,
+        window_mins: ROLLOVER_LOOP_WINDOW_SECS / 60,
+    }
+    .into()
+}
+
+/// Number of newest messages to include verbatim in a rollover prompt.
+///
+/// In auto mode the tail takes as many messages as fit in the summariser's
+/// budget after the pending message, but never fewer than one.
+fn rollover_tail_len(
+    conn: &rusqlite::Connection,
+    models: &ModelManager,
+    config: &SummarizerConfig,
+    messages: &[MessageRecord],
+    pending_message: Option<(&str, &str)>,
+) -> usize {
+    if !config.rollover_tail_auto {
+        return config.rollover_tail;
+    }
+    let (budget, counter) = summary_token_budget(conn, models, config);
+    let budget = budget.saturating_sub(pending_message.map_or(0, |(_, body)| counter.count(body)));
+    tail_len_for_budget(messages, budget, counter.as_ref())
+}
+
+fn tail_len_for_budget(
+    messages: &[MessageRecord],
+    budget: usize,
+    counter: &dyn TokenCounter,
+) -> usize {
+    let mut used = 0;
+    let mut count = 0;
+    for msg in messages.iter().rev() {
+        used += counter.count(&msg.body);
+        if used > budget && count > 0 {
+            break;
+        }
+        count += 1;
+    }
+    count.max(1)
+}
+
 fn select_conversation_excerpts(
     messages: &[MessageRecord],
     pending_message: Option<(&str, &str)>,
+    tail: usize,
 ) -> Vec<String> {
     let mut excerpts = Vec::new();
-    let mut keywords: HashSet<String> = HashSet::new();
+    let total = messages.len();
+    let tail_start = total.saturating_sub(tail);
+    let history = &messages[..tail_start];
+    let keywords = match pending_message {
+        Some((_, body)) => salient_keywords(body, history),
+        None => HashSet::new(),
+    };
+
+    if !keywords.is_empty() {
+        // Rank older messages by how many salient terms they share, keeping
+        // the most recent on ties, then restore chronological order.
+        let mut matches: Vec<(usize, usize)> = history
+            .iter()
+            .enumerate()
+            .filter_map(|(index, msg)| {
+                let words = extract_keywords(&msg.body);
+                let hits = keywords.intersection(&words).count();
+                (hits > 0).then_some((index, hits))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+        matches.truncate(MAX_BACK_REFERENCES);
+        matches.sort_by_key(|(index, _)| *index);
+        for (index, _) in matches {
+            let msg = &history[index];
+            excerpts.push(format!("{}: {}", msg.role, msg.body));
+        }
+    }
+
     if let Some((role, body)) = pending_message {
         excerpts.push(format!("{}: {}", role, body));
-        keywords.extend(extract_keywords(body));
     }
-    let total = messages.len();
-    let tail_start = total.saturating_sub(12);
     for msg in messages.iter().skip(tail_start) {
         excerpts.push(format!("{}: {}", msg.role, msg.body));
     }
-    if keywords.is_empty() {
-        return excerpts;
-    }
-    for msg in messages.iter().take(tail_start) {
-        if keywords.iter().any(|k| msg.body.to_lowercase().contains(k)) {
-            excerpts.insert(0, format!("{}: {}", msg.role, msg.body));
-        }
-    }
     excerpts
 }
 
+/// Keywords of `text` that are rare enough in `history` to identify related
+/// messages. Terms found in more than [`MAX_KEYWORD_SHARE`] of the older
+/// messages say nothing about which of them matter.
+fn salient_keywords(text: &str, history: &[MessageRecord]) -> HashSet<String> {
+    let mut keywords = extract_keywords(text);
+    if history.is_empty() || keywords.is_empty() {
+        return keywords;
+    }
+    let limit = ((history.len() as f32 * MAX_KEYWORD_SHARE).ceil() as usize).max(1);
+    let histories: Vec<HashSet<String>> = history
+        .iter()
+        .map(|msg| extract_keywords(&msg.body))
+        .collect();
+    keywords.retain(|word| {
+        histories
+            .iter()
+            .filter(|words| words.contains(word))
+            .count()
+            <= limit
+    });
+    keywords
+}
+
 fn extract_keywords(text: &str) -> HashSet<String> {
     text.split_whitespace()
         .filter(|word| word.len() > 4)
@@ -720,7 +2710,7 @@ fn extract_keywords(text: &str) -> HashSet<String> {
             word.trim_matches(|c: char| !c.is_alphanumeric())
                 .to_lowercase()
         })
-        .filter(|word| !word.is_empty())
+        .filter(|word| word.chars().count() > 4 && !STOPWORDS.contains(&word.as_str()))
         .collect()
 }
 
@@ -737,7 +2727,15 @@ fn summarise_text(
     if let Some(ctx) = context {
         excerpts.push(ctx.to_string());
     }
-    store_or_create_summary(conn, models, target_type, target_id, &mut excerpts, &config)
+    store_or_create_summary(
+        conn,
+        models,
+        target_type,
+        target_id,
+        &mut excerpts,
+        &[],
+        &config,
+    )
 }
 
 fn store_or_create_summary(
@@ -745,32 +2743,34 @@ fn store_or_create_summary(
     models: &ModelManager,
     target_type: &str,
     target_id: &str,
-    excerpts: &mut Vec<String>,
+    excerpts: &mut [String],
+    images: &[AiImage],
     config: &SummarizerConfig,
 ) -> Result<SummaryRecord> {
-    let hash = hash_strings(excerpts);
-    if let Some(summary) = find_cached_summary(conn, target_type, target_id, &hash)? {
-        return Ok(summary);
-    }
+    let mut hashed = excerpts.to_vec();
+    hashed.extend(source_stamp(conn, target_type, target_id)?);
+    let hash = hash_strings(&hashed);
+    // Fallback text is only provisional: it is served again if the model
+    // keeps failing, but never stops the next request from retrying it.
+    let cached = find_cached_summary(conn, target_type, target_id, &hash)?;
+    let provisional = match cached {
+        Some(summary) if summary.source != SOURCE_FALLBACK => return Ok(summary),
+        other => other,
+    };
 
     let prompt = excerpts.join("\n\n");
-    let messages = vec![
-        AiChatMessage {
-            role: "system".into(),
-            content: SUMMARISER_PROMPT.into(),
-        },
-        AiChatMessage {
-            role: "user".into(),
-            content: prompt.clone(),
-        },
-    ];
-    let input = AiChatInput {
-        messages,
-        temperature: Some(0.2),
+    let (budget, counter) = summary_token_budget(conn, models, config);
+    let (response, chunks) = if counter.count(&prompt) > budget {
+        let chunks = chunk_excerpts(excerpts, budget, counter.as_ref());
+        let count = chunks.len();
+        (
+            map_reduce_summary(models, config, chunks, images, budget, counter.as_ref(), 0),
+            count,
+        )
+    } else {
+        (request_summary(models, config, prompt.clone(), images), 1)
     };
 
-    let response = models.chat_blocking(input, None, config.summarizer_model.clone(), true);
-
     let (body, model_id, explain) = match response {
         Ok(resp) => {
             let body = resp.content.trim().to_string();
@@ -796,6 +2796,7 @@ fn store_or_create_summary(
                 Some(json!({
                     "target_type": target_type,
                     "target_id": target_id,
+                    "chunks": chunks,
                     "error": message,
                 })),
             )
@@ -804,7 +2805,28 @@ fn store_or_create_summary(
         }
     };
 
-    let created = insert_summary(conn, target_type, target_id, &body, &hash, model_id.clone())?;
+    let source = if explain.is_empty() {
+        SOURCE_AI
+    } else {
+        SOURCE_FALLBACK
+    };
+    if let Some(summary) =
+        provisional.filter(|summary| source == SOURCE_FALLBACK && summary.body == body)
+    {
+        return Ok(summary);
+    }
+    let created = insert_summary(
+        conn,
+        NewSummary {
+            target_type,
+            target_id,
+            body: &body,
+            source_hash: &hash,
+            model_id: model_id.clone(),
+            source,
+        },
+        counter.as_ref(),
+    )?;
     if explain.is_empty() {
         log_event(
             conn,
@@ -817,6 +2839,7 @@ fn store_or_create_summary(
                 "target_type": target_type,
                 "target_id": target_id,
                 "model": model_id,
+                "chunks": chunks,
             })),
         )
         .ok();
@@ -824,14 +2847,223 @@ fn store_or_create_summary(
     Ok(created)
 }
 
-fn insert_summary(
+/// Tokens a single summarisation prompt may use on the summariser model,
+/// together with the counter that measures text for that model.
+fn summary_token_budget(
     conn: &rusqlite::Connection,
-    target_type: &str,
-    target_id: &str,
-    body: &str,
-    source_hash: &str,
+    models: &ModelManager,
+    config: &SummarizerConfig,
+) -> (usize, Arc<dyn TokenCounter>) {
+    let selection = models
+        .resolve_runtime(None, config.summarizer_model.clone(), true)
+        .ok();
+    let counter: Arc<dyn TokenCounter> = match &selection {
+        Some(selection) => counter_for(conn, &selection.provider.id, &selection.model),
+        None => Arc::new(HeuristicCounter),
+    };
+    let limit = selection
+        .and_then(|selection| {
+            context_limit_from_tags(conn, &selection.provider.id, &selection.model).ok()
+        })
+        .unwrap_or(DEFAULT_CONTEXT_LIMIT);
+    let budget = (limit as f32 * SUMMARY_PROMPT_SHARE) as usize;
+    let budget = budget
+        .saturating_sub(counter.count(SUMMARISER_PROMPT))
+        .max(MIN_SUMMARY_BUDGET);
+    (budget, counter)
+}
+
+fn request_summary(
+    models: &ModelManager,
+    config: &SummarizerConfig,
+    prompt: String,
+    images: &[AiImage],
+) -> Result<AiChatResponse> {
+    let input = AiChatInput {
+        messages: vec![
+            AiChatMessage {
+                role: "system".into(),
+                content: match config.focus {
+                    Some(focus) => format!("{SUMMARISER_PROMPT} {focus}"),
+                    None => SUMMARISER_PROMPT.into(),
+                },
+                images: Vec::new(),
+            },
+            AiChatMessage {
+                role: "user".into(),
+                content: prompt,
+                images: images.to_vec(),
+            },
+        ],
+        temperature: Some(0.2),
+        response_format: None,
+        stop: None,
+        top_p: None,
+        max_tokens: None,
+        reasoning_effort: None,
+        request_id: None,
+    };
+    models.chat_blocking(input, None, config.summarizer_model.clone(), true, true)
+}
+
+/// Summarise each chunk, then summarise the joined partial summaries.
+///
+/// If the partials are still over budget they are chunked and reduced again,
+/// up to [`MAX_REDUCE_DEPTH`] passes. Images are only sent with the final
+/// reduce request.
+fn map_reduce_summary(
+    models: &ModelManager,
+    config: &SummarizerConfig,
+    chunks: Vec<String>,
+    images: &[AiImage],
+    budget: usize,
+    counter: &dyn TokenCounter,
+    depth: usize,
+) -> Result<AiChatResponse> {
+    let mut partials = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        let partial = request_summary(models, config, chunk, &[])?;
+        let body = partial.content.trim();
+        if !body.is_empty() {
+            partials.push(body.to_string());
+        }
+    }
+    let combined = partials.join("\n\n");
+    if counter.count(&combined) > budget && depth + 1 < MAX_REDUCE_DEPTH {
+        let chunks = chunk_excerpts(&partials, budget, counter);
+        return map_reduce_summary(models, config, chunks, images, budget, counter, depth + 1);
+    }
+    request_summary(models, config, combined, images)
+}
+
+/// List a note's attachments as an extra excerpt and load its images.
+///
+/// The excerpt keeps attachment changes in the source hash and tells
+/// text-only models what they cannot see.
+fn note_attachment_context(
+    conn: &rusqlite::Connection,
+    note_id: &str,
+    excerpts: &mut Vec<String>,
+) -> Result<Vec<AiImage>> {
+    let attached = attachments::list(conn, note_id)?;
+    if attached.is_empty() {
+        return Ok(Vec::new());
+    }
+    let names: Vec<String> = attached
+        .iter()
+        .map(|attachment| format!("{} ({})", attachment.filename, attachment.mime))
+        .collect();
+    excerpts.push(format!("Attachments: {}", names.join(", ")));
+    attachments::load_images(&attached)
+}
+
+/// Group excerpts into chunks that each fit within `budget` tokens,
+/// splitting any single oversized excerpt on whitespace.
+fn chunk_excerpts(excerpts: &[String], budget: usize, counter: &dyn TokenCounter) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for excerpt in excerpts {
+        for piece in split_to_budget(excerpt, budget, counter) {
+            let tokens = counter.count(&piece);
+            if !current.is_empty() && current_tokens + tokens > budget {
+                chunks.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(&piece);
+            current_tokens += tokens;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Split `text` on whitespace into pieces of at most `budget` tokens.
+///
+/// Pieces are cut with the same character/word heuristic as
+/// [`approx_tokens`], then any piece `counter` still finds over budget is
+/// halved until it fits or cannot be split further.
+fn split_to_budget(text: &str, budget: usize, counter: &dyn TokenCounter) -> Vec<String> {
+    if counter.count(text) <= budget {
+        return vec![text.to_string()];
+    }
+    let max_chars = budget * 4;
+    let max_words = (budget as f32 / 1.1) as usize;
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let (mut chars, mut words) = (0, 0);
+    for word in text.split_inclusive(char::is_whitespace) {
+        let word_chars = word.chars().count();
+        let word_words = usize::from(!word.trim().is_empty());
+        if !current.is_empty() && (chars + word_chars > max_chars || words + word_words > max_words)
+        {
+            pieces.push(std::mem::take(&mut current));
+            chars = 0;
+            words = 0;
+        }
+        current.push_str(word);
+        chars += word_chars;
+        words += word_words;
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+        .into_iter()
+        .flat_map(|piece| halve_to_budget(piece, budget, counter))
+        .collect()
+}
+
+fn halve_to_budget(piece: String, budget: usize, counter: &dyn TokenCounter) -> Vec<String> {
+    if counter.count(&piece) <= budget {
+        return vec![piece];
+    }
+    let middle = piece.len() / 2;
+    let cut = piece
+        .char_indices()
+        .filter(|(_, c)| c.is_whitespace())
+        .map(|(index, c)| index + c.len_utf8())
+        .filter(|&index| index < piece.len())
+        .min_by_key(|&index| index.abs_diff(middle));
+    let Some(cut) = cut else {
+        return vec![piece];
+    };
+    let (head, tail) = piece.split_at(cut);
+    let mut halves = halve_to_budget(head.to_string(), budget, counter);
+    halves.extend(halve_to_budget(tail.to_string(), budget, counter));
+    halves
+}
+
+/// A summary about to be stored by [`insert_summary`].
+struct NewSummary<'a> {
+    target_type: &'a str,
+    target_id: &'a str,
+    body: &'a str,
+    /// Hash of the excerpts the body was written from; keys the cache.
+    source_hash: &'a str,
     model_id: Option<String>,
+    /// [`SOURCE_AI`] or [`SOURCE_FALLBACK`].
+    source: &'a str,
+}
+
+fn insert_summary(
+    conn: &rusqlite::Connection,
+    summary: NewSummary<'_>,
+    counter: &dyn TokenCounter,
 ) -> Result<SummaryRecord> {
+    let NewSummary {
+        target_type,
+        target_id,
+        body,
+        source_hash,
+        model_id,
+        source,
+    } = summary;
     let now = OffsetDateTime::now_utc().unix_timestamp();
     let version: i64 = conn
         .query_row(
@@ -840,9 +3072,9 @@ fn insert_summary(
             |row| row.get(0),
         )?;
     let id = Uuid::new_v4().to_string();
-    let token_est = approx_tokens(body) as i64;
+    let token_est = counter.count(body) as i64;
     conn.execute(
-        "INSERT INTO summaries (id, target_type, target_id, version, body, token_est, source_hash, model_id, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO summaries (id, target_type, target_id, version, body, token_est, source_hash, model_id, created_at, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             id,
             target_type,
@@ -853,6 +3085,7 @@ fn insert_summary(
             source_hash,
             model_id,
             now,
+            source,
         ],
     )?;
     Ok(SummaryRecord {
@@ -865,6 +3098,9 @@ fn insert_summary(
         model_id,
         created_at: now,
         reused: false,
+        source: source.into(),
+        covered_through: None,
+        covered_message_id: None,
     })
 }
 
@@ -874,49 +3110,117 @@ fn find_cached_summary(
     target_id: &str,
     hash: &str,
 ) -> Result<Option<SummaryRecord>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, version, body, token_est, model_id, created_at FROM summaries WHERE target_type = ?1 AND target_id = ?2 AND source_hash = ?3 ORDER BY version DESC LIMIT 1",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM summaries WHERE target_type = ?1 AND target_id = ?2 AND source_hash = ?3 ORDER BY version DESC LIMIT 1"
+    ))?;
     let summary = stmt
-        .query_row(params![target_type, target_id, hash], |row| {
-            Ok(SummaryRecord {
-                id: row.get(0)?,
-                target_type: target_type.into(),
-                target_id: target_id.into(),
-                version: row.get(1)?,
-                body: row.get(2)?,
-                token_est: row.get(3)?,
-                model_id: row.get(4)?,
-                created_at: row.get(5)?,
-                reused: true,
-            })
-        })
+        .query_row(params![target_type, target_id, hash], row_to_summary)
         .optional()?;
-    Ok(summary)
+    Ok(summary.map(|summary| SummaryRecord {
+        reused: true,
+        ..summary
+    }))
 }
 
-fn load_summary(conn: &rusqlite::Connection, summary_id: &str) -> Result<Option<SummaryRecord>> {
-    let mut stmt = conn.prepare(
-        "SELECT target_type, target_id, version, body, token_est, model_id, created_at FROM summaries WHERE id = ?1",
-    )?;
+/// Newest model-written conversation summary that records the message it
+/// covers through. Fallback text is provisional, so it is never resumed.
+fn latest_covered_summary(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+) -> Result<Option<(SummaryRecord, String)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM summaries WHERE target_type = 'conversation' AND target_id = ?1 AND source = 'ai' AND covered_message_id IS NOT NULL ORDER BY version DESC LIMIT 1"
+    ))?;
     let summary = stmt
-        .query_row([summary_id], |row| {
-            Ok(SummaryRecord {
-                id: summary_id.into(),
-                target_type: row.get(0)?,
-                target_id: row.get(1)?,
-                version: row.get(2)?,
-                body: row.get(3)?,
-                token_est: row.get(4)?,
-                model_id: row.get(5)?,
-                created_at: row.get(6)?,
-                reused: true,
-            })
-        })
+        .query_row([conversation_id], row_to_summary)
         .optional()?;
+    Ok(summary.and_then(|summary| {
+        let covered = summary.covered_message_id.clone()?;
+        Some((summary, covered))
+    }))
+}
+
+fn load_summary(conn: &rusqlite::Connection, summary_id: &str) -> Result<Option<SummaryRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM summaries WHERE id = ?1"
+    ))?;
+    let summary = stmt.query_row([summary_id], row_to_summary).optional()?;
     Ok(summary)
 }
 
+fn list_summaries(
+    conn: &rusqlite::Connection,
+    target_type: &str,
+    target_id: &str,
+) -> Result<Vec<SummaryRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SUMMARY_COLUMNS} FROM summaries WHERE target_type = ?1 AND target_id = ?2 ORDER BY version DESC"
+    ))?;
+    let rows = stmt.query_map(params![target_type, target_id], row_to_summary)?;
+    let mut summaries = Vec::new();
+    for row in rows {
+        summaries.push(row?);
+    }
+    Ok(summaries)
+}
+
+/// Map a row selected with [`SUMMARY_COLUMNS`]. Callers that serve the row
+/// in place of a new summary set `reused` themselves.
+fn row_to_summary(row: &rusqlite::Row<'_>) -> rusqlite::Result<SummaryRecord> {
+    Ok(SummaryRecord {
+        id: row.get(0)?,
+        target_type: row.get(1)?,
+        target_id: row.get(2)?,
+        version: row.get(3)?,
+        body: row.get(4)?,
+        token_est: row.get(5)?,
+        model_id: row.get(6)?,
+        created_at: row.get(7)?,
+        reused: false,
+        source: row.get(8)?,
+        covered_through: row.get(9)?,
+        covered_message_id: row.get(10)?,
+    })
+}
+
+/// Version marker of a summary's source, mixed into its cache hash.
+///
+/// Excerpts only cover part of a conversation (the tail plus matched older
+/// messages), so activity outside that window would otherwise keep serving
+/// a stale summary. Conversations are stamped with their `updated_at` and
+/// message count, notes with their `updated_at`.
+fn source_stamp(
+    conn: &rusqlite::Connection,
+    target_type: &str,
+    target_id: &str,
+) -> Result<Option<String>> {
+    let stamp = match target_type {
+        "conversation" => conn
+            .query_row(
+                "SELECT c.updated_at, (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id)
+                 FROM conversations c WHERE c.id = ?1",
+                params![target_id],
+                |row| {
+                    Ok(format!(
+                        "conversation:{}:{}",
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, i64>(1)?
+                    ))
+                },
+            )
+            .optional()?,
+        "note" => conn
+            .query_row(
+                "SELECT updated_at FROM notes WHERE id = ?1",
+                params![target_id],
+                |row| Ok(format!("note:{}", row.get::<_, i64>(0)?)),
+            )
+            .optional()?,
+        _ => None,
+    };
+    Ok(stamp)
+}
+
 fn hash_strings(values: &[String]) -> String {
     let mut hasher = Sha256::new();
     for value in values {
@@ -925,6 +3229,169 @@ fn hash_strings(values: &[String]) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Remove a conversation and every row that depends on it.
+fn purge_conversation(conn: &rusqlite::Connection, conversation_id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM links WHERE src_id = ?1 OR dst_id = ?1
+            OR src_id IN (SELECT id FROM summaries WHERE target_type = 'conversation' AND target_id = ?1)
+            OR dst_id IN (SELECT id FROM summaries WHERE target_type = 'conversation' AND target_id = ?1)",
+        [conversation_id],
+    )?;
+    conn.execute(
+        "UPDATE usage SET conversation_id = NULL, message_id = NULL WHERE conversation_id = ?1
+            OR message_id IN (SELECT id FROM messages WHERE conversation_id = ?1)",
+        [conversation_id],
+    )?;
+    conn.execute(
+        "DELETE FROM messages WHERE conversation_id = ?1",
+        [conversation_id],
+    )?;
+    conn.execute(
+        "DELETE FROM summaries WHERE target_type = 'conversation' AND target_id = ?1",
+        [conversation_id],
+    )?;
+    conn.execute("DELETE FROM conversations WHERE id = ?1", [conversation_id])?;
+    Ok(())
+}
+
+/// Follow `summarised_as`/`rollover_to` links to the full thread chain.
+fn rollover_chain(conn: &rusqlite::Connection, conversation_id: &str) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut root = conversation_id.to_string();
+    seen.insert(root.clone());
+    while let Some(previous) = neighbour_thread(conn, &root, true)? {
+        if !seen.insert(previous.clone()) {
+            break;
+        }
+        root = previous;
+    }
+
+    let mut chain = vec![root.clone()];
+    let mut visited = HashSet::from([root.clone()]);
+    let mut current = root;
+    while let Some(next) = neighbour_thread(conn, &current, false)? {
+        if !visited.insert(next.clone()) {
+            break;
+        }
+        chain.push(next.clone());
+        current = next;
+    }
+    Ok(chain)
+}
+
+/// Conversation that rolled over into `conversation_id` (`backwards`) or
+/// that `conversation_id` rolled over into.
+fn neighbour_thread(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+    backwards: bool,
+) -> Result<Option<String>> {
+    let sql = if backwards {
+        "SELECT s.src_id FROM links r
+         JOIN links s ON s.dst_id = r.src_id AND s.rel = 'summarised_as' AND s.src_type = 'conversation'
+         WHERE r.dst_id = ?1 AND r.rel = 'rollover_to' AND r.dst_type = 'conversation'
+         ORDER BY r.created_at DESC LIMIT 1"
+    } else {
+        "SELECT r.dst_id FROM links s
+         JOIN links r ON r.src_id = s.dst_id AND r.rel = 'rollover_to' AND r.dst_type = 'conversation'
+         WHERE s.src_id = ?1 AND s.rel = 'summarised_as' AND s.src_type = 'conversation'
+         ORDER BY r.created_at ASC LIMIT 1"
+    };
+    Ok(conn
+        .query_row(sql, [conversation_id], |row| row.get(0))
+        .optional()?)
+}
+
+fn build_conversation_export(
+    conn: &rusqlite::Connection,
+    conversation_id: &str,
+) -> Result<ConversationExport> {
+    fetch_conversation(conn, conversation_id)?.ok_or(InkOsError::ConversationNotFound)?;
+    let mut threads = Vec::new();
+    for id in rollover_chain(conn, conversation_id)? {
+        let Some(conversation) = fetch_conversation(conn, &id)? else {
+            continue;
+        };
+        let messages = list_messages(conn, &id, None)?;
+        let mut stmt = conn.prepare(
+            "SELECT id FROM summaries WHERE target_type = 'conversation' AND target_id = ?1 ORDER BY version ASC",
+        )?;
+        let ids = stmt
+            .query_map([&id], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut summaries = Vec::new();
+        for summary_id in ids {
+            if let Some(summary) = load_summary(conn, &summary_id)? {
+                summaries.push(summary);
+            }
+        }
+        threads.push(ExportedThread {
+            conversation,
+            messages,
+            summaries,
+        });
+    }
+    Ok(ConversationExport {
+        exported_at: OffsetDateTime::now_utc().unix_timestamp(),
+        threads,
+    })
+}
+
+fn render_markdown_export(export: &ConversationExport) -> Result<String> {
+    let title = export
+        .threads
+        .iter()
+        .find_map(|thread| thread.conversation.title.clone())
+        .unwrap_or_else(|| "Untitled conversation".into());
+    let mut out = format!(
+        "# {title}\n\n_Exported {} · {} thread{}_\n",
+        format_ts(export.exported_at)?,
+        export.threads.len(),
+        if export.threads.len() == 1 { "" } else { "s" }
+    );
+    for (index, thread) in export.threads.iter().enumerate() {
+        let conversation = &thread.conversation;
+        out.push_str(&format!(
+            "\n## Thread {} · {}/{} · started {}\n",
+            index + 1,
+            conversation.provider_id,
+            conversation.model_id,
+            format_ts(conversation.created_at)?
+        ));
+        for message in &thread.messages {
+            out.push_str(&format!(
+                "\n### {} · {}\n\n{}\n",
+                capitalise(&message.role),
+                format_ts(message.created_at)?,
+                message.body.trim_end()
+            ));
+        }
+        for summary in &thread.summaries {
+            out.push_str(&format!(
+                "\n#### Summary v{} · {}\n\n",
+                summary.version,
+                format_ts(summary.created_at)?
+            ));
+            for line in summary.body.lines() {
+                out.push_str(&format!("> {line}\n"));
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn format_ts(ts: i64) -> Result<String> {
+    Ok(OffsetDateTime::from_unix_timestamp(ts)?.format(&Rfc3339)?)
+}
+
+fn capitalise(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
 fn insert_link(
     conn: &rusqlite::Connection,
     src_id: &str,
@@ -945,8 +3412,374 @@ fn insert_link(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::config::use_ollama_at;
     use r2d2_sqlite::rusqlite::Connection as SqliteConnection;
 
+    fn file_backed_summarizer() -> (Arc<Summarizer>, DbPool, std::path::PathBuf) {
+        let (models, pool, path) = crate::model_manager::file_backed_models();
+        (Summarizer::new(pool.clone(), models), pool, path)
+    }
+
+    #[test]
+    fn rollover_thresholds_never_reach_zero() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        assert!(write_config(&conn, 0.95, 0.9, None, DEFAULT_ROLLOVER_TAIL, false).is_err());
+        settings::upsert(&conn, "ai.rollover.force_ratio", "1.5").unwrap();
+        let config = read_config(&conn).unwrap();
+        assert_eq!(
+            (config.warn_ratio, config.force_ratio),
+            (DEFAULT_WARN_RATIO, DEFAULT_FORCE_RATIO)
+        );
+
+        assert_eq!(rollover_thresholds(0, &config), (3072, 3686));
+        assert_eq!(rollover_thresholds(1, &config), (1, 1));
+        assert_eq!(rollover_thresholds(8000, &config), (6000, 7200));
+    }
+
+    #[test]
+    fn update_config_rejects_unusable_ratios() {
+        let (summarizer, _pool, path) = file_backed_summarizer();
+        for (warn, force) in [
+            (0.9, 0.75),
+            (0.8, 0.8),
+            (0.5, 1.5),
+            (0.0, 0.9),
+            (f32::NAN, 0.9),
+        ] {
+            let err = summarizer
+                .update_config(warn, force, None, DEFAULT_ROLLOVER_TAIL, false)
+                .unwrap_err();
+            assert!(matches!(
+                err.downcast_ref::<InkOsError>(),
+                Some(InkOsError::ValidationFailed(_))
+            ));
+        }
+        let saved = summarizer
+            .update_config(0.6, 1.0, None, DEFAULT_ROLLOVER_TAIL, false)
+            .unwrap();
+        assert_eq!((saved.warn_ratio, saved.force_ratio), (0.6, 1.0));
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Serve one canned Ollama chat reply per connection, in order.
+    fn scripted_ollama(replies: &'static [&'static str]) -> String {
+        recording_ollama(replies).0
+    }
+
+    /// Like [`scripted_ollama`], also returning every request body received.
+    fn recording_ollama(
+        replies: &'static [&'static str],
+    ) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let base_url = crate::mock_provider::serve(move |index, request| {
+            let reply = replies.get(index)?;
+            recorded.lock().unwrap().push(request.body.clone());
+            Some(("200 OK", crate::mock_provider::ollama_reply(reply)))
+        });
+        (base_url, requests)
+    }
+
+    #[test]
+    fn empty_responses_are_retried_instead_of_cached() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, scripted_ollama(&["", "A real summary."]));
+        let models = summarizer.models();
+
+        let first = summarise_text(&conn, &models, "note", "n1", "Ship it", None).unwrap();
+        assert_eq!(first.source, SOURCE_FALLBACK);
+        let second = summarise_text(&conn, &models, "note", "n1", "Ship it", None).unwrap();
+        assert_eq!(second.source, SOURCE_AI);
+        assert_eq!(second.body, "A real summary.");
+        let third = summarise_text(&conn, &models, "note", "n1", "Ship it", None).unwrap();
+        assert_eq!(third.id, second.id);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn new_messages_invalidate_a_cached_conversation_summary() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, scripted_ollama(&["Plan drafted."]));
+        conn.execute(
+            "INSERT INTO conversations (id, title, provider_id, model_id, created_at, updated_at) VALUES ('c1', 'Plans', 'ollama', 'm', 0, 0)",
+            [],
+        )
+        .unwrap();
+        let counter = counter_for(&conn, "ollama", "m");
+        insert_message(&conn, counter.as_ref(), "c1", "user", "Draft the plan").unwrap();
+        let models = summarizer.models();
+
+        let excerpt = "user: Draft the plan";
+        let first = summarise_text(&conn, &models, "conversation", "c1", excerpt, None).unwrap();
+        let cached = summarise_text(&conn, &models, "conversation", "c1", excerpt, None).unwrap();
+        assert_eq!(cached.id, first.id);
+        assert!(!first.reused && cached.reused);
+        assert!(!load_summary(&conn, &first.id).unwrap().unwrap().reused);
+
+        insert_message(&conn, counter.as_ref(), "c1", "assistant", "Done").unwrap();
+        let fresh = summarise_text(&conn, &models, "conversation", "c1", excerpt, None).unwrap();
+        assert_ne!(fresh.id, first.id);
+        assert_eq!(fresh.version, first.version + 1);
+        assert!(!fresh.reused);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rollover_preview_leaves_the_conversation_open() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, scripted_ollama(&["Agreed to ship Friday."]));
+        drop(conn);
+        let conversation = summarizer
+            .create_conversation(None, Some("ollama".into()), None, None, None)
+            .unwrap();
+        for (role, body) in [
+            ("user", "Can we ship on Friday after the release review?"),
+            ("assistant", "Yes, Friday works once the review signs off."),
+        ] {
+            let appended = summarizer
+                .append_and_maybe_rollover(&conversation.id, role, body, None)
+                .unwrap();
+            assert_eq!(
+                (
+                    appended.context_limit,
+                    appended.warn_threshold,
+                    appended.force_threshold
+                ),
+                (
+                    conversation.context_limit,
+                    conversation.warn_threshold,
+                    conversation.force_threshold
+                )
+            );
+            assert!(appended.warn_threshold < appended.force_threshold);
+        }
+
+        let preview = summarizer.preview_rollover(&conversation.id).unwrap();
+        assert_eq!(preview.summary.body, "Agreed to ship Friday.");
+        assert_eq!(preview.excerpt_count, 2);
+        assert_eq!(
+            preview.token_delta,
+            preview.current_tokens - preview.seed_tokens
+        );
+        let conn = pool.get().unwrap();
+        let still_open = fetch_conversation(&conn, &conversation.id)
+            .unwrap()
+            .unwrap();
+        assert!(!still_open.ctx_force);
+        let conversations: i64 = conn
+            .query_row("SELECT COUNT(*) FROM conversations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(conversations, 1);
+
+        let outcome = summarizer.rollover(&conversation.id).unwrap();
+        assert_eq!(outcome.summary.unwrap().id, preview.summary.id);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rollovers_summarise_without_holding_the_write_lock() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let (arrived_tx, arrived) = std::sync::mpsc::channel();
+        let (release, release_rx) = std::sync::mpsc::channel::<()>();
+        let base_url = crate::mock_provider::serve(move |index, _| {
+            if index == 0 {
+                let _ = arrived_tx.send(());
+                let _ = release_rx.recv();
+            }
+            Some((
+                "200 OK",
+                crate::mock_provider::ollama_reply("Agreed on the plans."),
+            ))
+        });
+        use_ollama_at(&pool.get().unwrap(), base_url);
+        let conversation = summarizer
+            .create_conversation(None, Some("ollama".into()), None, None, None)
+            .unwrap();
+        summarizer
+            .append_and_maybe_rollover(&conversation.id, "user", "Agree the plans.", None)
+            .unwrap();
+
+        let rolling = {
+            let summarizer = Arc::clone(&summarizer);
+            let id = conversation.id.clone();
+            std::thread::spawn(move || summarizer.rollover(&id))
+        };
+        arrived.recv().unwrap();
+        // Another writer gets the lock while the model is still answering.
+        let conn = pool.get().unwrap();
+        conn.busy_timeout(std::time::Duration::from_millis(100))
+            .unwrap();
+        conn.execute_batch("BEGIN IMMEDIATE; COMMIT;").unwrap();
+        drop(conn);
+        release.send(()).unwrap();
+
+        let outcome = rolling.join().unwrap().unwrap();
+        assert!(outcome.new_conversation.is_some());
+        let err = summarizer.rollover(&conversation.id).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::ConversationRolled)
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_identical_summaries_share_one_computation() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+
+        let calls = (0..8).map(|_| summarizer.summarise("note", "n1", "# Plan\n\nShip it"));
+        let results: Vec<SummaryRecord> = futures_util::future::join_all(calls)
+            .await
+            .into_iter()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        let rows: i64 = pool
+            .get()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM summaries", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 1);
+        assert!(results.iter().all(|summary| summary.id == results[0].id));
+        assert_eq!(results.iter().filter(|summary| !summary.reused).count(), 1);
+        assert!(summarizer.in_flight.lock().await.is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pasted_text_is_cached_under_a_content_hash() {
+        let (summarizer, _pool, path) = file_backed_summarizer();
+
+        let first = summarizer
+            .summarise_text("Ship the beta on Friday.", None, None)
+            .await
+            .unwrap();
+        assert_eq!(first.target_type, TEXT_TARGET);
+        assert!(first.target_id.starts_with("text-"));
+        let again = summarizer
+            .summarise_text("Ship the beta on Friday.", None, None)
+            .await
+            .unwrap();
+        assert_eq!(again.id, first.id);
+        let other = summarizer
+            .summarise_text("Ship the beta on Monday.", None, None)
+            .await
+            .unwrap();
+        assert_ne!(other.target_id, first.target_id);
+
+        let named = summarizer
+            .summarise_text(
+                "Ship the beta on Friday.",
+                Some("selection".into()),
+                Some("s1".into()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            (named.target_type.as_str(), named.target_id.as_str()),
+            ("selection", "s1")
+        );
+        assert!(summarizer.summarise_text("  ", None, None).await.is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn conversation_summaries_resume_after_covered_messages() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let (base_url, requests) = recording_ollama(&[
+            "Two points made.",
+            "Three points made.",
+            "Four points made.",
+        ]);
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, base_url);
+        conn.execute(
+            "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at) VALUES ('c1', NULL, 'ollama', 'llama3.1', 0, 0, 0, 0)",
+            [],
+        )
+        .unwrap();
+        // Every message lands in the same second.
+        let add = |id: &str, body: &str| {
+            conn.execute(
+                "INSERT INTO messages (id, conversation_id, role, body, created_at) VALUES (?1, 'c1', 'user', ?2, 100)",
+                params![id, body],
+            )
+            .unwrap();
+        };
+        add("m1", "first point");
+        add("m2", "second point");
+
+        let full = summarizer.summarise_conversation("c1").await.unwrap();
+        assert_eq!(full.covered_message_id.as_deref(), Some("m2"));
+
+        add("m3", "third point");
+        let update = summarizer.summarise_conversation("c1").await.unwrap();
+        assert_eq!(update.version, 2);
+        assert_eq!(update.covered_message_id.as_deref(), Some("m3"));
+        let prompt = requests.lock().unwrap()[1].clone();
+        assert!(prompt.contains("Two points made."));
+        assert!(prompt.contains("third point"));
+        assert!(!prompt.contains("first point"));
+
+        let unchanged = summarizer.summarise_conversation("c1").await.unwrap();
+        assert_eq!(unchanged.id, update.id);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        // A newer fallback summary is provisional and never resumed.
+        conn.execute(
+            "INSERT INTO summaries (id, target_type, target_id, version, body, created_at, source, covered_message_id) VALUES ('fb', 'conversation', 'c1', 3, 'user: third point', 0, 'fallback', 'm3')",
+            [],
+        )
+        .unwrap();
+        add("m4", "fourth point");
+        summarizer.summarise_conversation("c1").await.unwrap();
+        let prompt = requests.lock().unwrap()[2].clone();
+        assert!(prompt.contains("Three points made."));
+        assert!(prompt.contains("fourth point"));
+        assert!(!prompt.contains("second point"));
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn editing_a_covered_message_resummarises_the_conversation() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let (base_url, requests) = recording_ollama(&["Two points made.", "Edited points made."]);
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, base_url);
+        conn.execute(
+            "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, created_at, updated_at) VALUES ('c1', NULL, 'ollama', 'llama3.1', 0, 0, 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, body, created_at) VALUES ('m1', 'c1', 'user', 'first point', 100), ('m2', 'c1', 'user', 'second point', 100)",
+            [],
+        )
+        .unwrap();
+
+        let full = summarizer.summarise_conversation("c1").await.unwrap();
+        assert_eq!(full.covered_message_id.as_deref(), Some("m2"));
+
+        summarizer.edit_message("m2", "revised point").unwrap();
+        let refreshed = summarizer.summarise_conversation("c1").await.unwrap();
+        assert_ne!(refreshed.id, full.id);
+        assert_eq!(refreshed.body, "Edited points made.");
+        assert_eq!(refreshed.covered_message_id.as_deref(), Some("m2"));
+        let prompt = requests.lock().unwrap()[1].clone();
+        assert!(prompt.contains("revised point"));
+        assert!(!prompt.contains("second point"));
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn approx_tokens_scales_with_length() {
         assert!(approx_tokens("short") > 0);
@@ -954,37 +3787,912 @@ mod tests {
     }
 
     #[test]
-    fn parse_context_tag_handles_suffixes() {
-        assert_eq!(parse_context_tag("ctx-4096"), Some(4096));
-        assert_eq!(parse_context_tag("ctx-8k"), Some(8000));
-        assert_eq!(parse_context_tag("other"), None);
+    fn context_limit_prefers_model_overrides() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        crate::agents::config::seed_defaults(&conn).unwrap();
+
+        let limit = |model: &str| context_limit_from_tags(&conn, "openai", model).unwrap();
+        assert_eq!(limit("gpt-3.5-turbo"), 16_385);
+        assert_eq!(limit("gpt-4o-mini"), 128_000);
+        assert_eq!(limit("gpt-unknown"), 128_000);
+
+        crate::agents::config::set_model_context_window(
+            &conn,
+            "openai",
+            "gpt-unknown",
+            Some(32_768),
+        )
+        .unwrap();
+        assert_eq!(limit("gpt-unknown"), 32_768);
+    }
+
+    #[test]
+    fn estimate_cost_uses_price_table() {
+        let cost = estimate_cost("gpt-4o-mini", 1_000_000, 1_000_000).unwrap();
+        assert!((cost - 0.75).abs() < 1e-9);
+        assert_eq!(estimate_cost("llama3.1", 10, 10), None);
+    }
+
+    fn message(index: usize, body: &str) -> MessageRecord {
+        MessageRecord {
+            id: format!("m{index}"),
+            conversation_id: "c1".into(),
+            role: "user".into(),
+            body: body.into(),
+            token_est: None,
+            created_at: index as i64,
+            quality_flags: None,
+        }
+    }
+
+    #[test]
+    fn stopwords_do_not_reinject_unrelated_history() {
+        assert!(
+            extract_keywords("Really, there was something about those things, right?").is_empty()
+        );
+
+        let mut messages = vec![
+            message(0, "There was really something about the weather"),
+            message(1, "Budget spreadsheet for the kitchen renovation"),
+        ];
+        messages.extend((2..14).map(|i| message(i, "tail")));
+        let excerpts = select_conversation_excerpts(
+            &messages,
+            Some(("user", "Really, what about there?")),
+            12,
+        );
+        assert_eq!(excerpts.len(), 13);
+        assert!(!excerpts.iter().any(|e| e.contains("weather")));
+
+        let excerpts = select_conversation_excerpts(
+            &messages,
+            Some(("user", "Revisit the renovation budget")),
+            12,
+        );
+        assert_eq!(
+            excerpts[0],
+            "user: Budget spreadsheet for the kitchen renovation"
+        );
+    }
+
+    #[test]
+    fn tail_length_is_respected_and_pending_message_kept() {
+        let messages: Vec<MessageRecord> = (0..10)
+            .map(|i| message(i, "word ".repeat(40).trim()))
+            .collect();
+        for tail in [1, 3, 20] {
+            let excerpts = select_conversation_excerpts(&messages, Some(("user", "latest")), tail);
+            assert_eq!(excerpts.len(), tail.min(10) + 1);
+            assert_eq!(excerpts[0], "user: latest");
+        }
+        let excerpts = select_conversation_excerpts(&messages, None, 3);
+        assert_eq!(excerpts.len(), 3);
+
+        let per_message = approx_tokens(&messages[0].body);
+        let counter = HeuristicCounter;
+        assert_eq!(tail_len_for_budget(&messages, per_message * 4, &counter), 4);
+        assert_eq!(tail_len_for_budget(&messages, 0, &counter), 1);
+        assert_eq!(tail_len_for_budget(&messages, usize::MAX, &counter), 10);
+    }
+
+    #[test]
+    fn back_references_are_capped_and_skip_common_terms() {
+        let mut messages: Vec<MessageRecord> = (0..20)
+            .map(|i| message(i, &format!("garden update {i} mentions tomatoes")))
+            .collect();
+        messages.extend((20..32).map(|i| message(i, "tail")));
+
+        // "garden" is in every older message, so it is not salient.
+        let excerpts = select_conversation_excerpts(&messages, Some(("user", "garden plans")), 12);
+        assert_eq!(excerpts.len(), 13);
+
+        let excerpts =
+            select_conversation_excerpts(&messages, Some(("user", "tomatoes and garden")), 12);
+        assert_eq!(excerpts.len(), 13);
+        messages[3].body = "the greenhouse needs repair".into();
+        messages[9].body = "greenhouse heater broke".into();
+        let excerpts = select_conversation_excerpts(&messages, Some(("user", "greenhouse")), 12);
+        assert_eq!(excerpts.len(), 15);
+        assert!(excerpts[0].contains("repair") && excerpts[1].contains("heater"));
+
+        let mut messages: Vec<MessageRecord> = (0..40)
+            .map(|i| {
+                let body = if i % 5 == 0 { "greenhouse" } else { "filler" };
+                message(i, body)
+            })
+            .collect();
+        messages.extend((40..52).map(|i| message(i, "tail")));
+        let excerpts = select_conversation_excerpts(&messages, Some(("user", "greenhouse")), 12);
+        assert_eq!(excerpts.len(), 13 + MAX_BACK_REFERENCES);
+        assert_eq!(excerpts[0], "user: greenhouse");
+    }
+
+    #[test]
+    fn rollover_chain_is_walked_in_both_directions() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE links (id TEXT PRIMARY KEY, src_id TEXT, src_type TEXT, dst_id TEXT, dst_type TEXT, rel TEXT, created_at INTEGER);",
+        )
+        .unwrap();
+        for (from, summary, to) in [("a", "s1", "b"), ("b", "s2", "c")] {
+            insert_link(
+                &conn,
+                from,
+                "conversation",
+                summary,
+                "summary",
+                "summarised_as",
+            )
+            .unwrap();
+            insert_link(&conn, summary, "summary", to, "conversation", "rollover_to").unwrap();
+        }
+        assert_eq!(rollover_chain(&conn, "b").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(rollover_chain(&conn, "z").unwrap(), vec!["z"]);
+    }
+
+    #[test]
+    fn fork_copies_messages_up_to_the_chosen_one() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        {
+            let conn = pool.get().unwrap();
+            conn.execute(
+                "INSERT INTO conversations (id, title, provider_id, model_id, ctx_warn, ctx_force, system_prompt, created_at, updated_at) VALUES ('src', 'Plans', 'ollama', 'llama3', 1, 1, 'Be terse.', 0, 0)",
+                [],
+            )
+            .unwrap();
+            for (id, role, tokens) in [("m1", "user", 3), ("m2", "assistant", 5), ("m3", "user", 7)]
+            {
+                conn.execute(
+                    "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES (?1, 'src', ?2, ?1, ?3, 10)",
+                    params![id, role, tokens],
+                )
+                .unwrap();
+            }
+        }
+
+        let fork = summarizer.fork_conversation("src", "m2").unwrap();
+        assert_ne!(fork.id, "src");
+        assert_eq!(fork.title.as_deref(), Some("Plans"));
+        assert_eq!(fork.provider_id, "ollama");
+        assert_eq!(fork.model_id, "llama3");
+        assert!(!fork.ctx_warn && !fork.ctx_force);
+        assert_eq!(fork.total_tokens, 8);
+        assert_eq!(fork.system_prompt.as_deref(), Some("Be terse."));
+
+        let conn = pool.get().unwrap();
+        let bodies: Vec<String> = list_messages(&conn, &fork.id, None)
+            .unwrap()
+            .into_iter()
+            .map(|message| message.body)
+            .collect();
+        assert_eq!(bodies, ["m1", "m2"]);
+        let source: String = conn
+            .query_row(
+                "SELECT dst_id FROM links WHERE src_id = ?1 AND rel = 'forked_from'",
+                [&fork.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(source, "src");
+        assert!(summarizer.fork_conversation("src", "elsewhere").is_err());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn edit_message_truncates_later_messages_and_recounts_tokens() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        {
+            let conn = pool.get().unwrap();
+            conn.execute(
+                "INSERT INTO conversations (id, provider_id, model_id, ctx_warn, created_at, updated_at) VALUES ('c1', 'ollama', 'llama3', 1, 0, 0)",
+                [],
+            )
+            .unwrap();
+            for (id, role) in [("m1", "user"), ("m2", "assistant"), ("m3", "user")] {
+                conn.execute(
+                    "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES (?1, 'c1', ?2, ?1, 1000, 10)",
+                    params![id, role],
+                )
+                .unwrap();
+            }
+        }
+
+        let update = summarizer.edit_message("m2", "  fixed typo\n").unwrap();
+        let ids: Vec<&str> = update.messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["m1", "m2"]);
+        assert_eq!(update.messages[1].body, "fixed typo");
+        assert!(update.messages[1].token_est.unwrap() < 1000);
+        assert_eq!(
+            update.conversation.total_tokens,
+            1000 + update.messages[1].token_est.unwrap()
+        );
+        assert!(!update.conversation.ctx_warn);
+        assert!(!update.rolled);
+
+        let conn = pool.get().unwrap();
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM event_log WHERE code = 'AI-EDIT' AND json_extract(data, '$.truncated') = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 1);
+        let err = summarizer.edit_message("m3", "gone").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::MessageNotFound)
+        ));
+        let err = summarizer.edit_message("m1", "  ").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::InvalidField { field, .. }) if field == "new_body"
+        ));
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// A rolled-over conversation with 20 messages, routed to `base_url`.
+    fn rolled_over_conversation(pool: &DbPool, base_url: String) {
+        let conn = pool.get().unwrap();
+        use_ollama_at(&conn, base_url);
+        conn.execute(
+            "INSERT INTO conversations (id, provider_id, model_id, ctx_force, created_at, updated_at) VALUES ('old', 'ollama', 'llama3', 1, 0, 0)",
+            [],
+        )
+        .unwrap();
+        for n in 0..20 {
+            conn.execute(
+                "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES (?1, 'old', 'user', ?2, 1, ?3)",
+                params![format!("m{n}"), format!("message {n}"), n],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn deleting_a_conversation_keeps_its_usage() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('a', 'ollama', 'llama3', 0, 0), ('b', 'ollama', 'llama3', 0, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES ('m', 'a', 'assistant', 'Hi.', 1, 0)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO usage (id, message_id, conversation_id, provider_id, model_id, total_tokens, created_at) VALUES ('u', 'm', 'a', 'ollama', 'llama3', 42, 0)",
+            [],
+        )
+        .unwrap();
+        insert_link(&conn, "a", "conversation", "s", "summary", "summarised_as").unwrap();
+        insert_link(&conn, "s", "summary", "b", "conversation", "rollover_to").unwrap();
+
+        let err = summarizer
+            .delete_conversation("a", false, false)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::ValidationFailed(_))
+        ));
+
+        summarizer.delete_conversation("a", false, true).unwrap();
+        let kept: (Option<String>, Option<String>, i64) = conn
+            .query_row(
+                "SELECT conversation_id, message_id, total_tokens FROM usage WHERE id = 'u'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(kept, (None, None, 42));
+        assert!(fetch_conversation(&conn, "a").unwrap().is_none());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn regenerate_keeps_the_old_reply_until_the_new_one_is_stored() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        // An empty reply is rejected by the append, a real one replaces the old.
+        let base_url = scripted_ollama(&["", "Second try."]);
+        {
+            let conn = pool.get().unwrap();
+            use_ollama_at(&conn, base_url);
+            conn.execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('c', 'ollama', 'llama3.1', 0, 0)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO messages (id, conversation_id, role, body, token_est, created_at) VALUES ('q', 'c', 'user', 'Ready?', 1, 0), ('a', 'c', 'assistant', 'First try.', 1, 1)",
+                [],
+            )
+            .unwrap();
+        }
+        let bodies = || -> Vec<String> {
+            summarizer
+                .list_messages("c", None)
+                .unwrap()
+                .into_iter()
+                .map(|message| message.body)
+                .collect()
+        };
+
+        let err = summarizer.regenerate_last("c").await.unwrap_err();
+        assert!(err.to_string().starts_with("content:"), "{err}");
+        assert_eq!(bodies(), ["Ready?", "First try."]);
+
+        let update = summarizer.regenerate_last("c").await.unwrap();
+        assert!(!update.rolled);
+        assert_eq!(bodies(), ["Ready?", "Second try."]);
+        drop(pool);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn edits_and_regenerations_keep_the_usage_of_removed_replies() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        {
+            let conn = pool.get().unwrap();
+            use_ollama_at(&conn, scripted_ollama(&["Second try."]));
+            conn.execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('c', 'ollama', 'llama3.1', 0, 0)",
+                [],
+            )
+            .unwrap();
+        }
+        let question = summarizer
+            .append_and_maybe_rollover("c", "user", "Ready?", None)
+            .unwrap()
+            .message;
+        let usage = AiUsageMetrics {
+            prompt_tokens: Some(10),
+            completion_tokens: Some(5),
+            total_tokens: None,
+        };
+        summarizer
+            .append_and_maybe_rollover("c", "assistant", "First try.", Some(&usage))
+            .unwrap();
+        let first = summarizer.conversation_usage("c").unwrap();
+        assert_eq!((first.recorded_calls, first.total_tokens), (1, 15));
+
+        summarizer.regenerate_last("c").await.unwrap();
+        let regenerated = summarizer.conversation_usage("c").unwrap();
+        assert_eq!(regenerated.recorded_calls, 2);
+        assert!(regenerated.total_tokens > first.total_tokens);
+
+        let update = summarizer.edit_message(&question.id, "Ready now?").unwrap();
+        assert_eq!(update.messages.len(), 1);
+        let edited = summarizer.conversation_usage("c").unwrap();
+        assert_eq!(
+            (edited.recorded_calls, edited.total_tokens),
+            (regenerated.recorded_calls, regenerated.total_tokens)
+        );
+        drop(pool);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn reply_usage_names_the_provider_that_answered() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let conn = pool.get().unwrap();
+        crate::agents::config::seed_defaults(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('c', 'openai', 'gpt-4o', 0, 0)",
+            [],
+        )
+        .unwrap();
+        let usage = |prompt, completion| AiUsageMetrics {
+            prompt_tokens: Some(prompt),
+            completion_tokens: Some(completion),
+            total_tokens: None,
+        };
+        // `ai_chat` fell back to another provider for this reply.
+        let response = AiChatResponse {
+            provider_id: "anthropic".into(),
+            model: "claude-3-5-sonnet".into(),
+            content: "Hello.".into(),
+            usage: Some(usage(10, 2)),
+            raw: serde_json::Value::Null,
+            cached: false,
+            reasoning: None,
+            request_id: None,
+        };
+        summarizer.record_pending_usage("c", &response).unwrap();
+        let first = summarizer
+            .append_and_maybe_rollover("c", "assistant", "Hello.", Some(&usage(12, 3)))
+            .unwrap();
+        // A regenerated reply knows its responder and has no pending row.
+        let second = summarizer
+            .append_message(
+                "c",
+                "assistant",
+                "Hi again.",
+                Some(&usage(20, 4)),
+                Some(("lmstudio", "qwen2.5")),
+                None,
+            )
+            .unwrap();
+
+        let rows: Vec<(Option<String>, String, String, i64)> = conn
+            .prepare("SELECT message_id, provider_id, model_id, total_tokens FROM usage ORDER BY total_tokens")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (
+                    Some(first.message.id),
+                    "anthropic".to_string(),
+                    "claude-3-5-sonnet".to_string(),
+                    15
+                ),
+                (
+                    Some(second.message.id),
+                    "lmstudio".to_string(),
+                    "qwen2.5".to_string(),
+                    24
+                ),
+            ]
+        );
+        drop(conn);
+        drop(pool);
+        let _ = std::fs::remove_file(path);
+    }
+
+    fn pruned_summaries(conn: &rusqlite::Connection) -> Vec<(String, String)> {
+        conn.prepare(
+            "SELECT s.source, s.body FROM links l JOIN summaries s ON s.id = l.dst_id
+             WHERE l.src_id = 'old' AND l.rel = 'summarised_as'",
+        )
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap()
+    }
+
+    #[test]
+    fn prune_summarises_the_removed_messages_and_keeps_the_tail() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        let (base_url, requests) = recording_ollama(&["Earlier chat"]);
+        rolled_over_conversation(&pool, base_url);
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO usage (id, message_id, conversation_id, provider_id, model_id, total_tokens, created_at) VALUES ('u1', 'm0', 'old', 'ollama', 'llama3', 42, 0)",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(
+            summarizer.prune_conversation("old").unwrap(),
+            20 - DEFAULT_ROLLOVER_TAIL
+        );
+
+        // The summary was written from exactly the messages that were removed.
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("user: message 0"));
+        assert!(requests[0].contains("user: message 7"));
+        assert!(!requests[0].contains("message 8"));
+        let conn = pool.get().unwrap();
+        assert_eq!(
+            pruned_summaries(&conn),
+            [(SOURCE_AI.to_string(), "Earlier chat".to_string())]
+        );
+        let kept = list_messages(&conn, "old", None).unwrap();
+        assert_eq!(kept.len(), DEFAULT_ROLLOVER_TAIL);
+        assert_eq!(kept.first().unwrap().id, "m8");
+        let spend: i64 = conn
+            .query_row(
+                "SELECT SUM(total_tokens) FROM usage WHERE conversation_id = 'old'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(spend, 42);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn prune_conversation_keeps_messages_behind_a_fallback_summary() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        rolled_over_conversation(&pool, scripted_ollama(&[""]));
+
+        let err = summarizer.prune_conversation("old").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::ValidationFailed(_))
+        ));
+        let conn = pool.get().unwrap();
+        assert_eq!(list_messages(&conn, "old", None).unwrap().len(), 20);
+        assert!(pruned_summaries(&conn).is_empty());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rollover_prunes_only_once_the_model_summarises_the_pruned_messages() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        {
+            let conn = pool.get().unwrap();
+            // Each rollover asks for its seed summary, then for a
+            // summary of the messages it is about to prune.
+            use_ollama_at(
+                &conn,
+                scripted_ollama(&[
+                    "Release discussed.",
+                    "",
+                    "Shipped the release.",
+                    "Asked whether the release was ready.",
+                ]),
+            );
+        }
+        summarizer.update_config(0.75, 0.9, None, 1, false).unwrap();
+        summarizer.set_prune_after_rollover(true).unwrap();
+
+        let mut remaining = Vec::new();
+        for _ in 0..2 {
+            let conversation = summarizer
+                .create_conversation(
+                    Some("Release".into()),
+                    Some("ollama".into()),
+                    None,
+                    None,
+                    None,
+                )
+                .unwrap();
+            for (role, body) in [
+                ("user", "Is the release ready?"),
+                ("assistant", "Yes, the checklist is done."),
+                ("user", "Ship it."),
+            ] {
+                summarizer
+                    .append_and_maybe_rollover(&conversation.id, role, body, None)
+                    .unwrap();
+            }
+            summarizer.rollover(&conversation.id).unwrap();
+            let conn = pool.get().unwrap();
+            remaining.push(list_messages(&conn, &conversation.id, None).unwrap().len());
+        }
+        // The first prune only got fallback text, so its messages must stay.
+        assert_eq!(remaining, [3, 1]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn append_trims_the_body_and_rejects_blank_messages() {
+        let (summarizer, pool, path) = file_backed_summarizer();
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at) VALUES ('c1', 'ollama', 'llama3', 0, 0)",
+                [],
+            )
+            .unwrap();
+
+        let err = summarizer
+            .append_and_maybe_rollover("c1", "user", "  \n ", None)
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::InvalidField { field, .. }) if field == "content"
+        ));
+        let appended = summarizer
+            .append_and_maybe_rollover("c1", "user", "  hello \n", None)
+            .unwrap();
+        assert_eq!(appended.message.body, "hello");
+        assert_eq!(summarizer.list_messages("c1", None).unwrap().len(), 1);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn pinned_conversations_sort_first_across_pages() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        for (id, updated_at, pinned) in [("a", 10, 0), ("b", 30, 0), ("c", 5, 1), ("d", 20, 1)] {
+            conn.execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at, pinned) VALUES (?1, 'ollama', 'm', 0, ?2, ?3)",
+                params![id, updated_at, pinned],
+            )
+            .unwrap();
+        }
+
+        let first = list_conversations(&conn, Some(3), None, false, false).unwrap();
+        let ids: Vec<&str> = first.items.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, ["d", "c", "b"]);
+        // Pinning the cursor's row before the next page loads neither
+        // repeats the pinned rows nor skips the rest.
+        conn.execute("UPDATE conversations SET pinned = 1 WHERE id = 'b'", [])
+            .unwrap();
+        let rest = list_conversations(&conn, Some(3), first.next_cursor, false, false).unwrap();
+        assert_eq!(rest.items.len(), 1);
+        assert_eq!(rest.items[0].id, "a");
+
+        let pinned = list_conversations(&conn, None, None, false, true).unwrap();
+        assert!(pinned.items.iter().all(|c| c.pinned));
+        assert_eq!(pinned.items.len(), 3);
+    }
+
+    #[test]
+    fn clean_title_keeps_one_short_line() {
+        assert_eq!(
+            clean_title("\n\"Planning the Q3 database migration rollout together.\"\nExtra")
+                .as_deref(),
+            Some("Planning the Q3 database migration rollout")
+        );
+        assert_eq!(
+            clean_title("Title: Trip ideas").as_deref(),
+            Some("Trip ideas")
+        );
+        assert_eq!(clean_title("  \n**").as_deref(), None);
+    }
+
+    #[test]
+    fn message_search_groups_hits_by_conversation() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        for (id, title) in [("infra", "Infra planning"), ("misc", "Misc")] {
+            conn.execute(
+                "INSERT INTO conversations (id, title, provider_id, model_id, created_at, updated_at) VALUES (?1, ?2, 'ollama', 'm', 0, 0)",
+                params![id, title],
+            )
+            .unwrap();
+        }
+        let counter = counter_for(&conn, "ollama", "m");
+        insert_message(
+            &conn,
+            counter.as_ref(),
+            "infra",
+            "user",
+            "Draft the migration plan",
+        )
+        .unwrap();
+        insert_message(
+            &conn,
+            counter.as_ref(),
+            "infra",
+            "assistant",
+            "The migration plan has three phases",
+        )
+        .unwrap();
+        insert_message(
+            &conn,
+            counter.as_ref(),
+            "misc",
+            "user",
+            "Lunch plan for Friday",
+        )
+        .unwrap();
+        insert_message(
+            &conn,
+            counter.as_ref(),
+            "misc",
+            "user",
+            "Bird migration facts",
+        )
+        .unwrap();
+
+        let groups = search_messages(&conn, "migration plan", 10).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].title.as_deref(), Some("Infra planning"));
+        assert_eq!(groups[0].match_count, 2);
+        assert!(groups[0].messages[0].snippet.contains("**migration**"));
+
+        assert_eq!(search_messages(&conn, "migration", 10).unwrap().len(), 2);
+        assert!(search_messages(&conn, "\"plan\" OR", 10).is_ok());
+        assert!(search_messages(&conn, "  ", 10).is_err());
+    }
+
+    #[test]
+    fn conversation_defaults_fill_gaps_in_chat_requests() {
+        let (summarizer, _pool, path) = file_backed_summarizer();
+        let conn = summarizer.pool.get().unwrap();
+        crate::agents::config::seed_defaults(&conn).unwrap();
+        drop(conn);
+        let conversation = summarizer
+            .create_conversation(
+                None,
+                Some("ollama".into()),
+                None,
+                Some("  Review strictly.  ".into()),
+                Some(0.1),
+            )
+            .unwrap();
+        assert_eq!(
+            conversation.system_prompt.as_deref(),
+            Some("Review strictly.")
+        );
+        assert!(summarizer
+            .set_conversation_prompt(&conversation.id, None, Some(3.5))
+            .is_err());
+
+        let user = AiChatMessage {
+            role: "user".into(),
+            content: "hi".into(),
+            images: Vec::new(),
+        };
+        let mut input = AiChatInput {
+            messages: vec![user.clone()],
+            temperature: None,
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        };
+        conversation.apply_chat_defaults(&mut input);
+        assert_eq!(input.temperature, Some(0.1));
+        assert_eq!(input.messages[0].role, "system");
+        assert_eq!(input.messages[0].content, "Review strictly.");
+
+        let mut overridden = AiChatInput {
+            messages: vec![
+                AiChatMessage {
+                    role: "system".into(),
+                    content: "Brainstorm.".into(),
+                    images: Vec::new(),
+                },
+                user,
+            ],
+            temperature: Some(0.9),
+            response_format: None,
+            stop: None,
+            top_p: None,
+            max_tokens: None,
+            reasoning_effort: None,
+            request_id: None,
+        };
+        conversation.apply_chat_defaults(&mut overridden);
+        assert_eq!(overridden.temperature, Some(0.9));
+        assert_eq!(overridden.messages.len(), 2);
+        assert_eq!(overridden.messages[0].content, "Brainstorm.");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn chunk_excerpts_respects_budget() {
+        let long = "word ".repeat(2_000);
+        let excerpts = vec!["short note".to_string(), long];
+        let chunks = chunk_excerpts(&excerpts, 300, &HeuristicCounter);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| approx_tokens(chunk) <= 300));
+        assert!(chunks[0].starts_with("short note"));
+    }
+
+    #[test]
+    fn chunk_excerpts_measures_with_the_selected_counter() {
+        // Counts every character, so heuristic-sized pieces are too big.
+        struct CharCounter;
+        impl TokenCounter for CharCounter {
+            fn count(&self, text: &str) -> usize {
+                text.chars().count()
+            }
+        }
+        let excerpts = vec!["word ".repeat(2_000)];
+        let chunks = chunk_excerpts(&excerpts, 300, &CharCounter);
+        assert!(chunks.len() >= 10000 / 300);
+        assert!(chunks.iter().all(|chunk| CharCounter.count(chunk) <= 300));
+    }
+
+    #[test]
+    fn rollover_guard_trips_after_repeated_rollovers() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE links (id TEXT PRIMARY KEY, src_id TEXT, src_type TEXT, dst_id TEXT, dst_type TEXT, rel TEXT, created_at INTEGER);
+             CREATE TABLE conversations (id TEXT PRIMARY KEY, created_at INTEGER);",
+        )
+        .unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for id in ["a", "b", "c"] {
+            conn.execute(
+                "INSERT INTO conversations (id, created_at) VALUES (?1, ?2)",
+                params![id, now],
+            )
+            .unwrap();
+        }
+        for (from, summary, to) in [("a", "s1", "b"), ("b", "s2", "c")] {
+            insert_link(
+                &conn,
+                from,
+                "conversation",
+                summary,
+                "summary",
+                "summarised_as",
+            )
+            .unwrap();
+            insert_link(&conn, summary, "summary", to, "conversation", "rollover_to").unwrap();
+        }
+        let conversation = ConversationRecord {
+            id: "c".into(),
+            title: None,
+            provider_id: "ollama".into(),
+            model_id: "llama3.1".into(),
+            ctx_warn: false,
+            ctx_force: false,
+            created_at: now,
+            updated_at: now,
+            closed_at: None,
+            quality_flags: None,
+            total_tokens: 0,
+            context_limit: DEFAULT_CONTEXT_LIMIT,
+            warn_threshold: 3072,
+            force_threshold: 3686,
+            pinned: false,
+            system_prompt: None,
+            default_temperature: None,
+        };
+        let mut config = SummarizerConfig {
+            warn_ratio: 0.75,
+            force_ratio: 0.9,
+            summarizer_model: None,
+            max_rollovers_per_window: 3,
+            rollover_tail: DEFAULT_ROLLOVER_TAIL,
+            rollover_tail_auto: false,
+            prune_after_rollover: false,
+            focus: None,
+        };
+        assert_eq!(
+            recent_rollovers(&conn, &conversation, &config).unwrap(),
+            None
+        );
+        config.max_rollovers_per_window = 2;
+        assert_eq!(
+            recent_rollovers(&conn, &conversation, &config).unwrap(),
+            Some(2)
+        );
     }
 
     #[test]
     fn insert_summary_assigns_incrementing_versions() {
         let conn = SqliteConnection::open_in_memory().unwrap();
         conn.execute_batch(
-            "CREATE TABLE summaries (id TEXT PRIMARY KEY, target_type TEXT, target_id TEXT, version INTEGER, body TEXT, token_est INTEGER, source_hash TEXT, model_id TEXT, created_at INTEGER);",
+            "CREATE TABLE summaries (id TEXT PRIMARY KEY, target_type TEXT, target_id TEXT, version INTEGER, body TEXT, token_est INTEGER, source_hash TEXT, model_id TEXT, created_at INTEGER, source TEXT, covered_through INTEGER, covered_message_id TEXT);",
         )
         .unwrap();
         let summary1 = insert_summary(
             &conn,
-            "conversation",
-            "a",
-            "Body",
-            "hash",
-            Some("model".into()),
+            NewSummary {
+                target_type: "conversation",
+                target_id: "a",
+                body: "Body",
+                source_hash: "hash",
+                model_id: Some("model".into()),
+                source: SOURCE_AI,
+            },
+            &HeuristicCounter,
         )
         .unwrap();
         let summary2 = insert_summary(
             &conn,
-            "conversation",
-            "a",
-            "Body",
-            "hash",
-            Some("model".into()),
+            NewSummary {
+                target_type: "conversation",
+                target_id: "a",
+                body: "Body",
+                source_hash: "hash",
+                model_id: Some("model".into()),
+                source: SOURCE_AI,
+            },
+            &HeuristicCounter,
         )
         .unwrap();
         assert_eq!(summary1.version + 1, summary2.version);
+
+        let history = list_summaries(&conn, "conversation", "a").unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].version, summary2.version);
+        assert_eq!(history[0].source, SOURCE_AI);
     }
 }
@@ -0,0 +1,39 @@
+//! Note tags shared by the tagging commands and the Markdown importer.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+/// Trim, lowercase, sort, and de-duplicate tag names, dropping empty ones.
+pub fn normalize(raw: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = raw
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Replace the tags on `note_id` with the already normalised `tags`, then
+/// drop tags no longer attached to any note. Callers check that the note
+/// exists and wrap this in their transaction.
+pub fn replace(conn: &Connection, note_id: &str, tags: &[String], now: i64) -> Result<()> {
+    conn.execute("DELETE FROM note_tags WHERE note_id = ?1", params![note_id])?;
+    for tag in tags {
+        conn.execute(
+            "INSERT INTO tags (id, name, created_at) VALUES (?1, ?2, ?3) ON CONFLICT(name) DO NOTHING",
+            params![Uuid::new_v4().to_string(), tag, now],
+        )?;
+        conn.execute(
+            "INSERT INTO note_tags (note_id, tag_id, created_at) SELECT ?1, id, ?3 FROM tags WHERE name = ?2",
+            params![note_id, tag, now],
+        )?;
+    }
+    conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM note_tags)",
+        [],
+    )?;
+    Ok(())
+}
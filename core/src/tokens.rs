@@ -0,0 +1,171 @@
+//! Token counting strategies used for context budgeting.
+//!
+//! The default [`HeuristicCounter`] is a cheap character/word estimate that
+//! works for any model. When the `tiktoken` feature is enabled, OpenAI-style
+//! models are counted with their real BPE vocabulary instead. Counters are
+//! cached per provider/model pair so callers can request one on every append.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use r2d2_sqlite::rusqlite::Connection;
+use serde_json::json;
+
+use crate::logging::log_event;
+
+/// Strategy for turning text into a token count for a specific model.
+pub trait TokenCounter: Send + Sync {
+    /// Count the tokens `text` would consume.
+    fn count(&self, text: &str) -> usize;
+
+    /// Whether the counts come from the model's real tokenizer.
+    fn is_exact(&self) -> bool {
+        false
+    }
+}
+
+/// Character/word heuristic that approximates most BPE vocabularies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicCounter;
+
+impl TokenCounter for HeuristicCounter {
+    fn count(&self, text: &str) -> usize {
+        approx_tokens(text)
+    }
+}
+
+/// Estimate tokens using a simple character based heuristic.
+pub fn approx_tokens(text: &str) -> usize {
+    let chars = text.chars().count() as f32;
+    let words = text.split_whitespace().count() as f32;
+    let char_est = (chars / 4.0).ceil();
+    let word_est = (words * 1.1).ceil();
+    char_est.max(word_est).max(1.0) as usize
+}
+
+/// Exact counter backed by a `tiktoken` BPE table.
+#[cfg(feature = "tiktoken")]
+pub struct BpeCounter {
+    bpe: tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl TokenCounter for BpeCounter {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len().max(1)
+    }
+
+    fn is_exact(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+fn bpe_counter(model_id: &str) -> Option<Arc<dyn TokenCounter>> {
+    let bpe = tiktoken_rs::get_bpe_from_model(model_id).ok()?;
+    Some(Arc::new(BpeCounter { bpe }))
+}
+
+#[cfg(not(feature = "tiktoken"))]
+fn bpe_counter(_model_id: &str) -> Option<Arc<dyn TokenCounter>> {
+    None
+}
+
+fn counter_cache() -> &'static Mutex<HashMap<String, Arc<dyn TokenCounter>>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, Arc<dyn TokenCounter>>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn approx_notified() -> &'static Mutex<HashSet<String>> {
+    static NOTIFIED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    NOTIFIED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Return the best available counter for a provider/model pair.
+///
+/// Models without a BPE table fall back to [`HeuristicCounter`]; the first
+/// fallback per model records an `AI-TOKEN-APPROX` event so users know the
+/// context figures are estimates.
+pub fn counter_for(conn: &Connection, provider_id: &str, model_id: &str) -> Arc<dyn TokenCounter> {
+    let key = format!("{provider_id}/{model_id}");
+    if let Some(counter) = counter_cache()
+        .lock()
+        .ok()
+        .and_then(|cache| cache.get(&key).cloned())
+    {
+        return counter;
+    }
+
+    let counter = bpe_counter(model_id).unwrap_or_else(|| Arc::new(HeuristicCounter));
+    // Heuristic counters are only marked as notified, and cached, once the
+    // event is written, so a failed write is retried on the next lookup.
+    let mut settled = true;
+    if !counter.is_exact() {
+        let pending = approx_notified()
+            .lock()
+            .map(|seen| !seen.contains(&key))
+            .unwrap_or(false);
+        if pending {
+            settled = log_event(
+                conn,
+                "info",
+                Some("AI-TOKEN-APPROX"),
+                "ai.context",
+                "Token counts are estimated",
+                Some("No tokenizer table is available for this model; using the heuristic."),
+                Some(json!({
+                    "provider": provider_id,
+                    "model": model_id,
+                })),
+            )
+            .is_ok();
+            if settled {
+                if let Ok(mut seen) = approx_notified().lock() {
+                    seen.insert(key.clone());
+                }
+            }
+        }
+    }
+
+    if settled {
+        if let Ok(mut cache) = counter_cache().lock() {
+            cache.insert(key, Arc::clone(&counter));
+        }
+    }
+    counter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_models_fall_back_to_heuristic() {
+        let conn = Connection::open_in_memory().unwrap();
+        let counter = counter_for(&conn, "ollama", "llama3.1");
+        assert!(!counter.is_exact());
+        assert_eq!(counter.count("hello world"), approx_tokens("hello world"));
+    }
+
+    #[test]
+    fn failed_approximation_notices_are_retried() {
+        let bare = Connection::open_in_memory().unwrap();
+        counter_for(&bare, "ollama", "notice-retry");
+        assert!(!approx_notified()
+            .lock()
+            .unwrap()
+            .contains("ollama/notice-retry"));
+
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        counter_for(&conn, "ollama", "notice-retry");
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM event_log WHERE code = 'AI-TOKEN-APPROX'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 1);
+    }
+}
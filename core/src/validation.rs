@@ -0,0 +1,72 @@
+//! Field-level checks for IPC inputs.
+//!
+//! Each failure is an [`InkOsError::InvalidField`] naming the offending
+//! field, so the UI can attach the message to the right control.
+
+use time::macros::format_description;
+use time::Date;
+
+use crate::errors::InkOsError;
+
+/// Trimmed `value`, rejecting it when nothing but whitespace is left.
+pub fn required_text(field: &str, value: &str) -> Result<String, InkOsError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(invalid(field, "must not be empty"));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Parse a `YYYY-MM-DD` date, ignoring surrounding whitespace.
+pub fn parse_date(field: &str, value: &str) -> Result<Date, InkOsError> {
+    Date::parse(value.trim(), &format_description!("[year]-[month]-[day]")).map_err(|_| {
+        invalid(
+            field,
+            &format!("expected a date in YYYY-MM-DD form, got '{}'", value.trim()),
+        )
+    })
+}
+
+/// Normalised tag names, rejecting any that contain a comma: tag lists are
+/// read back and exported joined with commas.
+pub fn tag_names(field: &str, raw: &[String]) -> Result<Vec<String>, InkOsError> {
+    if let Some(tag) = raw.iter().find(|tag| tag.contains(',')) {
+        return Err(invalid(
+            field,
+            &format!("tag '{}' must not contain a comma", tag.trim()),
+        ));
+    }
+    Ok(crate::tags::normalize(raw))
+}
+
+fn invalid(field: &str, reason: &str) -> InkOsError {
+    InkOsError::InvalidField {
+        field: field.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::IpcError;
+
+    #[test]
+    fn note_titles_are_trimmed_and_must_not_be_blank() {
+        assert_eq!(required_text("title", "  Plans \n").unwrap(), "Plans");
+        let err = IpcError::from(required_text("title", " \t ").unwrap_err());
+        assert_eq!(err.code, "VAL-1001");
+        assert_eq!(err.field.as_deref(), Some("title"));
+        assert_eq!(err.message, "title: must not be empty");
+    }
+
+    #[test]
+    fn dates_must_be_calendar_days() {
+        let date = parse_date("date", " 2024-02-29 ").unwrap();
+        assert_eq!(date.to_string(), "2024-02-29");
+        for bad in ["2023-02-29", "29/02/2024", "", "2024-2-9"] {
+            let err = IpcError::from(parse_date("start_date", bad).unwrap_err());
+            assert_eq!(err.field.as_deref(), Some("start_date"));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Retention policy for the `jobs` and `event_log` tables.
+//!
+//! Long-running installs accumulate finished jobs and diagnostic events
+//! indefinitely. Pruning removes rows older than a configurable horizon while
+//! keeping a minimum window of AI runtime events for the debugger console.
+
+use anyhow::{Context, Result};
+use r2d2_sqlite::rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use time::OffsetDateTime;
+
+use crate::logging::log_event;
+use crate::settings;
+
+const RETAIN_DAYS_KEY: &str = "maintenance.retain_days";
+const DEFAULT_RETAIN_DAYS: u32 = 90;
+/// AI runtime events are always kept for at least this many days.
+const AI_EVENT_MIN_RETAIN_DAYS: u32 = 30;
+/// Reclaim disk space only when a prune removed a meaningful number of rows.
+const VACUUM_THRESHOLD: usize = 1000;
+
+/// Row counts removed by a single prune pass.
+#[derive(Debug, Clone, Serialize)]
+pub struct PruneReport {
+    pub retain_days: u32,
+    pub jobs_deleted: usize,
+    pub events_deleted: usize,
+    pub vacuumed: bool,
+}
+
+/// Read `maintenance.retain_days` from `app_settings`, defaulting to 90.
+pub fn read_retain_days(conn: &Connection) -> Result<u32> {
+    Ok(settings::read(conn, RETAIN_DAYS_KEY)?
+        .and_then(|v| v.parse().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETAIN_DAYS))
+}
+
+/// Delete finished jobs and event log rows older than `retain_days`.
+pub fn prune_history(conn: &Connection, retain_days: u32) -> Result<PruneReport> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let cutoff = now - i64::from(retain_days) * 86_400;
+    let ai_cutoff = now - i64::from(retain_days.max(AI_EVENT_MIN_RETAIN_DAYS)) * 86_400;
+
+    let jobs_deleted = conn
+        .execute(
+            "DELETE FROM jobs WHERE state IN ('succeeded', 'failed', 'cancelled') AND updated_at < ?1",
+            params![cutoff],
+        )
+        .context("failed to prune jobs")?;
+    let events_deleted = conn
+        .execute(
+            "DELETE FROM event_log WHERE ts < ?1 AND (module != 'ai.runtime' OR ts < ?2)",
+            params![cutoff, ai_cutoff],
+        )
+        .context("failed to prune event log")?;
+
+    let vacuumed = jobs_deleted + events_deleted > VACUUM_THRESHOLD;
+    if vacuumed {
+        conn.execute_batch("VACUUM")
+            .context("failed to vacuum database")?;
+    }
+
+    let report = PruneReport {
+        retain_days,
+        jobs_deleted,
+        events_deleted,
+        vacuumed,
+    };
+    log_event(
+        conn,
+        "info",
+        Some("SYS-LOG-120"),
+        "jobs.maintenance",
+        "Pruned job and event history",
+        Some("Removed finished jobs and events older than the retention horizon."),
+        Some(json!(report)),
+    )
+    .context("failed to log prune summary")?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_recent_ai_events_inside_exemption_window() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE jobs (id TEXT PRIMARY KEY, state TEXT, updated_at INTEGER);
+             CREATE TABLE event_log (id TEXT PRIMARY KEY, ts INTEGER, level TEXT, code TEXT, module TEXT, message TEXT, explain TEXT, data TEXT);",
+        )
+        .unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let ten_days_ago = now - 10 * 86_400;
+        conn.execute(
+            "INSERT INTO event_log (id, ts, level, module, message) VALUES ('a', ?1, 'info', 'ai.runtime', 'ai'), ('b', ?1, 'info', 'jobs.daily', 'job')",
+            params![ten_days_ago],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO jobs (id, state, updated_at) VALUES ('done', 'succeeded', ?1), ('next', 'queued', ?1)",
+            params![ten_days_ago],
+        )
+        .unwrap();
+
+        let report = prune_history(&conn, 1).unwrap();
+        assert_eq!(report.jobs_deleted, 1);
+        assert_eq!(report.events_deleted, 1);
+        let remaining: String = conn
+            .query_row("SELECT module FROM event_log WHERE id = 'a'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(remaining, "ai.runtime");
+    }
+}
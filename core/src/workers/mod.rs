@@ -5,10 +5,13 @@
 //! queue, executes due jobs on blocking threads, and records structured output
 //! for the UI.
 
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, PoisonError};
 use std::time::Duration as StdDuration;
 
 use anyhow::{anyhow, Context, Result};
+use futures_util::future::{BoxFuture, FutureExt, Shared};
 use log::error;
 use r2d2_sqlite::rusqlite::Connection;
 use r2d2_sqlite::rusqlite::{params, OptionalExtension};
@@ -16,17 +19,94 @@ use serde::Serialize;
 use serde_json::{json, Value};
 use tauri::async_runtime;
 use time::macros::format_description;
-use time::{Date, Duration as TimeDuration, OffsetDateTime, Time};
-use tokio::sync::Notify;
+use time::{Date, Duration as TimeDuration, OffsetDateTime, Time, Weekday};
+use tokio::sync::{Mutex, Notify, OwnedMutexGuard};
 use tokio::task::spawn_blocking;
 use tokio::time::interval;
 use uuid::Uuid;
 
 use crate::db::DbPool;
+use crate::embeddings;
+use crate::errors::{unshare_error, InkOsError};
 use crate::logging::log_event;
-use crate::summarizer::{Summarizer, SummaryRecord};
+use crate::summarizer::{estimate_cost, Summarizer, SummaryRecord, SOURCE_AI, SOURCE_FALLBACK};
+
+mod maintenance;
+mod recurring;
+mod schedule;
+mod webhook;
+
+pub use maintenance::PruneReport;
+pub use recurring::JobSchedule;
+pub use schedule::DigestSchedule;
+use schedule::{
+    local_to_utc, read_job_timeout, read_paused, read_schedule, read_worker_count, write_paused,
+    write_schedule, write_worker_count,
+};
+use time_tz::OffsetDateTimeExt;
+pub use webhook::WebhookConfig;
 
 const DAILY_DIGEST_JOB: &str = "workspace.daily_digest";
+const WEEKLY_DIGEST_JOB: &str = "workspace.weekly_digest";
+const MONTHLY_DIGEST_JOB: &str = "workspace.monthly_digest";
+const PRUNE_JOB: &str = "maintenance.prune";
+const NOTE_EMBED_JOB: &str = "notes.embed";
+/// Kinds `run_job` knows how to execute, and so the kinds a schedule may queue.
+const JOB_KINDS: [&str; 5] = [
+    DAILY_DIGEST_JOB,
+    WEEKLY_DIGEST_JOB,
+    MONTHLY_DIGEST_JOB,
+    PRUNE_JOB,
+    NOTE_EMBED_JOB,
+];
+/// Note excerpts handed to an on-demand range summary.
+const RANGE_EXCERPTS: usize = 20;
+/// Longest span, in days, accepted by [`JobScheduler::summarize_range`].
+pub const MAX_RANGE_DAYS: i64 = 366;
+const RETRY_BASE_DELAY_SECS: i64 = 60;
+const RETRY_MAX_DELAY_SECS: i64 = 3600;
+
+/// Tauri event carrying [`DigestProgress`] for manually triggered digests.
+pub const DIGEST_PROGRESS_EVENT: &str = "digest://progress";
+
+/// Phase update emitted while a manually triggered daily digest runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestProgress {
+    pub job_id: String,
+    pub entry_date: String,
+    /// `counting_notes`, `gathering_excerpts`, `writing_timeline`, `calling_ai`,
+    /// `upgrading_summary` (only when the AI summary succeeds), or `done`.
+    pub phase: &'static str,
+    /// Rough completion estimate, 0–100.
+    pub percent: u8,
+}
+
+/// Callback that forwards digest progress to the UI, usually as a Tauri event.
+pub type ProgressEmitter = Arc<dyn Fn(&DigestProgress) + Send + Sync>;
+
+/// Tauri event carrying [`TimelineRefresh`] when a day's logbook entry and
+/// timeline are rewritten.
+pub const TIMELINE_REFRESH_EVENT: &str = "timeline://refresh";
+
+/// Sent once the deterministic digest is written and again when the AI
+/// summary replaces it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineRefresh {
+    pub entry_date: String,
+    /// `fallback` for the factual summary, `ai` for the upgraded one.
+    pub source: &'static str,
+    pub summary_id: Option<String>,
+}
+
+/// Callback that tells the UI to reload a day's timeline.
+pub type TimelineEmitter = Arc<dyn Fn(&TimelineRefresh) + Send + Sync>;
+
+/// Emitters handed to a job run; background runs never report progress.
+#[derive(Clone, Default)]
+struct JobEmitters {
+    progress: Option<ProgressEmitter>,
+    timeline: Option<TimelineEmitter>,
+}
 
 /// Result payload returned when a worker completes a job.
 #[derive(Debug, Clone, Serialize)]
@@ -35,6 +115,19 @@ pub struct JobRunResult {
     pub kind: String,
     pub state: String,
     pub result: Value,
+    pub attempts: i64,
+    pub max_attempts: i64,
+}
+
+/// Whether background dispatch is paused, with the size of the queue.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStatus {
+    pub paused: bool,
+    /// Due jobs run in parallel up to this many at a time.
+    pub workers: usize,
+    pub queued_jobs: i64,
+    /// Queued jobs whose `run_at` has passed.
+    pub due_jobs: i64,
 }
 
 struct PendingJob {
@@ -43,20 +136,68 @@ struct PendingJob {
     payload: Value,
 }
 
+/// Manual digest run shared by every caller asking for the same entry date.
+type PendingDigest = Shared<BoxFuture<'static, Result<JobRunResult, Arc<anyhow::Error>>>>;
+
+/// Per-key async locks serialising jobs that write the same rows, such as two
+/// daily digests for one entry date.
+#[derive(Default)]
+struct JobLocks(std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>);
+
+impl JobLocks {
+    async fn acquire(&self, key: String) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            // Locks nobody holds or waits for are dropped as new keys arrive.
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            Arc::clone(locks.entry(key).or_default())
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Key under which a job is serialised: its kind plus payload, so digests for
+/// different dates (or embeddings of different notes) still run in parallel.
+fn job_lock_key(kind: &str, payload: &Value) -> String {
+    format!("{kind}:{payload}")
+}
+
 /// Cooperative scheduler that executes queued jobs on background threads.
 pub struct JobScheduler {
     pool: DbPool,
     summarizer: Arc<Summarizer>,
     notifier: Arc<Notify>,
+    progress: OnceLock<ProgressEmitter>,
+    timeline: OnceLock<TimelineEmitter>,
+    digests_in_flight: Arc<Mutex<HashMap<String, PendingDigest>>>,
+    paused: AtomicBool,
+    job_locks: Arc<JobLocks>,
+    /// Ids of queued jobs handed to a task that has not finished yet, so a
+    /// job is never started twice while its first run is still going. A
+    /// reclaimed job keeps its slot until its blocking run returns.
+    dispatched: Arc<std::sync::Mutex<HashSet<String>>>,
 }
 
 impl JobScheduler {
     /// Construct a scheduler backed by the provided database pool and AI runtime.
+    ///
+    /// A pause persisted by a previous session is restored before the worker
+    /// starts.
     pub fn new(pool: DbPool, summarizer: Arc<Summarizer>) -> Arc<Self> {
+        let paused = match pool.get() {
+            Ok(conn) => read_paused(&conn).unwrap_or(false),
+            Err(_) => false,
+        };
         let scheduler = Arc::new(Self {
             pool,
             summarizer,
             notifier: Arc::new(Notify::new()),
+            progress: OnceLock::new(),
+            timeline: OnceLock::new(),
+            digests_in_flight: Arc::new(Mutex::new(HashMap::new())),
+            paused: AtomicBool::new(paused),
+            job_locks: Arc::new(JobLocks::default()),
+            dispatched: Arc::new(std::sync::Mutex::new(HashSet::new())),
         });
         scheduler.spawn_worker();
         scheduler
@@ -84,6 +225,41 @@ impl JobScheduler {
                 }
             }
         });
+
+        // Reclaim runs on its own timer so a hung job can never delay it.
+        let reaper = Arc::clone(self);
+        async_runtime::spawn(async move {
+            let mut tick = interval(StdDuration::from_secs(60));
+            loop {
+                tick.tick().await;
+                match reaper.reclaim_stuck_jobs().await {
+                    Ok(0) => {}
+                    Ok(_) => reaper.wake(),
+                    Err(err) => error!("failed to reclaim stuck jobs: {err:?}"),
+                }
+            }
+        });
+    }
+
+    /// Register the emitter used by [`run_now`](Self::run_now). Jobs picked up
+    /// by the background loop never report progress. Only the first
+    /// registration takes effect.
+    pub fn set_progress_emitter(&self, emitter: ProgressEmitter) {
+        let _ = self.progress.set(emitter);
+    }
+
+    /// Register the emitter notified whenever a daily digest rewrites a day's
+    /// timeline, for manual and background runs alike. Only the first
+    /// registration takes effect.
+    pub fn set_timeline_emitter(&self, emitter: TimelineEmitter) {
+        let _ = self.timeline.set(emitter);
+    }
+
+    fn emitters(&self, with_progress: bool) -> JobEmitters {
+        JobEmitters {
+            progress: self.progress.get().filter(|_| with_progress).cloned(),
+            timeline: self.timeline.get().cloned(),
+        }
     }
 
     fn wake(&self) {
@@ -91,20 +267,71 @@ impl JobScheduler {
     }
 
     /// Persist a job and execute it immediately on a worker thread.
+    ///
+    /// Daily digests are serialised per entry date: a caller arriving while a
+    /// digest for the same date is running waits for it and receives its
+    /// result instead of rebuilding the logbook entry concurrently.
     pub async fn run_now(&self, kind: &str, payload: Value) -> Result<JobRunResult> {
-        let now = OffsetDateTime::now_utc().unix_timestamp();
-        let job_id = self.persist_job(kind, &payload, Some(now)).await?;
-        let result = self
-            .run_existing_job(PendingJob {
-                id: job_id.clone(),
-                kind: kind.to_string(),
+        let result = if kind == DAILY_DIGEST_JOB {
+            self.run_digest_once(payload).await?
+        } else {
+            execute_now(
+                self.pool.clone(),
+                Arc::clone(&self.summarizer),
+                kind.to_string(),
                 payload,
-            })
-            .await?;
+                self.emitters(true),
+            )
+            .await?
+        };
         let _ = self.ensure_nightly_digest_schedule().await;
         Ok(result)
     }
 
+    async fn run_digest_once(&self, payload: Value) -> Result<JobRunResult> {
+        let pool = self.pool.clone();
+        let lookup = payload.clone();
+        let entry_date = spawn_blocking(move || {
+            let conn = pool.get()?;
+            resolve_entry_date(&lookup, &read_schedule(&conn)?)
+        })
+        .await??
+        .to_string();
+
+        let mut in_flight = self.digests_in_flight.lock().await;
+        if let Some(pending) = in_flight.get(&entry_date).cloned() {
+            drop(in_flight);
+            return pending.await.map_err(unshare_error);
+        }
+
+        let registry = Arc::clone(&self.digests_in_flight);
+        let task_key = entry_date.clone();
+        let payload = json!({ "date": entry_date });
+        let locks = Arc::clone(&self.job_locks);
+        let lock_key = job_lock_key(DAILY_DIGEST_JOB, &payload);
+        let run = execute_now(
+            self.pool.clone(),
+            Arc::clone(&self.summarizer),
+            DAILY_DIGEST_JOB.to_string(),
+            payload,
+            self.emitters(true),
+        );
+        // The entry is removed by the run itself, so it is cleared even if
+        // the first caller stops waiting. The job lock keeps a queued nightly
+        // digest for the same date from running alongside.
+        let pending = async move {
+            let _guard = locks.acquire(lock_key).await;
+            let result = run.await.map_err(Arc::new);
+            registry.lock().await.remove(&task_key);
+            result
+        }
+        .boxed()
+        .shared();
+        in_flight.insert(entry_date, pending.clone());
+        drop(in_flight);
+        pending.await.map_err(unshare_error)
+    }
+
     /// Blocking helper used by synchronous IPC handlers.
     pub fn run_now_blocking(&self, kind: &str, payload: Value) -> Result<JobRunResult> {
         async_runtime::block_on(self.run_now(kind, payload))
@@ -122,36 +349,332 @@ impl JobScheduler {
         async_runtime::block_on(self.enqueue_at(kind, payload, run_at))
     }
 
-    /// Ensure a nightly digest job exists for the upcoming 02:00 UTC run.
+    /// Ensure every enabled schedule (including the nightly digest), the
+    /// weekly/monthly rollups, and the prune job have their next run queued.
     pub async fn ensure_nightly_digest_schedule(&self) -> Result<()> {
         let pool = self.pool.clone();
         spawn_blocking(move || {
             let conn = pool.get()?;
-            schedule_next_digest(&conn)
+            recurring::enqueue_due(&conn)?;
+            schedule_next_rollups(&conn)?;
+            schedule_next_prune(&conn)
         })
         .await??;
         Ok(())
     }
 
+    /// Delete finished jobs and event log rows older than the retention horizon.
+    ///
+    /// When `retain_days` is `None` the `maintenance.retain_days` setting is used.
+    pub async fn prune_history(&self, retain_days: Option<u32>) -> Result<PruneReport> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            let days = match retain_days {
+                Some(days) => days,
+                None => maintenance::read_retain_days(&conn)?,
+            };
+            maintenance::prune_history(&conn, days)
+        })
+        .await?
+    }
+
+    /// Summarise activity between two local dates, inclusive, on demand.
+    pub async fn summarize_range(&self, start: Date, end: Date) -> Result<SummaryRecord> {
+        let pool = self.pool.clone();
+        let summarizer = Arc::clone(&self.summarizer);
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            perform_range_summary(&conn, &summarizer, start, end)
+        })
+        .await?
+    }
+
+    /// Return the configured nightly digest schedule.
+    pub async fn digest_schedule(&self) -> Result<DigestSchedule> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            read_schedule(&conn)
+        })
+        .await?
+    }
+
+    /// Persist a new digest schedule and re-prime the queued nightly job.
+    ///
+    /// The timezone applies to every schedule, so all of them are requeued.
+    pub async fn update_digest_schedule(&self, schedule: DigestSchedule) -> Result<DigestSchedule> {
+        let pool = self.pool.clone();
+        let updated = spawn_blocking(move || {
+            let conn = pool.get()?;
+            write_schedule(&conn, &schedule)?;
+            recurring::set_cron(
+                &conn,
+                recurring::NIGHTLY_DIGEST_SCHEDULE,
+                &format!("{} {} * * *", schedule.minute, schedule.hour),
+            )?;
+            recurring::reset(&conn)?;
+            recurring::enqueue_due(&conn)?;
+            read_schedule(&conn)
+        })
+        .await??;
+        self.wake();
+        Ok(updated)
+    }
+
+    /// Return the job webhook settings.
+    pub async fn webhook_config(&self) -> Result<WebhookConfig> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || webhook::read_config(&*pool.get()?)).await?
+    }
+
+    /// Validate and store the job webhook settings.
+    pub async fn update_webhook_config(&self, config: WebhookConfig) -> Result<WebhookConfig> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || webhook::write_config(&*pool.get()?, config)).await?
+    }
+
+    /// Post a sample notification to the configured webhook, returning the
+    /// HTTP status it answered with.
+    pub async fn test_webhook(&self) -> Result<u16> {
+        webhook::send_test(self.pool.clone(), self.summarizer.models().http_client()).await
+    }
+
+    /// Recurring job definitions, oldest first.
+    pub async fn list_schedules(&self) -> Result<Vec<JobSchedule>> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || recurring::list(&*pool.get()?)).await?
+    }
+
+    /// Define a recurring job from a cron expression and queue its first run.
+    pub async fn create_schedule(
+        &self,
+        kind: &str,
+        payload: Value,
+        cron_expr: &str,
+    ) -> Result<JobSchedule> {
+        let pool = self.pool.clone();
+        let (kind, cron_expr) = (kind.to_string(), cron_expr.to_string());
+        let created =
+            spawn_blocking(move || recurring::create(&*pool.get()?, &kind, payload, &cron_expr))
+                .await??;
+        self.wake();
+        Ok(created)
+    }
+
+    /// Delete a schedule along with its queued job.
+    pub async fn delete_schedule(&self, schedule_id: &str) -> Result<()> {
+        let pool = self.pool.clone();
+        let schedule_id = schedule_id.to_string();
+        spawn_blocking(move || recurring::delete(&*pool.get()?, &schedule_id)).await?
+    }
+
+    /// Enable or disable a schedule.
+    pub async fn toggle_schedule(&self, schedule_id: &str, enabled: bool) -> Result<JobSchedule> {
+        let pool = self.pool.clone();
+        let schedule_id = schedule_id.to_string();
+        let updated =
+            spawn_blocking(move || recurring::set_enabled(&*pool.get()?, &schedule_id, enabled))
+                .await??;
+        self.wake();
+        Ok(updated)
+    }
+
+    /// Today's date in the digest schedule's timezone.
+    pub fn local_today_blocking(&self) -> Result<Date> {
+        let conn = self.pool.get()?;
+        read_schedule(&conn)?.today()
+    }
+
     /// Blocking convenience wrapper for [`ensure_nightly_digest_schedule`].
     pub fn ensure_nightly_digest_schedule_blocking(&self) -> Result<()> {
         async_runtime::block_on(self.ensure_nightly_digest_schedule())
     }
 
+    /// Stop the background loop from executing due jobs.
+    ///
+    /// Jobs stay `queued` and the nightly schedule keeps being primed; jobs
+    /// started with [`run_now`](Self::run_now) are unaffected.
+    pub async fn pause(&self) -> Result<SchedulerStatus> {
+        self.set_paused(true).await
+    }
+
+    /// Resume background dispatch and pick up any jobs that fell due meanwhile.
+    pub async fn resume(&self) -> Result<SchedulerStatus> {
+        let status = self.set_paused(false).await?;
+        self.wake();
+        Ok(status)
+    }
+
+    async fn set_paused(&self, paused: bool) -> Result<SchedulerStatus> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            write_paused(&conn, paused)?;
+            let (code, message, explain) = if paused {
+                (
+                    "JOB-204",
+                    "Paused job scheduler",
+                    "Queued jobs will wait until the scheduler is resumed.",
+                )
+            } else {
+                (
+                    "JOB-205",
+                    "Resumed job scheduler",
+                    "Jobs that fell due while paused run now.",
+                )
+            };
+            let _ = log_event(
+                &conn,
+                "info",
+                Some(code),
+                "jobs.scheduler",
+                message,
+                Some(explain),
+                None,
+            );
+            Ok::<_, anyhow::Error>(())
+        })
+        .await??;
+        self.paused.store(paused, Ordering::SeqCst);
+        self.status().await
+    }
+
+    /// Report whether dispatch is paused and how many jobs are waiting.
+    pub async fn status(&self) -> Result<SchedulerStatus> {
+        let pool = self.pool.clone();
+        let paused = self.paused.load(Ordering::SeqCst);
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            let workers = read_worker_count(&conn)?;
+            let (queued_jobs, due_jobs) = conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(run_at IS NULL OR run_at <= ?1), 0) FROM jobs WHERE state = 'queued'",
+                params![now],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            Ok(SchedulerStatus {
+                paused,
+                workers,
+                queued_jobs,
+                due_jobs,
+            })
+        })
+        .await?
+    }
+
+    /// Set how many due jobs the background loop runs at once.
+    pub async fn set_worker_count(&self, workers: usize) -> Result<SchedulerStatus> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            write_worker_count(&conn, workers)
+        })
+        .await??;
+        self.status().await
+    }
+
+    /// Fail or requeue jobs stuck in `running` past `jobs.timeout_secs`.
+    ///
+    /// Runs on its own minute timer, the first tick of which fires at
+    /// startup, so rows left running by a crashed process are recovered too.
+    /// Only the row is reclaimed: a hung run keeps its worker slot and job
+    /// lock until its blocking call returns, and its late outcome is then
+    /// discarded because the row no longer matches its attempt.
+    async fn reclaim_stuck_jobs(&self) -> Result<usize> {
+        let pool = self.pool.clone();
+        spawn_blocking(move || {
+            reclaim_stuck_jobs(&*pool.get()?, OffsetDateTime::now_utc().unix_timestamp())
+        })
+        .await?
+    }
+
+    /// Start due jobs on background tasks, keeping at most `jobs.workers`
+    /// dispatched, and return without waiting for them. Jobs sharing a
+    /// [`job_lock_key`] still run one after another. Each finished job wakes
+    /// the dispatcher so the next due job can start.
     async fn dispatch_due_jobs(self: &Arc<Self>) -> Result<()> {
+        if self.paused.load(Ordering::SeqCst) {
+            return Ok(());
+        }
         let jobs = self.fetch_due_jobs().await?;
+        if jobs.is_empty() {
+            return Ok(());
+        }
+        let pool = self.pool.clone();
+        let workers = spawn_blocking(move || read_worker_count(&*pool.get()?)).await??;
+        let mut dispatched = self
+            .dispatched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let free = workers.saturating_sub(dispatched.len());
+        let jobs: Vec<PendingJob> = jobs
+            .into_iter()
+            .filter(|job| !dispatched.contains(&job.id))
+            .take(free)
+            .collect();
         for job in jobs {
-            if let Err(err) = self.run_existing_job(job).await {
-                error!("job execution failed: {err:?}");
-            }
+            dispatched.insert(job.id.clone());
+            let runner = Arc::clone(self);
+            async_runtime::spawn(async move {
+                let job_id = job.id.clone();
+                {
+                    let _guard = runner
+                        .job_locks
+                        .acquire(job_lock_key(&job.kind, &job.payload))
+                        .await;
+                    // A pause issued after dispatch leaves the job queued.
+                    if !runner.paused.load(Ordering::SeqCst) {
+                        if let Err(err) = runner.run_existing_job(job, runner.emitters(false)).await
+                        {
+                            error!("job execution failed: {err:?}");
+                        }
+                    }
+                }
+                runner
+                    .dispatched
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&job_id);
+                runner.wake();
+            });
         }
         Ok(())
     }
 
+    /// Cancel a queued job so the scheduler skips it.
+    ///
+    /// Jobs that are already running or finished cannot be cancelled. A
+    /// cancelled nightly digest is recreated on the next scheduler tick.
+    pub async fn cancel_job(&self, job_id: &str) -> Result<()> {
+        let pool = self.pool.clone();
+        let job_id = job_id.to_string();
+        spawn_blocking(move || {
+            let conn = pool.get()?;
+            cancel_job_with_conn(&conn, &job_id)
+        })
+        .await?
+    }
+
+    /// Clone a failed or cancelled job back into the queue.
+    ///
+    /// Returns the id of the new job, which runs at `run_at` (or immediately).
+    pub async fn requeue_job(&self, job_id: &str, run_at: Option<i64>) -> Result<String> {
+        let pool = self.pool.clone();
+        let job_id = job_id.to_string();
+        let id = spawn_blocking(move || {
+            let conn = pool.get()?;
+            requeue_job_with_conn(&conn, &job_id, run_at)
+        })
+        .await??;
+        self.wake();
+        Ok(id)
+    }
+
     async fn fetch_due_jobs(&self) -> Result<Vec<PendingJob>> {
         let pool = self.pool.clone();
         let now = OffsetDateTime::now_utc().unix_timestamp();
-        Ok(spawn_blocking(move || {
+        spawn_blocking(move || {
             let conn = pool.get()?;
             let mut stmt = conn.prepare(
                 "SELECT id, kind, payload FROM jobs WHERE state='queued' AND (run_at IS NULL OR run_at <= ?1) ORDER BY run_at IS NULL DESC, run_at ASC, created_at ASC",
@@ -169,19 +692,23 @@ impl JobScheduler {
             for row in rows {
                 pending.push(row?);
             }
-            Ok(pending)
+            Ok::<_, anyhow::Error>(pending)
         })
-        .await??)
+        .await?
     }
 
-    async fn run_existing_job(&self, job: PendingJob) -> Result<JobRunResult> {
-        let pool = self.pool.clone();
-        let summarizer = Arc::clone(&self.summarizer);
-        Ok(spawn_blocking(move || {
-            let conn = pool.get()?;
-            run_job(&conn, summarizer.as_ref(), &job.id, &job.kind, job.payload)
-        })
-        .await??)
+    async fn run_existing_job(
+        &self,
+        job: PendingJob,
+        emitters: JobEmitters,
+    ) -> Result<JobRunResult> {
+        run_on_worker(
+            self.pool.clone(),
+            Arc::clone(&self.summarizer),
+            job,
+            emitters,
+        )
+        .await
     }
 
     async fn persist_job(
@@ -193,14 +720,56 @@ impl JobScheduler {
         let pool = self.pool.clone();
         let kind = kind.to_string();
         let payload = payload.clone();
-        Ok(spawn_blocking(move || {
+        spawn_blocking(move || {
             let conn = pool.get()?;
             persist_job_with_conn(&conn, &kind, &payload, run_at)
         })
-        .await??)
+        .await?
     }
 }
 
+/// Persist a job due now and run it on a blocking thread.
+async fn execute_now(
+    pool: DbPool,
+    summarizer: Arc<Summarizer>,
+    kind: String,
+    payload: Value,
+    emitters: JobEmitters,
+) -> Result<JobRunResult> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let store = pool.clone();
+    let (job_kind, job_payload) = (kind.clone(), payload.clone());
+    let id = spawn_blocking(move || {
+        let conn = store.get()?;
+        persist_job_with_conn(&conn, &job_kind, &job_payload, Some(now))
+    })
+    .await??;
+    run_on_worker(pool, summarizer, PendingJob { id, kind, payload }, emitters).await
+}
+
+async fn run_on_worker(
+    pool: DbPool,
+    summarizer: Arc<Summarizer>,
+    job: PendingJob,
+    emitters: JobEmitters,
+) -> Result<JobRunResult> {
+    spawn_blocking(move || {
+        let conn = pool.get()?;
+        let outcome = run_job(
+            &conn,
+            summarizer.as_ref(),
+            &job.id,
+            &job.kind,
+            job.payload,
+            &emitters,
+        );
+        drop(conn);
+        webhook::job_finished(&pool, summarizer.models().http_client(), &job.id);
+        outcome
+    })
+    .await?
+}
+
 fn persist_job_with_conn(
     conn: &Connection,
     kind: &str,
@@ -217,6 +786,71 @@ fn persist_job_with_conn(
     Ok(id)
 }
 
+fn load_job_state(conn: &Connection, job_id: &str) -> Result<(String, String, String)> {
+    conn.query_row(
+        "SELECT kind, state, payload FROM jobs WHERE id = ?1",
+        params![job_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()?
+    .ok_or_else(|| InkOsError::JobNotFound.into())
+}
+
+fn cancel_job_with_conn(conn: &Connection, job_id: &str) -> Result<()> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let updated = conn
+        .execute(
+            "UPDATE jobs SET state='cancelled', updated_at=?2 WHERE id=?1 AND state='queued'",
+            params![job_id, now],
+        )
+        .context("failed to cancel job")?;
+    let (kind, state, _) = load_job_state(conn, job_id)?;
+    if updated == 0 {
+        return Err(InkOsError::JobInvalidState(format!(
+            "job {job_id} is {state} and cannot be cancelled"
+        ))
+        .into());
+    }
+    let _ = log_event(
+        conn,
+        "info",
+        Some("JOB-202"),
+        "jobs.scheduler",
+        "Cancelled queued job",
+        Some("The job was removed from the queue by the user."),
+        Some(json!({ "job_id": job_id, "kind": kind })),
+    );
+    Ok(())
+}
+
+fn requeue_job_with_conn(conn: &Connection, job_id: &str, run_at: Option<i64>) -> Result<String> {
+    let (kind, state, payload_json) = load_job_state(conn, job_id)?;
+    if state != "failed" && state != "cancelled" {
+        return Err(InkOsError::JobInvalidState(format!(
+            "only failed or cancelled jobs can be requeued; job {job_id} is {state}"
+        ))
+        .into());
+    }
+    let payload: Value = serde_json::from_str(&payload_json).unwrap_or_else(|_| json!({}));
+    let run_at = run_at.unwrap_or_else(|| OffsetDateTime::now_utc().unix_timestamp());
+    let id = persist_job_with_conn(conn, &kind, &payload, Some(run_at))?;
+    let _ = log_event(
+        conn,
+        "info",
+        Some("JOB-203"),
+        "jobs.scheduler",
+        "Requeued job",
+        Some("A copy of the job was queued with a fresh attempt budget."),
+        Some(json!({
+            "job_id": id,
+            "source_job_id": job_id,
+            "kind": kind,
+            "run_at": run_at,
+        })),
+    );
+    Ok(id)
+}
+
 /// Run a job and update its persisted state transitions.
 fn run_job(
     conn: &Connection,
@@ -224,40 +858,112 @@ fn run_job(
     id: &str,
     kind: &str,
     payload: Value,
+    emitters: &JobEmitters,
 ) -> Result<JobRunResult> {
     let now = OffsetDateTime::now_utc().unix_timestamp();
     conn.execute(
-        "UPDATE jobs SET state='running', updated_at=?2 WHERE id=?1",
+        "UPDATE jobs SET state='running', attempts = attempts + 1, started_at=?2, updated_at=?2 WHERE id=?1",
         params![id, now],
     )
     .with_context(|| format!("failed to update job {kind} to running"))?;
 
+    let (attempts, max_attempts): (i64, i64) = conn
+        .query_row(
+            "SELECT attempts, max_attempts FROM jobs WHERE id=?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .with_context(|| format!("failed to load attempt count for job {kind}"))?;
+    let final_attempt = attempts >= max_attempts;
+
     let result = match kind {
-        DAILY_DIGEST_JOB => perform_daily_digest(conn, summarizer, &payload),
+        DAILY_DIGEST_JOB => {
+            let report = |phase: &'static str, entry_date: &str, percent: u8| {
+                if let Some(emit) = &emitters.progress {
+                    emit(&DigestProgress {
+                        job_id: id.to_string(),
+                        entry_date: entry_date.to_string(),
+                        phase,
+                        percent,
+                    });
+                }
+            };
+            let refresh = |refresh: TimelineRefresh| {
+                if let Some(emit) = &emitters.timeline {
+                    emit(&refresh);
+                }
+            };
+            perform_daily_digest(conn, summarizer, &payload, final_attempt, &report, &refresh)
+        }
+        WEEKLY_DIGEST_JOB => perform_weekly_digest(conn, summarizer, &payload),
+        MONTHLY_DIGEST_JOB => perform_monthly_digest(conn, summarizer, &payload),
+        PRUNE_JOB => perform_prune(conn, &payload),
+        NOTE_EMBED_JOB => perform_note_embedding(conn, summarizer, &payload),
         other => Err(anyhow!("unknown job kind: {other}")),
     };
 
     match result {
         Ok(value) => {
             let finished = OffsetDateTime::now_utc().unix_timestamp();
-            conn.execute(
-                "UPDATE jobs SET state='succeeded', result=?2, updated_at=?3 WHERE id=?1",
-                params![id, value.to_string(), finished],
-            )
-            .with_context(|| format!("failed to mark job {kind} as succeeded"))?;
+            // Only a run that still owns the row may record its outcome; a
+            // reclaimed job has already been requeued, failed or restarted.
+            let recorded = conn
+                .execute(
+                    "UPDATE jobs SET state='succeeded', result=?2, updated_at=?3 WHERE id=?1 AND state='running' AND attempts=?4",
+                    params![id, value.to_string(), finished, attempts],
+                )
+                .with_context(|| format!("failed to mark job {kind} as succeeded"))?;
+            if recorded == 0 {
+                return Err(anyhow!(
+                    "job {id} ({kind}) was reclaimed after timing out; its result was discarded"
+                ));
+            }
             Ok(JobRunResult {
                 job_id: id.to_string(),
                 kind: kind.to_string(),
                 state: "succeeded".into(),
                 result: value,
+                attempts,
+                max_attempts,
             })
         }
+        Err(error) if !final_attempt => {
+            let finished = OffsetDateTime::now_utc().unix_timestamp();
+            let message = error.to_string();
+            let retry_at = finished + retry_delay_secs(attempts);
+            let requeued = conn
+                .execute(
+                    "UPDATE jobs SET state='queued', run_at=?2, result=?3, updated_at=?4 WHERE id=?1 AND state='running' AND attempts=?5",
+                    params![id, retry_at, message.as_str(), finished, attempts],
+                )
+                .with_context(|| format!("failed to requeue job {kind}"))?;
+            if requeued == 0 {
+                return Err(error);
+            }
+            let _ = log_event(
+                conn,
+                "warn",
+                Some("JOB-RETRY"),
+                "jobs.scheduler",
+                "Job failed and will be retried",
+                Some("The job is requeued with an increasing delay until it runs out of attempts."),
+                Some(json!({
+                    "job_id": id,
+                    "kind": kind,
+                    "attempt": attempts,
+                    "max_attempts": max_attempts,
+                    "retry_at": retry_at,
+                    "error": message,
+                })),
+            );
+            Err(error)
+        }
         Err(error) => {
             let finished = OffsetDateTime::now_utc().unix_timestamp();
             let message = error.to_string();
             conn.execute(
-                "UPDATE jobs SET state='failed', result=?2, updated_at=?3 WHERE id=?1",
-                params![id, message.as_str(), finished],
+                "UPDATE jobs SET state='failed', result=?2, updated_at=?3 WHERE id=?1 AND state='running' AND attempts=?4",
+                params![id, message.as_str(), finished, attempts],
             )
             .with_context(|| format!("failed to mark job {kind} as failed"))?;
             Err(error)
@@ -265,21 +971,288 @@ fn run_job(
     }
 }
 
+/// Delay before retrying a job that failed on attempt number `attempt`.
+///
+/// Starts at one minute and doubles per attempt, capped at one hour.
+fn retry_delay_secs(attempt: i64) -> i64 {
+    let exponent = attempt.saturating_sub(1).clamp(0, 6) as u32;
+    (RETRY_BASE_DELAY_SECS * 2_i64.pow(exponent)).min(RETRY_MAX_DELAY_SECS)
+}
+
+/// Reclaim jobs that have been `running` for longer than the job timeout.
+///
+/// Jobs with attempts left are requeued with the usual retry delay; the rest
+/// are marked failed. Returns how many jobs were reclaimed.
+fn reclaim_stuck_jobs(conn: &Connection, now: i64) -> Result<usize> {
+    let timeout = read_job_timeout(conn)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, kind, attempts, max_attempts FROM jobs
+         WHERE state = 'running' AND COALESCE(started_at, updated_at) <= ?1",
+    )?;
+    let stuck = stmt
+        .query_map(params![now - timeout], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let message = format!("job timed out after {timeout}s");
+    for (id, kind, attempts, max_attempts) in &stuck {
+        let retry_at = (attempts < max_attempts).then(|| now + retry_delay_secs(*attempts));
+        match retry_at {
+            Some(retry_at) => conn.execute(
+                "UPDATE jobs SET state='queued', run_at=?2, result=?3, updated_at=?4 WHERE id=?1 AND state='running'",
+                params![id, retry_at, message, now],
+            )?,
+            None => conn.execute(
+                "UPDATE jobs SET state='failed', result=?2, updated_at=?3 WHERE id=?1 AND state='running'",
+                params![id, message, now],
+            )?,
+        };
+        let _ = log_event(
+            conn,
+            "warn",
+            Some("AI-JOB-TIMEOUT"),
+            "jobs.scheduler",
+            "Reclaimed stuck job",
+            Some("The job stayed running past the job timeout, usually because a provider hung or the app exited mid-job."),
+            Some(json!({
+                "job_id": id,
+                "kind": kind,
+                "attempt": attempts,
+                "max_attempts": max_attempts,
+                "retry_at": retry_at,
+                "timeout_secs": timeout,
+            })),
+        );
+    }
+    Ok(stuck.len())
+}
+
+/// Apply the retention policy, honouring an optional `retain_days` override.
+fn perform_prune(conn: &Connection, payload: &Value) -> Result<Value> {
+    let retain_days = match payload.get("retain_days").and_then(|v| v.as_u64()) {
+        Some(days) => u32::try_from(days).context("retain_days out of range")?,
+        None => maintenance::read_retain_days(conn)?,
+    };
+    let report = maintenance::prune_history(conn, retain_days)?;
+    Ok(serde_json::to_value(report)?)
+}
+
+/// Refresh one note's vector for semantic search.
+fn perform_note_embedding(
+    conn: &Connection,
+    summarizer: &Summarizer,
+    payload: &Value,
+) -> Result<Value> {
+    let note_id = payload
+        .get("note_id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("note_id is required"))?;
+    embeddings::embed_note(conn, &summarizer.models(), note_id)
+}
+
 /// Generate the logbook summary and timeline entries for a given day.
+///
+/// The deterministic summary is written first, so the day has an entry while
+/// the AI call is slow or failing; the AI summary then replaces it. A
+/// summariser error fails the job for a retry, except on the final attempt,
+/// where the deterministic entry is kept.
 fn perform_daily_digest(
     conn: &Connection,
     summarizer: &Summarizer,
     payload: &Value,
+    final_attempt: bool,
+    report: &dyn Fn(&'static str, &str, u8),
+    refresh: &dyn Fn(TimelineRefresh),
 ) -> Result<Value> {
-    let date = resolve_entry_date(payload)?;
+    let schedule = read_schedule(conn)?;
+    let date = resolve_entry_date(payload, &schedule)?;
     let date_key = date.to_string();
-    let start_ts = date.with_time(Time::MIDNIGHT).assume_utc().unix_timestamp();
-    let end_ts =
-        (OffsetDateTime::from_unix_timestamp(start_ts)? + TimeDuration::DAY).unix_timestamp();
+    let (start_ts, end_ts) = schedule.day_bounds(date)?;
 
+    report("counting_notes", &date_key, 10);
+    let WindowActivity {
+        notes_count,
+        latest_note,
+        ai_calls,
+        ai_failures,
+        job_count,
+        usage,
+    } = window_activity(conn, start_ts, end_ts)?;
+
+    report("gathering_excerpts", &date_key, 30);
+    let note_excerpts = collect_note_excerpts(
+        conn,
+        start_ts,
+        end_ts,
+        schedule.excerpt_count,
+        schedule.excerpt_chars,
+    )?;
+
+    let latest_at = match &latest_note {
+        Some((_, ts)) => Some(format!(
+            "{} {}",
+            OffsetDateTime::from_unix_timestamp(*ts)?
+                .to_timezone(schedule.tz()?)
+                .format(&format_description!("[hour]:[minute]"))?,
+            schedule.timezone
+        )),
+        None => None,
+    };
+
+    let facts = DailyDigestFacts {
+        date_key: date_key.clone(),
+        notes_count,
+        ai_calls,
+        ai_failures,
+        job_count,
+        usage: usage.clone(),
+        latest_note,
+        note_excerpts,
+    };
+    let fallback_summary = compose_fallback_summary(&facts, latest_at.as_deref());
+
+    let over_budget = schedule
+        .token_budget
+        .filter(|budget| usage.total_tokens > 0 && usage.total_tokens as u64 > *budget)
+        .map(|budget| (usage.total_tokens, budget));
+
+    // A day the model already summarised keeps that text until a new AI
+    // summary replaces it; the factual fallback only fills empty days.
+    let current_ai = current_ai_summary(conn, &date_key)?;
+    let interim_summary = current_ai
+        .as_ref()
+        .map_or(fallback_summary.as_str(), |(_, body)| body.as_str());
+
+    report("writing_timeline", &date_key, 40);
+    let mut logbook_entry = upsert_logbook_entry(conn, &date_key, interim_summary, None)?;
+    let mut timeline = rebuild_timeline(
+        conn,
+        &date_key,
+        interim_summary,
+        notes_count,
+        ai_calls,
+        ai_failures,
+        over_budget,
+    )?;
+    if let Some(entry_id) = logbook_entry.get("id").and_then(|v| v.as_str()) {
+        link_note_mentions(conn, entry_id, &facts.note_excerpts)?;
+    }
+    refresh(TimelineRefresh {
+        entry_date: date_key.clone(),
+        source: if current_ai.is_some() {
+            SOURCE_AI
+        } else {
+            SOURCE_FALLBACK
+        },
+        summary_id: current_ai.as_ref().map(|(id, _)| id.clone()),
+    });
+
+    let facts_json = digest_facts_json(&facts);
+    report("calling_ai", &date_key, 60);
+    let summary_record =
+        match summarizer.summarise_daily_digest(&date_key, facts_json, &fallback_summary) {
+            Ok(record) => Some(record),
+            Err(err) if final_attempt => {
+                let _ = log_event(
+                    conn,
+                    "warn",
+                    Some("SYS-LOG-101"),
+                    "jobs.daily",
+                    "Daily digest summariser failed on final attempt",
+                    Some("Keeping the deterministic fallback summary instead."),
+                    Some(json!({
+                        "entry_date": date_key,
+                        "error": err.to_string(),
+                    })),
+                );
+                None
+            }
+            Err(err) => return Err(err),
+        };
+
+    match &summary_record {
+        Some(record) if record.source == SOURCE_AI => {
+            report("upgrading_summary", &date_key, 90);
+            logbook_entry = upsert_logbook_entry(conn, &date_key, &record.body, Some(record))?;
+            timeline = rebuild_timeline(
+                conn,
+                &date_key,
+                &record.body,
+                notes_count,
+                ai_calls,
+                ai_failures,
+                over_budget,
+            )?;
+            refresh(TimelineRefresh {
+                entry_date: date_key.clone(),
+                source: SOURCE_AI,
+                summary_id: Some(record.id.clone()),
+            });
+        }
+        // The summariser fell back too; keep the factual text and link its record.
+        Some(record) if current_ai.is_none() => {
+            if let Some(entry_id) = logbook_entry.get("id").and_then(|v| v.as_str()) {
+                link_summary(conn, entry_id, record)?;
+            }
+        }
+        _ => {}
+    }
+
+    log_event(
+        conn,
+        "info",
+        Some("SYS-LOG-100"),
+        "jobs.daily",
+        "Daily digest job completed",
+        Some("Created or refreshed logbook and timeline entries."),
+        Some(json!({
+            "entry_date": date_key,
+            "summary_id": summary_record.as_ref().map(|record| record.id.clone()),
+        })),
+    )
+    .context("failed to log daily digest completion")?;
+    report("done", &date_key, 100);
+
+    Ok(json!({
+        "entry_date": date.to_string(),
+        "logbook": logbook_entry,
+        "timeline": timeline,
+        "usage": usage,
+        "summary_id": summary_record.as_ref().map(|record| record.id.clone()),
+    }))
+}
+
+struct DailyDigestFacts {
+    date_key: String,
+    notes_count: i64,
+    ai_calls: i64,
+    ai_failures: i64,
+    job_count: i64,
+    usage: DailyUsage,
+    latest_note: Option<(String, i64)>,
+    note_excerpts: Vec<NoteExcerpt>,
+}
+
+/// Counts describing workspace activity in a `[start_ts, end_ts)` window.
+struct WindowActivity {
+    notes_count: i64,
+    latest_note: Option<(String, i64)>,
+    ai_calls: i64,
+    ai_failures: i64,
+    job_count: i64,
+    usage: DailyUsage,
+}
+
+/// Count notes, AI runs, jobs and token usage in `[start_ts, end_ts)`.
+fn window_activity(conn: &Connection, start_ts: i64, end_ts: i64) -> Result<WindowActivity> {
     let notes_count: i64 = conn
         .query_row(
-            "SELECT COUNT(*) FROM notes WHERE created_at >= ?1 AND created_at < ?2",
+            "SELECT COUNT(*) FROM notes WHERE created_at >= ?1 AND created_at < ?2 AND deleted_at IS NULL",
             params![start_ts, end_ts],
             |row| row.get(0),
         )
@@ -287,7 +1260,7 @@ fn perform_daily_digest(
 
     let latest_note: Option<(String, i64)> = conn
         .prepare(
-            "SELECT title, created_at FROM notes WHERE created_at >= ?1 AND created_at < ?2 ORDER BY created_at DESC LIMIT 1",
+            "SELECT title, created_at FROM notes WHERE created_at >= ?1 AND created_at < ?2 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT 1",
         )?
         .query_row(params![start_ts, end_ts], |row| {
             Ok((row.get(0)?, row.get(1)?))
@@ -318,159 +1291,768 @@ fn perform_daily_digest(
         )
         .context("failed to count job executions")?;
 
-    let note_excerpts = collect_note_excerpts(conn, start_ts, end_ts)?;
+    Ok(WindowActivity {
+        notes_count,
+        latest_note,
+        ai_calls,
+        ai_failures,
+        job_count,
+        usage: daily_usage(conn, start_ts, end_ts)?,
+    })
+}
+
+/// Workspace-wide counts for the home screen dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceStats {
+    pub notes_total: i64,
+    pub notes_today: i64,
+    pub conversations_total: i64,
+    pub open_conversations: i64,
+    pub summaries_total: i64,
+    /// Jobs queued or running.
+    pub jobs_pending: i64,
+    pub jobs_failed_24h: i64,
+    pub ai_calls_today: i64,
+    pub ai_failures_today: i64,
+}
+
+/// Gather [`WorkspaceStats`] on a single connection.
+///
+/// "Today" is the current day in the digest timezone, counted with the same
+/// queries as the daily digest. Deleted notes are left out.
+pub fn workspace_stats(conn: &Connection) -> Result<WorkspaceStats> {
+    let schedule = read_schedule(conn)?;
+    let (start_ts, end_ts) = schedule.day_bounds(schedule.today()?)?;
+    let today = window_activity(conn, start_ts, end_ts)?;
+    let count = |sql: &str| -> Result<i64> { Ok(conn.query_row(sql, [], |row| row.get(0))?) };
+    let failed_since = OffsetDateTime::now_utc().unix_timestamp() - 24 * 60 * 60;
+    let jobs_failed_24h: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM jobs WHERE state = 'failed' AND updated_at >= ?1",
+        params![failed_since],
+        |row| row.get(0),
+    )?;
+    Ok(WorkspaceStats {
+        notes_total: count("SELECT COUNT(*) FROM notes WHERE deleted_at IS NULL")?,
+        notes_today: today.notes_count,
+        conversations_total: count("SELECT COUNT(*) FROM conversations")?,
+        open_conversations: count("SELECT COUNT(*) FROM conversations WHERE closed_at IS NULL")?,
+        summaries_total: count("SELECT COUNT(*) FROM summaries")?,
+        jobs_pending: count("SELECT COUNT(*) FROM jobs WHERE state IN ('queued', 'running')")?,
+        jobs_failed_24h,
+        ai_calls_today: today.ai_calls,
+        ai_failures_today: today.ai_failures,
+    })
+}
 
-    let mut summary_parts = Vec::new();
-    summary_parts.push(format!(
-        "Captured {notes_count} note{} today.",
-        plural(notes_count)
-    ));
-    summary_parts.push(format!(
-        "Dispatched {ai_calls} AI run{} with {ai_failures} incident{}.",
-        plural(ai_calls),
-        plural(ai_failures)
-    ));
-    summary_parts.push(format!(
-        "Processed {job_count} background job{}.",
-        plural(job_count)
-    ));
+/// A logbook day matching a [`search_logbook`] query.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogbookSearchHit {
+    pub id: String,
+    pub entry_date: String,
+    /// Excerpt from the summary or manual notes with matched terms wrapped
+    /// in `**`.
+    pub snippet: String,
+    pub created_at: i64,
+}
 
-    if let Some((title, ts)) = &latest_note {
-        let when = OffsetDateTime::from_unix_timestamp(*ts)?
-            .format(&format_description!("[hour]:[minute] UTC"))?;
-        summary_parts.push(format!("Latest note \"{}\" captured at {}.", title, when));
+/// Full-text search over logbook summaries and manual notes, best match
+/// first. Every word of `query` must appear; FTS operators are treated as
+/// plain text. `from` and `to` bound `entry_date` inclusively.
+pub fn search_logbook(
+    conn: &Connection,
+    query: &str,
+    from: Option<Date>,
+    to: Option<Date>,
+    limit: usize,
+) -> Result<Vec<LogbookSearchHit>> {
+    let terms = crate::db::fts_phrase_query(query, " ");
+    if terms.is_empty() {
+        return Err(InkOsError::ValidationFailed("query must not be empty".into()).into());
     }
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.entry_date, snippet(fts_logbook, -1, '**', '**', '…', 16), l.created_at
+         FROM fts_logbook
+         JOIN logbook_entries l ON l.rowid = fts_logbook.rowid
+         WHERE fts_logbook MATCH ?1
+           AND (?2 IS NULL OR l.entry_date >= ?2)
+           AND (?3 IS NULL OR l.entry_date <= ?3)
+         ORDER BY bm25(fts_logbook) LIMIT ?4",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            terms,
+            from.map(|date| date.to_string()),
+            to.map(|date| date.to_string()),
+            limit as i64
+        ],
+        |row| {
+            Ok(LogbookSearchHit {
+                id: row.get(0)?,
+                entry_date: row.get(1)?,
+                snippet: row.get(2)?,
+                created_at: row.get(3)?,
+            })
+        },
+    )?;
+    let mut hits = Vec::new();
+    for row in rows {
+        hits.push(row?);
+    }
+    Ok(hits)
+}
 
-    let fallback_summary = summary_parts.join(" ");
+/// Token consumption recorded in the `usage` table for a window.
+#[derive(Debug, Clone, Default, Serialize)]
+struct DailyUsage {
+    prompt_tokens: i64,
+    completion_tokens: i64,
+    total_tokens: i64,
+    estimated_cost_usd: f64,
+}
 
-    let facts = DailyDigestFacts {
-        date_key: date_key.clone(),
-        notes_count,
-        ai_calls,
-        ai_failures,
-        job_count,
-        latest_note: latest_note.clone(),
-        note_excerpts,
+/// Sum recorded usage in `[start_ts, end_ts)`, pricing cloud models only.
+fn daily_usage(conn: &Connection, start_ts: i64, end_ts: i64) -> Result<DailyUsage> {
+    let local_providers: Vec<String> = crate::agents::config::list_providers(conn)?
+        .into_iter()
+        .filter(|provider| provider.kind == "local")
+        .map(|provider| provider.id)
+        .collect();
+    let mut stmt = conn.prepare(
+        "SELECT provider_id, model_id, SUM(prompt_tokens), SUM(completion_tokens), SUM(total_tokens)
+         FROM usage WHERE created_at >= ?1 AND created_at < ?2 GROUP BY provider_id, model_id",
+    )?;
+    let rows = stmt.query_map(params![start_ts, end_ts], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+    let mut usage = DailyUsage::default();
+    for row in rows {
+        let (provider_id, model_id, prompt, completion, total) = row?;
+        usage.prompt_tokens += prompt;
+        usage.completion_tokens += completion;
+        usage.total_tokens += total;
+        if !local_providers.contains(&provider_id) {
+            usage.estimated_cost_usd += estimate_cost(&model_id, prompt, completion).unwrap_or(0.0);
+        }
+    }
+    Ok(usage)
+}
+
+/// Notes in a day at or above which the fallback calls it busy.
+const BUSY_DAY_NOTES: i64 = 6;
+/// AI runs in a day at or above which the fallback calls it busy.
+const BUSY_DAY_AI_CALLS: i64 = 20;
+/// Note titles named in the fallback summary besides the latest one.
+const FALLBACK_TITLES: usize = 3;
+/// Theme groups named in the fallback summary.
+const FALLBACK_THEMES: usize = 3;
+/// Common words never treated as a theme.
+const THEME_STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "been", "before", "being", "could", "does", "done", "from",
+    "have", "into", "just", "like", "more", "much", "need", "next", "note", "notes", "only",
+    "other", "over", "should", "some", "than", "that", "their", "them", "then", "there", "these",
+    "they", "this", "today", "very", "want", "were", "what", "when", "which", "while", "will",
+    "with", "would", "your",
+];
+
+/// Build the deterministic daily summary kept when the AI is unavailable.
+///
+/// The opening line reflects how active the day was, followed by the notes
+/// worth remembering, any themes shared by several notes, and the AI, job
+/// and token counts. `latest_at` is the latest note's local time.
+fn compose_fallback_summary(facts: &DailyDigestFacts, latest_at: Option<&str>) -> String {
+    let notes = if facts.notes_count == 0 {
+        "no new notes".to_string()
+    } else {
+        format!(
+            "{} new note{}",
+            facts.notes_count,
+            plural(facts.notes_count)
+        )
     };
+    let mut parts = vec![if facts.notes_count == 0 && facts.ai_calls == 0 {
+        "A quiet day with no new notes and no AI runs.".to_string()
+    } else if facts.notes_count >= BUSY_DAY_NOTES || facts.ai_calls >= BUSY_DAY_AI_CALLS {
+        format!("A busy day with {notes}.")
+    } else if facts.notes_count <= 2 {
+        format!("A light day with {notes}.")
+    } else {
+        format!("A steady day with {notes}.")
+    }];
 
-    let facts_json = digest_facts_json(&facts);
+    if let (Some((title, _)), Some(at)) = (&facts.latest_note, latest_at) {
+        parts.push(format!("Latest note \"{title}\" captured at {at}."));
+    }
+    let others: Vec<String> = facts
+        .note_excerpts
+        .iter()
+        .filter(|note| facts.latest_note.as_ref().map(|(title, _)| title) != Some(&note.title))
+        .take(FALLBACK_TITLES)
+        .map(|note| format!("\"{}\"", note.title))
+        .collect();
+    if !others.is_empty() {
+        let named = others.len() + usize::from(facts.latest_note.is_some());
+        let more = more_notes_marker(facts.notes_count, named)
+            .map(|marker| format!(" {marker}"))
+            .unwrap_or_default();
+        parts.push(format!("Also captured {}{more}.", join_list(&others)));
+    }
+
+    let themes: Vec<String> = note_themes(&facts.note_excerpts)
+        .into_iter()
+        .take(FALLBACK_THEMES)
+        .map(|(keywords, count)| format!("{} ({count} notes)", keywords.join("/")))
+        .collect();
+    if !themes.is_empty() {
+        parts.push(format!("Recurring themes: {}.", join_list(&themes)));
+    }
+
+    if facts.ai_calls > 0 {
+        let incidents = if facts.ai_failures > 0 {
+            format!(
+                " with {} incident{}",
+                facts.ai_failures,
+                plural(facts.ai_failures)
+            )
+        } else {
+            String::new()
+        };
+        parts.push(format!(
+            "Ran {} AI request{}{incidents}.",
+            facts.ai_calls,
+            plural(facts.ai_calls)
+        ));
+    }
+    if facts.job_count > 0 {
+        parts.push(format!(
+            "Processed {} background job{}.",
+            facts.job_count,
+            plural(facts.job_count)
+        ));
+    }
+    let usage = &facts.usage;
+    if usage.total_tokens > 0 {
+        parts.push(format!(
+            "Used {} AI token{} ({} prompt, {} completion, ~${:.2}).",
+            usage.total_tokens,
+            plural(usage.total_tokens),
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            usage.estimated_cost_usd
+        ));
+    }
+    parts.join(" ")
+}
+
+/// Keywords shared by two or more notes, grouped when they appear in exactly
+/// the same notes, largest group first.
+///
+/// Keywords come from titles, previews and tags: lowercase words of four or
+/// more letters that are not in [`THEME_STOPWORDS`]. Each group is returned
+/// with at most two keywords and its note count. Ties are broken
+/// alphabetically so the result is stable.
+fn note_themes(excerpts: &[NoteExcerpt]) -> Vec<(Vec<String>, usize)> {
+    let mut notes_by_keyword: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    for (index, note) in excerpts.iter().enumerate() {
+        let text = format!("{} {}", note.title, note.preview).to_lowercase();
+        let words = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= 4)
+            .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+            .filter(|word| !THEME_STOPWORDS.contains(word))
+            .map(str::to_string);
+        let tags = note.tags.iter().map(|tag| tag.to_lowercase());
+        for keyword in words.chain(tags) {
+            notes_by_keyword.entry(keyword).or_default().insert(index);
+        }
+    }
+
+    let mut groups: BTreeMap<BTreeSet<usize>, Vec<String>> = BTreeMap::new();
+    for (keyword, notes) in notes_by_keyword {
+        if notes.len() >= 2 {
+            groups.entry(notes).or_default().push(keyword);
+        }
+    }
+    let mut themes: Vec<(Vec<String>, usize)> = groups
+        .into_iter()
+        .map(|(notes, mut keywords)| {
+            keywords.truncate(2);
+            (keywords, notes.len())
+        })
+        .collect();
+    themes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    themes
+}
+
+/// Join items as `a`, `a and b`, or `a, b and c`.
+fn join_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+fn digest_facts_json(facts: &DailyDigestFacts) -> Value {
+    json!({
+        "date_key": facts.date_key,
+        "notes_count": facts.notes_count,
+        "ai_calls": facts.ai_calls,
+        "ai_failures": facts.ai_failures,
+        "job_count": facts.job_count,
+        "prompt_tokens": facts.usage.prompt_tokens,
+        "completion_tokens": facts.usage.completion_tokens,
+        "total_tokens": facts.usage.total_tokens,
+        "estimated_cost_usd": facts.usage.estimated_cost_usd,
+        "latest_note": facts.latest_note.as_ref().map(|(title, ts)| json!({
+            "title": title,
+            "created_at": ts,
+        })),
+        "note_excerpts": facts
+            .note_excerpts
+            .iter()
+            .map(|note| json!({
+                "id": note.id,
+                "title": note.title,
+                "preview": note.preview,
+                "tags": note.tags,
+            }))
+            .collect::<Vec<_>>(),
+        "more_notes": more_notes_marker(facts.notes_count, facts.note_excerpts.len()),
+    })
+}
+
+struct NoteExcerpt {
+    id: String,
+    title: String,
+    preview: String,
+    tags: Vec<String>,
+}
+
+/// The newest `limit` notes in the window, each body cut to `max_chars`.
+fn collect_note_excerpts(
+    conn: &Connection,
+    start_ts: i64,
+    end_ts: i64,
+    limit: usize,
+    max_chars: usize,
+) -> Result<Vec<NoteExcerpt>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, body,
+                (SELECT group_concat(t.name, ',') FROM note_tags nt JOIN tags t ON t.id = nt.tag_id WHERE nt.note_id = notes.id)
+         FROM notes WHERE created_at >= ?1 AND created_at < ?2 AND deleted_at IS NULL ORDER BY created_at DESC LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(params![start_ts, end_ts, limit as i64], |row| {
+        let body: String = row.get(2)?;
+        let tags: Option<String> = row.get(3)?;
+        Ok(NoteExcerpt {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            preview: truncate_at_word(&body.replace('\n', " "), max_chars),
+            tags: tags
+                .map(|raw| raw.split(',').map(str::to_string).collect())
+                .unwrap_or_default(),
+        })
+    })?;
+    let mut excerpts = Vec::new();
+    for row in rows {
+        excerpts.push(row?);
+    }
+    Ok(excerpts)
+}
+
+/// Cut `text` to at most `max_chars` characters, backing up to the last
+/// whitespace so no word is split, and mark the cut with `…`. A single word
+/// longer than the limit is cut mid-word.
+fn truncate_at_word(text: &str, max_chars: usize) -> String {
+    let Some((cut, next)) = text.char_indices().nth(max_chars) else {
+        return text.to_string();
+    };
+    let head = &text[..cut];
+    let head = if next.is_whitespace() {
+        head
+    } else {
+        match head.rfind(char::is_whitespace) {
+            Some(space) => &head[..space],
+            None => head,
+        }
+    };
+    format!("{}…", head.trim_end())
+}
+
+/// `(+N more)` when the day had more notes than were excerpted.
+fn more_notes_marker(notes_count: i64, excerpted: usize) -> Option<String> {
+    let omitted = notes_count - excerpted as i64;
+    (omitted > 0).then(|| format!("(+{omitted} more)"))
+}
+
+/// Roll the previous seven logbook entries into a weekly summary. Without a
+/// payload `start`, the week is the one before today in the digest timezone.
+fn perform_weekly_digest(
+    conn: &Connection,
+    summarizer: &Summarizer,
+    payload: &Value,
+) -> Result<Value> {
+    let start = match payload_start_date(payload)? {
+        Some(start) => start,
+        None => previous_week_start(read_schedule(conn)?.today()?),
+    };
+    let end = start + TimeDuration::days(7);
+    perform_rollup(conn, summarizer, "weekly", "week", start, end)
+}
+
+/// Roll the previous calendar month of logbook entries into one summary,
+/// counted from today in the digest timezone unless the payload sets `start`.
+fn perform_monthly_digest(
+    conn: &Connection,
+    summarizer: &Summarizer,
+    payload: &Value,
+) -> Result<Value> {
+    let start = match payload_start_date(payload)? {
+        Some(start) => start.replace_day(1)?,
+        None => previous_month_start(read_schedule(conn)?.today()?)?,
+    };
+    let end = start + TimeDuration::days(start.month().length(start.year()) as i64);
+    perform_rollup(conn, summarizer, "monthly", "month", start, end)
+}
+
+/// Summarise logbook entries in `[start, end)` and persist a rollup row.
+fn perform_rollup(
+    conn: &Connection,
+    summarizer: &Summarizer,
+    period: &str,
+    target_type: &str,
+    start: Date,
+    end: Date,
+) -> Result<Value> {
+    let start_key = start.to_string();
+    let end_key = end.to_string();
+    let entries = logbook_summaries(conn, start, end)?;
+
+    let mut fallback_parts = vec![format!(
+        "Rolled up {} daily log{} from {start_key} to {}.",
+        entries.len(),
+        plural(entries.len() as i64),
+        (end - TimeDuration::DAY)
+    )];
+    fallback_parts.extend(logbook_preview_lines(&entries));
+    let fallback_summary = fallback_parts.join("\n");
+
+    let facts = json!({
+        "period": period,
+        "period_start": start_key,
+        "period_end": end_key,
+        "entries": entries
+            .iter()
+            .map(|(entry_date, summary)| json!({
+                "entry_date": entry_date,
+                "summary": summary,
+            }))
+            .collect::<Vec<_>>(),
+    });
     let summary_record =
-        summarizer.summarise_daily_digest(&date_key, facts_json, &fallback_summary)?;
-    let summary_text = summary_record.body.clone();
+        summarizer.summarise_rollup(target_type, &start_key, facts, &fallback_summary)?;
 
-    let logbook_entry = upsert_logbook_entry(conn, &date_key, &summary_record)?;
-    let timeline = rebuild_timeline(
+    let rollup = upsert_rollup_entry(
         conn,
-        &date_key,
-        &summary_text,
-        notes_count,
-        ai_calls,
-        ai_failures,
+        period,
+        &start_key,
+        &end_key,
+        &summary_record,
+        entries.len() as i64,
     )?;
 
-    if let Some(entry_id) = logbook_entry.get("id").and_then(|v| v.as_str()) {
-        link_note_mentions(conn, entry_id, &facts.note_excerpts)?;
-    }
-
     log_event(
         conn,
         "info",
-        Some("SYS-LOG-100"),
-        "jobs.daily",
-        "Daily digest job completed",
-        Some("Created or refreshed logbook and timeline entries."),
+        Some("SYS-LOG-110"),
+        "jobs.rollup",
+        "Rollup digest job completed",
+        Some("Created or refreshed a weekly/monthly rollup entry."),
         Some(json!({
-            "entry_date": date_key,
+            "period": period,
+            "period_start": start_key,
             "summary_id": summary_record.id,
         })),
     )
-    .context("failed to log daily digest completion")?;
+    .context("failed to log rollup completion")?;
+
+    Ok(json!({
+        "period": period,
+        "period_start": start_key,
+        "period_end": end_key,
+        "rollup": rollup,
+    }))
+}
+
+/// Insert or update the rollup entry for `period` starting at `period_start`.
+fn upsert_rollup_entry(
+    conn: &Connection,
+    period: &str,
+    period_start: &str,
+    period_end: &str,
+    summary: &SummaryRecord,
+    entry_count: i64,
+) -> Result<Value> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT id FROM rollup_entries WHERE period = ?1 AND period_start = ?2",
+            params![period, period_start],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let entry_id = if let Some(id) = existing {
+        conn.execute(
+            "UPDATE rollup_entries SET period_end = ?2, summary = ?3, entry_count = ?4, updated_at = ?5 WHERE id = ?1",
+            params![id, period_end, summary.body, entry_count, now],
+        )
+        .context("failed to update rollup entry")?;
+        id
+    } else {
+        let id = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO rollup_entries (id, period, period_start, period_end, summary, entry_count, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+            params![id, period, period_start, period_end, summary.body, entry_count, now],
+        )
+        .context("failed to insert rollup entry")?;
+        id
+    };
+
+    replace_link(
+        conn,
+        &entry_id,
+        "rollup_entry",
+        &summary.id,
+        "summary",
+        "has_summary",
+    )?;
 
     Ok(json!({
-        "entry_date": date.to_string(),
-        "logbook": logbook_entry,
-        "timeline": timeline,
+        "id": entry_id,
+        "period": period,
+        "period_start": period_start,
+        "period_end": period_end,
+        "summary": summary.body,
+        "entry_count": entry_count,
     }))
 }
 
-struct DailyDigestFacts {
-    date_key: String,
-    notes_count: i64,
-    ai_calls: i64,
-    ai_failures: i64,
-    job_count: i64,
-    latest_note: Option<(String, i64)>,
-    note_excerpts: Vec<NoteExcerpt>,
+/// Daily logbook summaries dated `start` up to but excluding `end`, oldest
+/// first, as `(entry_date, summary)` pairs.
+fn logbook_summaries(conn: &Connection, start: Date, end: Date) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT entry_date, summary FROM logbook_entries WHERE entry_date >= ?1 AND entry_date < ?2 ORDER BY entry_date ASC",
+    )?;
+    let rows = stmt.query_map(params![start.to_string(), end.to_string()], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
 }
 
-fn digest_facts_json(facts: &DailyDigestFacts) -> Value {
-    json!({
-        "date_key": facts.date_key,
-        "notes_count": facts.notes_count,
-        "ai_calls": facts.ai_calls,
-        "ai_failures": facts.ai_failures,
-        "job_count": facts.job_count,
-        "latest_note": facts.latest_note.as_ref().map(|(title, ts)| json!({
+/// One `- date: summary` line per entry for fallback text, each summary cut
+/// to its first 160 characters on a single line.
+fn logbook_preview_lines(entries: &[(String, String)]) -> impl Iterator<Item = String> + '_ {
+    entries.iter().map(|(entry_date, summary)| {
+        let preview: String = summary.chars().take(160).collect();
+        format!("- {entry_date}: {}", preview.replace('\n', " "))
+    })
+}
+
+/// Summarise workspace activity from `start` to `end`, both inclusive.
+///
+/// Combines the counts used by the daily digest with the range's logbook
+/// summaries and recent note excerpts. The summary is cached per range and
+/// source material, so asking again without new activity reuses it.
+fn perform_range_summary(
+    conn: &Connection,
+    summarizer: &Summarizer,
+    start: Date,
+    end: Date,
+) -> Result<SummaryRecord> {
+    if end < start {
+        return Err(InkOsError::ValidationFailed(format!(
+            "end date {end} is before start date {start}"
+        ))
+        .into());
+    }
+    let days = (end - start).whole_days() + 1;
+    if days > MAX_RANGE_DAYS {
+        return Err(InkOsError::ValidationFailed(format!(
+            "ranges are limited to {MAX_RANGE_DAYS} days, got {days}"
+        ))
+        .into());
+    }
+    let schedule = read_schedule(conn)?;
+    let (start_ts, _) = schedule.day_bounds(start)?;
+    let (_, end_ts) = schedule.day_bounds(end)?;
+    let activity = window_activity(conn, start_ts, end_ts)?;
+    let note_excerpts = collect_note_excerpts(
+        conn,
+        start_ts,
+        end_ts,
+        RANGE_EXCERPTS,
+        schedule.excerpt_chars,
+    )?;
+
+    let start_key = start.to_string();
+    let end_key = end.to_string();
+    let entries = logbook_summaries(conn, start, end + TimeDuration::DAY)?;
+
+    let mut fallback_parts = vec![format!(
+        "From {start_key} to {end_key}: captured {} note{}, dispatched {} AI run{} with {} incident{}, processed {} background job{}.",
+        activity.notes_count,
+        plural(activity.notes_count),
+        activity.ai_calls,
+        plural(activity.ai_calls),
+        activity.ai_failures,
+        plural(activity.ai_failures),
+        activity.job_count,
+        plural(activity.job_count)
+    )];
+    fallback_parts.extend(logbook_preview_lines(&entries));
+    let fallback_summary = fallback_parts.join("\n");
+
+    let facts = json!({
+        "range_start": start_key,
+        "range_end": end_key,
+        "days": days,
+        "notes_count": activity.notes_count,
+        "ai_calls": activity.ai_calls,
+        "ai_failures": activity.ai_failures,
+        "job_count": activity.job_count,
+        "prompt_tokens": activity.usage.prompt_tokens,
+        "completion_tokens": activity.usage.completion_tokens,
+        "total_tokens": activity.usage.total_tokens,
+        "estimated_cost_usd": activity.usage.estimated_cost_usd,
+        "latest_note": activity.latest_note.as_ref().map(|(title, ts)| json!({
             "title": title,
             "created_at": ts,
         })),
-        "note_excerpts": facts
-            .note_excerpts
+        "logbook": entries
+            .iter()
+            .map(|(entry_date, summary)| json!({
+                "entry_date": entry_date,
+                "summary": summary,
+            }))
+            .collect::<Vec<_>>(),
+        "note_excerpts": note_excerpts
             .iter()
             .map(|note| json!({
                 "id": note.id,
                 "title": note.title,
                 "preview": note.preview,
+                "tags": note.tags,
             }))
             .collect::<Vec<_>>(),
-    })
+        "more_notes": more_notes_marker(activity.notes_count, note_excerpts.len()),
+    });
+    summarizer.summarise_range(&format!("{start_key}..{end_key}"), facts, &fallback_summary)
 }
 
-struct NoteExcerpt {
-    id: String,
-    title: String,
-    preview: String,
+fn payload_start_date(payload: &Value) -> Result<Option<Date>> {
+    payload
+        .get("start")
+        .and_then(Value::as_str)
+        .map(|value| {
+            Date::parse(value, &format_description!("[year]-[month]-[day]"))
+                .context("invalid start date supplied to rollup job")
+        })
+        .transpose()
 }
 
-fn collect_note_excerpts(
-    conn: &Connection,
-    start_ts: i64,
-    end_ts: i64,
-) -> Result<Vec<NoteExcerpt>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, body FROM notes WHERE created_at >= ?1 AND created_at < ?2 ORDER BY created_at DESC LIMIT 5",
+/// Monday of the week before the one containing `today`.
+fn previous_week_start(today: Date) -> Date {
+    let days_from_monday = today.weekday().number_days_from_monday() as i64;
+    today - TimeDuration::days(days_from_monday + 7)
+}
+
+/// First day of the month before the one containing `today`.
+fn previous_month_start(today: Date) -> Result<Date> {
+    let this_month = today.replace_day(1)?;
+    Ok((this_month - TimeDuration::DAY).replace_day(1)?)
+}
+
+/// Queue the upcoming weekly (Monday 03:00) and monthly (1st 03:00) rollup
+/// jobs, in the digest timezone, if they are not already pending.
+fn schedule_next_rollups(conn: &Connection) -> Result<()> {
+    let tz = read_schedule(conn)?.tz()?;
+    let now = OffsetDateTime::now_utc();
+    let rollup_time = Time::from_hms(3, 0, 0).context("failed to construct rollup time")?;
+    let run_at = |date: Date| local_to_utc(date, rollup_time, tz);
+
+    let today = now.to_timezone(tz).date();
+    let mut weekly_date = if today.weekday() == Weekday::Monday {
+        today
+    } else {
+        today.next_occurrence(Weekday::Monday)
+    };
+    if now >= run_at(weekly_date) {
+        weekly_date = weekly_date.next_occurrence(Weekday::Monday);
+    }
+    let weekly_start = weekly_date - TimeDuration::days(7);
+    queue_rollup_job(
+        conn,
+        WEEKLY_DIGEST_JOB,
+        run_at(weekly_date).unix_timestamp(),
+        weekly_start,
     )?;
-    let rows = stmt.query_map(params![start_ts, end_ts], |row| {
-        let body: String = row.get(2)?;
-        let preview: String = body.chars().take(240).collect();
-        Ok(NoteExcerpt {
-            id: row.get(0)?,
-            title: row.get(1)?,
-            preview: preview.replace('\n', " "),
-        })
-    })?;
-    let mut excerpts = Vec::new();
-    for row in rows {
-        excerpts.push(row?);
+
+    let this_month = today.replace_day(1)?;
+    let mut monthly_date = this_month;
+    if now >= run_at(monthly_date) {
+        monthly_date =
+            this_month + TimeDuration::days(this_month.month().length(this_month.year()) as i64);
     }
-    Ok(excerpts)
+    let monthly_start = previous_month_start(monthly_date)?;
+    queue_rollup_job(
+        conn,
+        MONTHLY_DIGEST_JOB,
+        run_at(monthly_date).unix_timestamp(),
+        monthly_start,
+    )?;
+    Ok(())
 }
 
-fn schedule_next_digest(conn: &Connection) -> Result<()> {
+/// Queue the daily retention prune for 04:00 in the digest timezone if none
+/// is pending.
+fn schedule_next_prune(conn: &Connection) -> Result<()> {
+    let pending: Option<String> = conn
+        .query_row(
+            "SELECT id FROM jobs WHERE kind = ?1 AND state = 'queued' LIMIT 1",
+            params![PRUNE_JOB],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if pending.is_some() {
+        return Ok(());
+    }
+
+    let tz = read_schedule(conn)?.tz()?;
     let now = OffsetDateTime::now_utc();
-    let target_time =
-        Time::from_hms(2, 0, 0).context("failed to construct digest schedule time")?;
-    let mut next_run = now.date().with_time(target_time).assume_utc();
-    if now >= next_run {
-        next_run += TimeDuration::DAY;
+    let prune_time = Time::from_hms(4, 0, 0).context("failed to construct prune time")?;
+    let mut run_date = now.to_timezone(tz).date();
+    if now >= local_to_utc(run_date, prune_time, tz) {
+        run_date = run_date
+            .next_day()
+            .ok_or_else(|| anyhow!("prune schedule date out of range"))?;
     }
-    let digest_date = (next_run - TimeDuration::DAY).date().to_string();
-    let run_at_ts = next_run.unix_timestamp();
+    let run_at_ts = local_to_utc(run_date, prune_time, tz).unix_timestamp();
+    persist_job_with_conn(conn, PRUNE_JOB, &json!({}), Some(run_at_ts))?;
+    Ok(())
+}
 
+fn queue_rollup_job(conn: &Connection, kind: &str, run_at_ts: i64, start: Date) -> Result<()> {
     let existing: Option<String> = conn
         .query_row(
-            "SELECT id FROM jobs WHERE kind = ?1 AND state = 'queued' AND run_at = ?2 LIMIT 1",
-            params![DAILY_DIGEST_JOB, run_at_ts],
+            "SELECT id FROM jobs WHERE kind = ?1 AND state = 'queued' AND json_extract(payload, '$.start') = ?2 LIMIT 1",
+            params![kind, start.to_string()],
             |row| row.get(0),
         )
         .optional()?;
@@ -478,17 +2060,18 @@ fn schedule_next_digest(conn: &Connection) -> Result<()> {
         return Ok(());
     }
 
-    let payload = json!({ "date": digest_date });
-    let id = persist_job_with_conn(conn, DAILY_DIGEST_JOB, &payload, Some(run_at_ts))?;
+    let payload = json!({ "start": start.to_string() });
+    let id = persist_job_with_conn(conn, kind, &payload, Some(run_at_ts))?;
     let _ = log_event(
         conn,
         "info",
-        Some("JOB-200"),
+        Some("JOB-201"),
         "jobs.scheduler",
-        "Scheduled nightly digest job",
-        Some("Will summarise the previous day at 02:00 UTC."),
+        "Scheduled rollup digest job",
+        Some("Will roll up logbook entries for the previous period."),
         Some(json!({
             "job_id": id,
+            "kind": kind,
             "run_at": run_at_ts,
             "payload": payload,
         })),
@@ -497,10 +2080,14 @@ fn schedule_next_digest(conn: &Connection) -> Result<()> {
 }
 
 /// Insert or update the daily logbook entry for `entry_date`.
+///
+/// Only the generated `summary` is written; `manual_notes` belongs to the
+/// user and is carried through untouched.
 fn upsert_logbook_entry(
     conn: &Connection,
     entry_date: &str,
-    summary: &SummaryRecord,
+    body: &str,
+    summary: Option<&SummaryRecord>,
 ) -> Result<Value> {
     let now = OffsetDateTime::now_utc().unix_timestamp();
     let existing: Option<(String, i64)> = conn
@@ -511,7 +2098,7 @@ fn upsert_logbook_entry(
     let entry_id = if let Some((id, _)) = existing {
         conn.execute(
             "UPDATE logbook_entries SET summary = ?2 WHERE id = ?1",
-            params![id, summary.body],
+            params![id, body],
         )
         .context("failed to update existing logbook entry")?;
         id
@@ -519,30 +2106,49 @@ fn upsert_logbook_entry(
         let id = Uuid::new_v4().to_string();
         conn.execute(
             "INSERT INTO logbook_entries (id, entry_date, summary, created_at) VALUES (?1, ?2, ?3, ?4)",
-            params![id, entry_date, summary.body, now],
+            params![id, entry_date, body, now],
         )
         .context("failed to insert logbook entry")?;
         id
     };
 
-    link_summary(conn, &entry_id, summary)?;
+    if let Some(summary) = summary {
+        link_summary(conn, &entry_id, summary)?;
+    }
 
-    let (created_at,): (i64,) = conn
+    let (created_at, manual_notes): (i64, Option<String>) = conn
         .query_row(
-            "SELECT created_at FROM logbook_entries WHERE id = ?1",
+            "SELECT created_at, manual_notes FROM logbook_entries WHERE id = ?1",
             params![entry_id],
-            |row| Ok((row.get(0)?,)),
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )
         .context("failed to reload logbook entry")?;
 
     Ok(json!({
         "id": entry_id,
         "entry_date": entry_date,
-        "summary": summary.body,
+        "summary": body,
+        "manual_notes": manual_notes,
         "created_at": created_at,
     }))
 }
 
+/// The id and body of the AI summary currently shown for a day, if any.
+fn current_ai_summary(conn: &Connection, entry_date: &str) -> Result<Option<(String, String)>> {
+    conn.query_row(
+        "SELECT s.id, e.summary FROM logbook_entries e
+         JOIN links l ON l.src_id = e.id AND l.src_type = 'logbook_entry'
+             AND l.dst_type = 'summary' AND l.rel = 'has_summary'
+         JOIN summaries s ON s.id = l.dst_id AND s.source = 'ai'
+         WHERE e.entry_date = ?1
+         ORDER BY s.created_at DESC LIMIT 1",
+        params![entry_date],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .context("failed to read the day's AI summary")
+}
+
 fn link_summary(conn: &Connection, entry_id: &str, summary: &SummaryRecord) -> Result<()> {
     replace_link(
         conn,
@@ -603,6 +2209,7 @@ fn replace_link(
 }
 
 /// Recreate the derived timeline events backing the daily digest view.
+/// Events the user added by hand are kept.
 fn rebuild_timeline(
     conn: &Connection,
     entry_date: &str,
@@ -610,9 +2217,10 @@ fn rebuild_timeline(
     notes_count: i64,
     ai_calls: i64,
     ai_failures: i64,
+    over_budget: Option<(i64, u64)>,
 ) -> Result<Value> {
     conn.execute(
-        "DELETE FROM timeline_events WHERE entry_date = ?1",
+        "DELETE FROM timeline_events WHERE entry_date = ?1 AND source = 'derived'",
         params![entry_date],
     )
     .context("failed to clear previous timeline events")?;
@@ -662,10 +2270,21 @@ fn rebuild_timeline(
         )?);
     }
 
+    if let Some((total_tokens, budget)) = over_budget {
+        events.push(create_timeline_event(
+            conn,
+            entry_date,
+            now,
+            "usage",
+            format!("{total_tokens} AI tokens used"),
+            format!("Today's usage exceeded the daily budget of {budget} tokens."),
+        )?);
+    }
+
     Ok(Value::Array(events))
 }
 
-/// Persist a single timeline event and return its serialised form.
+/// Persist a single derived timeline event and return its serialised form.
 fn create_timeline_event(
     conn: &Connection,
     entry_date: &str,
@@ -677,7 +2296,7 @@ fn create_timeline_event(
     let id = Uuid::new_v4().to_string();
     let created_at = OffsetDateTime::now_utc().unix_timestamp();
     conn.execute(
-        "INSERT INTO timeline_events (id, entry_date, event_time, kind, title, detail, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        "INSERT INTO timeline_events (id, entry_date, event_time, kind, title, detail, created_at, source) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'derived')",
         params![id, entry_date, event_time, kind, title, detail, created_at],
     )
     .context("failed to insert timeline event")?;
@@ -689,17 +2308,18 @@ fn create_timeline_event(
         "kind": kind,
         "title": title,
         "detail": detail,
+        "source": "derived",
         "created_at": created_at,
     }))
 }
 
-/// Resolve the target date for a digest run, defaulting to today.
-fn resolve_entry_date(payload: &Value) -> Result<Date> {
+/// Resolve the target date for a digest run, defaulting to the local today.
+fn resolve_entry_date(payload: &Value, schedule: &DigestSchedule) -> Result<Date> {
     if let Some(date_str) = payload.get("date").and_then(Value::as_str) {
         Date::parse(date_str, &format_description!("[year]-[month]-[day]"))
             .context("invalid date supplied to daily digest job")
     } else {
-        Ok(OffsetDateTime::now_utc().date())
+        schedule.today()
     }
 }
 
@@ -715,6 +2335,8 @@ fn plural(count: i64) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agents::config::use_ollama_at;
+    use crate::model_manager::file_backed_models;
     use r2d2_sqlite::rusqlite::Connection as SqliteConnection;
 
     #[test]
@@ -724,29 +2346,804 @@ mod tests {
         assert_eq!(plural(2), "s");
     }
 
+    #[test]
+    fn workspace_stats_counts_live_rows_and_recent_failures() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        for (id, created_at, deleted_at) in [
+            ("today", now, None),
+            ("old", now - 10 * 86_400, None),
+            ("trashed", now, Some(now)),
+        ] {
+            conn.execute(
+                "INSERT INTO notes (id, title, body, created_at, updated_at, deleted_at) VALUES (?1, ?1, '', ?2, ?2, ?3)",
+                params![id, created_at, deleted_at],
+            )
+            .unwrap();
+        }
+        for (id, closed_at) in [("open", None), ("closed", Some(now))] {
+            conn.execute(
+                "INSERT INTO conversations (id, provider_id, model_id, created_at, updated_at, closed_at) VALUES (?1, 'ollama', 'm', ?2, ?2, ?3)",
+                params![id, now, closed_at],
+            )
+            .unwrap();
+        }
+        for (id, state, updated_at) in [
+            ("q", "queued", now),
+            ("r", "running", now),
+            ("f", "failed", now),
+            ("stale", "failed", now - 2 * 86_400),
+            ("done", "succeeded", now),
+        ] {
+            conn.execute(
+                "INSERT INTO jobs (id, kind, state, payload, created_at, updated_at) VALUES (?1, ?2, ?3, '{}', ?4, ?4)",
+                params![id, PRUNE_JOB, state, updated_at],
+            )
+            .unwrap();
+        }
+
+        let stats = workspace_stats(&conn).unwrap();
+        assert_eq!((stats.notes_total, stats.notes_today), (2, 1));
+        assert_eq!(
+            (stats.conversations_total, stats.open_conversations),
+            (2, 1)
+        );
+        assert_eq!((stats.jobs_pending, stats.jobs_failed_24h), (2, 1));
+        assert_eq!(stats.summaries_total, 0);
+    }
+
+    #[test]
+    fn rollups_and_prune_follow_the_digest_timezone() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        let schedule = DigestSchedule {
+            timezone: "Asia/Tokyo".into(),
+            ..DigestSchedule::default()
+        };
+        write_schedule(&conn, &schedule).unwrap();
+        schedule_next_rollups(&conn).unwrap();
+        schedule_next_prune(&conn).unwrap();
+        // Tokyo is UTC+9 all year, so 03:00 and 04:00 local are 18:00 and
+        // 19:00 UTC the day before.
+        let hour = |kind: &str| -> Vec<u8> {
+            let mut stmt = conn
+                .prepare("SELECT run_at FROM jobs WHERE kind = ?1")
+                .unwrap();
+            stmt.query_map(params![kind], |row| row.get::<_, i64>(0))
+                .unwrap()
+                .map(|run_at| {
+                    OffsetDateTime::from_unix_timestamp(run_at.unwrap())
+                        .unwrap()
+                        .hour()
+                })
+                .collect()
+        };
+        assert_eq!(hour(WEEKLY_DIGEST_JOB), [18]);
+        assert_eq!(hour(MONTHLY_DIGEST_JOB), [18]);
+        assert_eq!(hour(PRUNE_JOB), [19]);
+
+        // Queued rollups are matched on their period, not their run time.
+        schedule_next_rollups(&conn).unwrap();
+        assert_eq!(hour(WEEKLY_DIGEST_JOB).len(), 1);
+    }
+
+    #[test]
+    fn digest_rewrites_leave_manual_notes_alone() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        let first = upsert_logbook_entry(&conn, "2024-03-01", "Quiet day", None).unwrap();
+        assert!(first["manual_notes"].is_null());
+        conn.execute(
+            "UPDATE logbook_entries SET manual_notes = 'Offsite with the design team' WHERE entry_date = '2024-03-01'",
+            [],
+        )
+        .unwrap();
+
+        let second = upsert_logbook_entry(&conn, "2024-03-01", "Wrote 3 notes", None).unwrap();
+        assert_eq!(second["id"], first["id"]);
+        assert_eq!(second["summary"], "Wrote 3 notes");
+        assert_eq!(second["manual_notes"], "Offsite with the design team");
+    }
+
+    #[test]
+    fn logbook_search_covers_summaries_notes_and_date_bounds() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        upsert_logbook_entry(&conn, "2024-03-01", "Drafted the billing migration", None).unwrap();
+        upsert_logbook_entry(&conn, "2024-03-02", "Quiet day", None).unwrap();
+        upsert_logbook_entry(&conn, "2024-03-03", "Billing migration shipped", None).unwrap();
+        conn.execute(
+            "UPDATE logbook_entries SET manual_notes = 'Pairing on billing with Sam' WHERE entry_date = '2024-03-02'",
+            [],
+        )
+        .unwrap();
+        // A digest rewrite replaces the indexed summary.
+        upsert_logbook_entry(&conn, "2024-03-01", "Planning only", None).unwrap();
+
+        let dates = |from: Option<Date>, to: Option<Date>| -> Vec<String> {
+            let mut dates: Vec<String> = search_logbook(&conn, "billing", from, to, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.entry_date)
+                .collect();
+            dates.sort();
+            dates
+        };
+        assert_eq!(dates(None, None), ["2024-03-02", "2024-03-03"]);
+        let march_2 = Date::from_calendar_date(2024, time::Month::March, 2).unwrap();
+        assert_eq!(dates(None, Some(march_2)), ["2024-03-02"]);
+
+        let hits = search_logbook(&conn, "shipped", None, None, 10).unwrap();
+        assert_eq!(hits[0].snippet, "Billing migration **shipped**");
+        assert!(search_logbook(&conn, " ", None, None, 10).is_err());
+    }
+
+    #[test]
+    fn excerpts_are_cut_on_word_boundaries() {
+        assert_eq!(truncate_at_word("short note", 40), "short note");
+        assert_eq!(
+            truncate_at_word("Planned the billing migration", 20),
+            "Planned the billing…"
+        );
+        assert_eq!(
+            truncate_at_word("Planned the billing migration", 15),
+            "Planned the…"
+        );
+        assert_eq!(truncate_at_word("Überraschungsparty", 5), "Überr…");
+    }
+
+    #[test]
+    fn excerpts_respect_the_configured_cap() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        for minute in 0..7 {
+            conn.execute(
+                "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES (?1, ?1, 'Long-form journaling about the week ahead', ?2, ?2)",
+                params![format!("n{minute}"), 1_000 + minute * 60],
+            )
+            .unwrap();
+        }
+        write_schedule(
+            &conn,
+            &DigestSchedule {
+                excerpt_count: 3,
+                excerpt_chars: 40,
+                ..DigestSchedule::default()
+            },
+        )
+        .unwrap();
+        let schedule = read_schedule(&conn).unwrap();
+        assert_eq!((schedule.excerpt_count, schedule.excerpt_chars), (3, 40));
+
+        let excerpts = collect_note_excerpts(
+            &conn,
+            0,
+            10_000,
+            schedule.excerpt_count,
+            schedule.excerpt_chars,
+        )
+        .unwrap();
+        let titles: Vec<&str> = excerpts.iter().map(|note| note.title.as_str()).collect();
+        assert_eq!(titles, ["n6", "n5", "n4"]);
+        assert_eq!(excerpts[0].preview, "Long-form journaling about the week…");
+        assert_eq!(
+            more_notes_marker(7, excerpts.len()).as_deref(),
+            Some("(+4 more)")
+        );
+        assert_eq!(more_notes_marker(3, 3), None);
+        assert!(write_schedule(
+            &conn,
+            &DigestSchedule {
+                excerpt_count: 0,
+                ..DigestSchedule::default()
+            },
+        )
+        .is_err());
+    }
+
+    fn excerpt(title: &str, preview: &str, tags: &[&str]) -> NoteExcerpt {
+        NoteExcerpt {
+            id: title.to_lowercase(),
+            title: title.to_string(),
+            preview: preview.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    fn facts(notes_count: i64, ai_calls: i64, note_excerpts: Vec<NoteExcerpt>) -> DailyDigestFacts {
+        DailyDigestFacts {
+            date_key: "2024-03-01".into(),
+            notes_count,
+            ai_calls,
+            ai_failures: 0,
+            job_count: 0,
+            usage: DailyUsage::default(),
+            latest_note: note_excerpts
+                .first()
+                .map(|note| (note.title.clone(), 1_709_294_400)),
+            note_excerpts,
+        }
+    }
+
+    #[test]
+    fn fallback_summary_names_notes_and_themes() {
+        let excerpts = vec![
+            excerpt(
+                "Invoice retries",
+                "Billing webhooks drop events under load",
+                &[],
+            ),
+            excerpt(
+                "Standup",
+                "Reviewed the billing dashboard with design",
+                &["work"],
+            ),
+            excerpt("Lunch spot", "Tried the ramen place", &["personal"]),
+            excerpt(
+                "Webhook backoff",
+                "Billing webhooks need jittered retries",
+                &["work"],
+            ),
+        ];
+        let summary = compose_fallback_summary(&facts(9, 3, excerpts), Some("12:00 UTC"));
+        assert_eq!(
+            summary,
+            "A busy day with 9 new notes. \
+             Latest note \"Invoice retries\" captured at 12:00 UTC. \
+             Also captured \"Standup\", \"Lunch spot\" and \"Webhook backoff\" (+5 more). \
+             Recurring themes: billing (3 notes), retries/webhooks (2 notes) and work (2 notes). \
+             Ran 3 AI requests."
+        );
+    }
+
+    #[test]
+    fn fallback_summary_phrasing_follows_activity() {
+        assert_eq!(
+            compose_fallback_summary(&facts(0, 0, Vec::new()), None),
+            "A quiet day with no new notes and no AI runs."
+        );
+        let light = compose_fallback_summary(
+            &facts(1, 0, vec![excerpt("Groceries", "Milk, eggs", &[])]),
+            Some("08:15 UTC"),
+        );
+        assert_eq!(
+            light,
+            "A light day with 1 new note. Latest note \"Groceries\" captured at 08:15 UTC."
+        );
+        let steady = compose_fallback_summary(&facts(4, 0, Vec::new()), None);
+        assert!(steady.starts_with("A steady day with 4 new notes."));
+    }
+
     #[test]
     fn resolve_entry_date_defaults_to_today() {
         let today = OffsetDateTime::now_utc().date();
-        let resolved = resolve_entry_date(&json!({})).unwrap();
+        let resolved = resolve_entry_date(&json!({}), &DigestSchedule::default()).unwrap();
         assert_eq!(resolved, today);
     }
 
     #[test]
     fn resolve_entry_date_parses_explicit_string() {
-        let resolved = resolve_entry_date(&json!({ "date": "2024-01-05" })).unwrap();
+        let resolved =
+            resolve_entry_date(&json!({ "date": "2024-01-05" }), &DigestSchedule::default())
+                .unwrap();
         assert_eq!(resolved.to_string(), "2024-01-05");
     }
 
     #[test]
-    fn rebuild_timeline_generates_entries() {
+    fn stale_running_jobs_are_requeued_or_failed() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let stale = now - schedule::DEFAULT_JOB_TIMEOUT_SECS - 1;
+        for (id, attempts, started_at) in
+            [("retry", 1, stale), ("spent", 3, stale), ("fresh", 1, now)]
+        {
+            conn.execute(
+                "INSERT INTO jobs (id, kind, state, payload, created_at, updated_at, attempts, started_at)
+                 VALUES (?1, ?2, 'running', '{}', ?3, ?3, ?4, ?3)",
+                params![id, PRUNE_JOB, started_at, attempts],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(reclaim_stuck_jobs(&conn, now).unwrap(), 2);
+        let state = |id: &str| -> (String, Option<i64>) {
+            conn.query_row(
+                "SELECT state, run_at FROM jobs WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(state("retry"), ("queued".into(), Some(now + 60)));
+        assert_eq!(state("spent").0, "failed");
+        assert_eq!(state("fresh").0, "running");
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM event_log WHERE code = 'AI-JOB-TIMEOUT'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 2);
+    }
+
+    #[test]
+    fn retry_delay_doubles_and_caps() {
+        assert_eq!(retry_delay_secs(1), 60);
+        assert_eq!(retry_delay_secs(2), 120);
+        assert_eq!(retry_delay_secs(3), 240);
+        assert_eq!(retry_delay_secs(20), RETRY_MAX_DELAY_SECS);
+    }
+
+    #[test]
+    fn previous_period_starts_align_to_calendar() {
+        let wednesday = Date::from_calendar_date(2024, time::Month::January, 10).unwrap();
+        assert_eq!(previous_week_start(wednesday).to_string(), "2024-01-01");
+        let monday = Date::from_calendar_date(2024, time::Month::January, 8).unwrap();
+        assert_eq!(previous_week_start(monday).to_string(), "2024-01-01");
+        let march = Date::from_calendar_date(2024, time::Month::March, 1).unwrap();
+        assert_eq!(
+            previous_month_start(march).unwrap().to_string(),
+            "2024-02-01"
+        );
+    }
+
+    #[test]
+    fn cancel_and_requeue_follow_job_state() {
         let conn = SqliteConnection::open_in_memory().unwrap();
         conn.execute_batch(
-            "CREATE TABLE timeline_events (id TEXT PRIMARY KEY, entry_date TEXT, event_time INTEGER, kind TEXT, title TEXT, detail TEXT, created_at INTEGER);",
+            "CREATE TABLE jobs (id TEXT PRIMARY KEY, kind TEXT, state TEXT DEFAULT 'queued', payload TEXT, created_at INTEGER, updated_at INTEGER, run_at INTEGER, result TEXT, attempts INTEGER DEFAULT 0, max_attempts INTEGER DEFAULT 3);",
+        )
+        .unwrap();
+
+        let invalid_state = |result: Result<String>| {
+            matches!(
+                result.unwrap_err().downcast_ref::<InkOsError>(),
+                Some(InkOsError::JobInvalidState(_))
+            )
+        };
+        let id = persist_job_with_conn(&conn, DAILY_DIGEST_JOB, &json!({}), None).unwrap();
+        assert!(invalid_state(requeue_job_with_conn(&conn, &id, None)));
+        cancel_job_with_conn(&conn, &id).unwrap();
+        assert!(invalid_state(
+            cancel_job_with_conn(&conn, &id).map(|()| String::new())
+        ));
+
+        let copy = requeue_job_with_conn(&conn, &id, Some(42)).unwrap();
+        let (state, run_at): (String, i64) = conn
+            .query_row(
+                "SELECT state, run_at FROM jobs WHERE id = ?1",
+                params![copy],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(state, "queued");
+        assert_eq!(run_at, 42);
+    }
+
+    #[test]
+    fn manual_digest_reports_each_phase() {
+        let (models, pool, path) = file_backed_models();
+        let conn = pool.get().unwrap();
+        let summarizer = Summarizer::new(pool.clone(), models);
+
+        let payload = json!({ "date": "2024-01-05" });
+        let id = persist_job_with_conn(&conn, DAILY_DIGEST_JOB, &payload, None).unwrap();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let emitter: ProgressEmitter = Arc::new(move |progress: &DigestProgress| {
+            assert_eq!(progress.entry_date, "2024-01-05");
+            sink.lock()
+                .unwrap()
+                .push((progress.phase, progress.percent));
+        });
+        let refreshed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let refresh_sink = Arc::clone(&refreshed);
+        let emitters = JobEmitters {
+            progress: Some(emitter),
+            timeline: Some(Arc::new(move |refresh: &TimelineRefresh| {
+                refresh_sink.lock().unwrap().push(refresh.source);
+            })),
+        };
+        let run = run_job(
+            &conn,
+            &summarizer,
+            &id,
+            DAILY_DIGEST_JOB,
+            payload,
+            &emitters,
+        )
+        .unwrap();
+        let summary_id = run.result["summary_id"].as_str().unwrap();
+        let summary = summarizer.fetch_summary(summary_id).unwrap().unwrap();
+        assert_eq!(
+            (summary.target_type.as_str(), summary.target_id.as_str()),
+            ("day", "2024-01-05")
+        );
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                ("counting_notes", 10),
+                ("gathering_excerpts", 30),
+                ("writing_timeline", 40),
+                ("calling_ai", 60),
+                ("done", 100),
+            ]
+        );
+        // No provider is reachable, so the deterministic entry is kept.
+        assert_eq!(*refreshed.lock().unwrap(), [SOURCE_FALLBACK]);
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn fallback_digests_keep_an_existing_ai_summary() {
+        let (models, pool, path) = file_backed_models();
+        let conn = pool.get().unwrap();
+        let summarizer = Summarizer::new(pool.clone(), models);
+
+        conn.execute(
+            "INSERT INTO summaries (id, target_type, target_id, version, body, model_id, created_at, source) VALUES ('s-ai', 'day', '2024-01-05', 1, 'Planned the launch.', 'llama3', 0, 'ai')",
+            [],
+        )
+        .unwrap();
+        let entry = upsert_logbook_entry(&conn, "2024-01-05", "Planned the launch.", None).unwrap();
+        let entry_id = entry["id"].as_str().unwrap();
+        let ai_summary = summarizer.fetch_summary("s-ai").unwrap().unwrap();
+        link_summary(&conn, entry_id, &ai_summary).unwrap();
+
+        let payload = json!({ "date": "2024-01-05" });
+        let id = persist_job_with_conn(&conn, DAILY_DIGEST_JOB, &payload, None).unwrap();
+        let refreshed = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let refresh_sink = Arc::clone(&refreshed);
+        let emitters = JobEmitters {
+            progress: None,
+            timeline: Some(Arc::new(move |refresh: &TimelineRefresh| {
+                refresh_sink
+                    .lock()
+                    .unwrap()
+                    .push((refresh.source, refresh.summary_id.clone()));
+            })),
+        };
+        // No provider is reachable, so the summariser falls back.
+        let run = run_job(
+            &conn,
+            &summarizer,
+            &id,
+            DAILY_DIGEST_JOB,
+            payload,
+            &emitters,
+        )
+        .unwrap();
+        assert_eq!(run.result["logbook"]["summary"], "Planned the launch.");
+        let stored: String = conn
+            .query_row(
+                "SELECT summary FROM logbook_entries WHERE entry_date = '2024-01-05'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored, "Planned the launch.");
+        assert_eq!(
+            *refreshed.lock().unwrap(),
+            [(SOURCE_AI, Some("s-ai".to_string()))]
+        );
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn range_summaries_cover_every_day_in_the_range() {
+        let (models, pool, path) = file_backed_models();
+        let conn = pool.get().unwrap();
+        let summarizer = Summarizer::new(pool.clone(), models);
+
+        let start = Date::from_calendar_date(2024, time::Month::January, 1).unwrap();
+        let end = Date::from_calendar_date(2024, time::Month::January, 14).unwrap();
+        let (_, end_ts) = DigestSchedule::default().day_bounds(end).unwrap();
+        conn.execute(
+            "INSERT INTO notes (id, title, body, created_at, updated_at) VALUES ('n1', 'Kickoff', '', ?1, ?1), ('n2', 'Later', '', ?2, ?2)",
+            params![end_ts - 60, end_ts],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO logbook_entries (id, entry_date, summary, created_at) VALUES ('a', '2024-01-14', 'Shipped beta', 0), ('b', '2024-01-15', 'Out of range', 0)",
+            [],
+        )
+        .unwrap();
+
+        let summary = perform_range_summary(&conn, &summarizer, start, end).unwrap();
+        assert_eq!(
+            (summary.target_type.as_str(), summary.target_id.as_str()),
+            ("range", "2024-01-01..2024-01-14")
+        );
+        assert!(summary.body.contains("captured 1 note,"));
+        assert!(summary.body.contains("2024-01-14: Shipped beta"));
+        assert!(!summary.body.contains("Out of range"));
+        assert!(perform_range_summary(&conn, &summarizer, end, start).is_err());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_manual_digests_for_one_date_share_a_run() {
+        let (models, pool, path) = file_backed_models();
+        let scheduler = JobScheduler::new(pool.clone(), Summarizer::new(pool.clone(), models));
+
+        // Hold the date's job lock until both callers wait on the same run,
+        // so the first run cannot finish before the second caller arrives.
+        let held = scheduler
+            .job_locks
+            .acquire(job_lock_key(
+                DAILY_DIGEST_JOB,
+                &json!({ "date": "2024-01-05" }),
+            ))
+            .await;
+        let release = async {
+            loop {
+                let waiting = scheduler
+                    .digests_in_flight
+                    .lock()
+                    .await
+                    .get("2024-01-05")
+                    .and_then(Shared::strong_count);
+                // One handle in the registry plus one per waiting caller.
+                if waiting == Some(3) {
+                    break;
+                }
+                tokio::time::sleep(StdDuration::from_millis(10)).await;
+            }
+            drop(held);
+        };
+        let (first, second, ()) = tokio::join!(
+            scheduler.run_now(DAILY_DIGEST_JOB, json!({ "date": "2024-01-05" })),
+            scheduler.run_now(DAILY_DIGEST_JOB, json!({ "date": "2024-01-05" })),
+            release,
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.job_id, second.job_id);
+
+        let conn = pool.get().unwrap();
+        let runs: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM jobs WHERE kind = ?1 AND payload LIKE '%2024-01-05%'",
+                params![DAILY_DIGEST_JOB],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let events: usize = conn
+            .query_row(
+                "SELECT COUNT(*) FROM timeline_events WHERE entry_date = '2024-01-05'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(runs, 1);
+        assert_eq!(events, first.result["timeline"].as_array().unwrap().len());
+        assert!(scheduler.digests_in_flight.lock().await.is_empty());
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn job_locks_serialise_only_matching_keys() {
+        let locks = JobLocks::default();
+        let digest = job_lock_key(DAILY_DIGEST_JOB, &json!({ "date": "2024-01-05" }));
+        let held = locks.acquire(digest.clone()).await;
+        let wait = StdDuration::from_millis(50);
+        assert!(tokio::time::timeout(wait, locks.acquire(digest.clone()))
+            .await
+            .is_err());
+        let other = job_lock_key(DAILY_DIGEST_JOB, &json!({ "date": "2024-01-06" }));
+        assert!(tokio::time::timeout(wait, locks.acquire(other))
+            .await
+            .is_ok());
+        drop(held);
+        assert!(tokio::time::timeout(wait, locks.acquire(digest))
+            .await
+            .is_ok());
+        locks.acquire("prune".into()).await;
+        assert_eq!(locks.0.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn paused_scheduler_leaves_due_jobs_queued_across_restarts() {
+        let (models, pool, path) = file_backed_models();
+        let summarizer = Summarizer::new(pool.clone(), models);
+        let scheduler = JobScheduler::new(pool.clone(), Arc::clone(&summarizer));
+        assert!(scheduler.pause().await.unwrap().paused);
+
+        let job_id = scheduler
+            .enqueue_at(
+                PRUNE_JOB,
+                json!({}),
+                OffsetDateTime::now_utc().unix_timestamp() - 5,
+            )
+            .await
+            .unwrap();
+        scheduler.dispatch_due_jobs().await.unwrap();
+        let state = |id: &str| -> String {
+            pool.get()
+                .unwrap()
+                .query_row("SELECT state FROM jobs WHERE id = ?1", params![id], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        assert_eq!(state(&job_id), "queued");
+
+        let restarted = JobScheduler::new(pool.clone(), summarizer);
+        let status = restarted.status().await.unwrap();
+        assert!(status.paused);
+        assert!(status.due_jobs >= 1);
+        assert!(!restarted.resume().await.unwrap().paused);
+        for _ in 0..100 {
+            if state(&job_id) == "succeeded" {
+                break;
+            }
+            tokio::time::sleep(StdDuration::from_millis(50)).await;
+        }
+        assert_eq!(state(&job_id), "succeeded");
+        let _ = std::fs::remove_file(path);
+    }
+
+    /// Serve Ollama chat replies, holding the first request until `release`
+    /// fires. `arrived` fires once that request has been read.
+    fn stalling_ollama(
+        arrived: std::sync::mpsc::Sender<()>,
+        release: std::sync::mpsc::Receiver<()>,
+    ) -> String {
+        crate::mock_provider::serve(move |index, _| {
+            if index == 0 {
+                let _ = arrived.send(());
+                let _ = release.recv();
+            }
+            Some(("200 OK", crate::mock_provider::ollama_reply("A quiet day.")))
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reclaimed_jobs_keep_their_slot_until_the_run_returns() {
+        let (models, pool, path) = file_backed_models();
+        let (arrived_tx, arrived) = std::sync::mpsc::channel();
+        let (release, release_rx) = std::sync::mpsc::channel();
+        {
+            let conn = pool.get().unwrap();
+            use_ollama_at(&conn, stalling_ollama(arrived_tx, release_rx));
+        }
+        let scheduler = JobScheduler::new(pool.clone(), Summarizer::new(pool.clone(), models));
+        scheduler.set_worker_count(1).await.unwrap();
+        let state = |id: &str| -> String {
+            pool.get()
+                .unwrap()
+                .query_row("SELECT state FROM jobs WHERE id = ?1", params![id], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+        let digest = scheduler
+            .enqueue_at(DAILY_DIGEST_JOB, json!({ "date": "2024-01-05" }), now - 5)
+            .await
+            .unwrap();
+        scheduler.dispatch_due_jobs().await.unwrap();
+        spawn_blocking(move || arrived.recv_timeout(StdDuration::from_secs(10)))
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The provider call is still blocked when the run is reclaimed.
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE jobs SET started_at = ?2 WHERE id = ?1",
+                params![digest, now - schedule::DEFAULT_JOB_TIMEOUT_SECS - 1],
+            )
+            .unwrap();
+        assert_eq!(scheduler.reclaim_stuck_jobs().await.unwrap(), 1);
+        assert_eq!(state(&digest), "queued");
+        let prune = scheduler
+            .enqueue_at(PRUNE_JOB, json!({}), now - 5)
+            .await
+            .unwrap();
+        scheduler.dispatch_due_jobs().await.unwrap();
+        assert!(scheduler.dispatched.lock().unwrap().contains(&digest));
+        assert_eq!(state(&prune), "queued");
+
+        // Once the hung run returns its slot frees up, and its late result is
+        // discarded because the row was requeued under it.
+        release.send(()).unwrap();
+        for _ in 0..100 {
+            if state(&prune) == "succeeded" {
+                break;
+            }
+            tokio::time::sleep(StdDuration::from_millis(50)).await;
+        }
+        assert_eq!(state(&prune), "succeeded");
+        assert_eq!(state(&digest), "queued");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn daily_digest_totals_tokens_and_flags_budget_overruns() {
+        let (models, pool, path) = file_backed_models();
+        let conn = pool.get().unwrap();
+        let summarizer = Summarizer::new(pool.clone(), models);
+        write_schedule(
+            &conn,
+            &DigestSchedule {
+                token_budget: Some(1_000),
+                ..DigestSchedule::default()
+            },
+        )
+        .unwrap();
+        let noon = 1_704_456_000;
+        for (id, provider, model, prompt, completion, at) in [
+            ("cloud", "openai", "gpt-4o", 900, 300, noon),
+            ("local", "ollama", "llama3", 200, 100, noon),
+            ("other-day", "openai", "gpt-4o", 5_000, 0, noon - 86_400),
+        ] {
+            conn.execute(
+                "INSERT INTO usage (id, provider_id, model_id, prompt_tokens, completion_tokens, total_tokens, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?4 + ?5, ?6)",
+                params![id, provider, model, prompt, completion, at],
+            )
+            .unwrap();
+        }
+
+        let payload = json!({ "date": "2024-01-05" });
+        let id = persist_job_with_conn(&conn, DAILY_DIGEST_JOB, &payload, None).unwrap();
+        let run = run_job(
+            &conn,
+            &summarizer,
+            &id,
+            DAILY_DIGEST_JOB,
+            payload,
+            &JobEmitters::default(),
+        )
+        .unwrap();
+
+        let usage = &run.result["usage"];
+        assert_eq!(usage["prompt_tokens"], 1_100);
+        assert_eq!(usage["completion_tokens"], 400);
+        assert_eq!(usage["total_tokens"], 1_500);
+        let cost = usage["estimated_cost_usd"].as_f64().unwrap();
+        let expected = estimate_cost("gpt-4o", 900, 300).unwrap();
+        assert!((cost - expected).abs() < 1e-9);
+        let timeline = run.result["timeline"].as_array().unwrap();
+        assert!(timeline.iter().any(|event| event["kind"] == "usage"));
+        let summary = run.result["logbook"]["summary"].as_str().unwrap();
+        assert!(summary.contains("1500 AI tokens"));
+        drop(conn);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn rebuild_timeline_generates_entries() {
+        let conn = SqliteConnection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO timeline_events (id, entry_date, event_time, kind, title, detail, created_at, source)
+             VALUES ('release', '2024-01-05', 0, 'milestone', 'Shipped 1.0', NULL, 0, 'manual')",
+            [],
         )
         .unwrap();
 
-        let events = rebuild_timeline(&conn, "2024-01-05", "summary", 2, 1, 0).unwrap();
+        let events = rebuild_timeline(&conn, "2024-01-05", "summary", 2, 1, 0, None).unwrap();
         let array = events.as_array().unwrap();
         assert!(array.len() >= 2);
+        let rebuilt = rebuild_timeline(&conn, "2024-01-05", "summary", 2, 1, 0, None).unwrap();
+        let stored: Vec<(String, String)> = conn
+            .prepare("SELECT source, title FROM timeline_events WHERE entry_date = '2024-01-05'")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<r2d2_sqlite::rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(stored.len(), rebuilt.as_array().unwrap().len() + 1);
+        assert!(stored.contains(&("manual".to_string(), "Shipped 1.0".to_string())));
     }
 }
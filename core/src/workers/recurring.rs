@@ -0,0 +1,351 @@
+//! Recurring job definitions stored in the `schedules` table.
+//!
+//! Every enabled schedule keeps one queued job, tagged with its
+//! `schedule_id`, at `next_run`: the next time its cron expression matches in
+//! the digest timezone. Once that job has fallen due, the following
+//! occurrence is queued on the next scheduler tick. The nightly digest is the
+//! seeded `nightly-digest` row.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use r2d2_sqlite::rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::{json, Value};
+use time::{Duration as TimeDuration, OffsetDateTime, PrimitiveDateTime};
+use time_tz::{OffsetDateTimeExt, Tz};
+use uuid::Uuid;
+
+use super::schedule::{local_to_utc, read_schedule};
+use super::{persist_job_with_conn, JOB_KINDS};
+use crate::errors::InkOsError;
+use crate::logging::log_event;
+
+/// Id of the seeded schedule that queues the nightly digest.
+pub const NIGHTLY_DIGEST_SCHEDULE: &str = "nightly-digest";
+const SCHEDULE_COLUMNS: &str =
+    "id, kind, payload, cron_expr, next_run, enabled, created_at, updated_at";
+
+/// A recurring job definition.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSchedule {
+    pub id: String,
+    pub kind: String,
+    pub payload: Value,
+    pub cron_expr: String,
+    /// Run time of the job currently queued for this schedule.
+    pub next_run: Option<i64>,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Parse a cron expression.
+///
+/// Standard five-field expressions (`minute hour day month weekday`) are
+/// accepted alongside the six- and seven-field forms with seconds and years.
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule> {
+    let expr = expr.trim();
+    let expanded = if expr.split_whitespace().count() == 5 {
+        format!("0 {expr}")
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&expanded).map_err(|err| {
+        InkOsError::ValidationFailed(format!("invalid cron expression '{expr}': {err}")).into()
+    })
+}
+
+/// First instant after `after` at which `cron` matches the wall clock in `tz`.
+///
+/// Wall-clock times skipped by DST resolve like [`local_to_utc`]; repeated
+/// times fire once.
+pub fn next_occurrence(
+    cron: &cron::Schedule,
+    after: OffsetDateTime,
+    tz: &Tz,
+) -> Option<OffsetDateTime> {
+    let local = after.to_timezone(tz);
+    let wall = PrimitiveDateTime::new(local.date(), local.time())
+        .assume_utc()
+        .unix_timestamp();
+    let start = DateTime::from_timestamp(wall, 0)?;
+    cron.after(&start).take(4).find_map(|candidate| {
+        let wall = OffsetDateTime::from_unix_timestamp(candidate.timestamp()).ok()?;
+        let instant = local_to_utc(wall.date(), wall.time(), tz);
+        (instant > after).then_some(instant)
+    })
+}
+
+/// Substitute `"date": "today"` / `"yesterday"` with the local date of `run_at`.
+fn resolve_payload(payload: &Value, run_at: OffsetDateTime, tz: &Tz) -> Value {
+    let mut resolved = payload.clone();
+    let run_date = run_at.to_timezone(tz).date();
+    let date = match payload.get("date").and_then(Value::as_str) {
+        Some("today") => run_date,
+        Some("yesterday") => run_date - TimeDuration::DAY,
+        _ => return resolved,
+    };
+    resolved["date"] = json!(date.to_string());
+    resolved
+}
+
+/// All schedules, oldest first.
+pub fn list(conn: &Connection) -> Result<Vec<JobSchedule>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM schedules ORDER BY created_at ASC, id ASC"
+    ))?;
+    let rows = stmt.query_map([], row_to_schedule)?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Look up one schedule; unknown ids fail with `JobNotFound`.
+pub fn get(conn: &Connection, id: &str) -> Result<JobSchedule> {
+    conn.query_row(
+        &format!("SELECT {SCHEDULE_COLUMNS} FROM schedules WHERE id = ?1"),
+        params![id],
+        row_to_schedule,
+    )
+    .optional()?
+    .ok_or_else(|| InkOsError::JobNotFound.into())
+}
+
+fn row_to_schedule(row: &rusqlite::Row<'_>) -> rusqlite::Result<JobSchedule> {
+    let payload: String = row.get(2)?;
+    Ok(JobSchedule {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        payload: serde_json::from_str(&payload).unwrap_or_else(|_| json!({})),
+        cron_expr: row.get(3)?,
+        next_run: row.get(4)?,
+        enabled: row.get::<_, i64>(5)? != 0,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+    })
+}
+
+/// Define a new schedule and queue its first run.
+pub fn create(
+    conn: &Connection,
+    kind: &str,
+    payload: Value,
+    cron_expr: &str,
+) -> Result<JobSchedule> {
+    if !JOB_KINDS.contains(&kind) {
+        return Err(InkOsError::ValidationFailed(format!("unknown job kind '{kind}'")).into());
+    }
+    if !payload.is_object() {
+        return Err(InkOsError::ValidationFailed("payload must be a JSON object".into()).into());
+    }
+    parse_cron(cron_expr)?;
+    let id = Uuid::new_v4().to_string();
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    conn.execute(
+        "INSERT INTO schedules (id, kind, payload, cron_expr, next_run, enabled, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, NULL, 1, ?5, ?5)",
+        params![id, kind, payload.to_string(), cron_expr.trim(), now],
+    )?;
+    enqueue_due(conn)?;
+    get(conn, &id)
+}
+
+/// Remove a schedule and the job it has queued. The built-in nightly digest
+/// schedule can only be disabled.
+pub fn delete(conn: &Connection, id: &str) -> Result<()> {
+    get(conn, id)?;
+    if id == NIGHTLY_DIGEST_SCHEDULE {
+        return Err(InkOsError::ValidationFailed(
+            "the nightly digest schedule is built in; disable it instead".into(),
+        )
+        .into());
+    }
+    unqueue(conn, id)?;
+    conn.execute("DELETE FROM schedules WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Enable or disable a schedule. Disabling drops its queued job.
+pub fn set_enabled(conn: &Connection, id: &str, enabled: bool) -> Result<JobSchedule> {
+    get(conn, id)?;
+    unqueue(conn, id)?;
+    conn.execute(
+        "UPDATE schedules SET enabled = ?2, next_run = NULL, updated_at = ?3 WHERE id = ?1",
+        params![id, enabled, OffsetDateTime::now_utc().unix_timestamp()],
+    )?;
+    enqueue_due(conn)?;
+    get(conn, id)
+}
+
+/// Replace a schedule's cron expression, if the schedule exists, and requeue it.
+pub fn set_cron(conn: &Connection, id: &str, cron_expr: &str) -> Result<()> {
+    parse_cron(cron_expr)?;
+    unqueue(conn, id)?;
+    conn.execute(
+        "UPDATE schedules SET cron_expr = ?2, next_run = NULL, updated_at = ?3 WHERE id = ?1",
+        params![
+            id,
+            cron_expr.trim(),
+            OffsetDateTime::now_utc().unix_timestamp()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Drop every queued scheduled job so the next pass recomputes run times,
+/// e.g. after the digest timezone changed.
+pub fn reset(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM jobs WHERE schedule_id IS NOT NULL AND state = 'queued' AND run_at > ?1",
+        params![OffsetDateTime::now_utc().unix_timestamp()],
+    )?;
+    conn.execute("UPDATE schedules SET next_run = NULL", [])?;
+    Ok(())
+}
+
+/// Delete the not-yet-due job queued for a schedule.
+fn unqueue(conn: &Connection, id: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM jobs WHERE schedule_id = ?1 AND state = 'queued' AND run_at > ?2",
+        params![id, OffsetDateTime::now_utc().unix_timestamp()],
+    )?;
+    Ok(())
+}
+
+/// Make sure every enabled schedule has its next run queued.
+///
+/// A schedule whose queued job was cancelled gets it back; one whose job has
+/// fallen due moves on to the following occurrence. A schedule that fails to
+/// parse is logged and skipped.
+pub fn enqueue_due(conn: &Connection) -> Result<()> {
+    let tz = read_schedule(conn)?.tz()?;
+    let now = OffsetDateTime::now_utc();
+    for schedule in list(conn)?.into_iter().filter(|s| s.enabled) {
+        if let Err(err) = prime(conn, &schedule, now, tz) {
+            let _ = log_event(
+                conn,
+                "warn",
+                Some("JOB-206"),
+                "jobs.scheduler",
+                "Skipped recurring schedule",
+                Some("The schedule could not be queued; check its cron expression."),
+                Some(json!({ "schedule_id": schedule.id, "error": format!("{err:#}") })),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn prime(conn: &Connection, schedule: &JobSchedule, now: OffsetDateTime, tz: &Tz) -> Result<()> {
+    let next_run = match schedule.next_run {
+        Some(next_run) if next_run > now.unix_timestamp() => {
+            OffsetDateTime::from_unix_timestamp(next_run)?
+        }
+        _ => next_occurrence(&parse_cron(&schedule.cron_expr)?, now, tz)
+            .ok_or_else(|| anyhow!("cron expression never fires again"))?,
+    };
+    let run_at = next_run.unix_timestamp();
+    if schedule.next_run != Some(run_at) {
+        conn.execute(
+            "UPDATE schedules SET next_run = ?2 WHERE id = ?1",
+            params![schedule.id, run_at],
+        )?;
+    }
+
+    let queued: Option<String> = conn
+        .query_row(
+            "SELECT id FROM jobs WHERE schedule_id = ?1 AND state = 'queued' AND run_at = ?2 LIMIT 1",
+            params![schedule.id, run_at],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if queued.is_some() {
+        return Ok(());
+    }
+
+    let payload = resolve_payload(&schedule.payload, next_run, tz);
+    let job_id = persist_job_with_conn(conn, &schedule.kind, &payload, Some(run_at))?;
+    conn.execute(
+        "UPDATE jobs SET schedule_id = ?2 WHERE id = ?1",
+        params![job_id, schedule.id],
+    )?;
+    let _ = log_event(
+        conn,
+        "info",
+        Some("JOB-200"),
+        "jobs.scheduler",
+        "Queued scheduled job",
+        Some(&format!(
+            "Schedule '{}' ({}) queued its next run.",
+            schedule.id, schedule.cron_expr
+        )),
+        Some(json!({
+            "job_id": job_id,
+            "schedule_id": schedule.id,
+            "kind": schedule.kind,
+            "run_at": run_at,
+            "payload": payload,
+        })),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+    use time::macros::datetime;
+    use time_tz::timezones;
+
+    #[test]
+    fn cron_occurrences_follow_the_local_wall_clock() {
+        let tokyo = timezones::get_by_name("Asia/Tokyo").unwrap();
+        let nightly = parse_cron("0 2 * * *").unwrap();
+        let next = next_occurrence(&nightly, datetime!(2024-01-05 12:00 UTC), tokyo).unwrap();
+        assert_eq!(next, datetime!(2024-01-05 17:00 UTC));
+        assert!(parse_cron("every night").is_err());
+
+        let payload = resolve_payload(&json!({ "date": "yesterday" }), next, tokyo);
+        assert_eq!(payload["date"], "2024-01-05");
+    }
+
+    #[test]
+    fn schedules_keep_one_queued_job() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        enqueue_due(&conn).unwrap();
+        let nightly = get(&conn, NIGHTLY_DIGEST_SCHEDULE).unwrap();
+        assert_eq!(nightly.cron_expr, "0 2 * * *");
+        assert!(nightly.next_run.is_some());
+
+        let hourly = create(&conn, "maintenance.prune", json!({}), "0 * * * *").unwrap();
+        enqueue_due(&conn).unwrap();
+        let queued = |id: &str| -> i64 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM jobs WHERE schedule_id = ?1 AND state = 'queued'",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(queued(&hourly.id), 1);
+        assert_eq!(queued(NIGHTLY_DIGEST_SCHEDULE), 1);
+        assert!(create(&conn, "nope", json!({}), "0 * * * *").is_err());
+        let err = delete(&conn, NIGHTLY_DIGEST_SCHEDULE).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::ValidationFailed(_))
+        ));
+        assert_eq!(queued(NIGHTLY_DIGEST_SCHEDULE), 1);
+
+        let disabled = set_enabled(&conn, &hourly.id, false).unwrap();
+        assert!(!disabled.enabled && disabled.next_run.is_none());
+        assert_eq!(queued(&hourly.id), 0);
+        delete(&conn, &hourly.id).unwrap();
+        let err = get(&conn, &hourly.id).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<InkOsError>(),
+            Some(InkOsError::JobNotFound)
+        ));
+    }
+}
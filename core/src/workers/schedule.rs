@@ -0,0 +1,381 @@
+//! User configurable digest schedule stored in `app_settings`.
+//!
+//! The nightly digest runs at a local wall-clock time in an IANA timezone so
+//! that each logbook entry covers the user's actual day rather than a UTC one.
+
+use anyhow::Result;
+use r2d2_sqlite::rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+use time::{Date, OffsetDateTime, Time};
+use time_tz::{timezones, OffsetDateTimeExt, OffsetResult, PrimitiveDateTimeExt, Tz};
+
+use crate::errors::InkOsError;
+use crate::logging::log_event;
+use crate::settings;
+
+const HOUR_KEY: &str = "digest.hour";
+const MINUTE_KEY: &str = "digest.minute";
+const TIMEZONE_KEY: &str = "digest.timezone";
+const TOKEN_BUDGET_KEY: &str = "digest.token_budget";
+const EXCERPT_COUNT_KEY: &str = "digest.excerpt_count";
+const EXCERPT_CHARS_KEY: &str = "digest.excerpt_chars";
+/// Note excerpts handed to the daily digest prompt when unset.
+pub const DEFAULT_EXCERPT_COUNT: usize = 5;
+/// Characters kept from each note body when unset.
+pub const DEFAULT_EXCERPT_CHARS: usize = 240;
+/// Accepted range for `digest.excerpt_count`.
+pub const EXCERPT_COUNT_RANGE: std::ops::RangeInclusive<usize> = 1..=50;
+/// Accepted range for `digest.excerpt_chars`.
+pub const EXCERPT_CHARS_RANGE: std::ops::RangeInclusive<usize> = 40..=4_000;
+const PAUSED_KEY: &str = "jobs.paused";
+const WORKERS_KEY: &str = "jobs.workers";
+const JOB_TIMEOUT_KEY: &str = "jobs.timeout_secs";
+/// Running jobs older than this are presumed stuck when no timeout is set.
+pub const DEFAULT_JOB_TIMEOUT_SECS: i64 = 30 * 60;
+/// Jobs dispatched in parallel when `jobs.workers` is unset.
+pub const DEFAULT_WORKERS: usize = 2;
+/// Upper bound for `jobs.workers`; each worker holds a pooled connection.
+pub const MAX_WORKERS: usize = 8;
+
+/// Wall-clock time and timezone at which the nightly digest runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSchedule {
+    pub hour: u8,
+    pub minute: u8,
+    pub timezone: String,
+    /// Daily token total above which the digest adds a `usage` timeline event.
+    #[serde(default)]
+    pub token_budget: Option<u64>,
+    /// Newest notes of the day quoted in the digest prompt.
+    #[serde(default = "default_excerpt_count")]
+    pub excerpt_count: usize,
+    /// Characters kept from each quoted note body, cut at a word boundary.
+    #[serde(default = "default_excerpt_chars")]
+    pub excerpt_chars: usize,
+}
+
+fn default_excerpt_count() -> usize {
+    DEFAULT_EXCERPT_COUNT
+}
+
+fn default_excerpt_chars() -> usize {
+    DEFAULT_EXCERPT_CHARS
+}
+
+impl Default for DigestSchedule {
+    fn default() -> Self {
+        Self {
+            hour: 2,
+            minute: 0,
+            timezone: "UTC".into(),
+            token_budget: None,
+            excerpt_count: DEFAULT_EXCERPT_COUNT,
+            excerpt_chars: DEFAULT_EXCERPT_CHARS,
+        }
+    }
+}
+
+impl DigestSchedule {
+    /// Resolve the configured IANA timezone.
+    pub fn tz(&self) -> Result<&'static Tz> {
+        timezones::get_by_name(&self.timezone).ok_or_else(|| {
+            InkOsError::InvalidField {
+                field: "timezone".into(),
+                reason: format!("unknown timezone '{}'", self.timezone),
+            }
+            .into()
+        })
+    }
+
+    /// Local time of day at which the digest runs.
+    pub fn time(&self) -> Result<Time> {
+        let (field, reason) = match (self.hour, self.minute) {
+            (0..=23, 0..=59) => return Ok(Time::from_hms(self.hour, self.minute, 0)?),
+            (24.., _) => ("hour", "must be between 0 and 23"),
+            _ => ("minute", "must be between 0 and 59"),
+        };
+        Err(InkOsError::InvalidField {
+            field: field.into(),
+            reason: reason.into(),
+        }
+        .into())
+    }
+
+    /// Ensure the hour, minute, timezone, and excerpt limits are all usable.
+    pub fn validate(&self) -> Result<()> {
+        self.time()?;
+        self.tz()?;
+        if !EXCERPT_COUNT_RANGE.contains(&self.excerpt_count) {
+            return Err(InkOsError::InvalidField {
+                field: "excerpt_count".into(),
+                reason: format!(
+                    "must be between {} and {}",
+                    EXCERPT_COUNT_RANGE.start(),
+                    EXCERPT_COUNT_RANGE.end()
+                ),
+            }
+            .into());
+        }
+        if !EXCERPT_CHARS_RANGE.contains(&self.excerpt_chars) {
+            return Err(InkOsError::InvalidField {
+                field: "excerpt_chars".into(),
+                reason: format!(
+                    "must be between {} and {}",
+                    EXCERPT_CHARS_RANGE.start(),
+                    EXCERPT_CHARS_RANGE.end()
+                ),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Today's date in the configured timezone.
+    pub fn today(&self) -> Result<Date> {
+        Ok(OffsetDateTime::now_utc().to_timezone(self.tz()?).date())
+    }
+
+    /// Unix timestamps bounding `date` as a local day: `[start, end)`.
+    ///
+    /// `end` is the next date's local midnight rather than `start + 24h`, so
+    /// DST transition days span 23 or 25 hours.
+    pub fn day_bounds(&self, date: Date) -> Result<(i64, i64)> {
+        let tz = self.tz()?;
+        let next = date.next_day().ok_or_else(|| InkOsError::InvalidField {
+            field: "date".into(),
+            reason: format!("{date} is the last representable date"),
+        })?;
+        let start = local_to_utc(date, Time::MIDNIGHT, tz);
+        let end = local_to_utc(next, Time::MIDNIGHT, tz);
+        Ok((start.unix_timestamp(), end.unix_timestamp()))
+    }
+}
+
+/// Convert a local wall-clock time to an absolute instant.
+///
+/// Ambiguous times (DST fall-back) resolve to the earlier instant; times that
+/// do not exist (DST spring-forward) are interpreted with the UTC offset in
+/// effect at that moment.
+pub fn local_to_utc(date: Date, time: Time, tz: &Tz) -> OffsetDateTime {
+    let local = date.with_time(time);
+    match local.assume_timezone(tz) {
+        OffsetResult::Some(value) | OffsetResult::Ambiguous(value, _) => value,
+        OffsetResult::None => local.assume_timezone_utc(tz),
+    }
+}
+
+/// Read the schedule from `app_settings`; unset values fall back to 02:00
+/// UTC. A stored value that does not parse or is out of range falls back to
+/// its default and is logged, so one bad setting cannot stop the digest.
+pub fn read_schedule(conn: &Connection) -> Result<DigestSchedule> {
+    let defaults = DigestSchedule::default();
+    let schedule = DigestSchedule {
+        hour: read_parsed(conn, HOUR_KEY, |hour: &u8| *hour <= 23)?.unwrap_or(defaults.hour),
+        minute: read_parsed(conn, MINUTE_KEY, |minute: &u8| *minute <= 59)?
+            .unwrap_or(defaults.minute),
+        timezone: read_parsed(conn, TIMEZONE_KEY, |name: &String| {
+            timezones::get_by_name(name).is_some()
+        })?
+        .unwrap_or(defaults.timezone),
+        token_budget: read_parsed(conn, TOKEN_BUDGET_KEY, |_: &u64| true)?,
+        excerpt_count: read_parsed(conn, EXCERPT_COUNT_KEY, |_: &usize| true)?
+            .unwrap_or(defaults.excerpt_count)
+            .clamp(*EXCERPT_COUNT_RANGE.start(), *EXCERPT_COUNT_RANGE.end()),
+        excerpt_chars: read_parsed(conn, EXCERPT_CHARS_KEY, |_: &usize| true)?
+            .unwrap_or(defaults.excerpt_chars)
+            .clamp(*EXCERPT_CHARS_RANGE.start(), *EXCERPT_CHARS_RANGE.end()),
+    };
+    schedule.validate()?;
+    Ok(schedule)
+}
+
+/// Persist a validated schedule to `app_settings`.
+pub fn write_schedule(conn: &Connection, schedule: &DigestSchedule) -> Result<()> {
+    schedule.validate()?;
+    for (key, value) in [
+        (HOUR_KEY, schedule.hour.to_string()),
+        (MINUTE_KEY, schedule.minute.to_string()),
+        (TIMEZONE_KEY, schedule.timezone.clone()),
+        (EXCERPT_COUNT_KEY, schedule.excerpt_count.to_string()),
+        (EXCERPT_CHARS_KEY, schedule.excerpt_chars.to_string()),
+    ] {
+        settings::upsert(conn, key, &value)?;
+    }
+    match schedule.token_budget {
+        Some(budget) => settings::upsert(conn, TOKEN_BUDGET_KEY, &budget.to_string()),
+        None => settings::remove(conn, TOKEN_BUDGET_KEY),
+    }
+}
+
+/// Whether background dispatch has been paused by the user.
+pub fn read_paused(conn: &Connection) -> Result<bool> {
+    Ok(settings::read(conn, PAUSED_KEY)?.is_some_and(|value| value == "true"))
+}
+
+/// Persist the paused flag so it survives a restart.
+pub fn write_paused(conn: &Connection, paused: bool) -> Result<()> {
+    settings::upsert(conn, PAUSED_KEY, &paused.to_string())
+}
+
+/// Number of due jobs the scheduler runs at once, clamped to `1..=MAX_WORKERS`.
+pub fn read_worker_count(conn: &Connection) -> Result<usize> {
+    Ok(settings::read(conn, WORKERS_KEY)?
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WORKERS)
+        .clamp(1, MAX_WORKERS))
+}
+
+/// Persist the worker count after validating it.
+pub fn write_worker_count(conn: &Connection, workers: usize) -> Result<()> {
+    if !(1..=MAX_WORKERS).contains(&workers) {
+        return Err(InkOsError::ValidationFailed(format!(
+            "worker count must be between 1 and {MAX_WORKERS}"
+        ))
+        .into());
+    }
+    settings::upsert(conn, WORKERS_KEY, &workers.to_string())
+}
+
+/// Seconds a job may stay `running` before it is reclaimed; at least one minute.
+pub fn read_job_timeout(conn: &Connection) -> Result<i64> {
+    Ok(settings::read(conn, JOB_TIMEOUT_KEY)?
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_JOB_TIMEOUT_SECS)
+        .max(60))
+}
+
+/// Parse the value stored under `key`. A value that does not parse or is
+/// rejected by `valid` is logged and treated as unset.
+fn read_parsed<T: FromStr>(
+    conn: &Connection,
+    key: &str,
+    valid: impl Fn(&T) -> bool,
+) -> Result<Option<T>> {
+    let Some(raw) = settings::read(conn, key)? else {
+        return Ok(None);
+    };
+    match raw.parse().ok().filter(|value| valid(value)) {
+        Some(value) => Ok(Some(value)),
+        None => {
+            let _ = log_event(
+                conn,
+                "warn",
+                Some("JOB-SCHEDULE-INVALID"),
+                "jobs.schedule",
+                "Ignored invalid digest schedule setting",
+                Some("The stored value was replaced by its default; save the schedule again to fix it."),
+                Some(json!({ "key": key, "value": raw })),
+            );
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Month;
+
+    #[test]
+    fn day_bounds_follow_the_configured_timezone() {
+        let schedule = DigestSchedule {
+            hour: 2,
+            minute: 0,
+            timezone: "Asia/Tokyo".into(),
+            token_budget: None,
+            ..DigestSchedule::default()
+        };
+        let date = Date::from_calendar_date(2024, Month::January, 5).unwrap();
+        let (start, end) = schedule.day_bounds(date).unwrap();
+        let utc_midnight = date.with_time(Time::MIDNIGHT).assume_utc().unix_timestamp();
+        assert_eq!(start, utc_midnight - 9 * 3600);
+        assert_eq!(end - start, 86_400);
+    }
+
+    #[test]
+    fn day_bounds_span_dst_transitions() {
+        let schedule = DigestSchedule {
+            hour: 2,
+            minute: 0,
+            timezone: "America/New_York".into(),
+            token_budget: None,
+            ..DigestSchedule::default()
+        };
+        let spring = Date::from_calendar_date(2024, Month::March, 10).unwrap();
+        let (start, end) = schedule.day_bounds(spring).unwrap();
+        let utc_midnight = spring
+            .with_time(Time::MIDNIGHT)
+            .assume_utc()
+            .unix_timestamp();
+        assert_eq!(start, utc_midnight + 5 * 3600);
+        assert_eq!(end - start, 23 * 3600);
+        let (next_start, _) = schedule.day_bounds(spring.next_day().unwrap()).unwrap();
+        assert_eq!(next_start, end);
+
+        let fall = Date::from_calendar_date(2024, Month::November, 3).unwrap();
+        let (start, end) = schedule.day_bounds(fall).unwrap();
+        assert_eq!(end - start, 25 * 3600);
+        let (_, previous_end) = schedule.day_bounds(fall.previous_day().unwrap()).unwrap();
+        assert_eq!(previous_end, start);
+    }
+
+    #[test]
+    fn rejects_unknown_timezones() {
+        let schedule = DigestSchedule {
+            hour: 2,
+            minute: 0,
+            timezone: "Mars/Olympus".into(),
+            token_budget: None,
+            ..DigestSchedule::default()
+        };
+        assert!(schedule.validate().is_err());
+    }
+
+    #[test]
+    fn invalid_stored_values_fall_back_to_defaults() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::apply_migrations(&conn).unwrap();
+        settings::upsert(&conn, HOUR_KEY, "5").unwrap();
+        settings::upsert(&conn, MINUTE_KEY, "half past").unwrap();
+        settings::upsert(&conn, TIMEZONE_KEY, "Mars/Olympus").unwrap();
+
+        let schedule = read_schedule(&conn).unwrap();
+        assert_eq!((schedule.hour, schedule.minute), (5, 0));
+        assert_eq!(schedule.timezone, "UTC");
+        let logged: Vec<String> = conn
+            .prepare("SELECT json_extract(data, '$.key') FROM event_log WHERE code = 'JOB-SCHEDULE-INVALID' ORDER BY json_extract(data, '$.key')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(logged, vec![MINUTE_KEY, TIMEZONE_KEY]);
+
+        settings::upsert(&conn, HOUR_KEY, "24").unwrap();
+        assert_eq!(read_schedule(&conn).unwrap().hour, 2);
+    }
+
+    #[test]
+    fn validation_names_the_invalid_field() {
+        let field_of = |schedule: DigestSchedule| match schedule
+            .validate()
+            .unwrap_err()
+            .downcast::<InkOsError>()
+        {
+            Ok(InkOsError::InvalidField { field, .. }) => field,
+            other => panic!("unexpected error: {other:?}"),
+        };
+        let minute = DigestSchedule {
+            minute: 60,
+            ..DigestSchedule::default()
+        };
+        assert_eq!(field_of(minute), "minute");
+        let timezone = DigestSchedule {
+            timezone: "Mars/Olympus".into(),
+            ..DigestSchedule::default()
+        };
+        assert_eq!(field_of(timezone), "timezone");
+    }
+}
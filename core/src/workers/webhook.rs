@@ -0,0 +1,300 @@
+//! Outbound webhook fired when a background job finishes.
+//!
+//! The target URL and the job states worth reporting are stored in
+//! `app_settings`. Delivery runs on the async runtime after the job has been
+//! recorded, so a slow or broken endpoint never affects the job itself;
+//! failures are only logged as `WEBHOOK-ERR`.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use log::error;
+use r2d2_sqlite::rusqlite::{params, Connection, OptionalExtension};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::async_runtime;
+use time::OffsetDateTime;
+
+use crate::db::DbPool;
+use crate::errors::InkOsError;
+use crate::logging::log_event;
+use crate::settings;
+
+const URL_KEY: &str = "webhook.url";
+const EVENTS_KEY: &str = "webhook.events";
+/// Job states a webhook can subscribe to; `retrying` covers failed attempts
+/// that were requeued.
+pub const WEBHOOK_EVENTS: [&str; 3] = ["succeeded", "failed", "retrying"];
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where job notifications are posted and which job states trigger them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// `None` disables the webhook.
+    pub url: Option<String>,
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+}
+
+fn default_events() -> Vec<String> {
+    vec!["failed".into()]
+}
+
+/// Body posted to the webhook.
+///
+/// `text` and `content` repeat a one-line summary so Slack and Discord
+/// incoming webhooks can display it without a relay.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobNotification {
+    pub job_id: String,
+    pub kind: String,
+    pub state: String,
+    pub entry_date: Option<String>,
+    pub error: Option<String>,
+    pub text: String,
+    pub content: String,
+}
+
+impl JobNotification {
+    fn new(
+        job_id: String,
+        kind: String,
+        state: String,
+        entry_date: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        let mut text = format!("InkOS job {kind} {state}");
+        if let Some(date) = &entry_date {
+            text.push_str(&format!(" for {date}"));
+        }
+        if let Some(error) = &error {
+            text.push_str(&format!(": {error}"));
+        }
+        Self {
+            job_id,
+            kind,
+            state,
+            entry_date,
+            error,
+            content: text.clone(),
+            text,
+        }
+    }
+}
+
+/// Read the webhook settings; no URL means the webhook is off.
+pub fn read_config(conn: &Connection) -> Result<WebhookConfig> {
+    let url = settings::read(conn, URL_KEY)?.filter(|url| !url.is_empty());
+    let events = settings::read(conn, EVENTS_KEY)?
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_else(default_events);
+    Ok(WebhookConfig { url, events })
+}
+
+/// Validate and persist the webhook settings.
+pub fn write_config(conn: &Connection, config: WebhookConfig) -> Result<WebhookConfig> {
+    let url = config
+        .url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+    if let Some(url) = &url {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|err| InkOsError::ValidationFailed(format!("invalid webhook url: {err}")))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(
+                InkOsError::ValidationFailed("webhook url must use http or https".into()).into(),
+            );
+        }
+    }
+    let mut events = config.events;
+    events.sort();
+    events.dedup();
+    if let Some(unknown) = events
+        .iter()
+        .find(|event| !WEBHOOK_EVENTS.contains(&event.as_str()))
+    {
+        return Err(InkOsError::ValidationFailed(format!(
+            "unknown webhook event '{unknown}'; expected one of {}",
+            WEBHOOK_EVENTS.join(", ")
+        ))
+        .into());
+    }
+    settings::upsert(conn, URL_KEY, url.as_deref().unwrap_or_default())?;
+    settings::upsert(conn, EVENTS_KEY, &serde_json::to_string(&events)?)?;
+    Ok(WebhookConfig { url, events })
+}
+
+/// Describe a finished job, or `None` while it is still running.
+fn job_notification(conn: &Connection, job_id: &str) -> Result<Option<JobNotification>> {
+    let (kind, state, payload, result): (String, String, String, Option<String>) = conn
+        .query_row(
+            "SELECT kind, state, payload, result FROM jobs WHERE id = ?1",
+            params![job_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow!("job {job_id} not found"))?;
+    let state = match state.as_str() {
+        "succeeded" | "failed" => state,
+        // A job back in the queue after running has had an attempt fail.
+        "queued" => "retrying".to_string(),
+        _ => return Ok(None),
+    };
+    let payload: Value = serde_json::from_str(&payload).unwrap_or_else(|_| json!({}));
+    let entry_date = payload
+        .get("date")
+        .or_else(|| payload.get("start"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let error = (state != "succeeded").then_some(result).flatten();
+    Ok(Some(JobNotification::new(
+        job_id.to_string(),
+        kind,
+        state,
+        entry_date,
+        error,
+    )))
+}
+
+/// Post `notification` to `url`, returning the response status.
+pub async fn deliver(client: &Client, url: &str, notification: &JobNotification) -> Result<u16> {
+    let response = client
+        .post(url)
+        .timeout(DELIVERY_TIMEOUT)
+        .json(notification)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(anyhow!("webhook responded with {status}"));
+    }
+    Ok(status.as_u16())
+}
+
+/// Post a sample failure to the configured URL so users can check it.
+pub async fn send_test(pool: DbPool, client: Client) -> Result<u16> {
+    let store = pool.clone();
+    let config = tokio::task::spawn_blocking(move || read_config(&*store.get()?)).await??;
+    let url = config.url.ok_or_else(|| {
+        anyhow::Error::from(InkOsError::ValidationFailed(
+            "no webhook url is configured".into(),
+        ))
+    })?;
+    let notification = JobNotification::new(
+        "test".into(),
+        "workspace.daily_digest".into(),
+        "failed".into(),
+        Some(OffsetDateTime::now_utc().date().to_string()),
+        Some("This is a test notification from InkOS.".into()),
+    );
+    let result = deliver(&client, &url, &notification).await;
+    if let Err(err) = &result {
+        log_delivery_error(&pool, "test", err);
+    }
+    result
+}
+
+/// Notify the webhook about a job that just finished, if it subscribes to
+/// the job's final state. Never fails; problems are logged.
+pub fn job_finished(pool: &DbPool, client: Client, job_id: &str) {
+    let prepared = (|| -> Result<Option<(String, JobNotification)>> {
+        let conn = pool.get()?;
+        let config = read_config(&conn)?;
+        let Some(url) = config.url else {
+            return Ok(None);
+        };
+        Ok(job_notification(&conn, job_id)?
+            .filter(|notification| config.events.contains(&notification.state))
+            .map(|notification| (url, notification)))
+    })();
+    let (url, notification) = match prepared {
+        Ok(Some(prepared)) => prepared,
+        Ok(None) => return,
+        Err(err) => {
+            error!("failed to prepare job webhook: {err:?}");
+            return;
+        }
+    };
+    let pool = pool.clone();
+    async_runtime::spawn(async move {
+        if let Err(err) = deliver(&client, &url, &notification).await {
+            log_delivery_error(&pool, &notification.job_id, &err);
+        }
+    });
+}
+
+fn log_delivery_error(pool: &DbPool, job_id: &str, err: &anyhow::Error) {
+    if let Ok(conn) = pool.get() {
+        let _ = log_event(
+            &conn,
+            "warn",
+            Some("WEBHOOK-ERR"),
+            "jobs.webhook",
+            "Failed to deliver job webhook",
+            Some("The job result is unaffected; check the webhook URL and that the endpoint is reachable."),
+            Some(json!({ "job_id": job_id, "error": format!("{err:#}") })),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::apply_migrations;
+
+    #[test]
+    fn finished_jobs_describe_their_outcome() {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&conn).unwrap();
+        assert!(write_config(
+            &conn,
+            WebhookConfig {
+                url: Some("ftp://example.com".into()),
+                events: vec!["failed".into()],
+            }
+        )
+        .is_err());
+        assert!(write_config(
+            &conn,
+            WebhookConfig {
+                url: Some("https://example.com/hook".into()),
+                events: vec!["exploded".into()],
+            }
+        )
+        .is_err());
+        let saved = write_config(
+            &conn,
+            WebhookConfig {
+                url: Some(" https://example.com/hook ".into()),
+                events: vec!["failed".into(), "retrying".into(), "failed".into()],
+            },
+        )
+        .unwrap();
+        assert_eq!(saved.url.as_deref(), Some("https://example.com/hook"));
+        assert_eq!(read_config(&conn).unwrap().events, ["failed", "retrying"]);
+
+        for (id, state, result) in [
+            ("done", "succeeded", Some("{}")),
+            ("broke", "failed", Some("provider unreachable")),
+            ("busy", "running", None),
+        ] {
+            conn.execute(
+                "INSERT INTO jobs (id, kind, state, payload, result, created_at, updated_at)
+                 VALUES (?1, 'workspace.daily_digest', ?2, '{\"date\":\"2024-01-05\"}', ?3, 0, 0)",
+                params![id, state, result],
+            )
+            .unwrap();
+        }
+        let failed = job_notification(&conn, "broke").unwrap().unwrap();
+        assert_eq!(failed.entry_date.as_deref(), Some("2024-01-05"));
+        assert_eq!(failed.error.as_deref(), Some("provider unreachable"));
+        assert!(failed.text.contains("failed for 2024-01-05"));
+        assert!(job_notification(&conn, "done")
+            .unwrap()
+            .unwrap()
+            .error
+            .is_none());
+        assert!(job_notification(&conn, "busy").unwrap().is_none());
+    }
+}
@@ -0,0 +1,20 @@
+PRAGMA foreign_keys = ON;
+
+ALTER TABLE notes ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_notes_deleted_at ON notes(deleted_at);
+
+-- Soft-deleted notes are kept out of the FTS index; restoring re-indexes them.
+DROP TRIGGER IF EXISTS notes_ad;
+CREATE TRIGGER notes_ad AFTER DELETE ON notes BEGIN
+  INSERT INTO fts_notes(fts_notes, rowid, title, body)
+    SELECT 'delete', old.rowid, old.title, old.body WHERE old.deleted_at IS NULL;
+END;
+
+DROP TRIGGER IF EXISTS notes_au;
+CREATE TRIGGER notes_au AFTER UPDATE ON notes BEGIN
+  INSERT INTO fts_notes(fts_notes, rowid, title, body)
+    SELECT 'delete', old.rowid, old.title, old.body WHERE old.deleted_at IS NULL;
+  INSERT INTO fts_notes(rowid, title, body)
+    SELECT new.rowid, new.title, new.body WHERE new.deleted_at IS NULL;
+END;
@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Whether a summary came from the AI ('ai') or the deterministic fallback ('fallback').
+ALTER TABLE summaries ADD COLUMN source TEXT;
+
+UPDATE summaries
+SET source = CASE WHEN model_id IS NULL THEN 'fallback' ELSE 'ai' END
+WHERE source IS NULL;
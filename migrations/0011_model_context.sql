@@ -0,0 +1,13 @@
+PRAGMA foreign_keys = ON;
+
+-- Explicit context window for a provider's default model, in tokens.
+ALTER TABLE ai_providers ADD COLUMN context_window INTEGER;
+
+-- Per-model context window overrides; consulted before provider metadata.
+CREATE TABLE IF NOT EXISTS model_context (
+  provider_id TEXT NOT NULL REFERENCES ai_providers(id) ON DELETE CASCADE,
+  model_id TEXT NOT NULL,
+  context_window INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL,
+  PRIMARY KEY (provider_id, model_id)
+);
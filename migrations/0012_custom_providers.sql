@@ -0,0 +1,4 @@
+PRAGMA foreign_keys = ON;
+
+-- Providers registered at runtime; `seed_defaults` never overwrites these.
+ALTER TABLE ai_providers ADD COLUMN is_custom INTEGER NOT NULL DEFAULT 0;
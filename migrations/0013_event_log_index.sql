@@ -0,0 +1,2 @@
+-- Keeps module-filtered event queries fast as the log grows.
+CREATE INDEX IF NOT EXISTS idx_event_log_module_ts ON event_log(module, ts);
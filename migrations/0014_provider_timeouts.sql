@@ -0,0 +1,4 @@
+PRAGMA foreign_keys = ON;
+
+-- Per-request HTTP timeout in seconds; NULL uses the orchestrator default.
+ALTER TABLE ai_providers ADD COLUMN request_timeout_secs INTEGER;
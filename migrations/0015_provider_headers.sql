@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- Extra HTTP headers sent with every request, as a JSON object. Values may
+-- reference environment variables as `${NAME}`.
+ALTER TABLE ai_providers ADD COLUMN headers_json TEXT NOT NULL DEFAULT '{}';
@@ -0,0 +1,15 @@
+PRAGMA foreign_keys = ON;
+
+-- One vector per note for semantic search, stored as little-endian f32s.
+-- `content_hash` lets the embedding job skip notes whose text is unchanged.
+CREATE TABLE IF NOT EXISTS note_embeddings (
+  note_id TEXT PRIMARY KEY REFERENCES notes(id) ON DELETE CASCADE,
+  provider_id TEXT NOT NULL,
+  model TEXT NOT NULL,
+  dims INTEGER NOT NULL,
+  vector BLOB NOT NULL,
+  content_hash TEXT NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_embeddings_model ON note_embeddings(provider_id, model);
@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- Creation time of the newest message a conversation summary accounts for.
+-- Incremental summaries resume after it; NULL rows force a full pass.
+ALTER TABLE summaries ADD COLUMN covered_through INTEGER;
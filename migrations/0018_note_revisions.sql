@@ -0,0 +1,12 @@
+PRAGMA foreign_keys = ON;
+
+-- Prior title/body of a note, captured before each edit or restore.
+CREATE TABLE IF NOT EXISTS note_revisions (
+  id TEXT PRIMARY KEY,
+  note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+  title TEXT NOT NULL,
+  body TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_note_revisions_note ON note_revisions(note_id, created_at);
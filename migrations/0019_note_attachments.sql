@@ -0,0 +1,15 @@
+PRAGMA foreign_keys = ON;
+
+-- Files attached to notes. The bytes live under `<workspace>/attachments`;
+-- `path` is relative to the workspace directory.
+CREATE TABLE IF NOT EXISTS attachments (
+  id TEXT PRIMARY KEY,
+  note_id TEXT NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+  filename TEXT NOT NULL,
+  mime TEXT NOT NULL,
+  path TEXT NOT NULL,
+  size_bytes INTEGER NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_note ON attachments(note_id, created_at);
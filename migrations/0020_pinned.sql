@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Pinned rows sort ahead of everything else in their list.
+ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE notes ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_pinned ON conversations(pinned, updated_at);
+CREATE INDEX IF NOT EXISTS idx_notes_pinned ON notes(pinned, updated_at);
@@ -0,0 +1,20 @@
+PRAGMA foreign_keys = ON;
+
+-- FTS for conversation messages
+CREATE VIRTUAL TABLE IF NOT EXISTS fts_messages USING fts5(
+  body, content='messages', content_rowid='rowid'
+);
+
+CREATE TRIGGER IF NOT EXISTS messages_ai AFTER INSERT ON messages BEGIN
+  INSERT INTO fts_messages(rowid, body) VALUES (new.rowid, new.body);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_ad AFTER DELETE ON messages BEGIN
+  INSERT INTO fts_messages(fts_messages, rowid, body) VALUES('delete', old.rowid, old.body);
+END;
+CREATE TRIGGER IF NOT EXISTS messages_au AFTER UPDATE OF body ON messages BEGIN
+  INSERT INTO fts_messages(fts_messages, rowid, body) VALUES('delete', old.rowid, old.body);
+  INSERT INTO fts_messages(rowid, body) VALUES (new.rowid, new.body);
+END;
+
+-- Index messages stored before this migration.
+INSERT INTO fts_messages(fts_messages) VALUES('rebuild');
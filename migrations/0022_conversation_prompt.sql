@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- Per-conversation defaults applied by ai_chat when a request leaves them out.
+ALTER TABLE conversations ADD COLUMN system_prompt TEXT;
+ALTER TABLE conversations ADD COLUMN default_temperature REAL;
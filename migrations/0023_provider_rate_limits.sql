@@ -0,0 +1,4 @@
+PRAGMA foreign_keys = ON;
+
+-- Outbound requests per minute allowed for a provider; NULL is unlimited.
+ALTER TABLE ai_providers ADD COLUMN rate_limit_rpm INTEGER;
@@ -0,0 +1,13 @@
+PRAGMA foreign_keys = ON;
+
+-- Stored replies for chat requests that opt into caching.
+CREATE TABLE IF NOT EXISTS chat_cache (
+  key TEXT PRIMARY KEY,
+  provider_id TEXT NOT NULL,
+  model TEXT NOT NULL,
+  response_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_cache_expires ON chat_cache(expires_at);
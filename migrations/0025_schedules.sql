@@ -0,0 +1,38 @@
+PRAGMA foreign_keys = ON;
+
+-- Recurring job definitions. `cron_expr` is evaluated in the digest timezone;
+-- `next_run` is the run time of the job currently queued for the schedule.
+CREATE TABLE IF NOT EXISTS schedules (
+  id TEXT PRIMARY KEY,
+  kind TEXT NOT NULL,
+  payload TEXT NOT NULL DEFAULT '{}',
+  cron_expr TEXT NOT NULL,
+  next_run INTEGER,
+  enabled INTEGER NOT NULL DEFAULT 1,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
+
+-- The nightly digest, at the configured digest time, summarising the day before.
+INSERT OR IGNORE INTO schedules (id, kind, payload, cron_expr, next_run, enabled, created_at, updated_at)
+VALUES (
+  'nightly-digest',
+  'workspace.daily_digest',
+  '{"date":"yesterday"}',
+  COALESCE((SELECT value FROM app_settings WHERE key = 'digest.minute'), '0') || ' ' ||
+    COALESCE((SELECT value FROM app_settings WHERE key = 'digest.hour'), '2') || ' * * *',
+  NULL,
+  1,
+  CAST(strftime('%s', 'now') AS INTEGER),
+  CAST(strftime('%s', 'now') AS INTEGER)
+);
+
+-- Jobs queued by a schedule point back at it.
+ALTER TABLE jobs ADD COLUMN schedule_id TEXT;
+CREATE INDEX IF NOT EXISTS idx_jobs_schedule ON jobs(schedule_id, state);
+
+-- Adopt the nightly digest already queued by earlier versions.
+UPDATE jobs SET schedule_id = 'nightly-digest'
+WHERE kind = 'workspace.daily_digest'
+  AND state = 'queued'
+  AND run_at > CAST(strftime('%s', 'now') AS INTEGER);
@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- When the current attempt moved the job to `running`; used to reclaim jobs
+-- left running by a hung provider or a crashed process.
+ALTER TABLE jobs ADD COLUMN started_at INTEGER;
@@ -0,0 +1,6 @@
+PRAGMA foreign_keys = ON;
+
+-- Sampling used when a chat request leaves temperature or top_p unset; NULL
+-- falls back to the orchestrator default (temperature 0.2, top_p omitted).
+ALTER TABLE ai_providers ADD COLUMN default_temperature REAL;
+ALTER TABLE ai_providers ADD COLUMN default_top_p REAL;
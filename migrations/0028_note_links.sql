@@ -0,0 +1,8 @@
+PRAGMA foreign_keys = ON;
+
+-- Note-to-note links share the generic links table; one row per
+-- (source, target, relation) and fast lookups in both directions.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_links_note_pair
+    ON links(src_id, dst_id, rel)
+    WHERE src_type = 'note' AND dst_type = 'note';
+CREATE INDEX IF NOT EXISTS idx_links_dst ON links(dst_id, dst_type);
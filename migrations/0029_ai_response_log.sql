@@ -0,0 +1,17 @@
+PRAGMA foreign_keys = ON;
+
+-- Redacted provider replies kept for the debugger while `ai.capture_raw` is
+-- on. Rows expire after a day and are pruned on the next write.
+CREATE TABLE IF NOT EXISTS ai_response_log (
+  id TEXT PRIMARY KEY,
+  request_id TEXT NOT NULL,
+  provider_id TEXT NOT NULL,
+  model TEXT NOT NULL,
+  outcome TEXT NOT NULL,
+  raw_json TEXT NOT NULL,
+  created_at INTEGER NOT NULL,
+  expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_response_log_request ON ai_response_log(request_id);
+CREATE INDEX IF NOT EXISTS idx_ai_response_log_expires ON ai_response_log(expires_at);
@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- Hand-written addendum for a day. The daily digest only rewrites `summary`,
+-- so anything here survives every re-run.
+ALTER TABLE logbook_entries ADD COLUMN manual_notes TEXT;
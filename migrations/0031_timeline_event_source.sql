@@ -0,0 +1,5 @@
+PRAGMA foreign_keys = ON;
+
+-- `derived` rows are rebuilt by every digest run; `manual` rows are added by
+-- the user and never cleared by a rebuild.
+ALTER TABLE timeline_events ADD COLUMN source TEXT NOT NULL DEFAULT 'derived';
@@ -0,0 +1,24 @@
+PRAGMA foreign_keys = ON;
+
+-- FTS over each day's AI summary and hand-written notes.
+CREATE VIRTUAL TABLE IF NOT EXISTS fts_logbook USING fts5(
+  summary, manual_notes, content='logbook_entries', content_rowid='rowid'
+);
+
+CREATE TRIGGER IF NOT EXISTS logbook_entries_ai AFTER INSERT ON logbook_entries BEGIN
+  INSERT INTO fts_logbook(rowid, summary, manual_notes)
+    VALUES (new.rowid, new.summary, new.manual_notes);
+END;
+CREATE TRIGGER IF NOT EXISTS logbook_entries_ad AFTER DELETE ON logbook_entries BEGIN
+  INSERT INTO fts_logbook(fts_logbook, rowid, summary, manual_notes)
+    VALUES('delete', old.rowid, old.summary, old.manual_notes);
+END;
+CREATE TRIGGER IF NOT EXISTS logbook_entries_au AFTER UPDATE OF summary, manual_notes ON logbook_entries BEGIN
+  INSERT INTO fts_logbook(fts_logbook, rowid, summary, manual_notes)
+    VALUES('delete', old.rowid, old.summary, old.manual_notes);
+  INSERT INTO fts_logbook(rowid, summary, manual_notes)
+    VALUES (new.rowid, new.summary, new.manual_notes);
+END;
+
+-- Index entries written before this migration.
+INSERT INTO fts_logbook(fts_logbook) VALUES('rebuild');
@@ -0,0 +1,6 @@
+PRAGMA foreign_keys = ON;
+
+-- Id of the newest message a conversation summary accounts for. Messages
+-- can share a `created_at` second, so incremental summaries resume after
+-- this message rather than `covered_through`; NULL rows force a full pass.
+ALTER TABLE summaries ADD COLUMN covered_message_id TEXT;
@@ -1,13 +1,14 @@
 use directories::ProjectDirs;
-use inkos_core::agents::AiOrchestrator;
+use inkos_core::agents::{AiOrchestrator, OrchestratorConfig};
 use inkos_core::api::v1::{self, ApiState};
-use inkos_core::db::init_db;
-use inkos_core::model_manager::ModelManager;
+use inkos_core::db::{init_db, DbConfig};
+use inkos_core::logging::{self, EVENT_LOG_NEW_EVENT};
+use inkos_core::model_manager::{ModelManager, PULL_PROGRESS_EVENT};
 use inkos_core::summarizer::Summarizer;
-use inkos_core::workers::JobScheduler;
+use inkos_core::workers::{JobScheduler, DIGEST_PROGRESS_EVENT, TIMELINE_REFRESH_EVENT};
 use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 fn workspace_dir() -> PathBuf {
     if let Some(proj) = ProjectDirs::from("com", "InkOS", "InkOS") {
@@ -20,12 +21,34 @@ fn workspace_dir() -> PathBuf {
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
-            let db = init_db(workspace_dir()).expect("failed to init db");
+            let handle = app.handle().clone();
+            logging::set_event_sink(Arc::new(move |event| {
+                let _ = handle.emit(EVENT_LOG_NEW_EVENT, event);
+            }));
+            let db = init_db(workspace_dir(), DbConfig::default()).expect("failed to init db");
             let orchestrator =
-                Arc::new(AiOrchestrator::new().expect("failed to initialise AI orchestrator"));
+                Arc::new(AiOrchestrator::new(OrchestratorConfig::default()).expect("failed to initialise AI orchestrator"));
             let model_manager = ModelManager::new(db.clone(), Arc::clone(&orchestrator));
+            let handle = app.handle().clone();
+            model_manager.set_pull_emitter(Arc::new(move |progress| {
+                let _ = handle.emit(PULL_PROGRESS_EVENT, progress);
+            }));
+            let discovery = Arc::clone(&model_manager);
+            tauri::async_runtime::spawn(async move {
+                if let Err(err) = discovery.select_local_provider_if_unconfigured().await {
+                    eprintln!("failed to discover local AI providers: {err}");
+                }
+            });
             let summarizer = Summarizer::new(db.clone(), Arc::clone(&model_manager));
             let scheduler = JobScheduler::new(db.clone(), Arc::clone(&summarizer));
+            let handle = app.handle().clone();
+            scheduler.set_progress_emitter(Arc::new(move |progress| {
+                let _ = handle.emit(DIGEST_PROGRESS_EVENT, progress);
+            }));
+            let handle = app.handle().clone();
+            scheduler.set_timeline_emitter(Arc::new(move |refresh| {
+                let _ = handle.emit(TIMELINE_REFRESH_EVENT, refresh);
+            }));
             if let Err(err) = scheduler.ensure_nightly_digest_schedule_blocking() {
                 eprintln!("failed to prime nightly digest schedule: {err}");
             }
@@ -40,25 +63,109 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             v1::ping,
             v1::db_status,
+            v1::db_pool_stats,
+            v1::workspace_stats,
             v1::create_note,
+            v1::get_note,
             v1::list_notes,
+            v1::list_note_revisions,
+            v1::restore_note_revision,
+            v1::search_notes_semantic,
+            v1::update_note,
+            v1::set_note_tags,
+            v1::list_tags,
+            v1::delete_note,
+            v1::restore_note,
+            v1::set_note_pinned,
+            v1::add_note_attachment,
+            v1::list_note_attachments,
+            v1::delete_note_attachment,
+            v1::link_notes,
+            v1::list_note_links,
+            v1::unlink_notes,
+            v1::import_notes,
+            v1::export_notes,
             v1::list_logbook_entries,
+            v1::update_logbook_note,
+            v1::search_logbook,
+            v1::list_rollup_entries,
             v1::list_timeline_events,
+            v1::add_timeline_event,
+            v1::delete_timeline_event,
             v1::list_ai_events,
+            v1::list_events,
+            v1::set_log_level,
+            v1::set_raw_capture,
+            v1::set_ollama_auto_pull,
+            v1::get_ai_raw_response,
+            v1::backup_db,
+            v1::restore_db,
+            v1::rekey_db,
+            v1::list_jobs,
+            v1::get_job,
+            v1::cancel_job,
+            v1::requeue_job,
+            v1::scheduler_pause,
+            v1::scheduler_resume,
+            v1::scheduler_status,
+            v1::scheduler_set_workers,
             v1::run_daily_digest,
+            v1::get_digest_schedule,
+            v1::update_digest_schedule,
+            v1::list_schedules,
+            v1::create_schedule,
+            v1::delete_schedule,
+            v1::toggle_schedule,
+            v1::get_webhook_config,
+            v1::update_webhook_config,
+            v1::test_webhook,
             v1::ai_list_providers,
             v1::ai_list_models,
+            v1::ai_set_model_context,
+            v1::ai_set_provider_timeout,
+            v1::ai_set_provider_rate_limit,
+            v1::ai_set_provider_sampling,
+            v1::ai_provider_capabilities,
+            v1::ai_set_provider_headers,
+            v1::ai_set_embedding_model,
+            v1::ai_breaker_states,
+            v1::ai_rate_limit_states,
+            v1::discover_local_providers,
+            v1::get_fallback_order,
+            v1::set_fallback_order,
+            v1::add_provider,
+            v1::update_provider,
+            v1::remove_provider,
             v1::ai_get_settings,
             v1::ai_update_settings,
+            v1::get_summarizer_config,
+            v1::update_summarizer_config,
             v1::ai_chat,
+            v1::ai_chat_json,
             v1::chat_create_conversation,
             v1::chat_list_conversations,
+            v1::set_conversation_pinned,
             v1::chat_get_messages,
+            v1::search_messages,
             v1::chat_append_and_maybe_rollover,
+            v1::ai_conversation_usage,
+            v1::export_conversation,
+            v1::delete_conversation,
+            v1::fork_conversation,
+            v1::edit_message,
+            v1::regenerate_last,
+            v1::prune_conversation,
+            v1::rename_conversation,
+            v1::auto_title_conversation,
             v1::ai_rollover_chat,
+            v1::ai_preview_rollover,
             v1::ai_set_model,
+            v1::set_conversation_prompt,
             v1::ai_summarize,
-            v1::ai_get_summary
+            v1::ai_summarize_text,
+            v1::ai_summarize_range,
+            v1::ai_get_summary,
+            v1::ai_list_summaries
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,5 +1,5 @@
 import React, { useEffect, useState } from 'react'
-import { listAiEvents, type AiRuntimeEvent } from '../lib/api'
+import { describeError, listAiEvents, type AiRuntimeEvent } from '../lib/api'
 
 /** Props consumed by the AI debugger console modal. */
 interface ConsoleProps {
@@ -38,10 +38,10 @@ export default function Console({ open, onClose }: ConsoleProps) {
     setLoading(true)
     setError(null)
     try {
-      const data = await listAiEvents(40)
+      const { items: data } = await listAiEvents(40)
       setEvents(data)
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
     } finally {
       setLoading(false)
@@ -1,4 +1,5 @@
 import React, { useEffect, useMemo, useRef, useState } from 'react'
+import { describeError } from '../lib/api'
 
 /**
  * Command palette command descriptor consumed by the Palette component.
@@ -84,7 +85,7 @@ export default function Palette({ open, onClose, commands }: PaletteProps) {
       setError(null)
       onClose()
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
       setRunningCommand(null)
     }
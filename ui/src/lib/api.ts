@@ -1,4 +1,26 @@
 import { invoke } from '@tauri-apps/api/core'
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+
+/** Error object rejected by every IPC command. */
+export interface IpcError {
+  code: string
+  message: string
+  explain: string
+  /** Rejected input, for validation errors about a single field. */
+  field?: string
+}
+
+export function isIpcError(err: unknown): err is IpcError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err
+}
+
+/** Render any thrown value for display, appending the remediation hint for catalogued errors. */
+export function describeError(err: unknown): string {
+  if (isIpcError(err)) {
+    return err.code === 'GEN-1000' ? err.message : `${err.message} — ${err.explain}`
+  }
+  return err instanceof Error ? err.message : String(err)
+}
 
 /**
  * Thin wrapper around Tauri's `invoke` helper so TypeScript callers can work
@@ -16,14 +38,255 @@ export async function dbStatus(): Promise<any> {
   return invoke('db_status')
 }
 
+export interface DbPoolStats {
+  connections: number
+  idle: number
+  in_use: number
+  max_connections: number
+  min_idle: number | null
+  connection_timeout_ms: number
+}
+
+/** Report SQLite connection pool occupancy for diagnostics. */
+export async function dbPoolStats(): Promise<DbPoolStats> {
+  return invoke('db_pool_stats')
+}
+
+export interface CreatedNote {
+  id: string
+  title: string
+  created_at: number
+  updated_at: number
+}
+
+export interface WorkspaceStats {
+  notes_total: number
+  notes_today: number
+  conversations_total: number
+  open_conversations: number
+  summaries_total: number
+  jobs_pending: number
+  jobs_failed_24h: number
+  ai_calls_today: number
+  ai_failures_today: number
+}
+
+/** Fetch the dashboard counts in a single call. */
+export async function workspaceStats(): Promise<WorkspaceStats> {
+  return invoke('workspace_stats')
+}
+
 /** Create a note record with the supplied title/body. */
-export async function createNote(input: { title: string, body?: string }): Promise<{ id: string }> {
+export async function createNote(input: { title: string, body?: string }): Promise<CreatedNote> {
   return invoke('create_note', { input })
 }
 
-/** Fetch notes optionally filtered by a full-text search query. */
-export async function listNotes(q?: string): Promise<Array<{ id: string, title: string, created_at: number }>> {
-  return invoke('list_notes', { input: q ? { q } : undefined })
+/** Fetch one note with its body. */
+export async function getNote(id: string): Promise<NoteRecord> {
+  return invoke('get_note', { id })
+}
+
+export interface NoteSummary {
+  id: string
+  title: string
+  created_at: number
+  updated_at: number
+  deleted_at?: number | null
+  pinned: boolean
+  tags: string[]
+}
+
+/** Keyset cursor: the sort key and id of the last row on the previous page. */
+export interface Cursor<K = number> {
+  key: K
+  id: string
+}
+
+/** Sort key of lists that show pinned rows first. */
+export interface PinnedKey {
+  pinned: boolean
+  updated_at: number
+}
+
+/** One page of results; pass `next_cursor` back as `before` to continue. */
+export interface Page<T, K = number> {
+  items: T[]
+  next_cursor?: Cursor<K> | null
+}
+
+export interface ListNotesOptions {
+  q?: string
+  includeDeleted?: boolean
+  tags?: string[]
+  before?: Cursor<PinnedKey> | null
+  limit?: number
+}
+
+/** Fetch a page of notes optionally filtered by a full-text search query and tags (ANDed). */
+export async function listNotes(options: ListNotesOptions = {}): Promise<Page<NoteSummary, PinnedKey>> {
+  const { q, includeDeleted = false, tags = [], before, limit } = options
+  return invoke('list_notes', { input: { q, include_deleted: includeDeleted, tags, before, limit } })
+}
+
+export interface SemanticHit {
+  id: string
+  title: string
+  score: number
+}
+
+export interface SemanticSearchResult {
+  /** `fts` when no embedding model is available and full-text search was used. */
+  mode: 'semantic' | 'fts'
+  items: SemanticHit[]
+}
+
+/** Find the `k` notes closest in meaning to `query`. */
+export async function searchNotesSemantic(query: string, k?: number): Promise<SemanticSearchResult> {
+  return invoke('search_notes_semantic', { query, k })
+}
+
+/** Replace a note's tags; returns the normalised (trimmed, lowercased) names. Commas are rejected. */
+export async function setNoteTags(noteId: string, tags: string[]): Promise<string[]> {
+  return invoke('set_note_tags', { input: { note_id: noteId, tags } })
+}
+
+/** List tags with their note counts for the sidebar. */
+export async function listTags(): Promise<Array<{ name: string, note_count: number }>> {
+  return invoke('list_tags')
+}
+
+export interface NoteRecord {
+  id: string
+  title: string
+  body: string
+  created_at: number
+  updated_at: number
+  pinned: boolean
+}
+
+/**
+ * Edit a note. Pass `expected_updated_at` to reject the write with an
+ * `NTE-CONFLICT` error if the note changed since it was loaded.
+ */
+export async function updateNote(input: { id: string, title?: string, body?: string, expected_updated_at?: number }): Promise<NoteRecord> {
+  return invoke('update_note', { input })
+}
+
+/** Pin or unpin a note; pinned notes are listed first. */
+export async function setNotePinned(note_id: string, pinned: boolean): Promise<NoteRecord> {
+  return invoke('set_note_pinned', { input: { note_id, pinned } })
+}
+
+/** Soft-delete a note; it disappears from lists and search until restored. */
+export async function deleteNote(id: string): Promise<void> {
+  return invoke('delete_note', { id })
+}
+
+/** Restore a soft-deleted note. */
+export async function restoreNote(id: string): Promise<void> {
+  return invoke('restore_note', { id })
+}
+
+export interface NoteAttachment {
+  id: string
+  note_id: string
+  filename: string
+  mime: string
+  /** Absolute path of the stored file. */
+  path: string
+  size_bytes: number
+  created_at: number
+}
+
+/** Attach a file to a note; `data` is the base64-encoded content. */
+export async function addNoteAttachment(input: { note_id: string, filename: string, mime: string, data: string }): Promise<NoteAttachment> {
+  return invoke('add_note_attachment', { input })
+}
+
+/** List a note's attachments, oldest first. */
+export async function listNoteAttachments(noteId: string): Promise<NoteAttachment[]> {
+  return invoke('list_note_attachments', { noteId })
+}
+
+/** Delete an attachment and its stored file. */
+export async function deleteNoteAttachment(id: string): Promise<void> {
+  return invoke('delete_note_attachment', { id })
+}
+
+export interface NoteLink {
+  link_id: string
+  /** The note at the other end of the link. */
+  note_id: string
+  title: string
+  rel: string
+  direction: 'outgoing' | 'incoming'
+  created_at: number
+}
+
+/** Link one note to another; `rel` defaults to `related`. */
+export async function linkNotes(input: { src_id: string, dst_id: string, rel?: string }): Promise<NoteLink> {
+  return invoke('link_notes', { input })
+}
+
+/** A note's outgoing links followed by its backlinks. */
+export async function listNoteLinks(noteId: string): Promise<NoteLink[]> {
+  return invoke('list_note_links', { noteId })
+}
+
+/** Remove a link; without `rel`, every relation from `src_id` to `dst_id`. Returns how many were removed. */
+export async function unlinkNotes(input: { src_id: string, dst_id: string, rel?: string }): Promise<number> {
+  return invoke('unlink_notes', { input })
+}
+
+export interface ImportReport {
+  imported: number
+  note_ids: string[]
+  failed: { path: string, error: string }[]
+}
+
+export interface ImportProgress {
+  processed: number
+  total: number
+  imported: number
+}
+
+/** Import every `.md` file under a directory as a note. */
+export async function importNotes(dirPath: string): Promise<ImportReport> {
+  return invoke('import_notes', { dirPath })
+}
+
+/** Subscribe to per-file progress while `importNotes` runs. */
+export async function onImportProgress(handler: (progress: ImportProgress) => void): Promise<UnlistenFn> {
+  return listen<ImportProgress>('notes://import-progress', (event) => handler(event.payload))
+}
+
+export interface ExportReport {
+  path: string
+  bytes: number
+  notes: number
+}
+
+/** Write every live note as Markdown into a zip archive at `destPath`. */
+export async function exportNotes(destPath: string): Promise<ExportReport> {
+  return invoke('export_notes', { destPath })
+}
+
+export interface NoteRevision {
+  id: string
+  note_id: string
+  title: string
+  body: string
+  created_at: number
+}
+
+/** Previous versions of a note, newest first. */
+export async function listNoteRevisions(noteId: string): Promise<NoteRevision[]> {
+  return invoke('list_note_revisions', { noteId })
+}
+
+/** Make a previous version current again; the replaced text becomes a revision. */
+export async function restoreNoteRevision(noteId: string, revisionId: string): Promise<NoteRecord> {
+  return invoke('restore_note_revision', { input: { note_id: noteId, revision_id: revisionId } })
 }
 
 export interface AiProviderInfo {
@@ -37,6 +300,30 @@ export interface AiProviderInfo {
   capability_tags: string[]
   requires_api_key: boolean
   has_credentials: boolean
+  context_window?: number | null
+  is_custom: boolean
+  request_timeout_secs?: number | null
+  headers: Record<string, string>
+  rate_limit_rpm?: number | null
+  /** Used when a request sets no temperature; null means 0.2. */
+  default_temperature?: number | null
+  /** Used when a request sets no top_p; null leaves it out. */
+  default_top_p?: number | null
+}
+
+export interface CustomProviderInput {
+  id: string
+  /** `azure-openai` treats models as deployment names. */
+  kind: 'cloud' | 'local' | 'azure-openai'
+  display_name: string
+  description?: string | null
+  base_url: string
+  default_model?: string | null
+  models: string[]
+  /** Include `openai-compatible` or `ollama` to pick the wire format. */
+  capability_tags?: string[]
+  requires_api_key?: boolean
+  request_timeout_secs?: number | null
 }
 
 export interface AiSettingsSnapshot {
@@ -49,6 +336,9 @@ export interface AiSettingsView extends AiSettingsSnapshot {
   warn_ratio: number
   force_ratio: number
   summarizer_model?: string | null
+  rollover_tail: number
+  rollover_tail_auto: boolean
+  prune_after_rollover: boolean
 }
 
 export interface AiUpdateSettingsPayload {
@@ -59,6 +349,10 @@ export interface AiUpdateSettingsPayload {
   warn_ratio?: number | null
   force_ratio?: number | null
   summarizer_model?: string | null
+  /** Newest messages kept verbatim in rollover summaries (default 12). */
+  rollover_tail?: number | null
+  /** Fit the tail to the summariser's context budget instead. */
+  rollover_tail_auto?: boolean | null
 }
 
 export interface AiChatMessage {
@@ -66,11 +360,27 @@ export interface AiChatMessage {
   content: string
 }
 
+export type ReasoningEffort = 'low' | 'medium' | 'high'
+
 export interface AiChatCommand {
   messages: AiChatMessage[]
   temperature?: number
+  stop?: string[]
+  top_p?: number
+  max_tokens?: number
+  /** Extended thinking (Anthropic) or reasoning effort (OpenAI-compatible). */
+  reasoning_effort?: ReasoningEffort
   provider_id?: string
   model?: string
+  conversation_id?: string
+  /** Race fallback providers concurrently; may bill more than one cloud provider. */
+  race?: boolean
+  /** Note attachments whose images go with the last user message. */
+  attachment_ids?: string[]
+  /** Reuse the reply to an identical earlier request while it is cached. */
+  cache?: boolean
+  /** Fail with the chosen provider's error instead of trying other providers. */
+  no_fallback?: boolean
 }
 
 export interface AiUsageMetrics {
@@ -85,6 +395,11 @@ export interface AiChatResponse {
   content: string
   usage?: AiUsageMetrics
   raw: unknown
+  cached: boolean
+  /** Thinking returned apart from the answer, when reasoning was requested. */
+  reasoning?: string | null
+  /** Correlation id; pass it to `listAiEvents` to see every log line for this request. */
+  request_id?: string | null
 }
 
 export interface ConversationRecord {
@@ -99,6 +414,28 @@ export interface ConversationRecord {
   closed_at?: number | null
   quality_flags?: string | null
   total_tokens: number
+  /** Context window of the conversation's model, in tokens. */
+  context_limit: number
+  warn_threshold: number
+  force_threshold: number
+  pinned: boolean
+  system_prompt?: string | null
+  default_temperature?: number | null
+}
+
+export interface MessageSearchHit {
+  message_id: string
+  conversation_id: string
+  role: string
+  snippet: string
+  created_at: number
+}
+
+export interface ConversationMatches {
+  conversation_id: string
+  title?: string | null
+  match_count: number
+  messages: MessageSearchHit[]
 }
 
 export interface MessageRecord {
@@ -120,7 +457,13 @@ export interface SummaryRecord {
   token_est?: number | null
   model_id?: string | null
   created_at: number
+  /** True when a stored summary was served instead of a new one. */
   reused: boolean
+  source: 'ai' | 'fallback'
+  /** Conversation summaries: `created_at` of the newest message covered. */
+  covered_through?: number | null
+  /** Conversation summaries: id of the newest message covered. */
+  covered_message_id?: string | null
 }
 
 export interface AppendResult {
@@ -130,6 +473,20 @@ export interface AppendResult {
   new_conversation?: ConversationRecord | null
   summary?: SummaryRecord | null
   total_tokens: number
+  context_limit: number
+  warn_threshold: number
+  force_threshold: number
+}
+
+export interface ConversationUsage {
+  conversation_id: string
+  prompt_tokens: number
+  completion_tokens: number
+  total_tokens: number
+  recorded_calls: number
+  estimated_calls: number
+  estimated_cost_usd: number
+  unpriced_tokens: number
 }
 
 export interface RolloverOutcome {
@@ -158,19 +515,80 @@ export async function aiUpdateSettings(payload: AiUpdateSettingsPayload): Promis
   return invoke('ai_update_settings', { input: payload })
 }
 
+export interface SummarizerConfig {
+  warn_ratio: number
+  force_ratio: number
+  summarizer_model: string | null
+  max_rollovers_per_window: number
+  rollover_tail: number
+  rollover_tail_auto: boolean
+}
+
+export interface SummarizerConfigUpdate {
+  warn_ratio?: number
+  force_ratio?: number
+  /** Empty string clears the override. */
+  summarizer_model?: string
+  rollover_tail?: number
+  rollover_tail_auto?: boolean
+  /** Trim rolled-over threads to their tail once the summary is linked. */
+  prune_after_rollover?: boolean
+}
+
+/** Read the rollover thresholds and summariser model. */
+export async function getSummarizerConfig(): Promise<SummarizerConfig> {
+  return invoke('get_summarizer_config')
+}
+
+/** Update summariser settings without touching the active provider. */
+export async function updateSummarizerConfig(input: SummarizerConfigUpdate): Promise<SummarizerConfig> {
+  return invoke('update_summarizer_config', { input })
+}
+
 /** Execute a chat completion against the selected runtime. */
 export async function aiChat(payload: AiChatCommand): Promise<AiChatResponse> {
   return invoke('ai_chat', { input: payload })
 }
 
+export interface AiChatJsonCommand {
+  messages: AiChatMessage[]
+  temperature?: number
+  stop?: string[]
+  top_p?: number
+  max_tokens?: number
+  reasoning_effort?: ReasoningEffort
+  provider_id?: string
+  model?: string
+  /** Optional JSON Schema the reply should match. */
+  schema?: unknown
+}
+
+/** Ask for strict JSON; rejects with `AI-JSON-INVALID` when the reply does not parse. */
+export async function aiChatJson<T = unknown>(payload: AiChatJsonCommand): Promise<T> {
+  return invoke('ai_chat_json', { input: payload })
+}
+
 /** Create a new chat conversation row. */
-export async function chatCreateConversation(payload: { title?: string | null, provider_id?: string | null, model_id?: string | null }): Promise<ConversationRecord> {
+export async function chatCreateConversation(payload: { title?: string | null, provider_id?: string | null, model_id?: string | null, system_prompt?: string | null, default_temperature?: number | null }): Promise<ConversationRecord> {
   return invoke('chat_create_conversation', { input: payload })
 }
 
-/** List conversations ordered by most recent activity. */
-export async function chatListConversations(limit?: number): Promise<ConversationRecord[]> {
-  return invoke('chat_list_conversations', { limit })
+/** List a page of conversations, pinned first, then by most recent activity. Closed threads are hidden unless requested. */
+export async function chatListConversations(limit?: number, before?: Cursor<PinnedKey> | null, includeClosed = false, pinnedOnly = false): Promise<Page<ConversationRecord, PinnedKey>> {
+  return invoke('chat_list_conversations', { limit, before, includeClosed, pinnedOnly })
+}
+
+/** Pin or unpin a conversation. */
+export async function setConversationPinned(conversation_id: string, pinned: boolean): Promise<ConversationRecord> {
+  return invoke('set_conversation_pinned', { input: { conversation_id, pinned } })
+}
+
+/**
+ * Delete a conversation (or close it with `soft`). Threads in a rollover chain
+ * are only removed together, by passing `chain`.
+ */
+export async function deleteConversation(conversation_id: string, options: { soft?: boolean, chain?: boolean } = {}): Promise<string[]> {
+  return invoke('delete_conversation', { input: { conversation_id, ...options } })
 }
 
 /** Fetch messages for a conversation. */
@@ -178,9 +596,195 @@ export async function chatGetMessages(conversation_id: string, limit?: number):
   return invoke('chat_get_messages', { input: { conversation_id, limit } })
 }
 
+/** Full-text search over message bodies, grouped by conversation (best match first). */
+export async function searchMessages(query: string, limit?: number): Promise<ConversationMatches[]> {
+  return invoke('search_messages', { query, limit })
+}
+
 /** Append a message and trigger rollover checks. */
-export async function chatAppendAndMaybeRollover(conversation_id: string, content: string, role?: string): Promise<AppendResult> {
-  return invoke('chat_append_and_maybe_rollover', { input: { conversation_id, content, role } })
+export async function chatAppendAndMaybeRollover(conversation_id: string, content: string, role?: string, usage?: AiUsageMetrics): Promise<AppendResult> {
+  return invoke('chat_append_and_maybe_rollover', { input: { conversation_id, content, role, usage } })
+}
+
+/** Aggregate token usage and estimated cost for a conversation. */
+export async function aiConversationUsage(conversation_id: string): Promise<ConversationUsage> {
+  return invoke('ai_conversation_usage', { input: { conversation_id } })
+}
+
+/** Register a self-hosted or third-party provider. */
+export async function addProvider(input: CustomProviderInput): Promise<AiProviderInfo> {
+  return invoke('add_provider', { input })
+}
+
+/** Replace the definition of a custom provider. */
+export async function updateProvider(input: CustomProviderInput): Promise<AiProviderInfo> {
+  return invoke('update_provider', { input })
+}
+
+/** Remove a custom provider and its stored credentials. */
+export async function removeProvider(providerId: string): Promise<void> {
+  return invoke('remove_provider', { providerId })
+}
+
+export interface BreakerStatus {
+  provider_id: string
+  state: 'closed' | 'open' | 'half_open'
+  recent_failures: number
+  retry_in_secs?: number | null
+}
+
+/** Providers currently skipped (or being probed) after repeated failures. */
+export async function aiBreakerStates(): Promise<BreakerStatus[]> {
+  return invoke('ai_breaker_states')
+}
+
+export interface RateLimitStatus {
+  provider_id: string
+  rate_limit_rpm: number
+  recent_calls: number
+  queued: number
+}
+
+/** Calls made in the last minute and calls waiting, per rate-limited provider. */
+export async function aiRateLimitStates(): Promise<RateLimitStatus[]> {
+  return invoke('ai_rate_limit_states')
+}
+
+export interface LocalRuntime {
+  provider_id: string
+  base_url: string
+  reachable: boolean
+  models: string[]
+}
+
+/** Probe the local Ollama and LM Studio endpoints; cached for a minute unless `refresh` is set. */
+export async function discoverLocalProviders(refresh = false): Promise<LocalRuntime[]> {
+  return invoke('discover_local_providers', { refresh })
+}
+
+export interface FallbackOrder {
+  order: string[]
+  excluded: string[]
+}
+
+/** Read the provider ids tried first on fallback and those never used. */
+export async function getFallbackOrder(): Promise<FallbackOrder> {
+  return invoke('get_fallback_order')
+}
+
+/** Replace the fallback ordering; ids must be registered providers. */
+export async function setFallbackOrder(input: FallbackOrder): Promise<FallbackOrder> {
+  return invoke('set_fallback_order', { input })
+}
+
+/** Store (or clear with `null`) a per-model context window override. */
+export async function aiSetModelContext(
+  provider_id: string,
+  model_id: string,
+  context_window: number | null,
+): Promise<void> {
+  return invoke('ai_set_model_context', { input: { provider_id, model_id, context_window } })
+}
+
+/** Store (or clear with `null`) the HTTP request timeout for a provider. */
+export async function aiSetProviderTimeout(
+  provider_id: string,
+  timeout_secs: number | null,
+): Promise<AiProviderInfo> {
+  return invoke('ai_set_provider_timeout', { input: { provider_id, timeout_secs } })
+}
+
+/** Store (or clear with `null`) a provider's requests-per-minute limit. */
+export async function aiSetProviderRateLimit(
+  provider_id: string,
+  rate_limit_rpm: number | null,
+): Promise<AiProviderInfo> {
+  return invoke('ai_set_provider_rate_limit', { input: { provider_id, rate_limit_rpm } })
+}
+
+export interface ProviderCapabilities {
+  streaming: boolean
+  /** Native JSON response mode; otherwise JSON requests rely on an instruction. */
+  json_mode: boolean
+  vision: boolean
+  embeddings: boolean
+  context_window: number
+  requires_api_key: boolean
+}
+
+/** What a provider supports, derived from its tags and kind. */
+export async function aiProviderCapabilities(providerId: string): Promise<ProviderCapabilities> {
+  return invoke('ai_provider_capabilities', { providerId })
+}
+
+/** Store a provider's default temperature/top-p; `null` restores the bundled default. */
+export async function aiSetProviderSampling(
+  provider_id: string,
+  default_temperature: number | null,
+  default_top_p: number | null,
+): Promise<AiProviderInfo> {
+  return invoke('ai_set_provider_sampling', { input: { provider_id, default_temperature, default_top_p } })
+}
+
+/** Replace a provider's extra request headers. Values may use `${ENV_VAR}`. */
+export async function aiSetProviderHeaders(
+  provider_id: string,
+  headers: Record<string, string>,
+): Promise<AiProviderInfo> {
+  return invoke('ai_set_provider_headers', { input: { provider_id, headers } })
+}
+
+/** Choose the embedding provider/model; pass nulls to use the active provider's default. */
+export async function aiSetEmbeddingModel(
+  provider_id: string | null,
+  model: string | null,
+): Promise<void> {
+  return invoke('ai_set_embedding_model', { input: { provider_id, model } })
+}
+
+/** Rename a conversation. */
+export async function renameConversation(conversation_id: string, title: string): Promise<ConversationRecord> {
+  return invoke('rename_conversation', { input: { conversation_id, title } })
+}
+
+/** Ask the conversation's model for a short title and store it. */
+export async function autoTitleConversation(conversationId: string): Promise<ConversationRecord> {
+  return invoke('auto_title_conversation', { conversationId })
+}
+
+/**
+ * Branch a conversation at a message. The new thread keeps the provider/model
+ * and every message up to and including `up_to_message_id`.
+ */
+export async function forkConversation(conversation_id: string, up_to_message_id: string): Promise<ConversationRecord> {
+  return invoke('fork_conversation', { input: { conversation_id, up_to_message_id } })
+}
+
+/** Delete a rolled-over conversation's messages beyond the retained tail; returns how many were removed. */
+export async function pruneConversation(conversationId: string): Promise<number> {
+  return invoke('prune_conversation', { conversationId })
+}
+
+export interface HistoryUpdate {
+  conversation: ConversationRecord
+  messages: MessageRecord[]
+  rolled: boolean
+  new_conversation: ConversationRecord | null
+}
+
+/** Rewrite a message; every message after it is removed. */
+export async function editMessage(message_id: string, new_body: string): Promise<HistoryUpdate> {
+  return invoke('edit_message', { input: { message_id, new_body } })
+}
+
+/** Replace the last assistant reply with a newly generated one. */
+export async function regenerateLast(conversationId: string): Promise<HistoryUpdate> {
+  return invoke('regenerate_last', { conversationId })
+}
+
+/** Export a conversation, including its full rollover chain, as Markdown or JSON. */
+export async function exportConversation(conversation_id: string, format: 'markdown' | 'json'): Promise<string> {
+  return invoke('export_conversation', { input: { conversation_id, format } })
 }
 
 /** Force rollover for a conversation. */
@@ -188,6 +792,27 @@ export async function aiRolloverChat(conversation_id: string): Promise<RolloverO
   return invoke('ai_rollover_chat', { input: { conversation_id } })
 }
 
+export interface RolloverPreview {
+  conversation_id: string
+  /** Cached, so a rollover forced next reuses it. */
+  summary: SummaryRecord
+  excerpt_count: number
+  current_tokens: number
+  seed_tokens: number
+  /** Context freed by rolling over: current_tokens - seed_tokens. */
+  token_delta: number
+}
+
+/** Preview the summary and token savings of a rollover without performing it. */
+export async function aiPreviewRollover(conversation_id: string): Promise<RolloverPreview> {
+  return invoke('ai_preview_rollover', { input: { conversation_id } })
+}
+
+/** Replace (or clear with `null`) a conversation's system prompt and default temperature. */
+export async function setConversationPrompt(conversation_id: string, system_prompt: string | null, default_temperature: number | null): Promise<ConversationRecord> {
+  return invoke('set_conversation_prompt', { input: { conversation_id, system_prompt, default_temperature } })
+}
+
 /** Override the provider/model associated with a conversation. */
 export async function aiSetModel(conversation_id: string, provider_id?: string | null, model_id?: string | null): Promise<ConversationRecord> {
   return invoke('ai_set_model', { input: { conversation_id, provider_id, model_id } })
@@ -198,18 +823,46 @@ export async function aiSummarize(target_type: string, target_id: string): Promi
   return invoke('ai_summarize', { input: { target_type, target_id } })
 }
 
+/** Summarise pasted text; omit the target to cache under a hash of the content. */
+export async function aiSummarizeText(content: string, target_type?: string, target_id?: string): Promise<SummaryRecord> {
+  return invoke('ai_summarize_text', { input: { content, target_type, target_id } })
+}
+
+/** Summarise activity between two local dates (`YYYY-MM-DD`), inclusive. */
+export async function aiSummarizeRange(start_date: string, end_date: string): Promise<SummaryRecord> {
+  return invoke('ai_summarize_range', { input: { start_date, end_date } })
+}
+
 /** Retrieve an existing summary by id. */
 export async function aiGetSummary(summary_id: string): Promise<SummaryRecord | null> {
   return invoke('ai_get_summary', { input: { summary_id } })
 }
 
+/** List every version of a summary target, newest first. */
+export async function aiListSummaries(target_type: string, target_id: string): Promise<SummaryRecord[]> {
+  return invoke('ai_list_summaries', { input: { target_type, target_id } })
+}
+
 export interface LogbookEntry {
   id: string
   entry_date: string
   summary: string
+  /** Hand-written addendum kept across digest re-runs. */
+  manual_notes?: string | null
   created_at: number
 }
 
+export interface RollupEntry {
+  id: string
+  period: 'weekly' | 'monthly'
+  period_start: string
+  period_end: string
+  summary: string
+  entry_count: number
+  created_at: number
+  updated_at: number
+}
+
 export interface TimelineEvent {
   id: string
   entry_date: string
@@ -217,6 +870,8 @@ export interface TimelineEvent {
   kind: string
   title: string
   detail?: string | null
+  /** `manual` events are user-added and survive digest rebuilds. */
+  source: 'derived' | 'manual'
   created_at: number
 }
 
@@ -235,11 +890,36 @@ export interface JobRunResult<T = unknown> {
   kind: string
   state: string
   result: T
+  attempts: number
+  max_attempts: number
+}
+
+/** Return a page of logbook entries, newest date first. */
+export async function listLogbookEntries(limit?: number, before?: Cursor<string> | null): Promise<Page<LogbookEntry, string>> {
+  return invoke('list_logbook_entries', { limit, before })
 }
 
-/** Return logbook entries up to the provided limit. */
-export async function listLogbookEntries(limit?: number): Promise<LogbookEntry[]> {
-  return invoke('list_logbook_entries', { limit })
+export interface LogbookSearchHit {
+  id: string
+  entry_date: string
+  /** Excerpt with the matched terms wrapped in `**`. */
+  snippet: string
+  created_at: number
+}
+
+/** Search logbook summaries and manual notes, optionally within an inclusive date range. */
+export async function searchLogbook(query: string, fromDate?: string, toDate?: string, limit?: number): Promise<LogbookSearchHit[]> {
+  return invoke('search_logbook', { query, fromDate, toDate, limit })
+}
+
+/** Set or clear (blank text) the hand-written note on a day's logbook entry. */
+export async function updateLogbookNote(entry_date: string, text: string): Promise<LogbookEntry> {
+  return invoke('update_logbook_note', { input: { entry_date, text } })
+}
+
+/** Return a page of weekly/monthly rollup entries, newest first. */
+export async function listRollupEntries(period?: 'weekly' | 'monthly', limit?: number, before?: Cursor<string> | null): Promise<Page<RollupEntry, string>> {
+  return invoke('list_rollup_entries', { period, limit, before })
 }
 
 /** Fetch timeline events for a given ISO date string. */
@@ -247,12 +927,294 @@ export async function listTimelineEvents(date?: string): Promise<TimelineEvent[]
   return invoke('list_timeline_events', { date })
 }
 
-/** Load recent AI runtime events for the debugger console. */
-export async function listAiEvents(limit?: number): Promise<AiRuntimeEvent[]> {
-  return invoke('list_ai_events', { limit })
+/** Add a user event to a day's timeline; `event_time` (unix seconds) defaults to now. */
+export async function addTimelineEvent(input: { entry_date: string; kind: string; title: string; detail?: string | null; event_time?: number }): Promise<TimelineEvent> {
+  return invoke('add_timeline_event', { input })
+}
+
+/** Delete a manual timeline event. */
+export async function deleteTimelineEvent(id: string): Promise<void> {
+  return invoke('delete_timeline_event', { id })
+}
+
+/** Load a page of recent AI runtime events for the debugger console, optionally for one request. */
+export async function listAiEvents(limit?: number, before?: Cursor | null, requestId?: string): Promise<Page<AiRuntimeEvent>> {
+  return invoke('list_ai_events', { limit, before, requestId })
+}
+
+export interface ListEventsOptions {
+  /** Exact module, or a prefix such as `jobs` matching `jobs.daily`. */
+  module?: string
+  level?: string
+  code?: string
+  since?: number
+  until?: number
+  /** Only events logged for this chat request. */
+  request_id?: string
+  before?: Cursor | null
+  limit?: number
+}
+
+/** Load a page of event log rows across all modules, newest first. */
+export async function listEvents(options: ListEventsOptions = {}): Promise<Page<AiRuntimeEvent>> {
+  return invoke('list_events', { input: options })
+}
+
+/** An event log row pushed as soon as it is written. */
+export interface LoggedEvent extends AiRuntimeEvent {
+  module: string
+}
+
+/** Subscribe to new event log rows, so the debugger can append without polling. */
+export async function onEventLogged(handler: (event: LoggedEvent) => void): Promise<UnlistenFn> {
+  return listen<LoggedEvent>('event-log://new', (event) => handler(event.payload))
+}
+
+export type LogLevel = 'debug' | 'info' | 'warn' | 'error'
+
+/** Set the minimum level persisted to the event log. */
+export async function setLogLevel(level: LogLevel): Promise<LogLevel> {
+  return invoke('set_log_level', { level })
+}
+
+export interface RawResponse {
+  id: string
+  request_id: string
+  provider_id: string
+  model: string
+  outcome: 'ok' | 'error'
+  raw: unknown
+  created_at: number
+}
+
+export async function setRawCapture(enabled: boolean): Promise<boolean> {
+  return invoke('set_raw_capture', { enabled })
+}
+
+export async function getAiRawResponse(requestId: string): Promise<RawResponse[]> {
+  return invoke('get_ai_raw_response', { requestId })
+}
+
+export interface PullProgress {
+  provider_id: string
+  model: string
+  status: string
+  completed: number | null
+  total: number | null
+}
+
+/** Allow chats to download a missing Ollama model instead of falling back. */
+export async function setOllamaAutoPull(enabled: boolean): Promise<boolean> {
+  return invoke('set_ollama_auto_pull', { enabled })
+}
+
+/** Subscribe to download progress while a missing Ollama model is pulled. */
+export async function onPullProgress(handler: (progress: PullProgress) => void): Promise<UnlistenFn> {
+  return listen<PullProgress>('ollama://pull-progress', (event) => handler(event.payload))
+}
+
+export interface BackupReport {
+  path: string
+  bytes: number
+  sha256: string
+}
+
+export interface RestoreReport {
+  staged_path: string
+  bytes: number
+  sha256: string
+  migrations: number
+}
+
+/** Copy the live database to `destPath` using SQLite's online backup. */
+export async function backupDb(destPath: string): Promise<BackupReport> {
+  return invoke('backup_db', { destPath })
+}
+
+/** Stage a backup to replace the database when the app next starts. */
+export async function restoreDb(srcPath: string): Promise<RestoreReport> {
+  return invoke('restore_db', { srcPath })
+}
+
+/** Change the passphrase of an encrypted workspace. Restart afterwards. */
+export async function rekeyDb(newKey: string): Promise<void> {
+  return invoke('rekey_db', { newKey })
+}
+
+export type JobState = 'queued' | 'running' | 'succeeded' | 'failed' | 'cancelled'
+
+export interface JobRecord {
+  id: string
+  kind: string
+  state: JobState
+  payload: unknown
+  result?: unknown
+  attempts: number
+  max_attempts: number
+  run_at?: number | null
+  created_at: number
+  updated_at: number
+  /** When the latest attempt started running. */
+  started_at?: number | null
+}
+
+/** List background jobs, newest first, optionally filtered by state and kind. */
+export async function listJobs(state?: JobState, kind?: string, limit?: number, before?: Cursor | null): Promise<Page<JobRecord>> {
+  return invoke('list_jobs', { jobState: state, kind, limit, before })
+}
+
+/** Fetch a single background job by id. */
+export async function getJob(jobId: string): Promise<JobRecord | null> {
+  return invoke('get_job', { jobId })
+}
+
+/** Cancel a queued job. Running or finished jobs are rejected. */
+export async function cancelJob(jobId: string): Promise<void> {
+  return invoke('cancel_job', { jobId })
+}
+
+/** Queue a copy of a failed or cancelled job and return the new job id. */
+export async function requeueJob(jobId: string, runAt?: number): Promise<string> {
+  return invoke('requeue_job', { jobId, runAt })
+}
+
+export interface SchedulerStatus {
+  paused: boolean
+  /** Due jobs run in parallel up to this many at a time. */
+  workers: number
+  queued_jobs: number
+  /** Queued jobs whose run time has passed. */
+  due_jobs: number
+}
+
+/** Pause background job dispatch. Manually triggered digests still run. */
+export async function schedulerPause(): Promise<SchedulerStatus> {
+  return invoke('scheduler_pause')
+}
+
+/** Resume background job dispatch. */
+export async function schedulerResume(): Promise<SchedulerStatus> {
+  return invoke('scheduler_resume')
+}
+
+/** Report whether the scheduler is paused and how many jobs are waiting. */
+export async function schedulerStatus(): Promise<SchedulerStatus> {
+  return invoke('scheduler_status')
+}
+
+/** Set how many due jobs run in parallel (1–8). */
+export async function schedulerSetWorkers(workers: number): Promise<SchedulerStatus> {
+  return invoke('scheduler_set_workers', { workers })
+}
+
+export interface DigestSchedule {
+  hour: number
+  minute: number
+  timezone: string
+  /** Daily token total that triggers a `usage` timeline event; null disables it. */
+  token_budget?: number | null
+  /** Newest notes quoted in the digest prompt, 1–50 (default 5). */
+  excerpt_count?: number
+  /** Characters kept from each quoted note, 40–4000 (default 240). */
+  excerpt_chars?: number
+}
+
+export interface DailyUsage {
+  prompt_tokens: number
+  completion_tokens: number
+  total_tokens: number
+  estimated_cost_usd: number
+}
+
+/** Read the nightly digest schedule. */
+export async function getDigestSchedule(): Promise<DigestSchedule> {
+  return invoke('get_digest_schedule')
+}
+
+/** Update the nightly digest time and IANA timezone. */
+export async function updateDigestSchedule(input: DigestSchedule): Promise<DigestSchedule> {
+  return invoke('update_digest_schedule', { input })
+}
+
+export type WebhookEvent = 'succeeded' | 'failed' | 'retrying'
+
+export interface WebhookConfig {
+  /** Null disables the webhook. */
+  url: string | null
+  events: WebhookEvent[]
+}
+
+/** Read the job completion webhook settings. */
+export async function getWebhookConfig(): Promise<WebhookConfig> {
+  return invoke('get_webhook_config')
+}
+
+/** Set the webhook URL and the job states that trigger it. */
+export async function updateWebhookConfig(input: WebhookConfig): Promise<WebhookConfig> {
+  return invoke('update_webhook_config', { input })
+}
+
+/** Post a sample notification to the configured webhook; resolves with the HTTP status. */
+export async function testWebhook(): Promise<number> {
+  return invoke('test_webhook')
+}
+
+export interface JobSchedule {
+  id: string
+  kind: string
+  payload: Record<string, unknown>
+  cron_expr: string
+  /** Run time of the job currently queued for this schedule. */
+  next_run: number | null
+  enabled: boolean
+  created_at: number
+  updated_at: number
+}
+
+/** List recurring job schedules, including the seeded nightly digest. */
+export async function listSchedules(): Promise<JobSchedule[]> {
+  return invoke('list_schedules')
+}
+
+/** Define a recurring job. `cronExpr` is evaluated in the digest timezone. */
+export async function createSchedule(kind: string, cronExpr: string, payload?: Record<string, unknown>): Promise<JobSchedule> {
+  return invoke('create_schedule', { input: { kind, cron_expr: cronExpr, payload } })
+}
+
+/** Delete a schedule and its queued job. */
+export async function deleteSchedule(scheduleId: string): Promise<void> {
+  return invoke('delete_schedule', { scheduleId })
+}
+
+/** Enable or disable a schedule. */
+export async function toggleSchedule(scheduleId: string, enabled: boolean): Promise<JobSchedule> {
+  return invoke('toggle_schedule', { scheduleId, enabled })
 }
 
 /** Trigger the daily digest job and receive the resulting payload. */
-export async function runDailyDigest(date?: string): Promise<JobRunResult<{ entry_date: string; logbook: LogbookEntry; timeline: TimelineEvent[] }>> {
+export async function runDailyDigest(date?: string): Promise<JobRunResult<{ entry_date: string; logbook: LogbookEntry; timeline: TimelineEvent[]; usage: DailyUsage; summary_id: string | null }>> {
   return invoke('run_daily_digest', { date })
 }
+
+export interface DigestProgress {
+  job_id: string
+  entry_date: string
+  phase: 'counting_notes' | 'gathering_excerpts' | 'writing_timeline' | 'calling_ai' | 'upgrading_summary' | 'done'
+  percent: number
+}
+
+/** Subscribe to progress of manually triggered digests (`runDailyDigest`). */
+export async function onDigestProgress(handler: (progress: DigestProgress) => void): Promise<UnlistenFn> {
+  return listen<DigestProgress>('digest://progress', (event) => handler(event.payload))
+}
+
+export interface TimelineRefresh {
+  entry_date: string
+  source: 'fallback' | 'ai'
+  summary_id: string | null
+}
+
+/** Subscribe to logbook/timeline rewrites from any daily digest run, manual or nightly. */
+export async function onTimelineRefresh(handler: (refresh: TimelineRefresh) => void): Promise<UnlistenFn> {
+  return listen<TimelineRefresh>('timeline://refresh', (event) => handler(event.payload))
+}
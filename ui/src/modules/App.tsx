@@ -4,7 +4,7 @@ import Settings from './Settings'
 import Timeline from './Timeline'
 import Palette, { type PaletteCommand } from '../components/Palette'
 import Console from '../components/Console'
-import { ping, createNote, listNotes, runDailyDigest } from '../lib/api'
+import { describeError, ping, createNote, listNotes, runDailyDigest } from '../lib/api'
 
 /**
  * Minimal note object used within the demo sandbox list. The canonical schema
@@ -26,7 +26,7 @@ function NotesPanel({ onNotify }: { onNotify?: (message: string, kind?: 'info' |
     ;(async () => {
       const response = await ping()
       setStatus('Core online · ' + new Date(response.ts * 1000).toLocaleString())
-      setNotes(await listNotes())
+      setNotes((await listNotes()).items)
     })()
   }, [])
 
@@ -39,7 +39,7 @@ function NotesPanel({ onNotify }: { onNotify?: (message: string, kind?: 'info' |
     setBusy(true)
     try {
       const { id } = await createNote({ title: title.trim(), body: 'Created from UI v2' })
-      setNotes(await listNotes())
+      setNotes((await listNotes()).items)
       setTitle('')
       onNotify?.(`Note created: ${id}`, 'info')
     } finally {
@@ -172,7 +172,7 @@ export default function App() {
             bumpTimelineRefresh()
             setTab('timeline')
           } catch (error) {
-            const message = error instanceof Error ? error.message : String(error)
+            const message = describeError(error)
             notify(message, 'error')
             throw error instanceof Error ? error : new Error(message)
           }
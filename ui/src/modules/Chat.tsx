@@ -6,6 +6,7 @@ import {
   chatCreateConversation,
   chatGetMessages,
   chatListConversations,
+  describeError,
   type AiChatMessage,
   type AppendResult,
   type ConversationRecord,
@@ -44,7 +45,7 @@ export default function Chat({ onNotify }: ChatProps) {
     async (preferId?: string | null): Promise<ConversationRecord[]> => {
       setLoadingConversations(true)
       try {
-        const list = await chatListConversations(50)
+        const { items: list } = await chatListConversations(50)
         setConversations(list)
         setSelectedId((prev) => {
           if (preferId) return preferId
@@ -55,7 +56,7 @@ export default function Chat({ onNotify }: ChatProps) {
         })
         return list
       } catch (err) {
-        const message = err instanceof Error ? err.message : String(err)
+        const message = describeError(err)
         setError(message)
         onNotify?.(message, 'error')
         return []
@@ -77,7 +78,7 @@ export default function Chat({ onNotify }: ChatProps) {
         setWarnActive(Boolean(conversation?.ctx_warn))
         return data
       } catch (err) {
-        const message = err instanceof Error ? err.message : String(err)
+        const message = describeError(err)
         setError(message)
         onNotify?.(message, 'error')
         return []
@@ -170,6 +171,7 @@ export default function Chat({ onNotify }: ChatProps) {
         provider_id: providerId,
         model: modelId,
         messages: aiMessages,
+        conversation_id: conversationId,
       })
 
       let assistantResult = await chatAppendAndMaybeRollover(conversationId, reply.content, 'assistant')
@@ -188,7 +190,7 @@ export default function Chat({ onNotify }: ChatProps) {
       await loadMessages(conversationId, conversationList)
       setWarnActive(Boolean(conversationList.find((item) => item.id === conversationId)?.ctx_warn || assistantResult.warn))
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
       onNotify?.(message, 'error')
     } finally {
@@ -211,7 +213,7 @@ export default function Chat({ onNotify }: ChatProps) {
         setStatusMessage('Conversation is already within safe context limits.')
       }
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
       onNotify?.(message, 'error')
     }
@@ -247,7 +249,7 @@ export default function Chat({ onNotify }: ChatProps) {
                 refreshConversations(created.id).catch(() => {})
               })
               .catch((err) => {
-                const message = err instanceof Error ? err.message : String(err)
+                const message = describeError(err)
                 setError(message)
                 onNotify?.(message, 'error')
               })
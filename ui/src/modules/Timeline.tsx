@@ -1,5 +1,5 @@
 import React, { useCallback, useEffect, useMemo, useState } from 'react'
-import { listLogbookEntries, listTimelineEvents, runDailyDigest, type LogbookEntry, type TimelineEvent } from '../lib/api'
+import { describeError, listLogbookEntries, listTimelineEvents, runDailyDigest, type LogbookEntry, type TimelineEvent } from '../lib/api'
 
 /** Props accepted by the timeline/logbook module. */
 interface TimelineProps {
@@ -27,7 +27,7 @@ export default function Timeline({ refreshKey, onNotify }: TimelineProps) {
       setEntriesState('loading')
       setError(null)
       try {
-        const data = await listLogbookEntries(14)
+        const { items: data } = await listLogbookEntries(14)
         setEntries(data)
         if (data.length === 0) {
           setSelectedDate('')
@@ -41,7 +41,7 @@ export default function Timeline({ refreshKey, onNotify }: TimelineProps) {
           : data[0].entry_date
         setSelectedDate(nextDate)
       } catch (err) {
-        const message = err instanceof Error ? err.message : String(err)
+        const message = describeError(err)
         setError(message)
         setEntriesState('error')
       } finally {
@@ -63,7 +63,7 @@ export default function Timeline({ refreshKey, onNotify }: TimelineProps) {
       setEvents(data)
       setTimelineState('idle')
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
       setTimelineState('error')
     }
@@ -94,7 +94,7 @@ export default function Timeline({ refreshKey, onNotify }: TimelineProps) {
         onNotify(`Daily digest refreshed for ${entryDate}.`, 'info')
       }
     } catch (err) {
-      const message = err instanceof Error ? err.message : String(err)
+      const message = describeError(err)
       setError(message)
       if (onNotify) onNotify(message, 'error')
     } finally {
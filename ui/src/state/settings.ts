@@ -1,6 +1,6 @@
 import { create } from 'zustand'
 import type { AiChatResponse, AiProviderInfo, AiSettingsView, AiUpdateSettingsPayload } from '../lib/api'
-import { aiChat, aiGetSettings, aiListProviders, aiUpdateSettings } from '../lib/api'
+import { aiChat, describeError, aiGetSettings, aiListProviders, aiUpdateSettings } from '../lib/api'
 
 /** Normalised provider shape tailored for the UI. */
 export type UiProvider = {
@@ -116,7 +116,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
         }
       })
     } catch (error) {
-      set({ loading: false, error: error instanceof Error ? error.message : String(error) })
+      set({ loading: false, error: describeError(error) })
     }
   },
   async loadCurrentSettings() {
@@ -144,7 +144,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
         }
       })
     } catch (error) {
-      set({ error: error instanceof Error ? error.message : String(error) })
+      set({ error: describeError(error) })
     }
   },
   selectProvider(id) {
@@ -247,7 +247,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
     } catch (error) {
       set({
         saving: false,
-        error: error instanceof Error ? error.message : String(error),
+        error: describeError(error),
       })
     }
   },
@@ -278,7 +278,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
     } catch (error) {
       set({
         testing: false,
-        error: error instanceof Error ? error.message : String(error),
+        error: describeError(error),
       })
     }
   },